@@ -0,0 +1,48 @@
+use std::collections::BTreeSet;
+
+use reCTBN::params;
+use reCTBN::process::ctbn::CtbnNetwork;
+use reCTBN::process::NetworkProcess;
+use reCTBN::tools::*;
+
+/// Generate a network with `n_nodes` nodes of cardinality `cardinality` and no edges.
+#[allow(dead_code)]
+pub fn generate_nodes(n_nodes: usize, cardinality: usize) -> CtbnNetwork {
+    let mut net = CtbnNetwork::new();
+    for node in 0..n_nodes {
+        let domain: BTreeSet<String> = (0..cardinality).map(|x| x.to_string()).collect();
+        let param = params::DiscreteStatesContinousTimeParams::new(node.to_string(), domain);
+        net.add_node(params::Params::DiscreteStatesContinousTime(param))
+            .unwrap();
+    }
+    net
+}
+
+/// Generate a network with `n_nodes` nodes of cardinality `cardinality`, random structure and
+/// random parameters.
+#[allow(dead_code)]
+pub fn generate_net(n_nodes: usize, cardinality: usize, density: f64) -> CtbnNetwork {
+    let mut net = generate_nodes(n_nodes, cardinality);
+    let mut structure_generator: UniformGraphGenerator =
+        RandomGraphGenerator::new(density, Some(7641630759785120));
+    structure_generator.generate_graph(&mut net);
+    let mut cim_generator: UniformParametersGenerator =
+        RandomParametersGenerator::new(0.0..7.0, Some(7641630759785120));
+    cim_generator.generate_parameters(&mut net);
+    net
+}
+
+/// Generate a network with `n_nodes` nodes of cardinality `cardinality`, random structure and
+/// random parameters, together with a dataset sampled from it.
+#[allow(dead_code)]
+pub fn generate_net_with_data(
+    n_nodes: usize,
+    cardinality: usize,
+    density: f64,
+    n_trajectories: u64,
+    t_end: f64,
+) -> (CtbnNetwork, Dataset) {
+    let net = generate_net(n_nodes, cardinality, density);
+    let data = trajectory_generator(&net, n_trajectories, t_end, Some(6347747169756259));
+    (net, data)
+}
@@ -0,0 +1,102 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use reCTBN::parameter_learning::{
+    sufficient_statistics, Alpha, BayesianApproach, DatasetStatistics, Tau,
+};
+use reCTBN::structure_learning::constraint_based_algorithm::CTPC;
+use reCTBN::structure_learning::hypothesis_test::{ChiSquare, F};
+use reCTBN::structure_learning::StructuralLearningAlgorithm;
+use reCTBN::tools::SuffStatsCache;
+
+mod common;
+
+fn ctpc(c: &mut Criterion) {
+    let (_, data) = common::generate_net_with_data(5, 3, 0.3, 100, 30.0);
+    let mut group = c.benchmark_group("ctpc");
+    group.sample_size(10);
+    group.bench_function("ctpc", |b| {
+        b.iter(|| {
+            let ctpc = CTPC::new(
+                BayesianApproach {
+                    alpha: Alpha::Constant(1.0),
+                    tau: Tau::Constant(1.0),
+                },
+                F::new(1e-6),
+                ChiSquare::new(1e-4),
+            );
+            ctpc.fit_transform(common::generate_nodes(5, 3), &data)
+                .unwrap()
+        })
+    });
+    group.bench_function("ctpc_suff_stats_cache", |b| {
+        b.iter(|| {
+            let ctpc = CTPC::new_with_suff_stats_cache(
+                BayesianApproach {
+                    alpha: Alpha::Constant(1.0),
+                    tau: Tau::Constant(1.0),
+                },
+                F::new(1e-6),
+                ChiSquare::new(1e-4),
+                Arc::new(SuffStatsCache::new()),
+            );
+            ctpc.fit_transform(common::generate_nodes(5, 3), &data)
+                .unwrap()
+        })
+    });
+    let dataset_statistics = Arc::new(DatasetStatistics::new(&data));
+    group.bench_function("ctpc_shared_dataset_statistics", |b| {
+        b.iter(|| {
+            let ctpc = CTPC::new_with_suff_stats_cache(
+                BayesianApproach {
+                    alpha: Alpha::Constant(1.0),
+                    tau: Tau::Constant(1.0),
+                },
+                F::new(1e-6),
+                ChiSquare::new(1e-4),
+                Arc::new(SuffStatsCache::with_dataset_statistics(
+                    dataset_statistics.clone(),
+                )),
+            );
+            ctpc.fit_transform(common::generate_nodes(5, 3), &data)
+                .unwrap()
+        })
+    });
+    group.finish();
+}
+
+fn suff_stats(c: &mut Criterion) {
+    let (net, data) = common::generate_net_with_data(5, 3, 0.3, 100, 30.0);
+    //Each node given each pair of the other nodes, as in the second level of CTPC.
+    let keys: Vec<(usize, BTreeSet<usize>)> = (0..5)
+        .flat_map(|node| {
+            (0..5)
+                .flat_map(move |x| (x + 1..5).map(move |y| (x, y)))
+                .filter(move |(x, y)| *x != node && *y != node)
+                .map(move |(x, y)| (node, BTreeSet::from([x, y])))
+        })
+        .collect();
+    let mut group = c.benchmark_group("suff_stats");
+    group.bench_function("sufficient_statistics", |b| {
+        b.iter(|| {
+            keys.iter()
+                .map(|(node, parent_set)| sufficient_statistics(&net, &data, *node, parent_set))
+                .count()
+        })
+    });
+    group.bench_function("dataset_statistics", |b| {
+        b.iter(|| {
+            let dataset_statistics = DatasetStatistics::new(&data);
+            keys.iter()
+                .map(|(node, parent_set)| {
+                    dataset_statistics.sufficient_statistics(&net, *node, parent_set)
+                })
+                .count()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, ctpc, suff_stats);
+criterion_main!(benches);
@@ -0,0 +1,53 @@
+use std::fs::File;
+use std::io::BufReader;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use reCTBN::tools::Dataset;
+
+mod common;
+
+fn dataset_io(c: &mut Criterion) {
+    //About 1M events.
+    let (_, data) = common::generate_net_with_data(10, 3, 0.2, 1000, 33.0);
+    let variable_names: Vec<String> = (0..10).map(|x| format!("var_{}", x)).collect();
+    let variable_columns: Vec<&str> = variable_names.iter().map(|x| x.as_str()).collect();
+
+    let dir = std::env::temp_dir();
+    let csv_path = dir.join("reCTBN_bench_dataset_io.csv");
+    let parquet_path = dir.join("reCTBN_bench_dataset_io.parquet");
+    data.to_csv(File::create(&csv_path).unwrap(), None).unwrap();
+    data.to_parquet(File::create(&parquet_path).unwrap(), None)
+        .unwrap();
+
+    let mut group = c.benchmark_group("dataset_io");
+    group.sample_size(10);
+    group.bench_function("from_csv_1M_events", |b| {
+        b.iter(|| {
+            Dataset::from_csv(
+                BufReader::new(File::open(&csv_path).unwrap()),
+                "time",
+                &variable_columns,
+                Some("trajectory_id"),
+            )
+            .unwrap()
+        })
+    });
+    group.bench_function("from_parquet_1M_events", |b| {
+        b.iter(|| {
+            Dataset::from_parquet(
+                File::open(&parquet_path).unwrap(),
+                "time",
+                &variable_columns,
+                Some("trajectory_id"),
+            )
+            .unwrap()
+        })
+    });
+    group.finish();
+
+    std::fs::remove_file(&csv_path).unwrap();
+    std::fs::remove_file(&parquet_path).unwrap();
+}
+
+criterion_group!(benches, dataset_io);
+criterion_main!(benches);
@@ -0,0 +1,53 @@
+use std::sync::Arc;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use reCTBN::structure_learning::score_based_algorithm::HillClimbing;
+use reCTBN::structure_learning::score_function::BIC;
+use reCTBN::structure_learning::StructuralLearningAlgorithm;
+use reCTBN::tools::SuffStatsCache;
+
+mod common;
+
+fn hill_climbing(c: &mut Criterion) {
+    let (_, data) = common::generate_net_with_data(5, 3, 0.3, 100, 30.0);
+    let mut group = c.benchmark_group("hill_climbing");
+    group.sample_size(10);
+    group.bench_function("hill_climbing_bic", |b| {
+        b.iter(|| {
+            let hc = HillClimbing::new(BIC::new(1, 1.0), None);
+            hc.fit_transform(common::generate_nodes(5, 3), &data)
+                .unwrap()
+        })
+    });
+    group.bench_function("hill_climbing_bic_suff_stats_cache", |b| {
+        b.iter(|| {
+            let bic = BIC::new_with_suff_stats_cache(1, 1.0, Arc::new(SuffStatsCache::new()));
+            let hc = HillClimbing::new(bic, None);
+            hc.fit_transform(common::generate_nodes(5, 3), &data)
+                .unwrap()
+        })
+    });
+    group.finish();
+
+    let (_, data) = common::generate_net_with_data(10, 3, 0.3, 100, 30.0);
+    let mut group = c.benchmark_group("hill_climbing_10_nodes");
+    group.sample_size(10);
+    group.bench_function("hill_climbing_bic", |b| {
+        b.iter(|| {
+            let hc = HillClimbing::new(BIC::new(1, 1.0), None);
+            hc.fit_transform(common::generate_nodes(10, 3), &data)
+                .unwrap()
+        })
+    });
+    group.bench_function("hill_climbing_bic_best_first", |b| {
+        b.iter(|| {
+            let hc = HillClimbing::new(BIC::new(1, 1.0), None).with_best_first(true);
+            hc.fit_transform(common::generate_nodes(10, 3), &data)
+                .unwrap()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, hill_climbing);
+criterion_main!(benches);
@@ -0,0 +1,80 @@
+use std::sync::Arc;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use reCTBN::parameter_learning::{Alpha, BayesianApproach, Tau};
+use reCTBN::structure_learning::constraint_based_algorithm::CTPC;
+use reCTBN::structure_learning::hybrid_algorithm::HybridLearner;
+use reCTBN::structure_learning::hypothesis_test::{ChiSquare, F};
+use reCTBN::structure_learning::score_based_algorithm::HillClimbing;
+use reCTBN::structure_learning::score_function::BIC;
+use reCTBN::structure_learning::StructuralLearningAlgorithm;
+use reCTBN::tools::SuffStatsCache;
+
+mod common;
+
+fn ctpc() -> CTPC<BayesianApproach> {
+    CTPC::new(
+        BayesianApproach {
+            alpha: Alpha::Constant(1.0),
+            tau: Tau::Constant(1.0),
+        },
+        F::new(1e-6),
+        ChiSquare::new(1e-4),
+    )
+}
+
+fn hill_climbing() -> HillClimbing<BIC> {
+    HillClimbing::new(BIC::new(1, 1.0), None)
+}
+
+//The two searches share the sufficient statistics: the refinement reuses most of the statistics
+//computed for the hypothesis tests.
+fn hybrid_learner() -> HybridLearner<BayesianApproach, BIC> {
+    let suff_stats_cache = Arc::new(SuffStatsCache::new());
+    let ctpc = CTPC::new_with_suff_stats_cache(
+        BayesianApproach {
+            alpha: Alpha::Constant(1.0),
+            tau: Tau::Constant(1.0),
+        },
+        F::new(1e-6),
+        ChiSquare::new(1e-4),
+        suff_stats_cache.clone(),
+    );
+    let hill_climbing = HillClimbing::new(
+        BIC::new_with_suff_stats_cache(1, 1.0, suff_stats_cache),
+        None,
+    );
+    HybridLearner::new(ctpc, hill_climbing)
+}
+
+fn hybrid(c: &mut Criterion) {
+    let (_, data) = common::generate_net_with_data(8, 3, 0.3, 100, 30.0);
+
+    let mut group = c.benchmark_group("hybrid_8_nodes");
+    group.sample_size(10);
+    group.bench_function("ctpc", |b| {
+        b.iter(|| {
+            ctpc()
+                .fit_transform(common::generate_nodes(8, 3), &data)
+                .unwrap()
+        })
+    });
+    group.bench_function("hill_climbing_bic", |b| {
+        b.iter(|| {
+            hill_climbing()
+                .fit_transform(common::generate_nodes(8, 3), &data)
+                .unwrap()
+        })
+    });
+    group.bench_function("hybrid", |b| {
+        b.iter(|| {
+            hybrid_learner()
+                .fit_transform(common::generate_nodes(8, 3), &data)
+                .unwrap()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, hybrid);
+criterion_main!(benches);
@@ -0,0 +1,124 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use reCTBN::params::ParamsTrait;
+use reCTBN::process::{NetworkProcess, NetworkProcessState};
+use reCTBN::sampling::{ForwardSampler, Sample};
+use reCTBN::tools::trajectory_generator;
+
+mod common;
+
+//Baseline for the `ForwardSampler`: same sampling loop, but the parameter index of each node is
+//computed over the whole network and the nodes and children are looked up at each transition.
+struct UncachedSampler<'a, T: NetworkProcess> {
+    net: &'a T,
+    rng: ChaCha8Rng,
+    current_time: f64,
+    current_state: NetworkProcessState,
+    next_transitions: Vec<Option<f64>>,
+}
+
+impl<'a, T: NetworkProcess> UncachedSampler<'a, T> {
+    fn new(net: &'a T, seed: u64) -> UncachedSampler<'a, T> {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let current_state = net
+            .get_node_indices()
+            .map(|x| net.get_node(x).get_random_state_uniform(&mut rng))
+            .collect();
+        UncachedSampler {
+            net,
+            rng,
+            current_time: 0.0,
+            current_state,
+            next_transitions: net.get_node_indices().map(|_| None).collect(),
+        }
+    }
+}
+
+impl<'a, T: NetworkProcess> Iterator for UncachedSampler<'a, T> {
+    type Item = Sample;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let ret_time = self.current_time;
+        let ret_state = self.current_state.clone();
+
+        for (idx, val) in self.next_transitions.iter_mut().enumerate() {
+            if val.is_none() {
+                *val = Some(
+                    self.net
+                        .get_node(idx)
+                        .get_random_transition_time(
+                            self.net
+                                .get_node(idx)
+                                .state_to_index(&self.current_state[idx]),
+                            self.net.get_param_index_network(idx, &self.current_state),
+                            self.current_time,
+                            &mut self.rng,
+                        )
+                        .unwrap(),
+                );
+            }
+        }
+
+        let next_node_transition = self
+            .next_transitions
+            .iter()
+            .enumerate()
+            .min_by(|x, y| x.1.unwrap().partial_cmp(&y.1.unwrap()).unwrap())
+            .unwrap()
+            .0;
+        self.current_time = self.next_transitions[next_node_transition].unwrap();
+
+        self.current_state[next_node_transition] = self
+            .net
+            .get_node(next_node_transition)
+            .get_random_state_at(
+                self.net
+                    .get_node(next_node_transition)
+                    .state_to_index(&self.current_state[next_node_transition]),
+                self.net
+                    .get_param_index_network(next_node_transition, &self.current_state),
+                self.current_time,
+                &mut self.rng,
+            )
+            .unwrap();
+
+        self.next_transitions[next_node_transition] = None;
+        for child in self.net.get_children_set(next_node_transition) {
+            self.next_transitions[child] = None;
+        }
+
+        Some(Sample {
+            t: ret_time,
+            state: ret_state,
+        })
+    }
+}
+
+fn sampling(c: &mut Criterion) {
+    let net = common::generate_net(30, 3, 0.1);
+    let mut group = c.benchmark_group("sampling");
+    group.sample_size(10);
+    group.bench_function("trajectory_generator_30_nodes", |b| {
+        b.iter(|| trajectory_generator(&net, 10, 10.0, Some(6347747169756259)))
+    });
+    //The same number of samples drawn with and without the structure cache.
+    group.bench_function("forward_sampler_30_nodes", |b| {
+        b.iter(|| {
+            ForwardSampler::new(&net, Some(6347747169756259), None)
+                .take(10000)
+                .count()
+        })
+    });
+    group.bench_function("uncached_sampler_30_nodes", |b| {
+        b.iter(|| {
+            UncachedSampler::new(&net, 6347747169756259)
+                .take(10000)
+                .count()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, sampling);
+criterion_main!(benches);
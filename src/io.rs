@@ -0,0 +1,500 @@
+//! Module containing readers for network descriptions stored in external formats.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+
+use ndarray::Array3;
+use thiserror::Error;
+
+use crate::params::{DiscreteStatesContinousTimeParams, Params};
+use crate::process::ctbn::CtbnNetwork;
+use crate::process::NetworkProcess;
+
+/// Error types for the network readers
+#[derive(Error, Debug, PartialEq)]
+pub enum IoError {
+    #[error("Malformed XML: {0}")]
+    MalformedXml(String),
+    #[error("Invalid network: {0}")]
+    InvalidNetwork(String),
+    #[error("I/O error: {0}")]
+    Io(String),
+}
+
+/// Read a CTBN from a file in the XMLBIF-like format described in [`parse_xml_ctbn`].
+pub fn read_xml_ctbn<P: AsRef<Path>>(path: P) -> Result<CtbnNetwork, IoError> {
+    let content = fs::read_to_string(path).map_err(|e| IoError::Io(e.to_string()))?;
+    parse_xml_ctbn(&content)
+}
+
+/// Build a CTBN from an XMLBIF-like description.
+///
+/// The document contains a `NETWORK` element with one `VARIABLE` element per node and one
+/// `DEFINITION` element per node. Tag names are case insensitive; any other element (e.g.
+/// `NAME` of the network or `PROPERTY`) is ignored.
+///
+/// ```xml
+/// <NETWORK>
+///     <VARIABLE>
+///         <NAME>X</NAME>
+///         <OUTCOME>low</OUTCOME>
+///         <OUTCOME>high</OUTCOME>
+///     </VARIABLE>
+///     <DEFINITION>
+///         <FOR>X</FOR>
+///         <GIVEN>Y</GIVEN>
+///         <TABLE>-1.0 1.0 2.0 -2.0 -3.0 3.0 4.0 -4.0</TABLE>
+///     </DEFINITION>
+/// </NETWORK>
+/// ```
+///
+/// # Conventions
+///
+/// * The `TABLE` contains one CIM block per parent configuration; each block is a
+///   `domain x domain` matrix in row-major order (row: current state, column: next state),
+///   with rows and columns following the order of the `OUTCOME` elements.
+/// * The parent configurations follow the order of the `GIVEN` elements, with the **last**
+///   parent varying fastest, as in XMLBIF.
+/// * Both orderings are converted to the ones used by this crate: the state indices follow the
+///   sorted outcomes (see [`DiscreteStatesContinousTimeParams`]) and the parent configurations
+///   are indexed with the parent of lowest node index varying fastest. The nodes are added in
+///   the order of the `VARIABLE` elements.
+///
+/// # Errors
+///
+/// Malformed documents, unknown variables, duplicated outcomes and CIMs with the wrong number
+/// of entries or failing [`validate_params`](crate::params::ParamsTrait::validate_params) are
+/// reported naming the offending node.
+pub fn parse_xml_ctbn(xml: &str) -> Result<CtbnNetwork, IoError> {
+    let root = parse_xml(xml)?;
+    let network = if root.is("NETWORK") {
+        &root
+    } else {
+        root.child("NETWORK")
+            .ok_or_else(|| invalid(String::from("missing NETWORK element")))?
+    };
+
+    //Label and outcomes, in declaration order, of each variable.
+    let mut variables: Vec<(String, Vec<String>)> = vec![];
+    for variable in network.children_named("VARIABLE") {
+        let label = variable
+            .child("NAME")
+            .map(|x| x.text.trim().to_string())
+            .ok_or_else(|| invalid(String::from("VARIABLE without NAME")))?;
+        let outcomes: Vec<String> = variable
+            .children_named("OUTCOME")
+            .map(|x| x.text.trim().to_string())
+            .collect();
+        if outcomes.is_empty() {
+            return Err(invalid(format!("variable {}: no OUTCOME", label)));
+        }
+        if outcomes.iter().collect::<BTreeSet<&String>>().len() != outcomes.len() {
+            return Err(invalid(format!("variable {}: duplicated OUTCOME", label)));
+        }
+        if variables.iter().any(|(x, _)| *x == label) {
+            return Err(invalid(format!("variable {}: declared twice", label)));
+        }
+        variables.push((label, outcomes));
+    }
+    let node_of: HashMap<&str, usize> = variables
+        .iter()
+        .enumerate()
+        .map(|(idx, (label, _))| (label.as_str(), idx))
+        .collect();
+
+    //Parents (in declaration order) and table of each node.
+    let mut definitions: Vec<Option<(Vec<usize>, Vec<f64>)>> = vec![None; variables.len()];
+    for definition in network.children_named("DEFINITION") {
+        let label = definition
+            .child("FOR")
+            .map(|x| x.text.trim())
+            .ok_or_else(|| invalid(String::from("DEFINITION without FOR")))?;
+        let node = *node_of
+            .get(label)
+            .ok_or_else(|| invalid(format!("DEFINITION for unknown variable {}", label)))?;
+        let mut parents = vec![];
+        for given in definition.children_named("GIVEN") {
+            let parent = *node_of.get(given.text.trim()).ok_or_else(|| {
+                invalid(format!(
+                    "node {}: unknown parent {}",
+                    label,
+                    given.text.trim()
+                ))
+            })?;
+            if parent == node || parents.contains(&parent) {
+                return Err(invalid(format!(
+                    "node {}: invalid parent {}",
+                    label,
+                    given.text.trim()
+                )));
+            }
+            parents.push(parent);
+        }
+        let table = definition
+            .child("TABLE")
+            .ok_or_else(|| invalid(format!("node {}: missing TABLE", label)))?
+            .text
+            .split_whitespace()
+            .map(|x| x.parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>()
+            .map_err(|e| invalid(format!("node {}: invalid TABLE entry ({})", label, e)))?;
+        if definitions[node].is_some() {
+            return Err(invalid(format!("node {}: defined twice", label)));
+        }
+        definitions[node] = Some((parents, table));
+    }
+
+    let mut net = CtbnNetwork::new();
+    for (label, outcomes) in variables.iter() {
+        let param = DiscreteStatesContinousTimeParams::new(
+            label.clone(),
+            outcomes.iter().cloned().collect(),
+        );
+        net.add_node(Params::DiscreteStatesContinousTime(param))
+            .map_err(|e| invalid(format!("node {}: {}", label, e)))?;
+    }
+    net.initialize_adj_matrix();
+    for (node, definition) in definitions.iter().enumerate() {
+        let (parents, _) = definition
+            .as_ref()
+            .ok_or_else(|| invalid(format!("node {}: missing DEFINITION", variables[node].0)))?;
+        for parent in parents.iter() {
+            net.add_edge(*parent, node);
+        }
+    }
+
+    for (node, definition) in definitions.into_iter().enumerate() {
+        let (parents, table) = definition.unwrap();
+        let (label, outcomes) = &variables[node];
+        //Position of each declared outcome in the sorted domain.
+        let state_index = sorted_positions(outcomes);
+        let domain = outcomes.len();
+        let parents_domain: Vec<usize> = parents.iter().map(|x| variables[*x].1.len()).collect();
+        let n_configurations: usize = parents_domain.iter().product();
+        if table.len() != n_configurations * domain * domain {
+            return Err(invalid(format!(
+                "node {}: TABLE has {} entries, {} expected ({} parent configurations of a {}x{} \
+                 CIM)",
+                label,
+                table.len(),
+                n_configurations * domain * domain,
+                n_configurations,
+                domain,
+                domain
+            )));
+        }
+        let multipliers: HashMap<usize, usize> = net
+            .get_param_index_multipliers(node)
+            .unwrap()
+            .into_iter()
+            .collect();
+        let parents_state_index: Vec<Vec<usize>> = parents
+            .iter()
+            .map(|x| sorted_positions(&variables[*x].1))
+            .collect();
+
+        let mut cim = Array3::<f64>::zeros((n_configurations, domain, domain));
+        for (block, values) in table.chunks(domain * domain).enumerate() {
+            //Decode the XMLBIF configuration (last parent fastest) and encode it for the crate.
+            let mut rest = block;
+            let mut configuration = 0;
+            for (p, parent) in parents.iter().enumerate().rev() {
+                let declared_state = rest % parents_domain[p];
+                rest /= parents_domain[p];
+                configuration += parents_state_index[p][declared_state] * multipliers[parent];
+            }
+            for (idx, value) in values.iter().enumerate() {
+                cim[[
+                    configuration,
+                    state_index[idx / domain],
+                    state_index[idx % domain],
+                ]] = *value;
+            }
+        }
+        match net.get_node_mut(node) {
+            Params::DiscreteStatesContinousTime(param) => param
+                .set_cim(cim)
+                .map_err(|e| invalid(format!("node {}: {:?}", label, e)))?,
+        }
+    }
+    Ok(net)
+}
+
+fn invalid(message: String) -> IoError {
+    IoError::InvalidNetwork(message)
+}
+
+//Index of each element of `outcomes` in the sorted order.
+fn sorted_positions(outcomes: &[String]) -> Vec<usize> {
+    let sorted: BTreeSet<&String> = outcomes.iter().collect();
+    outcomes
+        .iter()
+        .map(|x| sorted.iter().position(|y| *y == x).unwrap())
+        .collect()
+}
+
+//Minimal XML element: the text of the mixed content is concatenated.
+pub(crate) struct XmlElement {
+    pub(crate) name: String,
+    pub(crate) attributes: Vec<(String, String)>,
+    pub(crate) children: Vec<XmlElement>,
+    pub(crate) text: String,
+}
+
+impl XmlElement {
+    pub(crate) fn is(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name)
+    }
+
+    pub(crate) fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(x, _)| x == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub(crate) fn child<'a>(&'a self, name: &'a str) -> Option<&'a XmlElement> {
+        self.children_named(name).next()
+    }
+
+    pub(crate) fn children_named<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> impl Iterator<Item = &'a XmlElement> {
+        self.children.iter().filter(move |x| x.is(name))
+    }
+}
+
+//Parse a whole XML document and return its root element.
+pub(crate) fn parse_xml(xml: &str) -> Result<XmlElement, IoError> {
+    XmlParser::new(xml).parse_document()
+}
+
+//Escape the text for use in XML content and attribute values.
+pub(crate) fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+//Recursive descent parser supporting elements, attributes, comments, processing instructions,
+//doctype declarations, CDATA sections and the predefined entities.
+struct XmlParser<'a> {
+    xml: &'a str,
+    pos: usize,
+}
+
+impl<'a> XmlParser<'a> {
+    fn new(xml: &'a str) -> XmlParser<'a> {
+        XmlParser { xml, pos: 0 }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.xml[self.pos..]
+    }
+
+    fn error(&self, message: &str) -> IoError {
+        let line = self.xml[..self.pos].matches('\n').count() + 1;
+        IoError::MalformedXml(format!("line {}: {}", line, message))
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    //Skip everything up to and including `end`.
+    fn skip_past(&mut self, end: &str) -> Result<(), IoError> {
+        match self.rest().find(end) {
+            Some(idx) => {
+                self.pos += idx + end.len();
+                Ok(())
+            }
+            None => Err(self.error(&format!("missing {}", end))),
+        }
+    }
+
+    //Skip comments, processing instructions and declarations; return true if something was
+    //skipped.
+    fn skip_misc(&mut self) -> Result<bool, IoError> {
+        if self.rest().starts_with("<!--") {
+            self.skip_past("-->")?;
+        } else if self.rest().starts_with("<?") {
+            self.skip_past("?>")?;
+        } else if self.rest().starts_with("<!DOCTYPE") {
+            //The internal subset, if any, is enclosed in brackets.
+            let mut depth = 0;
+            for (idx, c) in self.rest().char_indices() {
+                match c {
+                    '[' => depth += 1,
+                    ']' => depth -= 1,
+                    '>' if depth == 0 => {
+                        self.pos += idx + 1;
+                        return Ok(true);
+                    }
+                    _ => (),
+                }
+            }
+            return Err(self.error("unterminated DOCTYPE"));
+        } else {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    fn parse_document(&mut self) -> Result<XmlElement, IoError> {
+        self.skip_whitespace();
+        while self.skip_misc()? {
+            self.skip_whitespace();
+        }
+        let root = self.parse_element()?;
+        self.skip_whitespace();
+        while self.skip_misc()? {
+            self.skip_whitespace();
+        }
+        if !self.rest().is_empty() {
+            return Err(self.error("content after the root element"));
+        }
+        Ok(root)
+    }
+
+    fn parse_name(&mut self) -> Result<String, IoError> {
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| c.is_whitespace() || c == '>' || c == '/' || c == '=')
+            .unwrap_or(rest.len());
+        if len == 0 {
+            return Err(self.error("expected a name"));
+        }
+        self.pos += len;
+        Ok(rest[..len].to_string())
+    }
+
+    fn parse_element(&mut self) -> Result<XmlElement, IoError> {
+        if !self.rest().starts_with('<') {
+            return Err(self.error("expected an element"));
+        }
+        self.pos += 1;
+        let name = self.parse_name()?;
+
+        //Attributes
+        let mut attributes = vec![];
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("/>") {
+                self.pos += 2;
+                return Ok(XmlElement {
+                    name,
+                    attributes,
+                    children: vec![],
+                    text: String::new(),
+                });
+            }
+            if self.rest().starts_with('>') {
+                self.pos += 1;
+                break;
+            }
+            let attribute = self.parse_name()?;
+            self.skip_whitespace();
+            if !self.rest().starts_with('=') {
+                return Err(self.error("expected '=' after the attribute name"));
+            }
+            self.pos += 1;
+            self.skip_whitespace();
+            let quote = match self.rest().chars().next() {
+                Some(c) if c == '"' || c == '\'' => c,
+                _ => return Err(self.error("expected a quoted attribute value")),
+            };
+            self.pos += 1;
+            let end = self
+                .rest()
+                .find(quote)
+                .ok_or_else(|| self.error("unterminated attribute value"))?;
+            let value = unescape(&self.rest()[..end]).map_err(|e| self.error(&e))?;
+            self.pos += end + 1;
+            attributes.push((attribute, value));
+        }
+
+        //Content
+        let mut children = vec![];
+        let mut text = String::new();
+        loop {
+            if self.rest().starts_with("</") {
+                self.pos += 2;
+                let closing = self.parse_name()?;
+                if closing != name {
+                    return Err(
+                        self.error(&format!("closing tag {} does not match {}", closing, name))
+                    );
+                }
+                self.skip_whitespace();
+                if !self.rest().starts_with('>') {
+                    return Err(self.error("expected '>'"));
+                }
+                self.pos += 1;
+                return Ok(XmlElement {
+                    name,
+                    attributes,
+                    children,
+                    text,
+                });
+            } else if self.rest().starts_with("<![CDATA[") {
+                self.pos += "<![CDATA[".len();
+                let end = self
+                    .rest()
+                    .find("]]>")
+                    .ok_or_else(|| self.error("unterminated CDATA"))?;
+                text.push_str(&self.rest()[..end]);
+                self.pos += end + 3;
+            } else if self.skip_misc()? {
+                continue;
+            } else if self.rest().starts_with('<') {
+                children.push(self.parse_element()?);
+            } else if self.rest().is_empty() {
+                return Err(self.error(&format!("unterminated element {}", name)));
+            } else {
+                let end = self.rest().find('<').unwrap_or(self.rest().len());
+                text.push_str(&unescape(&self.rest()[..end]).map_err(|e| self.error(&e))?);
+                self.pos += end;
+            }
+        }
+    }
+}
+
+fn unescape(text: &str) -> Result<String, String> {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(idx) = rest.find('&') {
+        unescaped.push_str(&rest[..idx]);
+        rest = &rest[idx..];
+        let end = rest
+            .find(';')
+            .ok_or_else(|| String::from("unterminated entity"))?;
+        let entity = &rest[1..end];
+        let c = match entity {
+            "lt" => '<',
+            "gt" => '>',
+            "amp" => '&',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => {
+                let code = if let Some(hex) = entity.strip_prefix("#x") {
+                    u32::from_str_radix(hex, 16).ok()
+                } else if let Some(dec) = entity.strip_prefix('#') {
+                    dec.parse().ok()
+                } else {
+                    None
+                };
+                code.and_then(char::from_u32)
+                    .ok_or_else(|| format!("unknown entity &{};", entity))?
+            }
+        };
+        unescaped.push(c);
+        rest = &rest[end + 1..];
+    }
+    unescaped.push_str(rest);
+    Ok(unescaped)
+}
@@ -0,0 +1,168 @@
+//! Machine-readable records of the learning runs, used for reproducibility audits.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::json;
+
+use crate::process::NetworkProcess;
+use crate::tools::Dataset;
+
+/// Fingerprint identifying a dataset.
+///
+/// # Attributes
+///
+/// * `n_trajectories` - number of trajectories.
+/// * `n_events` - total number of rows over all the trajectories.
+/// * `hash` - FNV-1a hash of the bytes returned by
+///   [`Trajectory::to_bytes`](crate::tools::Trajectory::to_bytes) for each trajectory, followed
+///   by the little-endian bytes of the weights, if any.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DatasetFingerprint {
+    pub n_trajectories: usize,
+    pub n_events: usize,
+    pub hash: u64,
+}
+
+//FNV-1a hash of `bytes`. It is used since, unlike the standard hasher, it is stable across
+//releases.
+pub(crate) fn fnv1a<I: IntoIterator<Item = u8>>(bytes: I) -> u64 {
+    bytes.into_iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+impl DatasetFingerprint {
+    pub fn new(dataset: &Dataset) -> DatasetFingerprint {
+        let weight_bytes = dataset
+            .get_weights()
+            .into_iter()
+            .flatten()
+            .flat_map(|x| x.to_bits().to_le_bytes());
+        let hash = fnv1a(
+            dataset
+                .get_trajectories()
+                .iter()
+                .flat_map(|trj| trj.to_bytes())
+                .chain(weight_bytes),
+        );
+        DatasetFingerprint {
+            n_trajectories: dataset.get_trajectories().len(),
+            n_events: dataset
+                .get_trajectories()
+                .iter()
+                .map(|x| x.get_time().len())
+                .sum(),
+            hash,
+        }
+    }
+}
+
+/// Record of a single learning run.
+///
+/// # Attributes
+///
+/// * `version` - version of the library.
+/// * `algorithm` - name of the algorithm.
+/// * `hyperparameters` - name and value of each hyperparameter.
+/// * `dataset` - fingerprint of the dataset used for learning.
+/// * `seeds` - seeds used by the algorithm.
+/// * `timestamp` - seconds since the Unix epoch at the end of the run.
+/// * `elapsed_seconds` - wall-clock duration of the run.
+/// * `adjacency_matrix` - learned adjacency matrix (`adjacency_matrix[parent][child]`).
+#[derive(Clone, Debug, PartialEq)]
+pub struct JournalRecord {
+    pub version: String,
+    pub algorithm: String,
+    pub hyperparameters: Vec<(String, String)>,
+    pub dataset: DatasetFingerprint,
+    pub seeds: Vec<u64>,
+    pub timestamp: u64,
+    pub elapsed_seconds: f64,
+    pub adjacency_matrix: Vec<Vec<u8>>,
+}
+
+impl JournalRecord {
+    /// Create a record for a run that has just ended, reading the learned structure from `net`.
+    pub fn new<T: NetworkProcess>(
+        algorithm: &str,
+        hyperparameters: Vec<(String, String)>,
+        dataset: &Dataset,
+        seeds: Vec<u64>,
+        elapsed_seconds: f64,
+        net: &T,
+    ) -> JournalRecord {
+        let n_nodes = net.get_number_of_nodes();
+        let mut adjacency_matrix = vec![vec![0; n_nodes]; n_nodes];
+        for (parent, child) in net.edges() {
+            adjacency_matrix[parent][child] = 1;
+        }
+        JournalRecord {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            algorithm: algorithm.to_string(),
+            hyperparameters,
+            dataset: DatasetFingerprint::new(dataset),
+            seeds,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |x| x.as_secs()),
+            elapsed_seconds,
+            adjacency_matrix,
+        }
+    }
+
+    /// Serialize the record as a single-line JSON object.
+    pub fn to_json(&self) -> String {
+        let hyperparameters: serde_json::Map<String, serde_json::Value> = self
+            .hyperparameters
+            .iter()
+            .map(|(name, value)| (name.clone(), json!(value)))
+            .collect();
+        json!({
+            "version": self.version,
+            "algorithm": self.algorithm,
+            "hyperparameters": hyperparameters,
+            "dataset": {
+                "n_trajectories": self.dataset.n_trajectories,
+                "n_events": self.dataset.n_events,
+                "hash": format!("{:016x}", self.dataset.hash),
+            },
+            "seeds": self.seeds,
+            "timestamp": self.timestamp,
+            "elapsed_seconds": self.elapsed_seconds,
+            "adjacency_matrix": self.adjacency_matrix,
+        })
+        .to_string()
+    }
+}
+
+/// Sink receiving the records of the learning runs.
+pub trait Journal: Send + Sync {
+    fn record(&self, record: &JournalRecord) -> io::Result<()>;
+}
+
+/// Journal appending one JSON object per line to a file.
+pub struct JsonLinesJournal {
+    file: Mutex<File>,
+}
+
+impl JsonLinesJournal {
+    /// Open (or create) the file at `path` in append mode.
+    pub fn new<P: AsRef<Path>>(path: P) -> io::Result<JsonLinesJournal> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(JsonLinesJournal {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl Journal for JsonLinesJournal {
+    fn record(&self, record: &JournalRecord) -> io::Result<()> {
+        let mut file = self.file.lock().unwrap();
+        writeln!(file, "{}", record.to_json())?;
+        file.flush()
+    }
+}
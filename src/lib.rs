@@ -3,6 +3,8 @@
 #[cfg(test)]
 extern crate approx;
 
+pub mod io;
+pub mod journal;
 pub mod parameter_learning;
 pub mod params;
 pub mod process;
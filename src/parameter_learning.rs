@@ -1,13 +1,50 @@
 //! Module containing methods used to learn the parameters.
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
 
 use ndarray::prelude::*;
+use rand::Rng;
+use rand_chacha::ChaCha8Rng;
+use statrs::distribution::{ContinuousCDF, Gamma, Normal};
 
 use crate::params::*;
-use crate::{process, tools::Dataset};
+use crate::process;
+use crate::tools::{Dataset, DatasetError, SuffStatsCache, Trajectory};
 
-use log::debug;
+use log::{debug, warn};
+use rayon::prelude::*;
+use thiserror::Error;
+
+/// Error types for parameter learning
+#[derive(Error, Debug, PartialEq)]
+pub enum ParameterLearningError {
+    #[error("Invalid tied nodes specification")]
+    InvalidTiedNodes(String),
+    #[error("Tied nodes with mismatched local models")]
+    MismatchedTiedNodes(String),
+    #[error("Invalid sufficient statistics")]
+    InvalidSufficientStatistics(String),
+    #[error("I/O error")]
+    Io(String),
+    #[error("Unknown node")]
+    UnknownNode(String),
+    #[error("Invalid dataset: {0}")]
+    InvalidDataset(DatasetError),
+    #[error("Configuration not observed in the dataset")]
+    MissingConfiguration(String),
+}
+
+/// Summary of the data used to fit the parameters of a node.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FitSummary {
+    /// Index of the node
+    pub node: usize,
+    /// Total number of transitions of the node
+    pub transitions: usize,
+    /// Total residence time of the node over all its states and parent configurations
+    pub exposure: f64,
+}
 
 /// It defines the required methods for learn the `Parameters` from data.
 pub trait ParameterLearning: Sync {
@@ -27,6 +64,206 @@ pub trait ParameterLearning: Sync {
         node: usize,
         parent_set: Option<BTreeSet<usize>>,
     ) -> Params;
+
+    /// Fit the parameter of the node labelled `label` over a `dataset` given a `parent_set`
+    ///
+    /// Same as [`fit`](ParameterLearning::fit), looking up the node with
+    /// [`get_node_index_by_label`](process::NetworkProcess::get_node_index_by_label). Returns
+    /// `ParameterLearningError::UnknownNode` if `net` has no node labelled `label`.
+    fn fit_by_label<T: process::NetworkProcess>(
+        &self,
+        net: &T,
+        dataset: &Dataset,
+        label: &str,
+        parent_set: Option<BTreeSet<usize>>,
+    ) -> Result<Params, ParameterLearningError> {
+        match net.get_node_index_by_label(label) {
+            Some(node) => Ok(self.fit(net, dataset, node, parent_set)),
+            None => Err(ParameterLearningError::UnknownNode(format!(
+                "no node labelled {}",
+                label
+            ))),
+        }
+    }
+
+    /// Fit the parameters as [`fit`](ParameterLearning::fit) does, returning an error instead of
+    /// panicking if the states of `node` or of its parents in `dataset` are outside their
+    /// domains.
+    ///
+    /// # Arguments
+    ///
+    /// * `net`: a `NetworkProcess` instance
+    /// * `dataset`: a dataset compatible with `net` used for computing the sufficient statistics
+    /// * `node`: the node index for which we want to learn the parameters
+    /// * `parent_set`: an `Option` containing the parent set used for learning the parameters of
+    ///   `node`. If `None`, the parent set defined in `net` will be used.
+    fn try_fit<T: process::NetworkProcess>(
+        &self,
+        net: &T,
+        dataset: &Dataset,
+        node: usize,
+        parent_set: Option<BTreeSet<usize>>,
+    ) -> Result<Params, ParameterLearningError> {
+        let parent_set = parent_set.unwrap_or_else(|| net.get_parent_set(node));
+        validate_dataset(net, dataset, node, &parent_set)
+            .map_err(ParameterLearningError::InvalidDataset)?;
+        Ok(self.fit(net, dataset, node, Some(parent_set)))
+    }
+
+    /// Compute the parameters of the `node` from already computed sufficient statistics
+    ///
+    /// # Arguments
+    ///
+    /// * `net`: a `NetworkProcess` instance
+    /// * `node`: the node index for which we want to compute the parameters
+    /// * `transitions`: number of transitions (`M`) computed by [`sufficient_statistics`]
+    /// * `residence_time`: residence time (`T`) computed by [`sufficient_statistics`]
+    fn fit_from_sufficient_statistics<T: process::NetworkProcess>(
+        &self,
+        net: &T,
+        node: usize,
+        transitions: Array3<usize>,
+        residence_time: Array2<f64>,
+    ) -> Params;
+
+    /// Compute the parameters of the `node` from the sufficient statistics of `parent_set`
+    ///
+    /// Same as [`fit_from_sufficient_statistics`](ParameterLearning::fit_from_sufficient_statistics)
+    /// for statistics computed with a parent set that may differ from the one defined in `net`,
+    /// e.g. the candidate parent sets of the structure learning algorithms. By default the parent
+    /// set is not used, since only the shape of the statistics matters; the learners whose
+    /// parameterization depends on the parents (e.g. [`NoisyOrMLE`]) override it.
+    ///
+    /// # Arguments
+    ///
+    /// * `net`: a `NetworkProcess` instance
+    /// * `node`: the node index for which we want to compute the parameters
+    /// * `parent_set`: the parent set used to compute the sufficient statistics
+    /// * `transitions`: number of transitions (`M`) computed by [`sufficient_statistics`]
+    /// * `residence_time`: residence time (`T`) computed by [`sufficient_statistics`]
+    fn fit_from_parent_set_statistics<T: process::NetworkProcess>(
+        &self,
+        net: &T,
+        node: usize,
+        parent_set: &BTreeSet<usize>,
+        transitions: Array3<usize>,
+        residence_time: Array2<f64>,
+    ) -> Params {
+        let _ = parent_set;
+        self.fit_from_sufficient_statistics(net, node, transitions, residence_time)
+    }
+
+    /// Compute the parameters of the `node` from the weighted sufficient statistics of
+    /// `parent_set`
+    ///
+    /// Same as [`fit_from_parent_set_statistics`](ParameterLearning::fit_from_parent_set_statistics)
+    /// for the statistics computed by [`weighted_sufficient_statistics`], e.g. pooled over
+    /// several nodes by [`TiedParameterLearning`].
+    ///
+    /// # Arguments
+    ///
+    /// * `net`: a `NetworkProcess` instance
+    /// * `node`: the node index for which we want to compute the parameters
+    /// * `parent_set`: the parent set used to compute the sufficient statistics
+    /// * `transitions`: weighted number of transitions (`M`)
+    /// * `residence_time`: weighted residence time (`T`)
+    fn fit_from_weighted_parent_set_statistics<T: process::NetworkProcess>(
+        &self,
+        net: &T,
+        node: usize,
+        parent_set: &BTreeSet<usize>,
+        transitions: Array3<f64>,
+        residence_time: Array2<f64>,
+    ) -> Params;
+
+    /// Name and value of each hyperparameter, e.g. recorded by the learners in a
+    /// [`Journal`](crate::journal::Journal). Empty by default.
+    fn hyperparameters(&self) -> Vec<(String, String)> {
+        vec![]
+    }
+
+    /// Fit one CIM for each segment of a time-inhomogeneous, piecewise-constant model
+    ///
+    /// # Arguments
+    ///
+    /// * `net`: a `NetworkProcess` instance
+    /// * `dataset`: a dataset compatible with `net` used for computing the sufficient statistics
+    /// * `node`: the node index for which we want to compute the parameters
+    /// * `parent_set`: an `Option` containing the parent set used for computing the parameters of
+    ///   `node`. If `None`, the parent set defined in `net` will be used.
+    /// * `boundaries`: the starting time of each segment; the first one must be `0.0`.
+    fn fit_piecewise<T: process::NetworkProcess>(
+        &self,
+        net: &T,
+        dataset: &Dataset,
+        node: usize,
+        parent_set: Option<BTreeSet<usize>>,
+        boundaries: &[f64],
+    ) -> Params {
+        //Use parent_set from parameter if present. Otherwise use parent_set from network.
+        let parent_set = match parent_set {
+            Some(p) => p,
+            None => net.get_parent_set(node),
+        };
+
+        let piecewise_cim =
+            piecewise_sufficient_statistics(net, dataset, node, &parent_set, boundaries)
+                .into_iter()
+                .zip(boundaries.iter())
+                .map(|((M, T), boundary)| {
+                    match self.fit_from_parent_set_statistics(net, node, &parent_set, M, T) {
+                        Params::DiscreteStatesContinousTime(dsct) => {
+                            (*boundary, dsct.get_expanded_cim().unwrap())
+                        }
+                    }
+                })
+                .collect();
+
+        let mut n: Params = net.get_node(node).clone();
+        match n {
+            Params::DiscreteStatesContinousTime(ref mut dsct) => {
+                dsct.set_piecewise_cim_unchecked(piecewise_cim);
+            }
+        };
+        return n;
+    }
+
+    /// Fit the parameters of every node of `net` using the parent sets defined in `net`
+    ///
+    /// The nodes are fitted in parallel and the learned parameters (CIM, transitions and
+    /// residence time) replace the ones in `net`. Returns a summary for each node, ordered by
+    /// node index.
+    ///
+    /// # Arguments
+    ///
+    /// * `net`: a `NetworkProcess` instance whose parameters will be replaced
+    /// * `dataset`: a dataset compatible with `net`
+    fn fit_all<T: process::NetworkProcess>(
+        &self,
+        net: &mut T,
+        dataset: &Dataset,
+    ) -> Vec<FitSummary> {
+        let learned_params: Vec<(usize, Params)> = net
+            .get_node_indices()
+            .into_par_iter()
+            .map(|node| (node, self.fit(&*net, dataset, node, None)))
+            .collect();
+
+        learned_params
+            .into_iter()
+            .map(|(node, params)| {
+                let summary = match &params {
+                    Params::DiscreteStatesContinousTime(dsct) => FitSummary {
+                        node,
+                        transitions: dsct.get_transitions().as_ref().map_or(0, |M| M.sum()),
+                        exposure: dsct.get_residence_time().as_ref().map_or(0.0, |T| T.sum()),
+                    },
+                };
+                *net.get_node_mut(node) = params;
+                summary
+            })
+            .collect()
+    }
 }
 
 /// Compute the sufficient statistics of a parameters computed from a dataset
@@ -38,6 +275,12 @@ pub trait ParameterLearning: Sync {
 /// * `node`: the node index for which we want to compute the sufficient statistics
 /// * `parent_set`: the set of nodes (identified by indices) we want to use as parents of `node`
 ///
+/// The trajectories generated under an intervention on `node` are skipped, since they carry no
+/// information about its dynamics. The last interval of a censored trajectory (see
+/// [`Trajectory::with_censored_end`]) contributes to the residence time but never to the
+/// transitions. The weights of the trajectories, if any, are ignored: see
+/// [`weighted_sufficient_statistics`].
+///
 /// # Return
 ///
 ///  * A tuple containing the number of transitions (`Array3<usize>`) and the residence time
@@ -48,17 +291,254 @@ pub fn sufficient_statistics<T: process::NetworkProcess>(
     node: usize,
     parent_set: &BTreeSet<usize>,
 ) -> (Array3<usize>, Array2<f64>) {
+    piecewise_sufficient_statistics(net, dataset, node, parent_set, &[0.0])
+        .pop()
+        .unwrap()
+}
+
+/// Compute the sufficient statistics of a parameters computed from a weighted dataset
+///
+/// Both the transitions and the residence time contributed by each trajectory are multiplied by
+/// its weight (see [`Dataset::with_weights`]); a dataset without weights gives each trajectory
+/// weight `1.0`.
+///
+/// # Arguments
+///
+/// * `net`: a `NetworkProcess` instance
+/// * `dataset`: a dataset compatible with `net` used for computing the sufficient statistics
+/// * `node`: the node index for which we want to compute the sufficient statistics
+/// * `parent_set`: the set of nodes (identified by indices) we want to use as parents of `node`
+///
+/// # Return
+///
+///  * A tuple containing the weighted number of transitions (`Array3<f64>`) and the weighted
+///    residence time (`Array2<f64>`).
+pub fn weighted_sufficient_statistics<T: process::NetworkProcess>(
+    net: &T,
+    dataset: &Dataset,
+    node: usize,
+    parent_set: &BTreeSet<usize>,
+) -> (Array3<f64>, Array2<f64>) {
+    fold_sufficient_statistics(net, dataset, node, parent_set, &[0.0], |weight| {
+        (weight, weight)
+    })
+    .pop()
+    .unwrap()
+}
+
+/// Compute the sufficient statistics of each segment of a piecewise-constant CIM
+///
+/// # Arguments
+///
+/// * `net`: a `NetworkProcess` instance
+/// * `dataset`: a dataset compatible with `net` used for computing the sufficient statistics
+/// * `node`: the node index for which we want to compute the sufficient statistics
+/// * `parent_set`: the set of nodes (identified by indices) we want to use as parents of `node`
+/// * `boundaries`: the (strictly increasing) starting time of each segment
+///
+/// The residence times are split among the segments they overlap, while each transition is
+/// assigned to the segment containing the time instant in which it happens.
+///
+/// # Return
+///
+///  * A vector containing, for each segment, the number of transitions (`Array3<usize>`) and the
+///    residence time (`Array2<f64>`).
+pub fn piecewise_sufficient_statistics<T: process::NetworkProcess>(
+    net: &T,
+    dataset: &Dataset,
+    node: usize,
+    parent_set: &BTreeSet<usize>,
+    boundaries: &[f64],
+) -> Vec<(Array3<usize>, Array2<f64>)> {
+    fold_sufficient_statistics(net, dataset, node, parent_set, boundaries, |_| (1.0, 1))
+}
+
+/// Events of a dataset stored in contiguous arrays, computed once and shared by the computations
+/// of the sufficient statistics.
+///
+/// [`sufficient_statistics`] walks every trajectory for each `(node, parent_set)` pair; a
+/// `DatasetStatistics` flattens the states of all the trajectories in a single matrix, together
+/// with the duration of each event, so that the statistics of a pair are folded from these arrays
+/// looking only at the columns of the node and of its parents. The results are the same of
+/// [`sufficient_statistics`] and [`weighted_sufficient_statistics`].
+///
+/// **Note:** the precomputation is bound to the dataset used to create it.
+#[derive(Clone, Debug)]
+pub struct DatasetStatistics {
+    //States of the variables for each event of each trajectory, stacked by rows.
+    states: Array2<usize>,
+    //Time spent by the process after each event; the last event of a trajectory has no duration.
+    durations: Array1<f64>,
+    //Rows of `states`, weight, intervened node and censoring of each trajectory.
+    trajectories: Vec<(std::ops::Range<usize>, f64, Option<usize>, bool)>,
+}
+
+impl DatasetStatistics {
+    /// Flatten the trajectories of `dataset` in one pass.
+    pub fn new(dataset: &Dataset) -> DatasetStatistics {
+        let n_variables = dataset
+            .get_trajectories()
+            .first()
+            .map_or(0, |trj| trj.get_events().shape()[1]);
+        let n_events: usize = dataset
+            .get_trajectories()
+            .iter()
+            .map(|trj| trj.get_time().len())
+            .sum();
+
+        let mut states: Array2<usize> = Array::zeros((n_events, n_variables));
+        let mut durations: Array1<f64> = Array::zeros(n_events);
+        let mut trajectories = Vec::with_capacity(dataset.get_trajectories().len());
+        let mut start = 0;
+        for (trj_idx, trj) in dataset.get_trajectories().iter().enumerate() {
+            let end = start + trj.get_time().len();
+            states
+                .slice_mut(s![start..end, ..])
+                .assign(trj.get_events());
+            for idx in 0..(trj.get_time().len() - 1) {
+                durations[start + idx] = trj.get_time()[idx + 1] - trj.get_time()[idx];
+            }
+            let weight = dataset
+                .get_weights()
+                .map_or(1.0, |weights| weights[trj_idx]);
+            let intervention = trj.get_intervention().map(|(node, _)| node);
+            trajectories.push((start..end, weight, intervention, trj.is_censored()));
+            start = end;
+        }
+
+        DatasetStatistics {
+            states,
+            durations,
+            trajectories,
+        }
+    }
+
+    /// Number of events stored, summed over all the trajectories.
+    pub fn get_number_of_events(&self) -> usize {
+        self.durations.len()
+    }
+
+    /// Same as [`sufficient_statistics`] on the dataset used to create `self`.
+    pub fn sufficient_statistics<T: process::NetworkProcess>(
+        &self,
+        net: &T,
+        node: usize,
+        parent_set: &BTreeSet<usize>,
+    ) -> (Array3<usize>, Array2<f64>) {
+        self.fold(net, node, parent_set, |_| (1.0, 1))
+    }
+
+    /// Same as [`weighted_sufficient_statistics`] on the dataset used to create `self`.
+    pub fn weighted_sufficient_statistics<T: process::NetworkProcess>(
+        &self,
+        net: &T,
+        node: usize,
+        parent_set: &BTreeSet<usize>,
+    ) -> (Array3<f64>, Array2<f64>) {
+        self.fold(net, node, parent_set, |weight| (weight, weight))
+    }
+
+    //`weigh` maps the weight of a trajectory to the factor of its residence times and to the
+    //count of each of its transitions.
+    fn fold<T: process::NetworkProcess, A: Copy + Default + std::ops::AddAssign>(
+        &self,
+        net: &T,
+        node: usize,
+        parent_set: &BTreeSet<usize>,
+        weigh: impl Fn(f64) -> (f64, A),
+    ) -> (Array3<A>, Array2<f64>) {
+        let (vector_to_idx, (mut M, mut T)) = empty_sufficient_statistics(net, node, parent_set);
+        //Only the columns of the parents contribute to the index of the configuration.
+        let strides: Vec<(usize, usize)> = parent_set
+            .iter()
+            .map(|parent| (*parent, vector_to_idx[*parent]))
+            .collect();
+
+        for (rows, weight, intervention, censored) in self.trajectories.iter() {
+            if *intervention == Some(node) {
+                continue;
+            }
+            let (weight, count) = weigh(*weight);
+            for idx in rows.start..(rows.end - 1) {
+                let u: usize = strides
+                    .iter()
+                    .map(|(parent, stride)| self.states[[idx, *parent]] * stride)
+                    .sum();
+                let (from, to) = (self.states[[idx, node]], self.states[[idx + 1, node]]);
+                T[[u, from]] += weight * self.durations[idx];
+                //The change of state at the censoring time is not a transition.
+                if from != to && !(*censored && idx + 2 == rows.end) {
+                    M[[u, from, to]] += count;
+                }
+            }
+        }
+        (M, T)
+    }
+}
+
+//Walk the trajectories of the dataset accumulating the sufficient statistics of each segment.
+//`weigh` maps the weight of a trajectory (1.0 when the dataset has no weights) to the factor of
+//its residence times and to the count of each of its transitions: the transitions are counted as
+//usize for the unweighted statistics and as f64 only for the weighted ones.
+fn fold_sufficient_statistics<
+    T: process::NetworkProcess,
+    A: Copy + Default + std::ops::AddAssign,
+>(
+    net: &T,
+    dataset: &Dataset,
+    node: usize,
+    parent_set: &BTreeSet<usize>,
+    boundaries: &[f64],
+    weigh: impl Fn(f64) -> (f64, A),
+) -> Vec<(Array3<A>, Array2<f64>)> {
+    if boundaries.is_empty() || boundaries.windows(2).any(|x| x[0] >= x[1]) {
+        panic!("boundaries must be non-empty and strictly increasing");
+    }
+
+    let (vector_to_idx, empty_stats) = empty_sufficient_statistics(net, node, parent_set);
+
+    //Number of transition and residence time given a specific configuration of the parent set,
+    //for each segment
+    let mut stats: Vec<(Array3<A>, Array2<f64>)> =
+        boundaries.iter().map(|_| empty_stats.clone()).collect();
+
+    //Compute the sufficient statistics
+    for (trj_idx, trj) in dataset.get_trajectories().iter().enumerate() {
+        let (weight, count) = weigh(
+            dataset
+                .get_weights()
+                .map_or(1.0, |weights| weights[trj_idx]),
+        );
+        fold_trajectory(
+            &mut stats,
+            trj,
+            node,
+            &vector_to_idx,
+            boundaries,
+            weight,
+            count,
+        );
+    }
+
+    return stats;
+}
+
+//Vector used to convert a specific configuration of the parent_set to the corresponding index
+//for CIM, M and T, together with zero-filled M and T.
+fn empty_sufficient_statistics<T: process::NetworkProcess, A: Clone + Default>(
+    net: &T,
+    node: usize,
+    parent_set: &BTreeSet<usize>,
+) -> (Array1<usize>, (Array3<A>, Array2<f64>)) {
     //Get the number of values assumable by the node
-    let node_domain = net.get_node(node.clone()).get_reserved_space_as_parent();
+    let node_domain = net.get_node(node).get_reserved_space_as_parent();
 
     //Get the number of values assumable by each parent of the node
     let parentset_domain: Vec<usize> = parent_set
         .iter()
-        .map(|x| net.get_node(x.clone()).get_reserved_space_as_parent())
+        .map(|x| net.get_node(*x).get_reserved_space_as_parent())
         .collect();
 
-    //Vector used to convert a specific configuration of the parent_set to the corresponding index
-    //for CIM, M and T
     let mut vector_to_idx: Array1<usize> = Array::zeros(net.get_number_of_nodes());
 
     parent_set
@@ -69,38 +549,218 @@ pub fn sufficient_statistics<T: process::NetworkProcess>(
             acc * x
         });
 
-    //Number of transition given a specific configuration of the parent set
-    let mut M: Array3<usize> =
-        Array::zeros((parentset_domain.iter().product(), node_domain, node_domain));
+    let n_configurations: usize = parentset_domain.iter().product();
+    (
+        vector_to_idx,
+        (
+            Array::default((n_configurations, node_domain, node_domain)),
+            Array::zeros((n_configurations, node_domain)),
+        ),
+    )
+}
 
-    //Residence time given a specific configuration of the parent set
-    let mut T: Array2<f64> = Array::zeros((parentset_domain.iter().product(), node_domain));
+//Add the transitions of `trj`, each one counted as `count`, and its residence times, multiplied by
+//`weight`, to the sufficient statistics of each segment. The trajectories generated under an
+//intervention on `node` are skipped and the change of state at the censoring time of a censored
+//trajectory is ignored.
+fn fold_trajectory<A: Copy + std::ops::AddAssign>(
+    stats: &mut [(Array3<A>, Array2<f64>)],
+    trj: &Trajectory,
+    node: usize,
+    vector_to_idx: &Array1<usize>,
+    boundaries: &[f64],
+    weight: f64,
+    count: A,
+) {
+    if trj.get_intervention().is_some_and(|(x, _)| x == node) {
+        return;
+    }
 
-    //Compute the sufficient statistics
-    for trj in dataset.get_trajectories().iter() {
-        for idx in 0..(trj.get_time().len() - 1) {
-            let t1 = trj.get_time()[idx];
-            let t2 = trj.get_time()[idx + 1];
-            let ev1 = trj.get_events().row(idx);
-            let ev2 = trj.get_events().row(idx + 1);
-            let idx1 = vector_to_idx.dot(&ev1);
-
-            T[[idx1, ev1[node]]] += t2 - t1;
-            if ev1[node] != ev2[node] {
-                M[[idx1, ev1[node], ev2[node]]] += 1;
-            }
+    //Segment containing the time instant t. Instants before the first boundary belong to the
+    //first segment.
+    let segment_of = |t: f64| boundaries.iter().rposition(|x| *x <= t).unwrap_or(0);
+
+    let n_intervals = trj.get_time().len() - 1;
+    for idx in 0..n_intervals {
+        let t1 = trj.get_time()[idx];
+        let t2 = trj.get_time()[idx + 1];
+        let ev1 = trj.get_events().row(idx);
+        let ev2 = trj.get_events().row(idx + 1);
+        let idx1 = vector_to_idx.dot(&ev1);
+
+        //Split the residence time among the overlapped segments.
+        let (first_segment, last_segment) = (segment_of(t1), segment_of(t2));
+        for segment in first_segment..=last_segment {
+            let start = if segment == first_segment {
+                t1
+            } else {
+                boundaries[segment]
+            };
+            let end = if segment == last_segment {
+                t2
+            } else {
+                boundaries[segment + 1]
+            };
+            stats[segment].1[[idx1, ev1[node]]] += weight * (end - start);
+        }
+        if ev1[node] != ev2[node] && !(trj.is_censored() && idx + 1 == n_intervals) {
+            stats[last_segment].0[[idx1, ev1[node], ev2[node]]] += count;
+        }
+    }
+}
+
+/// Sufficient statistics of a node, given a parent set, updated one trajectory at a time.
+///
+/// Folding the trajectories of a dataset in order gives the same statistics computed by
+/// [`sufficient_statistics`] on the whole dataset, without walking the already seen trajectories
+/// again when new data arrive.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OnlineStatistics {
+    node: usize,
+    parent_set: BTreeSet<usize>,
+    vector_to_idx: Array1<usize>,
+    transitions: Array3<usize>,
+    residence_time: Array2<f64>,
+}
+
+impl OnlineStatistics {
+    /// Create empty sufficient statistics
+    ///
+    /// # Arguments
+    ///
+    /// * `net`: a `NetworkProcess` instance defining the domains of `node` and of its parents
+    /// * `node`: the node index for which we want to compute the sufficient statistics
+    /// * `parent_set`: the set of nodes (identified by indices) we want to use as parents of
+    ///   `node`
+    pub fn new<T: process::NetworkProcess>(
+        net: &T,
+        node: usize,
+        parent_set: BTreeSet<usize>,
+    ) -> OnlineStatistics {
+        let (vector_to_idx, (transitions, residence_time)) =
+            empty_sufficient_statistics(net, node, &parent_set);
+        OnlineStatistics {
+            node,
+            parent_set,
+            vector_to_idx,
+            transitions,
+            residence_time,
+        }
+    }
+
+    pub fn get_node(&self) -> usize {
+        self.node
+    }
+
+    pub fn get_parent_set(&self) -> &BTreeSet<usize> {
+        &self.parent_set
+    }
+
+    /// Number of transitions (`M`) and residence time (`T`) collected so far.
+    pub fn get_sufficient_statistics(&self) -> (Array3<usize>, Array2<f64>) {
+        (self.transitions.clone(), self.residence_time.clone())
+    }
+
+    /// Add the transitions and the residence times of `trajectory`
+    ///
+    /// The trajectories generated under an intervention on the node are skipped, as done by
+    /// [`sufficient_statistics`].
+    pub fn update(&mut self, trajectory: &Trajectory) {
+        if trajectory.get_events().shape()[1] != self.vector_to_idx.len() {
+            panic!("The trajectory must represent the same number of variables of the network");
+        }
+        let mut stats = [(
+            std::mem::take(&mut self.transitions),
+            std::mem::take(&mut self.residence_time),
+        )];
+        fold_trajectory(
+            &mut stats,
+            trajectory,
+            self.node,
+            &self.vector_to_idx,
+            &[0.0],
+            1.0,
+            1,
+        );
+        let [(transitions, residence_time)] = stats;
+        self.transitions = transitions;
+        self.residence_time = residence_time;
+    }
+
+    /// Add the statistics collected by `other`, e.g. on a different batch of trajectories
+    ///
+    /// Both the statistics must refer to the same node and parent set.
+    pub fn merge(&mut self, other: &OnlineStatistics) -> Result<(), ParameterLearningError> {
+        if self.node != other.node
+            || self.parent_set != other.parent_set
+            || self.vector_to_idx != other.vector_to_idx
+            || self.transitions.dim() != other.transitions.dim()
+        {
+            return Err(ParameterLearningError::InvalidSufficientStatistics(
+                format!(
+                "Cannot merge the statistics of node {} given {:?} with the ones of node {} given \
+                 {:?}",
+                self.node, self.parent_set, other.node, other.parent_set
+            ),
+            ));
         }
+        self.transitions += &other.transitions;
+        self.residence_time += &other.residence_time;
+        Ok(())
+    }
+
+    /// Estimate the parameters of the node from the statistics collected so far
+    ///
+    /// # Arguments
+    ///
+    /// * `net`: the `NetworkProcess` instance used to create the statistics
+    /// * `parameter_learning`: the method used to estimate the parameters (e.g. [`MLE`] or
+    ///   [`BayesianApproach`])
+    pub fn to_params<T: process::NetworkProcess, P: ParameterLearning>(
+        &self,
+        net: &T,
+        parameter_learning: &P,
+    ) -> Params {
+        let (M, T) = self.get_sufficient_statistics();
+        parameter_learning.fit_from_parent_set_statistics(net, self.node, &self.parent_set, M, T)
     }
+}
+
+//Check that the states of `node` and of its parents in `dataset` are inside their domains.
+fn validate_dataset<T: process::NetworkProcess>(
+    net: &T,
+    dataset: &Dataset,
+    node: usize,
+    parent_set: &BTreeSet<usize>,
+) -> Result<(), DatasetError> {
+    let nodes = std::iter::once(node).chain(parent_set.iter().cloned());
+    dataset.validate_nodes(net, nodes)
+}
 
-    return (M, T);
+//Panic with a descriptive message if the states of `node` or of its parents in `dataset` are
+//outside their domains.
+fn check_dataset<T: process::NetworkProcess>(
+    net: &T,
+    dataset: &Dataset,
+    node: usize,
+    parent_set: &BTreeSet<usize>,
+) {
+    if let Err(e) = validate_dataset(net, dataset, node, parent_set) {
+        panic!("{}", e);
+    }
 }
 
 /// Maximum Likelihood Estimation method for learning the parameters given a dataset.
 ///
+/// The rates of a state never visited under a parent configuration cannot be estimated, since
+/// its residence time is zero. They are replaced by the rates of the state estimated pooling the
+/// data of all the parent configurations or, if the state never occurs in the dataset, by uniform
+/// rates with unitary exit rate. Use [`try_fit`](ParameterLearning::try_fit) to get an error
+/// instead.
+///
 /// # Example
 /// ```rust
 ///
-/// use std::collections::BTreeSet;
 /// use reCTBN::process::NetworkProcess;
 /// use reCTBN::params;
 /// use reCTBN::process::ctbn::*;
@@ -109,22 +769,9 @@ pub fn sufficient_statistics<T: process::NetworkProcess>(
 /// use reCTBN::parameter_learning::*;
 /// use approx::AbsDiffEq;
 ///
-/// //Create the domain for a discrete node
-/// let mut domain = BTreeSet::new();
-/// domain.insert(String::from("A"));
-/// domain.insert(String::from("B"));
-///
-/// //Create the parameters for a discrete node using the domain
-/// let param = params::DiscreteStatesContinousTimeParams::new("X1".to_string(), domain);
-///
-/// //Create the node using the parameters
-/// let X1 = params::Params::DiscreteStatesContinousTime(param);
-///
-/// let mut domain = BTreeSet::new();
-/// domain.insert(String::from("A"));
-/// domain.insert(String::from("B"));
-/// let param = params::DiscreteStatesContinousTimeParams::new("X2".to_string(), domain);
-/// let X2 = params::Params::DiscreteStatesContinousTime(param);
+/// //Create two discrete nodes with states A and B
+/// let X1 = params::discrete_node("X1", &["A", "B"]);
+/// let X2 = params::discrete_node("X2", &["A", "B"]);
 ///
 /// //Initialize a ctbn
 /// let mut net = CtbnNetwork::new();
@@ -196,25 +843,62 @@ impl ParameterLearning for MLE {
             Some(p) => p,
             None => net.get_parent_set(node),
         };
+        check_dataset(net, dataset, node, &parent_set);
 
+        if dataset.get_weights().is_some() {
+            let (M, T) = weighted_sufficient_statistics(net, dataset, node, &parent_set);
+            return self.fit_from_weighted_sufficient_statistics(net, node, M, T);
+        }
         let (M, T) = sufficient_statistics(net, dataset, node.clone(), &parent_set);
-        //Compute the CIM as M[i,x,y]/T[i,x]
-        let mut CIM: Array3<f64> = Array::zeros((M.shape()[0], M.shape()[1], M.shape()[2]));
-        CIM.axis_iter_mut(Axis(2))
-            .zip(M.mapv(|x| x as f64).axis_iter(Axis(2)))
-            .for_each(|(mut C, m)| C.assign(&(&m / &T)));
-
-        //Set the diagonal of the inner matrices to the the row sum multiplied by -1
-        let tmp_diag_sum: Array2<f64> = CIM.sum_axis(Axis(2)).mapv(|x| x * -1.0);
-        CIM.outer_iter_mut()
-            .zip(tmp_diag_sum.outer_iter())
-            .for_each(|(mut C, diag)| {
-                C.diag_mut().assign(&diag);
-            });
-
-        let mut n: Params = net.get_node(node).clone();
+        self.fit_from_sufficient_statistics(net, node, M, T)
+    }
 
-        match n {
+    /// Same as [`ParameterLearning::try_fit`], also returning an error if a state of the node is
+    /// never visited under some configuration of the parent set.
+    fn try_fit<T: process::NetworkProcess>(
+        &self,
+        net: &T,
+        dataset: &Dataset,
+        node: usize,
+        parent_set: Option<BTreeSet<usize>>,
+    ) -> Result<Params, ParameterLearningError> {
+        let parent_set = match parent_set {
+            Some(p) => p,
+            None => net.get_parent_set(node),
+        };
+
+        validate_dataset(net, dataset, node, &parent_set)
+            .map_err(ParameterLearningError::InvalidDataset)?;
+        Ok(match dataset.get_weights() {
+            Some(_) => {
+                let (M, T) = weighted_sufficient_statistics(net, dataset, node, &parent_set);
+                MLE::check_observed_configurations(net, node, &M, &T)?;
+                self.fit_from_weighted_sufficient_statistics(net, node, M, T)
+            }
+            None => {
+                let (M, T) = sufficient_statistics(net, dataset, node, &parent_set);
+                MLE::check_observed_configurations(net, node, &M, &T)?;
+                self.fit_from_sufficient_statistics(net, node, M, T)
+            }
+        })
+    }
+
+    fn fit_from_sufficient_statistics<T: process::NetworkProcess>(
+        &self,
+        net: &T,
+        node: usize,
+        transitions: Array3<usize>,
+        residence_time: Array2<f64>,
+    ) -> Params {
+        let mut n: Params = net.get_node(node).clone();
+        let (M, T) = tie_configurations(&mut n, transitions, residence_time);
+        let CIM = MLE::cim(&M.mapv(|x| x as f64), &T);
+
+        MLE::unobserved_configurations(&T)
+            .iter()
+            .for_each(|(u, x)| warn_unobserved_configuration(n.get_label(), *u, *x));
+
+        match n {
             Params::DiscreteStatesContinousTime(ref mut dsct) => {
                 dsct.set_cim_unchecked(CIM);
                 dsct.set_transitions(M);
@@ -223,20 +907,688 @@ impl ParameterLearning for MLE {
         };
         return n;
     }
+
+    fn fit_from_weighted_parent_set_statistics<T: process::NetworkProcess>(
+        &self,
+        net: &T,
+        node: usize,
+        _parent_set: &BTreeSet<usize>,
+        transitions: Array3<f64>,
+        residence_time: Array2<f64>,
+    ) -> Params {
+        self.fit_from_weighted_sufficient_statistics(net, node, transitions, residence_time)
+    }
+}
+
+/// Hyperparameter for the priori over the number of transitions.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Alpha {
+    /// Total pseudo count of each transition, spread uniformly over the parent configurations.
+    Constant(f64),
+    /// Pseudo count for each parent configuration, starting state and ending state of the node.
+    /// The shape must match the one of the transitions `M`.
+    Matrix(Array3<f64>),
+}
+
+impl From<f64> for Alpha {
+    fn from(alpha: f64) -> Alpha {
+        Alpha::Constant(alpha)
+    }
+}
+
+impl From<usize> for Alpha {
+    fn from(alpha: usize) -> Alpha {
+        Alpha::Constant(alpha as f64)
+    }
+}
+
+impl Alpha {
+    /// Pseudo count for each entry of a transitions `M` with the given shape
+    ///
+    /// # Arguments
+    ///
+    /// * `shape`: shape `(parent_configurations, node_states, node_states)` of the transitions
+    ///   `M`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `self` is an `Alpha::Matrix` whose shape differs from `shape`.
+    pub fn transitions_prior(&self, shape: (usize, usize, usize)) -> Array3<f64> {
+        match self {
+            Alpha::Constant(alpha) => Array3::from_elem(shape, alpha / shape.0 as f64),
+            Alpha::Matrix(alpha) => {
+                if alpha.dim() != shape {
+                    panic!(
+                        "Alpha::Matrix has shape {:?} but the transitions have shape {:?}",
+                        alpha.dim(),
+                        shape
+                    );
+                }
+                alpha.clone()
+            }
+        }
+    }
+}
+
+/// Hyperparameter for the priori over the residence time.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Tau {
+    /// Total pseudo residence time, spread uniformly over the parent configurations.
+    Constant(f64),
+    /// Pseudo residence time for each parent configuration (rows) and state (columns) of the
+    /// node. The shape must match the one of the residence time `T`.
+    Matrix(Array2<f64>),
+}
+
+impl From<f64> for Tau {
+    fn from(tau: f64) -> Tau {
+        Tau::Constant(tau)
+    }
+}
+
+impl Tau {
+    /// Pseudo residence time for each entry of a residence time `T` with the given shape
+    ///
+    /// # Arguments
+    ///
+    /// * `shape`: shape `(parent_configurations, node_states)` of the residence time `T`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `self` is a `Tau::Matrix` whose shape differs from `shape`.
+    pub fn residence_time_prior(&self, shape: (usize, usize)) -> Array2<f64> {
+        match self {
+            Tau::Constant(tau) => Array2::from_elem(shape, tau / shape.0 as f64),
+            Tau::Matrix(tau) => {
+                if tau.dim() != shape {
+                    panic!(
+                        "Tau::Matrix has shape {:?} but the residence time has shape {:?}",
+                        tau.dim(),
+                        shape
+                    );
+                }
+                tau.clone()
+            }
+        }
+    }
+}
+
+impl MLE {
+    /// Compute the parameters of the `node` from weighted sufficient statistics
+    ///
+    /// The transitions stored in the returned parameters are rounded to the nearest integer.
+    ///
+    /// # Arguments
+    ///
+    /// * `net`: a `NetworkProcess` instance
+    /// * `node`: the node index for which we want to compute the parameters
+    /// * `transitions`: weighted number of transitions (`M`) computed by
+    ///   [`weighted_sufficient_statistics`]
+    /// * `residence_time`: weighted residence time (`T`) computed by
+    ///   [`weighted_sufficient_statistics`]
+    pub fn fit_from_weighted_sufficient_statistics<T: process::NetworkProcess>(
+        &self,
+        net: &T,
+        node: usize,
+        transitions: Array3<f64>,
+        residence_time: Array2<f64>,
+    ) -> Params {
+        let mut n: Params = net.get_node(node).clone();
+        let (M, T) = tie_configurations(&mut n, transitions, residence_time);
+        let CIM = MLE::cim(&M, &T);
+
+        MLE::unobserved_configurations(&T)
+            .iter()
+            .for_each(|(u, x)| warn_unobserved_configuration(n.get_label(), *u, *x));
+
+        match n {
+            Params::DiscreteStatesContinousTime(ref mut dsct) => {
+                dsct.set_cim_unchecked(CIM);
+                dsct.set_transitions(M.mapv(|x| x.round() as usize));
+                dsct.set_residence_time(T);
+            }
+        };
+        n
+    }
+
+    //Fail if a state of `node` is never visited under some configuration of the parent set. The
+    //states only need to be visited under some configuration of each group.
+    fn check_observed_configurations<
+        T: process::NetworkProcess,
+        A: Clone + Default + std::ops::AddAssign,
+    >(
+        net: &T,
+        node: usize,
+        M: &Array3<A>,
+        T: &Array2<f64>,
+    ) -> Result<(), ParameterLearningError> {
+        let (_, tied_T) = tie_configurations(&mut net.get_node(node).clone(), M.clone(), T.clone());
+        match MLE::unobserved_configurations(&tied_T).first() {
+            Some((u, x)) => Err(ParameterLearningError::MissingConfiguration(format!(
+                "Node {} never visited state {} under the parent configuration {}",
+                net.get_node(node).get_label(),
+                x,
+                u
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    //Pairs (parent configuration, state) without residence time.
+    fn unobserved_configurations(T: &Array2<f64>) -> Vec<(usize, usize)> {
+        T.indexed_iter()
+            .filter(|(_, t)| **t == 0.0)
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    //Compute the CIM as M[i,x,y]/T[i,x]
+    fn cim(M: &Array3<f64>, T: &Array2<f64>) -> Array3<f64> {
+        let mut CIM: Array3<f64> = Array::zeros((M.shape()[0], M.shape()[1], M.shape()[2]));
+        CIM.axis_iter_mut(Axis(2))
+            .zip(M.axis_iter(Axis(2)))
+            .for_each(|(mut C, m)| C.assign(&(&m / T)));
+
+        //Replace the rows without residence time with the rates estimated ignoring the parent set
+        //or, if the state has never been visited, with uniform rates.
+        let (pooled_M, pooled_T) = (M.sum_axis(Axis(0)), T.sum_axis(Axis(0)));
+        let domain = M.shape()[1];
+        for (u, x) in MLE::unobserved_configurations(T) {
+            let row = if pooled_T[x] > 0.0 {
+                &pooled_M.row(x) / pooled_T[x]
+            } else {
+                Array1::from_shape_fn(domain, |y| {
+                    if y != x {
+                        1.0 / (domain - 1) as f64
+                    } else {
+                        0.0
+                    }
+                })
+            };
+            CIM.slice_mut(s![u, x, ..]).assign(&row);
+        }
+
+        //Set the diagonal of the inner matrices to the the row sum multiplied by -1
+        let tmp_diag_sum: Array2<f64> = CIM.sum_axis(Axis(2)).mapv(|x| x * -1.0);
+        CIM.outer_iter_mut()
+            .zip(tmp_diag_sum.outer_iter())
+            .for_each(|(mut C, diag)| {
+                C.diag_mut().assign(&diag);
+            });
+        CIM
+    }
+}
+
+/// Maximum Likelihood Estimation with additive (Laplace-style) smoothing.
+///
+/// Each off-diagonal rate is estimated as
+/// `(M[u, x, y] + pseudo_transitions) / (T[u, x] + pseudo_time)`, so that the rates of rarely
+/// observed parent configurations are shrunk towards `pseudo_transitions / pseudo_time` instead
+/// of being zero or undefined.
+///
+/// The pseudo-counts are added as they are to every parent configuration. [`BayesianApproach`],
+/// instead, interprets `alpha` and `tau` as totals spread over the parent configurations, so that
+/// the strength of its prior does not depend on the size of the parent set. When both the
+/// pseudo-counts go to zero the estimates converge to the ones of [`MLE`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct SmoothedMLE {
+    pub pseudo_transitions: f64,
+    pub pseudo_time: f64,
+}
+
+impl SmoothedMLE {
+    /// Learn the parameters of `node` from the weighted sufficient statistics computed by
+    /// [`weighted_sufficient_statistics`]. The stored transitions are rounded to the nearest
+    /// integer.
+    pub fn fit_from_weighted_sufficient_statistics<T: process::NetworkProcess>(
+        &self,
+        net: &T,
+        node: usize,
+        transitions: Array3<f64>,
+        residence_time: Array2<f64>,
+    ) -> Params {
+        let mut n: Params = net.get_node(node).clone();
+        let (M, T) = tie_configurations(&mut n, transitions, residence_time);
+        let CIM = self.cim(&M, &T);
+
+        match n {
+            Params::DiscreteStatesContinousTime(ref mut dsct) => {
+                dsct.set_cim_unchecked(CIM);
+                dsct.set_transitions(M.mapv(|x| x.round() as usize));
+                dsct.set_residence_time(T);
+            }
+        };
+        n
+    }
+
+    //Compute the CIM as (M[i,x,y] + pseudo_transitions)/(T[i,x] + pseudo_time)
+    fn cim(&self, M: &Array3<f64>, T: &Array2<f64>) -> Array3<f64> {
+        if self.pseudo_transitions < 0.0 || self.pseudo_time < 0.0 {
+            panic!("pseudo_transitions and pseudo_time must be >= 0.0");
+        }
+        let M = Array3::from_shape_fn(M.dim(), |(u, x, y)| {
+            if x == y {
+                0.0
+            } else {
+                M[[u, x, y]] + self.pseudo_transitions
+            }
+        });
+        MLE::cim(&M, &(T + self.pseudo_time))
+    }
+}
+
+impl ParameterLearning for SmoothedMLE {
+    fn fit<T: process::NetworkProcess>(
+        &self,
+        net: &T,
+        dataset: &Dataset,
+        node: usize,
+        parent_set: Option<BTreeSet<usize>>,
+    ) -> Params {
+        debug!(
+            "Learning params for node {} with parent set {:?} with SmoothedMLE",
+            node, parent_set
+        );
+        //Use parent_set from parameter if present. Otherwise use parent_set from network.
+        let parent_set = match parent_set {
+            Some(p) => p,
+            None => net.get_parent_set(node),
+        };
+        check_dataset(net, dataset, node, &parent_set);
+
+        if dataset.get_weights().is_some() {
+            let (M, T) = weighted_sufficient_statistics(net, dataset, node, &parent_set);
+            return self.fit_from_weighted_sufficient_statistics(net, node, M, T);
+        }
+        let (M, T) = sufficient_statistics(net, dataset, node, &parent_set);
+        self.fit_from_sufficient_statistics(net, node, M, T)
+    }
+
+    fn fit_from_sufficient_statistics<T: process::NetworkProcess>(
+        &self,
+        net: &T,
+        node: usize,
+        transitions: Array3<usize>,
+        residence_time: Array2<f64>,
+    ) -> Params {
+        let mut n: Params = net.get_node(node).clone();
+        let (M, T) = tie_configurations(&mut n, transitions, residence_time);
+        let CIM = self.cim(&M.mapv(|x| x as f64), &T);
+
+        match n {
+            Params::DiscreteStatesContinousTime(ref mut dsct) => {
+                dsct.set_cim_unchecked(CIM);
+                dsct.set_transitions(M);
+                dsct.set_residence_time(T);
+            }
+        };
+        n
+    }
+
+    fn fit_from_weighted_parent_set_statistics<T: process::NetworkProcess>(
+        &self,
+        net: &T,
+        node: usize,
+        _parent_set: &BTreeSet<usize>,
+        transitions: Array3<f64>,
+        residence_time: Array2<f64>,
+    ) -> Params {
+        self.fit_from_weighted_sufficient_statistics(net, node, transitions, residence_time)
+    }
+
+    fn hyperparameters(&self) -> Vec<(String, String)> {
+        vec![
+            (
+                String::from("pseudo_transitions"),
+                self.pseudo_transitions.to_string(),
+            ),
+            (String::from("pseudo_time"), self.pseudo_time.to_string()),
+        ]
+    }
+}
+
+/// Maximum Likelihood Estimation of the noisy-OR parameterization of the CIM (see
+/// [`DiscreteStatesContinousTimeParams::set_noisy_or_cim`]).
+///
+/// The base CIM and the rate multipliers of the parents are fitted by coordinate ascent on the
+/// likelihood: each step has a closed form and never decreases the likelihood. The multipliers
+/// of the first state of each parent are fixed to `1.0`, so that the base CIM is the CIM of the
+/// configuration with every parent in its first state. The rows of the base CIM never visited
+/// in the dataset fall back to uniform rates with exit rate `1.0`, as done by [`MLE`].
+///
+/// # Attributes
+///
+/// * `max_iterations`: maximum number of sweeps over the base CIM and the multipliers.
+/// * `tolerance`: the fit stops when no parameter changes by more than `tolerance`, relatively.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NoisyOrMLE {
+    pub max_iterations: usize,
+    pub tolerance: f64,
+}
+
+impl NoisyOrMLE {
+    //Coordinate ascent on the likelihood of each row of the base CIM and of the multipliers of
+    //the same starting state.
+    fn fit_factors(
+        &self,
+        M: &Array3<f64>,
+        T: &Array2<f64>,
+        parentset_domain: &[usize],
+    ) -> (Array2<f64>, Vec<Array2<f64>>) {
+        let (n_configurations, n_states, _) = M.dim();
+        //State of each parent under each parent configuration.
+        let configurations: Vec<Vec<usize>> = (0..n_configurations)
+            .map(|u| {
+                let mut rest = u;
+                parentset_domain
+                    .iter()
+                    .map(|card| {
+                        let state = rest % card;
+                        rest /= card;
+                        state
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut base_cim: Array2<f64> = Array::zeros((n_states, n_states));
+        let mut rate_multipliers: Vec<Array2<f64>> = parentset_domain
+            .iter()
+            .map(|card| Array::ones((*card, n_states)))
+            .collect();
+
+        for x in 0..n_states {
+            let exits: Vec<f64> = (0..n_configurations)
+                .map(|u| M.slice(s![u, x, ..]).sum() - M[[u, x, x]])
+                .collect();
+            if T.column(x).sum() == 0.0 {
+                base_cim.row_mut(x).fill(1.0 / (n_states - 1) as f64);
+                base_cim[[x, x]] = -1.0;
+                continue;
+            }
+            let mut base: Array1<f64> = Array::from_shape_fn(n_states, |y| {
+                if x == y {
+                    0.0
+                } else {
+                    M.slice(s![.., x, y]).sum() / T.column(x).sum()
+                }
+            });
+            let multiplier =
+                |rate_multipliers: &Vec<Array2<f64>>, u: usize, skip: Option<usize>| {
+                    configurations[u]
+                        .iter()
+                        .enumerate()
+                        .filter(|(i, _)| Some(*i) != skip)
+                        .map(|(i, s)| rate_multipliers[i][[*s, x]])
+                        .product::<f64>()
+                };
+
+            for iteration in 0..self.max_iterations {
+                let mut max_change: f64 = 0.0;
+                let mut update = |old: &mut f64, new: f64| {
+                    if old.abs() > 0.0 || new.abs() > 0.0 {
+                        max_change = max_change.max((new - *old).abs() / old.abs().max(new.abs()));
+                    }
+                    *old = new;
+                };
+
+                //Base rates given the multipliers.
+                let exposure: f64 = (0..n_configurations)
+                    .map(|u| T[[u, x]] * multiplier(&rate_multipliers, u, None))
+                    .sum();
+                if exposure > 0.0 {
+                    for y in (0..n_states).filter(|y| *y != x) {
+                        update(&mut base[y], M.slice(s![.., x, y]).sum() / exposure);
+                    }
+                }
+                let exit_rate = base.sum();
+
+                //Multipliers of each parent given the base rates and the other multipliers.
+                for (i, card) in parentset_domain.iter().enumerate() {
+                    for parent_state in 1..*card {
+                        let (n_exits, exposure) = (0..n_configurations)
+                            .filter(|u| configurations[*u][i] == parent_state)
+                            .fold((0.0, 0.0), |acc, u| {
+                                (
+                                    acc.0 + exits[u],
+                                    acc.1
+                                        + T[[u, x]]
+                                            * exit_rate
+                                            * multiplier(&rate_multipliers, u, Some(i)),
+                                )
+                            });
+                        if exposure > 0.0 {
+                            update(
+                                &mut rate_multipliers[i][[parent_state, x]],
+                                n_exits / exposure,
+                            );
+                        }
+                    }
+                }
+
+                if max_change <= self.tolerance {
+                    debug!(
+                        "Noisy-OR fit of row {} converged after {} iterations",
+                        x,
+                        iteration + 1
+                    );
+                    break;
+                }
+            }
+            base[x] = -base.sum();
+            base_cim.row_mut(x).assign(&base);
+        }
+        (base_cim, rate_multipliers)
+    }
+}
+
+impl ParameterLearning for NoisyOrMLE {
+    fn fit<T: process::NetworkProcess>(
+        &self,
+        net: &T,
+        dataset: &Dataset,
+        node: usize,
+        parent_set: Option<BTreeSet<usize>>,
+    ) -> Params {
+        debug!(
+            "Learning params for node {} with parent set {:?} with NoisyOrMLE",
+            node, parent_set
+        );
+        //Use parent_set from parameter if present. Otherwise use parent_set from network.
+        let parent_set = match parent_set {
+            Some(p) => p,
+            None => net.get_parent_set(node),
+        };
+        check_dataset(net, dataset, node, &parent_set);
+
+        let (M, T) = weighted_sufficient_statistics(net, dataset, node, &parent_set);
+        self.fit_from_weighted_parent_set_statistics(net, node, &parent_set, M, T)
+    }
+
+    fn fit_from_sufficient_statistics<T: process::NetworkProcess>(
+        &self,
+        net: &T,
+        node: usize,
+        transitions: Array3<usize>,
+        residence_time: Array2<f64>,
+    ) -> Params {
+        self.fit_from_parent_set_statistics(
+            net,
+            node,
+            &net.get_parent_set(node),
+            transitions,
+            residence_time,
+        )
+    }
+
+    fn fit_from_parent_set_statistics<T: process::NetworkProcess>(
+        &self,
+        net: &T,
+        node: usize,
+        parent_set: &BTreeSet<usize>,
+        transitions: Array3<usize>,
+        residence_time: Array2<f64>,
+    ) -> Params {
+        self.fit_from_weighted_parent_set_statistics(
+            net,
+            node,
+            parent_set,
+            transitions.mapv(|x| x as f64),
+            residence_time,
+        )
+    }
+
+    fn fit_from_weighted_parent_set_statistics<T: process::NetworkProcess>(
+        &self,
+        net: &T,
+        node: usize,
+        parent_set: &BTreeSet<usize>,
+        transitions: Array3<f64>,
+        residence_time: Array2<f64>,
+    ) -> Params {
+        let (M, T) = (transitions, residence_time);
+        let parentset_domain: Vec<usize> = parent_set
+            .iter()
+            .map(|x| net.get_node(*x).get_reserved_space_as_parent())
+            .collect();
+        if parentset_domain.iter().product::<usize>() != M.shape()[0] {
+            panic!("The sufficient statistics do not match the parent set");
+        }
+        let (base_cim, rate_multipliers) = self.fit_factors(&M, &T, &parentset_domain);
+
+        let mut n: Params = net.get_node(node).clone();
+        match n {
+            Params::DiscreteStatesContinousTime(ref mut dsct) => {
+                dsct.set_noisy_or_cim_unchecked(base_cim, rate_multipliers);
+                dsct.set_transitions(M.mapv(|x| x.round() as usize));
+                dsct.set_residence_time(T);
+            }
+        };
+        n
+    }
+
+    fn hyperparameters(&self) -> Vec<(String, String)> {
+        vec![
+            (
+                String::from("max_iterations"),
+                self.max_iterations.to_string(),
+            ),
+            (String::from("tolerance"), self.tolerance.to_string()),
+        ]
+    }
+}
+
+//Pool the sufficient statistics of the parent configurations sharing the same CIM (see
+//`DiscreteStatesContinousTimeParams::set_configuration_groups`). When the statistics were computed
+//for a parent set whose number of configurations differs from the one of the groups, the groups
+//are removed from `node` and the statistics are returned as they are.
+fn tie_configurations<A: Clone + Default + std::ops::AddAssign>(
+    node: &mut Params,
+    transitions: Array3<A>,
+    residence_time: Array2<f64>,
+) -> (Array3<A>, Array2<f64>) {
+    let Params::DiscreteStatesContinousTime(dsct) = node;
+    let groups = match dsct.get_configuration_groups() {
+        Some(groups) if groups.len() == transitions.shape()[0] => groups.clone(),
+        Some(_) => {
+            debug!(
+                "Removing the configuration groups of node {}: mismatched parent set",
+                dsct.get_label()
+            );
+            dsct.reset_configuration_groups();
+            return (transitions, residence_time);
+        }
+        None => return (transitions, residence_time),
+    };
+    let n_groups = dsct.get_number_of_configuration_groups().unwrap();
+    let (_, n_states, _) = transitions.dim();
+
+    let mut M: Array3<A> = Array::default((n_groups, n_states, n_states));
+    let mut T: Array2<f64> = Array::zeros((n_groups, n_states));
+    for (u, g) in groups.iter().enumerate() {
+        M.index_axis_mut(Axis(0), *g)
+            .zip_mut_with(&transitions.index_axis(Axis(0), u), |x, y| *x += y.clone());
+        T.row_mut(*g)
+            .zip_mut_with(&residence_time.row(u), |x, y| *x += y);
+    }
+    (M, T)
+}
+
+fn warn_unobserved_configuration(label: &str, u: usize, x: usize) {
+    warn!(
+        "Node {} never visited state {} under the parent configuration {}: using a fallback",
+        label, x, u
+    );
+}
+
+/// Standard errors and confidence intervals of the entries of a CIM.
+///
+/// All the arrays have the same shape of the CIM. On the diagonal they refer to the exit rate
+/// with opposite sign.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RateConfidenceIntervals {
+    pub standard_error: Array3<f64>,
+    pub lower: Array3<f64>,
+    pub upper: Array3<f64>,
+}
+
+/// Compute the standard errors and the Wald confidence intervals of the rates estimated with
+/// [`MLE`]
+///
+/// Each rate is estimated as `m / t`, with variance `m / t^2`, where `m` is the number of
+/// transitions and `t` the residence time. Entries whose residence time is zero are `NaN`.
+///
+/// # Arguments
+///
+/// * `transitions`: number of transitions (`M`) computed by [`sufficient_statistics`]
+/// * `residence_time`: residence time (`T`) computed by [`sufficient_statistics`]
+/// * `p`: confidence level of the intervals (e.g. `0.95`).
+pub fn mle_confidence_intervals(
+    transitions: &Array3<usize>,
+    residence_time: &Array2<f64>,
+    p: f64,
+) -> RateConfidenceIntervals {
+    if !(0.0..1.0).contains(&p) {
+        panic!("p must be in [0.0, 1.0)");
+    }
+    let z = Normal::new(0.0, 1.0).unwrap().inverse_cdf((1.0 + p) / 2.0);
+
+    //The exit rate of a state counts all the transitions leaving it.
+    let exit_transitions = transitions.sum_axis(Axis(2));
+    let mut estimate = Array3::from_elem(transitions.dim(), f64::NAN);
+    let mut standard_error = Array3::from_elem(transitions.dim(), f64::NAN);
+    for ((u, x, y), m) in transitions.indexed_iter() {
+        let t = residence_time[[u, x]];
+        if t == 0.0 {
+            continue;
+        }
+        let (m, sign) = if x == y {
+            (exit_transitions[[u, x]] as f64, -1.0)
+        } else {
+            (*m as f64, 1.0)
+        };
+        estimate[[u, x, y]] = sign * m / t;
+        standard_error[[u, x, y]] = m.sqrt() / t;
+    }
+
+    RateConfidenceIntervals {
+        lower: &estimate - &(&standard_error * z),
+        upper: &estimate + &(&standard_error * z),
+        standard_error,
+    }
 }
 
 /// Bayesian Approach for learning the parameters given a dataset.
 ///
 /// # Arguments
 ///
-/// `alpha`: hyperparameter for the priori over the number of transitions.
-/// `tau`: hyperparameter for the priori over the residence time.
+/// `alpha`: hyperparameter for the priori over the number of transitions, see [`Alpha`].
+/// `tau`: hyperparameter for the priori over the residence time, see [`Tau`].
 ///
 /// # Example
 ///
 /// ```rust
 ///
-/// use std::collections::BTreeSet;
 /// use reCTBN::process::NetworkProcess;
 /// use reCTBN::params;
 /// use reCTBN::process::ctbn::*;
@@ -245,22 +1597,9 @@ impl ParameterLearning for MLE {
 /// use reCTBN::parameter_learning::*;
 /// use approx::AbsDiffEq;
 ///
-/// //Create the domain for a discrete node
-/// let mut domain = BTreeSet::new();
-/// domain.insert(String::from("A"));
-/// domain.insert(String::from("B"));
-///
-/// //Create the parameters for a discrete node using the domain
-/// let param = params::DiscreteStatesContinousTimeParams::new("X1".to_string(), domain);
-///
-/// //Create the node using the parameters
-/// let X1 = params::Params::DiscreteStatesContinousTime(param);
-///
-/// let mut domain = BTreeSet::new();
-/// domain.insert(String::from("A"));
-/// domain.insert(String::from("B"));
-/// let param = params::DiscreteStatesContinousTimeParams::new("X2".to_string(), domain);
-/// let X2 = params::Params::DiscreteStatesContinousTime(param);
+/// //Create two discrete nodes with states A and B
+/// let X1 = params::discrete_node("X1", &["A", "B"]);
+/// let X2 = params::discrete_node("X2", &["A", "B"]);
 ///
 /// //Initialize a ctbn
 /// let mut net = CtbnNetwork::new();
@@ -294,7 +1633,10 @@ impl ParameterLearning for MLE {
 ///  let data = trajectory_generator(&net, 100, 100.0, Some(6347747169756259));
 ///
 /// //Initialize the `struct BayesianApproach`
-///  let pl = BayesianApproach{alpha: 1, tau: 1.0};
+///  let pl = BayesianApproach {
+///      alpha: Alpha::Constant(1.0),
+///      tau: Tau::Constant(1.0),
+///  };
 ///
 ///  // Fit the parameters for X2
 ///  let p = match pl.fit(&net, &data, X2, None) {
@@ -314,8 +1656,120 @@ impl ParameterLearning for MLE {
 ///  ));
 /// ```
 pub struct BayesianApproach {
-    pub alpha: usize,
-    pub tau: f64,
+    pub alpha: Alpha,
+    pub tau: Tau,
+}
+
+impl BayesianApproach {
+    /// Compute the posterior distribution of the CIM of `node` over a `dataset` given a
+    /// `parent_set`
+    ///
+    /// # Arguments
+    ///
+    /// * `net`: a `NetworkProcess` instance
+    /// * `dataset`: a dataset compatible with `net` used for computing the sufficient statistics
+    /// * `node`: the node index for which we want to compute the posterior
+    /// * `parent_set`: an `Option` containing the parent set used for computing the posterior of
+    ///   `node`. If `None`, the parent set defined in `net` will be used.
+    ///
+    /// Panics, as [`fit`](ParameterLearning::fit) does, if the states of `node` or of its parents
+    /// in `dataset` are outside their domains.
+    pub fn fit_posterior<T: process::NetworkProcess>(
+        &self,
+        net: &T,
+        dataset: &Dataset,
+        node: usize,
+        parent_set: Option<BTreeSet<usize>>,
+    ) -> CIMPosterior {
+        //Use parent_set from parameter if present. Otherwise use parent_set from network.
+        let parent_set = match parent_set {
+            Some(p) => p,
+            None => net.get_parent_set(node),
+        };
+        check_dataset(net, dataset, node, &parent_set);
+
+        if dataset.get_weights().is_some() {
+            let (M, T) = weighted_sufficient_statistics(net, dataset, node, &parent_set);
+            return self.posterior_from_weighted_sufficient_statistics(&M, &T);
+        }
+        let (M, T) = sufficient_statistics(net, dataset, node, &parent_set);
+        self.posterior_from_sufficient_statistics(&M, &T)
+    }
+
+    /// Compute the posterior distribution of a CIM from already computed sufficient statistics
+    ///
+    /// # Arguments
+    ///
+    /// * `transitions`: number of transitions (`M`) computed by [`sufficient_statistics`]
+    /// * `residence_time`: residence time (`T`) computed by [`sufficient_statistics`]
+    pub fn posterior_from_sufficient_statistics(
+        &self,
+        transitions: &Array3<usize>,
+        residence_time: &Array2<f64>,
+    ) -> CIMPosterior {
+        self.posterior_from_weighted_sufficient_statistics(
+            &transitions.mapv(|x| x as f64),
+            residence_time,
+        )
+    }
+
+    /// Compute the posterior distribution of a CIM from weighted sufficient statistics
+    ///
+    /// # Arguments
+    ///
+    /// * `transitions`: weighted number of transitions (`M`) computed by
+    ///   [`weighted_sufficient_statistics`]
+    /// * `residence_time`: weighted residence time (`T`) computed by
+    ///   [`weighted_sufficient_statistics`]
+    pub fn posterior_from_weighted_sufficient_statistics(
+        &self,
+        transitions: &Array3<f64>,
+        residence_time: &Array2<f64>,
+    ) -> CIMPosterior {
+        let alpha: Array3<f64> = self.alpha.transitions_prior(transitions.dim());
+        let tau: Array2<f64> = self.tau.residence_time_prior(residence_time.dim());
+
+        CIMPosterior {
+            shape: transitions + &alpha,
+            rate: residence_time + &tau,
+        }
+    }
+
+    /// Compute the parameters of the `node` from weighted sufficient statistics
+    ///
+    /// The transitions stored in the returned parameters are rounded to the nearest integer.
+    ///
+    /// # Arguments
+    ///
+    /// * `net`: a `NetworkProcess` instance
+    /// * `node`: the node index for which we want to compute the parameters
+    /// * `transitions`: weighted number of transitions (`M`) computed by
+    ///   [`weighted_sufficient_statistics`]
+    /// * `residence_time`: weighted residence time (`T`) computed by
+    ///   [`weighted_sufficient_statistics`]
+    pub fn fit_from_weighted_sufficient_statistics<T: process::NetworkProcess>(
+        &self,
+        net: &T,
+        node: usize,
+        transitions: Array3<f64>,
+        residence_time: Array2<f64>,
+    ) -> Params {
+        let mut n: Params = net.get_node(node).clone();
+        let (M, T) = tie_configurations(&mut n, transitions, residence_time);
+
+        let CIM = self
+            .posterior_from_weighted_sufficient_statistics(&M, &T)
+            .mean();
+
+        match n {
+            Params::DiscreteStatesContinousTime(ref mut dsct) => {
+                dsct.set_cim_unchecked(CIM);
+                dsct.set_transitions(M.mapv(|x| x.round() as usize));
+                dsct.set_residence_time(T);
+            }
+        };
+        n
+    }
 }
 
 impl ParameterLearning for BayesianApproach {
@@ -335,39 +1789,505 @@ impl ParameterLearning for BayesianApproach {
             Some(p) => p,
             None => net.get_parent_set(node),
         };
+        check_dataset(net, dataset, node, &parent_set);
 
+        if dataset.get_weights().is_some() {
+            let (M, T) = weighted_sufficient_statistics(net, dataset, node, &parent_set);
+            return self.fit_from_weighted_sufficient_statistics(net, node, M, T);
+        }
         let (M, T) = sufficient_statistics(net, dataset, node.clone(), &parent_set);
+        self.fit_from_sufficient_statistics(net, node, M, T)
+    }
 
-        let alpha: f64 = self.alpha as f64 / M.shape()[0] as f64;
-        let tau: f64 = self.tau as f64 / M.shape()[0] as f64;
+    fn fit_from_sufficient_statistics<T: process::NetworkProcess>(
+        &self,
+        net: &T,
+        node: usize,
+        transitions: Array3<usize>,
+        residence_time: Array2<f64>,
+    ) -> Params {
+        let mut n: Params = net.get_node(node).clone();
+        let (M, T) = tie_configurations(&mut n, transitions, residence_time);
 
-        //Compute the CIM as M[i,x,y]/T[i,x]
-        let mut CIM: Array3<f64> = Array::zeros((M.shape()[0], M.shape()[1], M.shape()[2]));
-        CIM.axis_iter_mut(Axis(2))
-            .zip(M.mapv(|x| x as f64).axis_iter(Axis(2)))
-            .for_each(|(mut C, m)| C.assign(&(&m.mapv(|y| y + alpha) / &T.mapv(|y| y + tau))));
+        let CIM = self.posterior_from_sufficient_statistics(&M, &T).mean();
+
+        match n {
+            Params::DiscreteStatesContinousTime(ref mut dsct) => {
+                dsct.set_cim_unchecked(CIM);
+                dsct.set_transitions(M);
+                dsct.set_residence_time(T);
+            }
+        };
+        return n;
+    }
+
+    fn fit_from_weighted_parent_set_statistics<T: process::NetworkProcess>(
+        &self,
+        net: &T,
+        node: usize,
+        _parent_set: &BTreeSet<usize>,
+        transitions: Array3<f64>,
+        residence_time: Array2<f64>,
+    ) -> Params {
+        self.fit_from_weighted_sufficient_statistics(net, node, transitions, residence_time)
+    }
+
+    fn hyperparameters(&self) -> Vec<(String, String)> {
+        vec![
+            (String::from("alpha"), format!("{:?}", self.alpha)),
+            (String::from("tau"), format!("{:?}", self.tau)),
+        ]
+    }
+}
+
+/// Posterior distribution of the CIM of a node learned with [`BayesianApproach`].
+///
+/// Each off-diagonal rate `q[u, x, y]` follows a Gamma distribution with shape
+/// `alpha[u, x, y] + M[u, x, y]` and rate `tau[u, x] + T[u, x]`. Since the rates of a row share
+/// the same Gamma rate, the exit rate of the state `x` (the diagonal, with opposite sign) follows
+/// a Gamma distribution whose shape is the sum of the shapes of the row.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CIMPosterior {
+    shape: Array3<f64>,
+    rate: Array2<f64>,
+}
+
+impl CIMPosterior {
+    /// Shape hyperparameter of the Gamma posterior of each rate. The diagonal is not used.
+    pub fn get_shape(&self) -> &Array3<f64> {
+        &self.shape
+    }
+
+    /// Rate hyperparameter of the Gamma posterior, shared by the rates of the same row.
+    pub fn get_rate(&self) -> &Array2<f64> {
+        &self.rate
+    }
+
+    /// Posterior mean of the CIM.
+    pub fn mean(&self) -> Array3<f64> {
+        self.off_diagonal_cim(|u, x, y| self.shape[[u, x, y]] / self.rate[[u, x]])
+    }
+
+    /// Posterior mode (MAP) of each rate. The diagonal is filled so that each row sums to zero.
+    pub fn mode(&self) -> Array3<f64> {
+        self.off_diagonal_cim(|u, x, y| {
+            f64::max(self.shape[[u, x, y]] - 1.0, 0.0) / self.rate[[u, x]]
+        })
+    }
+
+    /// Equal-tailed credible interval of each entry of the CIM
+    ///
+    /// Returns the lower and upper bounds of the intervals. On the diagonal the interval refers
+    /// to the exit rate with opposite sign, thus its lower bound is minus the upper bound of the
+    /// exit rate.
+    ///
+    /// # Arguments
+    ///
+    /// * `p`: probability mass of the interval (e.g. `0.95`).
+    pub fn credible_interval(&self, p: f64) -> (Array3<f64>, Array3<f64>) {
+        if !(0.0..1.0).contains(&p) {
+            panic!("p must be in [0.0, 1.0)");
+        }
+        let quantile = |shape: f64, rate: f64, q: f64| -> f64 {
+            //A null shape is a point mass in zero
+            if shape == 0.0 {
+                return 0.0;
+            }
+            Gamma::new(shape, rate).unwrap().inverse_cdf(q)
+        };
+        let (q_lower, q_upper) = ((1.0 - p) / 2.0, (1.0 + p) / 2.0);
+
+        let mut lower = Array3::zeros(self.shape.dim());
+        let mut upper = Array3::zeros(self.shape.dim());
+        for ((u, x, y), shape) in self.shape.indexed_iter() {
+            let rate = self.rate[[u, x]];
+            if x == y {
+                let exit_shape = self.exit_shape(u, x);
+                lower[[u, x, y]] = -quantile(exit_shape, rate, q_upper);
+                upper[[u, x, y]] = -quantile(exit_shape, rate, q_lower);
+            } else {
+                lower[[u, x, y]] = quantile(*shape, rate, q_lower);
+                upper[[u, x, y]] = quantile(*shape, rate, q_upper);
+            }
+        }
+        (lower, upper)
+    }
+
+    /// Draw a CIM from the posterior
+    ///
+    /// Each off-diagonal rate is sampled from its Gamma posterior and the diagonal is filled so
+    /// that each row sums to zero.
+    ///
+    /// # Arguments
+    ///
+    /// * `rng`: random number generator used for the draw.
+    pub fn sample(&self, rng: &mut ChaCha8Rng) -> Array3<f64> {
+        let draws = self.shape.mapv(|shape| {
+            //A null shape is a point mass in zero
+            if shape == 0.0 {
+                return 0.0;
+            }
+            rng.sample(Gamma::new(shape, 1.0).unwrap())
+        });
+        self.off_diagonal_cim(|u, x, y| draws[[u, x, y]] / self.rate[[u, x]])
+    }
+
+    //Sum of the off-diagonal shapes of the row x.
+    fn exit_shape(&self, u: usize, x: usize) -> f64 {
+        self.shape
+            .slice(s![u, x, ..])
+            .indexed_iter()
+            .filter(|(y, _)| *y != x)
+            .map(|(_, shape)| shape)
+            .sum()
+    }
+
+    //Build a CIM computing each off-diagonal rate with `f(u, x, y)`.
+    fn off_diagonal_cim<F: Fn(usize, usize, usize) -> f64>(&self, f: F) -> Array3<f64> {
+        let mut CIM = Array3::from_shape_fn(self.shape.dim(), |(u, x, y)| f(u, x, y));
 
         CIM.outer_iter_mut().for_each(|mut C| {
             C.diag_mut().fill(0.0);
         });
 
         //Set the diagonal of the inner matrices to the the row sum multiplied by -1
-        let tmp_diag_sum: Array2<f64> = CIM.sum_axis(Axis(2)).mapv(|x| x * -1.0);
+        let tmp_diag_sum: Array2<f64> = CIM.sum_axis(Axis(2)).mapv(|x| -x);
         CIM.outer_iter_mut()
             .zip(tmp_diag_sum.outer_iter())
             .for_each(|(mut C, diag)| {
                 C.diag_mut().assign(&diag);
             });
+        CIM
+    }
+}
 
-        let mut n: Params = net.get_node(node).clone();
+/// Clone `net` replacing the CIM of each node in `posteriors` with a draw from its posterior
+///
+/// The returned network can be directly simulated, e.g. with
+/// [`trajectory_generator`](crate::tools::trajectory_generator).
+///
+/// # Arguments
+///
+/// * `net`: a `NetworkProcess` instance
+/// * `posteriors`: the posterior of the CIM of each node to sample, computed with the parent set
+///   of the node in `net`
+/// * `rng`: random number generator used for the draws.
+pub fn sample_network<T: process::NetworkProcess + Clone>(
+    net: &T,
+    posteriors: &BTreeMap<usize, CIMPosterior>,
+    rng: &mut ChaCha8Rng,
+) -> Result<T, ParamsError> {
+    let mut sampled_net = net.clone();
+    for (node, posterior) in posteriors.iter() {
+        match sampled_net.get_node_mut(*node) {
+            Params::DiscreteStatesContinousTime(dsct) => dsct.set_cim(posterior.sample(rng))?,
+        }
+    }
+    Ok(sampled_net)
+}
 
-        match n {
-            Params::DiscreteStatesContinousTime(ref mut dsct) => {
-                dsct.set_cim_unchecked(CIM);
-                dsct.set_transitions(M);
-                dsct.set_residence_time(T);
+/// Groups of nodes constrained to share the same local model (CIM).
+///
+/// All the members of a group must have the same domain cardinality and their parent sets must
+/// have the same cardinalities, taken in ascending order of parent index, so that the parent
+/// configurations of the members can be aligned.
+#[derive(Clone, Debug)]
+pub struct TiedNodes {
+    groups: Vec<Vec<usize>>,
+}
+
+impl TiedNodes {
+    /// Create a `TiedNodes` specification.
+    ///
+    /// # Arguments
+    ///
+    /// * `groups`: groups of node indices; a node can belong to at most one group.
+    pub fn new(groups: Vec<Vec<usize>>) -> Result<TiedNodes, ParameterLearningError> {
+        let mut seen = BTreeSet::new();
+        for node in groups.iter().flatten() {
+            if !seen.insert(*node) {
+                return Err(ParameterLearningError::InvalidTiedNodes(format!(
+                    "Node {} belongs to more than one group",
+                    node
+                )));
+            }
+        }
+        Ok(TiedNodes { groups })
+    }
+
+    pub fn get_groups(&self) -> &Vec<Vec<usize>> {
+        &self.groups
+    }
+}
+
+/// Wrapper around a `ParameterLearning` that estimates a single CIM for each group of
+/// [`TiedNodes`], pooling the sufficient statistics of all the members of the group.
+///
+/// # Attributes
+///
+/// * `parameter_learning`: the method used to estimate the CIM from the pooled statistics.
+/// * `tied_nodes`: the groups of nodes sharing the same local model.
+pub struct TiedParameterLearning<P: ParameterLearning> {
+    parameter_learning: P,
+    tied_nodes: TiedNodes,
+}
+
+impl<P: ParameterLearning> TiedParameterLearning<P> {
+    pub fn new(parameter_learning: P, tied_nodes: TiedNodes) -> TiedParameterLearning<P> {
+        TiedParameterLearning {
+            parameter_learning,
+            tied_nodes,
+        }
+    }
+
+    /// Fit the parameters of every node of `net` using the parent sets defined in `net`.
+    ///
+    /// The nodes not belonging to any group are fitted independently; the members of a group
+    /// receive the CIM estimated from the sum of their sufficient statistics, weighted when the
+    /// dataset has weights (see [`Dataset::with_weights`]). The network is left unchanged if the
+    /// groups are invalid or if a state in `dataset` is outside the domain of its node
+    /// (`ParameterLearningError::InvalidDataset`).
+    ///
+    /// # Arguments
+    ///
+    /// * `net`: a `NetworkProcess` instance whose parameters will be replaced
+    /// * `dataset`: a dataset compatible with `net`
+    pub fn fit_all<T: process::NetworkProcess>(
+        &self,
+        net: &mut T,
+        dataset: &Dataset,
+    ) -> Result<(), ParameterLearningError> {
+        let n_nodes = net.get_number_of_nodes();
+        //Check the groups before modifying the network.
+        for group in self.tied_nodes.get_groups() {
+            if let Some(node) = group.iter().find(|x| **x >= n_nodes) {
+                return Err(ParameterLearningError::InvalidTiedNodes(format!(
+                    "Node {} does not exist",
+                    node
+                )));
+            }
+            if let Some(first) = group.first() {
+                let first_shape = local_model_shape(&*net, *first);
+                if let Some(node) = group
+                    .iter()
+                    .find(|x| local_model_shape(&*net, **x) != first_shape)
+                {
+                    return Err(ParameterLearningError::MismatchedTiedNodes(format!(
+                        "Nodes {} and {} have different domain or parent set cardinalities",
+                        first, node
+                    )));
+                }
             }
+        }
+
+        //The states of every node, tied or not, must be inside their domains before indexing the
+        //statistics.
+        for node in net.get_node_indices() {
+            validate_dataset(&*net, dataset, node, &net.get_parent_set(node))
+                .map_err(ParameterLearningError::InvalidDataset)?;
+        }
+
+        let mut learned_params: Vec<(usize, Params)> = vec![];
+        for group in self.tied_nodes.get_groups() {
+            if group.is_empty() {
+                continue;
+            }
+            let parent_sets: Vec<BTreeSet<usize>> =
+                group.iter().map(|x| net.get_parent_set(*x)).collect();
+            //Pool the sufficient statistics of all the members of the group, weighting them when
+            //the dataset has weights.
+            if dataset.get_weights().is_some() {
+                let (M, T) = pool_sufficient_statistics(group.iter().zip(parent_sets.iter()).map(
+                    |(node, parent_set)| {
+                        weighted_sufficient_statistics(&*net, dataset, *node, parent_set)
+                    },
+                ));
+                for (node, parent_set) in group.iter().zip(parent_sets.iter()) {
+                    learned_params.push((
+                        *node,
+                        self.parameter_learning
+                            .fit_from_weighted_parent_set_statistics(
+                                &*net,
+                                *node,
+                                parent_set,
+                                M.clone(),
+                                T.clone(),
+                            ),
+                    ));
+                }
+            } else {
+                let (M, T) = pool_sufficient_statistics(group.iter().zip(parent_sets.iter()).map(
+                    |(node, parent_set)| sufficient_statistics(&*net, dataset, *node, parent_set),
+                ));
+                for (node, parent_set) in group.iter().zip(parent_sets.iter()) {
+                    learned_params.push((
+                        *node,
+                        self.parameter_learning.fit_from_parent_set_statistics(
+                            &*net,
+                            *node,
+                            parent_set,
+                            M.clone(),
+                            T.clone(),
+                        ),
+                    ));
+                }
+            }
+        }
+
+        //Fit the untied nodes independently.
+        let tied: BTreeSet<usize> = self
+            .tied_nodes
+            .get_groups()
+            .iter()
+            .flatten()
+            .cloned()
+            .collect();
+        for node in net.get_node_indices().filter(|x| !tied.contains(x)) {
+            learned_params.push((
+                node,
+                self.parameter_learning.fit(&*net, dataset, node, None),
+            ));
+        }
+
+        for (node, params) in learned_params {
+            *net.get_node_mut(node) = params;
+        }
+        Ok(())
+    }
+}
+
+//Sum of the sufficient statistics of the members of a group of tied nodes.
+fn pool_sufficient_statistics<A: Clone + std::ops::AddAssign>(
+    stats: impl Iterator<Item = (Array3<A>, Array2<f64>)>,
+) -> (Array3<A>, Array2<f64>) {
+    stats
+        .reduce(|(mut M, mut T), (m, t)| {
+            M.zip_mut_with(&m, |x, y| *x += y.clone());
+            T += &t;
+            (M, T)
+        })
+        .unwrap()
+}
+
+//Domain cardinality of the node followed by the cardinalities of its parents.
+fn local_model_shape<T: process::NetworkProcess>(net: &T, node: usize) -> Vec<usize> {
+    std::iter::once(node)
+        .chain(net.get_parent_set(node))
+        .map(|x| net.get_node(x).get_reserved_space_as_parent())
+        .collect()
+}
+
+/// Wrapper around a `ParameterLearning` that answers [`fit`](ParameterLearning::fit) from a
+/// [`SuffStatsCache`](crate::tools::SuffStatsCache).
+///
+/// The statistics missing from the store are computed from the dataset and added to it, so that
+/// the store can be saved with [`save`](crate::tools::SuffStatsCache::save) after a run and
+/// reused by the following runs on the same dataset. The store holds unweighted statistics: a
+/// weighted dataset (see [`Dataset::with_weights`]) is fitted by the wrapped method without
+/// using the store.
+pub struct StoredStatisticsLearning<P: ParameterLearning> {
+    parameter_learning: P,
+    suff_stats_cache: Arc<SuffStatsCache>,
+}
+
+impl<P: ParameterLearning> StoredStatisticsLearning<P> {
+    pub fn new(
+        parameter_learning: P,
+        suff_stats_cache: Arc<SuffStatsCache>,
+    ) -> StoredStatisticsLearning<P> {
+        StoredStatisticsLearning {
+            parameter_learning,
+            suff_stats_cache,
+        }
+    }
+
+    pub fn get_suff_stats_cache(&self) -> &Arc<SuffStatsCache> {
+        &self.suff_stats_cache
+    }
+}
+
+impl<P: ParameterLearning> ParameterLearning for StoredStatisticsLearning<P> {
+    fn fit<T: process::NetworkProcess>(
+        &self,
+        net: &T,
+        dataset: &Dataset,
+        node: usize,
+        parent_set: Option<BTreeSet<usize>>,
+    ) -> Params {
+        //Use parent_set from parameter if present. Otherwise use parent_set from network.
+        let parent_set = match parent_set {
+            Some(p) => p,
+            None => net.get_parent_set(node),
         };
-        return n;
+
+        if dataset.get_weights().is_some() {
+            return self
+                .parameter_learning
+                .fit(net, dataset, node, Some(parent_set));
+        }
+        let stats = self
+            .suff_stats_cache
+            .get_or_compute(net, dataset, node, &parent_set);
+        self.parameter_learning.fit_from_parent_set_statistics(
+            net,
+            node,
+            &parent_set,
+            stats.0.clone(),
+            stats.1.clone(),
+        )
+    }
+
+    fn fit_from_sufficient_statistics<T: process::NetworkProcess>(
+        &self,
+        net: &T,
+        node: usize,
+        transitions: Array3<usize>,
+        residence_time: Array2<f64>,
+    ) -> Params {
+        self.parameter_learning.fit_from_sufficient_statistics(
+            net,
+            node,
+            transitions,
+            residence_time,
+        )
+    }
+
+    fn fit_from_parent_set_statistics<T: process::NetworkProcess>(
+        &self,
+        net: &T,
+        node: usize,
+        parent_set: &BTreeSet<usize>,
+        transitions: Array3<usize>,
+        residence_time: Array2<f64>,
+    ) -> Params {
+        self.parameter_learning.fit_from_parent_set_statistics(
+            net,
+            node,
+            parent_set,
+            transitions,
+            residence_time,
+        )
+    }
+
+    fn fit_from_weighted_parent_set_statistics<T: process::NetworkProcess>(
+        &self,
+        net: &T,
+        node: usize,
+        parent_set: &BTreeSet<usize>,
+        transitions: Array3<f64>,
+        residence_time: Array2<f64>,
+    ) -> Params {
+        self.parameter_learning
+            .fit_from_weighted_parent_set_statistics(
+                net,
+                node,
+                parent_set,
+                transitions,
+                residence_time,
+            )
+    }
+
+    fn hyperparameters(&self) -> Vec<(String, String)> {
+        self.parameter_learning.hyperparameters()
     }
 }
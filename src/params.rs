@@ -1,14 +1,18 @@
 //! Module containing methods to define different types of nodes.
 
 use std::collections::BTreeSet;
+use std::fmt;
+use std::ops::{DivAssign, MulAssign, Range};
 
 use enum_dispatch::enum_dispatch;
-use log::{debug, error, trace, warn};
+use log::{debug, trace, warn};
 use ndarray::prelude::*;
 use rand::Rng;
 use rand_chacha::ChaCha8Rng;
 use thiserror::Error;
 
+use crate::parameter_learning;
+
 /// Error types for trait Params
 #[derive(Error, Debug, PartialEq)]
 pub enum ParamsError {
@@ -18,14 +22,37 @@ pub enum ParamsError {
     ParametersNotInitialized(String),
     #[error("Invalid cim for parameter")]
     InvalidCIM(String),
+    #[error("Configuration not observed in the dataset")]
+    MissingConfiguration(String),
+    #[error("Invalid grouping of the parent configurations")]
+    InvalidConfigurationGroups(String),
+    #[error("Index out of range")]
+    InvalidIndex(String),
 }
 
 /// Allowed type of states
-#[derive(Clone, Hash, PartialEq, Eq, Debug)]
+///
+/// States are ordered by their index, so that they can be used as keys of both `HashMap` and
+/// `BTreeMap`.
+#[derive(Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Debug)]
 pub enum StateType {
     Discrete(usize),
 }
 
+impl From<usize> for StateType {
+    fn from(state: usize) -> StateType {
+        StateType::Discrete(state)
+    }
+}
+
+impl From<StateType> for usize {
+    fn from(state: StateType) -> usize {
+        match state {
+            StateType::Discrete(x) => x,
+        }
+    }
+}
+
 /// This is a core element for building different types of nodes; the goal is to define the set of
 /// methods required to describes a generic node.
 #[enum_dispatch(Params)]
@@ -46,6 +73,21 @@ pub trait ParamsTrait {
         rng: &mut ChaCha8Rng,
     ) -> Result<f64, ParamsError>;
 
+    /// Randomly generate the time of the next transition of the node, given that the node entered
+    /// (or was last updated in) `state` at time `t`.
+    ///
+    /// Time-inhomogeneous parameters take into account the regime active at each time instant.
+    fn get_random_transition_time(
+        &self,
+        state: usize,
+        u: usize,
+        t: f64,
+        rng: &mut ChaCha8Rng,
+    ) -> Result<f64, ParamsError> {
+        self.get_random_residence_time(state, u, rng)
+            .map(|residence_time| residence_time + t)
+    }
+
     /// Randomly generate a possible state for the given node taking into account the node state
     /// and its parent set.
     fn get_random_state(
@@ -55,12 +97,27 @@ pub trait ParamsTrait {
         rng: &mut ChaCha8Rng,
     ) -> Result<StateType, ParamsError>;
 
+    /// Randomly generate the state reached by a transition happening at time `t`.
+    fn get_random_state_at(
+        &self,
+        state: usize,
+        u: usize,
+        _t: f64,
+        rng: &mut ChaCha8Rng,
+    ) -> Result<StateType, ParamsError> {
+        self.get_random_state(state, u, rng)
+    }
+
     /// Used by childern of the node described by this parameters to reserve spaces in their CIMs.
     fn get_reserved_space_as_parent(&self) -> usize;
 
     /// Index used by discrete node to represents their states as usize.
     fn state_to_index(&self, state: &StateType) -> usize;
 
+    /// Index of the parameters (e.g. the first axis of the CIM) used under the parent
+    /// configuration `u`, as returned by `get_param_index_network`.
+    fn get_cim_index(&self, u: usize) -> usize;
+
     /// Validate parameters against domain
     fn validate_params(&self) -> Result<(), ParamsError>;
 
@@ -70,12 +127,146 @@ pub trait ParamsTrait {
 
 /// Is a core element for building different types of nodes; the goal is to define all the
 /// supported type of Parameters
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 #[enum_dispatch]
 pub enum Params {
     DiscreteStatesContinousTime(DiscreteStatesContinousTimeParams),
 }
 
+impl Params {
+    /// Build the state of the node labelled `label`, `None` if `label` is not in the domain.
+    pub fn state_from_label(&self, label: &str) -> Option<StateType> {
+        match self {
+            Params::DiscreteStatesContinousTime(p) => {
+                p.index_of_label(label).map(StateType::Discrete)
+            }
+        }
+    }
+
+    /// Display the parameters naming each parent configuration after the states of `parents`.
+    ///
+    /// `parents` must be the parameters of the parent set of the node, ordered by node index.
+    pub fn display_with_parents<'a>(&'a self, parents: Vec<&'a Params>) -> ParamsDisplay<'a> {
+        match self {
+            Params::DiscreteStatesContinousTime(p) => ParamsDisplay {
+                param: p,
+                parents: Some(parents),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Params {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Params::DiscreteStatesContinousTime(p) => fmt::Display::fmt(p, f),
+        }
+    }
+}
+
+/// Build a discrete node labelled `label` whose domain is `states`.
+///
+/// The domain is ordered, so the index of each state is its position among the sorted `states`.
+///
+/// # Example
+///
+/// ```rust
+/// use reCTBN::params::{self, ParamsTrait};
+///
+/// let X1 = params::discrete_node("X1", &["A", "B"]);
+/// assert_eq!(X1.get_label(), "X1");
+/// assert_eq!(X1.get_reserved_space_as_parent(), 2);
+/// ```
+pub fn discrete_node(label: &str, states: &[&str]) -> Params {
+    let domain: BTreeSet<String> = states.iter().map(|x| x.to_string()).collect();
+    Params::DiscreteStatesContinousTime(DiscreteStatesContinousTimeParams::new(
+        label.to_string(),
+        domain,
+    ))
+}
+
+/// Build a discrete node labelled `label` whose domain is `"0"`, `"1"`, ..., up to
+/// `cardinality - 1`.
+pub fn generate_discrete_time_continous_node(label: String, cardinality: usize) -> Params {
+    let domain: BTreeSet<String> = (0..cardinality).map(|x| x.to_string()).collect();
+    Params::DiscreteStatesContinousTime(DiscreteStatesContinousTimeParams::new(label, domain))
+}
+
+// Solve the square linear system `a x = b` with Gaussian elimination and partial pivoting; `a`
+// must be non-singular.
+fn solve_linear_system(mut a: Array2<f64>, mut b: Array1<f64>) -> Array1<f64> {
+    let n = b.len();
+    for col in 0..n {
+        let pivot = (col..n)
+            .max_by(|x, y| a[[*x, col]].abs().total_cmp(&a[[*y, col]].abs()))
+            .unwrap();
+        if pivot != col {
+            for k in 0..n {
+                a.swap([col, k], [pivot, k]);
+            }
+            b.swap(col, pivot);
+        }
+        for row in (col + 1)..n {
+            let factor = a[[row, col]] / a[[col, col]];
+            if factor != 0.0 {
+                for k in col..n {
+                    a[[row, k]] -= factor * a[[col, k]];
+                }
+                b[row] -= factor * b[col];
+            }
+        }
+    }
+    let mut x = Array1::<f64>::zeros(n);
+    for row in (0..n).rev() {
+        let partial: f64 = ((row + 1)..n).map(|k| a[[row, k]] * x[k]).sum();
+        x[row] = (b[row] - partial) / a[[row, row]];
+    }
+    x
+}
+
+/// Distribution of the exit rates drawn by
+/// [`randomize_cim`](DiscreteStatesContinousTimeParams::randomize_cim).
+pub trait RateDistribution {
+    /// Draw a non-negative rate using `rng`.
+    fn sample_rate(&self, rng: &mut ChaCha8Rng) -> f64;
+}
+
+/// Rates uniformly distributed in the interval; domain: `≥ 0.0`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Uniform(pub Range<f64>);
+
+impl RateDistribution for Uniform {
+    fn sample_rate(&self, rng: &mut ChaCha8Rng) -> f64 {
+        if self.0.start < 0.0 || self.0.end < 0.0 {
+            panic!(
+                "Interval must be entirely greater or equal than 0, got {}..{}.",
+                self.0.start, self.0.end
+            );
+        }
+        rng.gen_range(self.0.clone())
+    }
+}
+
+/// Rates distributed as a Gamma with the given `shape` and `rate` (inverse scale); both must be
+/// positive.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gamma {
+    pub shape: f64,
+    pub rate: f64,
+}
+
+impl RateDistribution for Gamma {
+    fn sample_rate(&self, rng: &mut ChaCha8Rng) -> f64 {
+        let gamma = statrs::distribution::Gamma::new(self.shape, self.rate).unwrap_or_else(|_| {
+            panic!(
+                "Shape and rate must be positive, got shape {} and rate {}.",
+                self.shape, self.rate
+            )
+        });
+        rng.sample(gamma)
+    }
+}
+
 /// This represents the parameters of a classical discrete node for ctbn and it's composed by the
 /// following elements.
 ///
@@ -89,13 +280,23 @@ pub enum Params {
 ///   task.
 /// * `residence_time` - residence time in each possible state, given a specific realization of the
 ///   parent set; is a sufficient statistics are mainly used during the parameter learning task.
-#[derive(Clone)]
+/// * `tolerance` - relative tolerance used by [`validate_params`](ParamsTrait::validate_params)
+///   to check that the rows of the CIM sum to zero.
+/// * `configuration_groups` - optional map from each parent configuration to the CIM shared by
+///   its group (see [`set_configuration_groups`](Self::set_configuration_groups)).
+/// * `rate_multipliers` - optional noisy-OR factors of each parent, scaling the rates of a base
+///   CIM (see [`set_noisy_or_cim`](Self::set_noisy_or_cim)).
+#[derive(Clone, Debug)]
 pub struct DiscreteStatesContinousTimeParams {
     label: String,
     domain: BTreeSet<String>,
     cim: Option<Array3<f64>>,
+    piecewise_cim: Option<Vec<(f64, Array3<f64>)>>,
     transitions: Option<Array3<usize>>,
     residence_time: Option<Array2<f64>>,
+    tolerance: f64,
+    configuration_groups: Option<Array1<usize>>,
+    rate_multipliers: Option<Vec<Array2<f64>>>,
 }
 
 impl DiscreteStatesContinousTimeParams {
@@ -105,11 +306,67 @@ impl DiscreteStatesContinousTimeParams {
             label,
             domain,
             cim: Option::None,
+            piecewise_cim: Option::None,
             transitions: Option::None,
             residence_time: Option::None,
+            tolerance: f64::EPSILON.sqrt(),
+            configuration_groups: Option::None,
+            rate_multipliers: Option::None,
         }
     }
 
+    /// Create the parameters and set the CIM, validating it immediately.
+    ///
+    /// Returns the error raised by [`set_cim`](Self::set_cim) if `cim` is not a valid CIM for
+    /// `domain`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::collections::BTreeSet;
+    /// use ndarray::arr3;
+    /// use reCTBN::params::DiscreteStatesContinousTimeParams;
+    ///
+    /// let domain: BTreeSet<String> = ["A", "B"].iter().map(|x| x.to_string()).collect();
+    /// let param = DiscreteStatesContinousTimeParams::new_with_cim(
+    ///     "X1".to_string(),
+    ///     domain.clone(),
+    ///     arr3(&[[[-0.1, 0.1], [1.0, -1.0]]]),
+    /// );
+    /// assert!(param.is_ok());
+    ///
+    /// let param = DiscreteStatesContinousTimeParams::new_with_cim(
+    ///     "X1".to_string(),
+    ///     domain,
+    ///     arr3(&[[[-0.1, 0.2], [1.0, -1.0]]]),
+    /// );
+    /// assert!(param.is_err());
+    /// ```
+    pub fn new_with_cim(
+        label: String,
+        domain: BTreeSet<String>,
+        cim: Array3<f64>,
+    ) -> Result<DiscreteStatesContinousTimeParams, ParamsError> {
+        let mut params = DiscreteStatesContinousTimeParams::new(label, domain);
+        params.set_cim(cim)?;
+        Ok(params)
+    }
+
+    /// Getter function for the domain; the position of a state in the set is its index.
+    pub fn get_domain(&self) -> &BTreeSet<String> {
+        &self.domain
+    }
+
+    /// Label of the state with index `idx`, `None` if `idx` is outside the domain.
+    pub fn state_label(&self, idx: usize) -> Option<&String> {
+        self.domain.iter().nth(idx)
+    }
+
+    /// Index of the state labelled `label`, `None` if `label` is not in the domain.
+    pub fn index_of_label(&self, label: &str) -> Option<usize> {
+        self.domain.iter().position(|x| x == label)
+    }
+
     /// Getter function for CIM
     pub fn get_cim(&self) -> &Option<Array3<f64>> {
         debug!("Getting cim from node {}", self.label);
@@ -125,6 +382,8 @@ impl DiscreteStatesContinousTimeParams {
     pub fn set_cim(&mut self, cim: Array3<f64>) -> Result<(), ParamsError> {
         debug!("Setting cim for node {}", self.label);
         self.cim = Some(cim);
+        self.piecewise_cim = None;
+        self.rate_multipliers = None;
         match self.validate_params() {
             Ok(()) => Ok(()),
             Err(e) => {
@@ -135,10 +394,404 @@ impl DiscreteStatesContinousTimeParams {
         }
     }
 
+    /// Setter function for CIM, validating it with a custom `tolerance`.
+    ///
+    /// A row of the CIM is accepted if the absolute value of its sum is at most `tolerance`
+    /// times the largest absolute rate of the row (e.g. `1e-6` for CIMs computed in single
+    /// precision). The tolerance is kept for the following validations of the node.
+    pub fn set_cim_with_tolerance(
+        &mut self,
+        cim: Array3<f64>,
+        tolerance: f64,
+    ) -> Result<(), ParamsError> {
+        if tolerance.is_nan() || tolerance < 0.0 {
+            panic!("The tolerance must be >= 0.0");
+        }
+        self.tolerance = tolerance;
+        self.set_cim(cim)
+    }
+
+    /// Relative tolerance used to check that the rows of the CIM sum to zero.
+    pub fn get_tolerance(&self) -> f64 {
+        self.tolerance
+    }
+
+    /// Tie the parameters of the parent configurations belonging to the same group.
+    ///
+    /// `groups[u]` is the group of the parent configuration `u`: the groups must be numbered from
+    /// `0` to `k - 1` without gaps, and the CIM becomes an array of shape `[k, n, n]` whose `g`-th
+    /// matrix is shared by all the configurations of the group `g`. For instance, a node whose
+    /// dynamics only depend on whether any of its two binary parents is active uses the groups
+    /// `[0, 1, 1, 1]`. The samplers and the parameter learning methods index the reduced CIM
+    /// through [`get_cim_index`](ParamsTrait::get_cim_index).
+    ///
+    /// The CIM and the sufficient statistics are reset, since their shape changes. The groups
+    /// depend on the parent set, so they are removed by
+    /// [`reset_params`](ParamsTrait::reset_params) as well.
+    pub fn set_configuration_groups(&mut self, groups: Array1<usize>) -> Result<(), ParamsError> {
+        if groups.is_empty() {
+            let message = format!("No parent configuration for node {}", self.label);
+            warn!("{}", message);
+            return Err(ParamsError::InvalidConfigurationGroups(message));
+        }
+        let n_groups = groups.iter().max().map_or(0, |x| x + 1);
+        if let Some(g) = (0..n_groups).find(|g| !groups.iter().any(|x| x == g)) {
+            let message = format!("Group {} of node {} is empty", g, self.label);
+            warn!("{}", message);
+            return Err(ParamsError::InvalidConfigurationGroups(message));
+        }
+        debug!(
+            "Setting {} groups of parent configurations for node {}",
+            n_groups, self.label
+        );
+        self.cim = None;
+        self.piecewise_cim = None;
+        self.transitions = None;
+        self.residence_time = None;
+        self.rate_multipliers = None;
+        self.configuration_groups = Some(groups);
+        Ok(())
+    }
+
+    /// Remove the groups of parent configurations, resetting the CIM and the sufficient
+    /// statistics.
+    pub fn reset_configuration_groups(&mut self) {
+        debug!("Removing the configuration groups of node {}", self.label);
+        self.cim = None;
+        self.piecewise_cim = None;
+        self.transitions = None;
+        self.residence_time = None;
+        self.configuration_groups = None;
+    }
+
+    /// Getter function for the groups of parent configurations.
+    pub fn get_configuration_groups(&self) -> Option<&Array1<usize>> {
+        self.configuration_groups.as_ref()
+    }
+
+    /// Number of groups of parent configurations, `None` if the parameters are not tied.
+    pub fn get_number_of_configuration_groups(&self) -> Option<usize> {
+        self.configuration_groups
+            .as_ref()
+            .map(|groups| groups.iter().max().map_or(0, |x| x + 1))
+    }
+
     /// Unchecked version of the setter function for CIM.
     pub fn set_cim_unchecked(&mut self, cim: Array3<f64>) {
         debug!("Setting cim (unchecked) for node {}", self.label);
         self.cim = Some(cim);
+        self.piecewise_cim = None;
+        self.rate_multipliers = None;
+    }
+
+    /// Set a random CIM with `n_parent_configs` matrices.
+    ///
+    /// The exit rate of each state (the opposite of the diagonal) is drawn from `dist`, while the
+    /// probability of jumping to each other state is uniformly drawn and normalized, so that
+    /// every row sums to zero.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rand_chacha::ChaCha8Rng;
+    /// use rand_chacha::rand_core::SeedableRng;
+    /// use reCTBN::params::{self, Gamma, ParamsTrait};
+    ///
+    /// let mut rng = ChaCha8Rng::seed_from_u64(6813071588535822);
+    /// let params::Params::DiscreteStatesContinousTime(mut param) =
+    ///     params::discrete_node("X", &["A", "B", "C"]);
+    /// param.randomize_cim(2, &Gamma { shape: 2.0, rate: 1.0 }, &mut rng);
+    ///
+    /// assert_eq!(param.get_cim().as_ref().unwrap().shape(), [2, 3, 3]);
+    /// assert_eq!(Ok(()), param.validate_params());
+    /// ```
+    pub fn randomize_cim(
+        &mut self,
+        n_parent_configs: usize,
+        dist: &impl RateDistribution,
+        rng: &mut ChaCha8Rng,
+    ) {
+        let node_domain_cardinality = self.domain.len();
+        let mut cim = Array3::<f64>::from_shape_fn(
+            (
+                n_parent_configs,
+                node_domain_cardinality,
+                node_domain_cardinality,
+            ),
+            |_| rng.gen(),
+        );
+        cim.axis_iter_mut(Axis(0)).for_each(|mut x| {
+            x.diag_mut().fill(0.0);
+            x.div_assign(&x.sum_axis(Axis(1)).insert_axis(Axis(1)));
+            let diag =
+                Array1::<f64>::from_shape_fn(node_domain_cardinality, |_| dist.sample_rate(rng));
+            x.mul_assign(&diag.insert_axis(Axis(1)));
+            // Recomputing the diagonal in order to reduce the issues caused by the loss of
+            // precision when validating the parameters.
+            let diag_sum = -x.sum_axis(Axis(1));
+            x.diag_mut().assign(&diag_sum)
+        });
+        self.set_cim_unchecked(cim);
+    }
+
+    /// Setter function for the noisy-OR parameterization of the CIM.
+    ///
+    /// Instead of one CIM for each parent configuration, every parent scales the rates of a
+    /// single `base_cim` (shape `[n, n]`): under the parent configuration `u` the row `x` of the
+    /// CIM is the row `x` of `base_cim` multiplied by `rate_multipliers[i][[s_i, x]]` for each
+    /// parent `i` (in ascending order of node index) in state `s_i`. Each multiplier has shape
+    /// `[parent cardinality, n]`; usually the multipliers of the first state of each parent are
+    /// `1.0`. Since a whole row is scaled, the parents change how long the node stays in a
+    /// state but not the state it moves to.
+    ///
+    /// The parameters are validated as done by [`set_cim`](Self::set_cim), replacing the groups
+    /// of parent configurations, if any. A CIM set with [`set_cim`](Self::set_cim) replaces the
+    /// noisy-OR parameterization.
+    pub fn set_noisy_or_cim(
+        &mut self,
+        base_cim: Array2<f64>,
+        rate_multipliers: Vec<Array2<f64>>,
+    ) -> Result<(), ParamsError> {
+        debug!("Setting noisy-OR cim for node {}", self.label);
+        self.set_noisy_or_cim_unchecked(base_cim, rate_multipliers);
+        match self.validate_params() {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                warn!("Validation noisy-OR cim faild for node {}", self.label);
+                self.cim = None;
+                self.rate_multipliers = None;
+                Err(e)
+            }
+        }
+    }
+
+    /// Unchecked version of the setter function for the noisy-OR parameterization of the CIM.
+    pub fn set_noisy_or_cim_unchecked(
+        &mut self,
+        base_cim: Array2<f64>,
+        rate_multipliers: Vec<Array2<f64>>,
+    ) {
+        debug!("Setting noisy-OR cim (unchecked) for node {}", self.label);
+        self.cim = Some(base_cim.insert_axis(Axis(0)));
+        self.piecewise_cim = None;
+        self.configuration_groups = None;
+        self.rate_multipliers = Some(rate_multipliers);
+    }
+
+    /// Getter function for the noisy-OR rate multipliers of each parent.
+    pub fn get_rate_multipliers(&self) -> Option<&Vec<Array2<f64>>> {
+        self.rate_multipliers.as_ref()
+    }
+
+    /// Factor scaling the rates of the row `state` of the CIM under the parent configuration
+    /// `u`; `1.0` if the CIM is not parameterized as a noisy-OR.
+    pub fn get_rate_multiplier(&self, u: usize, state: usize) -> f64 {
+        match &self.rate_multipliers {
+            Some(rate_multipliers) => {
+                let mut rest = u;
+                rate_multipliers
+                    .iter()
+                    .map(|multipliers| {
+                        let parent_state = rest % multipliers.shape()[0];
+                        rest /= multipliers.shape()[0];
+                        multipliers[[parent_state, state]]
+                    })
+                    .product()
+            }
+            None => 1.0,
+        }
+    }
+
+    /// Rate of the transition from the state `from` to the state `to` under the parent
+    /// configuration `u`.
+    ///
+    /// The groups of parent configurations and the noisy-OR multipliers are taken into account;
+    /// when `from == to` the rate is the diagonal entry of the CIM, i.e. minus the exit rate. The
+    /// rates of a piecewise-constant CIM are the ones of the first segment (see
+    /// [`get_cim_at`](Self::get_cim_at)).
+    pub fn rate(&self, u: usize, from: usize, to: usize) -> Result<f64, ParamsError> {
+        let cim = self.check_rate_indices(u, &[from, to])?;
+        Ok(cim[[self.get_cim_index(u), from, to]] * self.get_rate_multiplier(u, from))
+    }
+
+    /// Rate at which the node leaves `state` under the parent configuration `u`.
+    pub fn exit_rate(&self, u: usize, state: usize) -> Result<f64, ParamsError> {
+        self.rate(u, state, state).map(|x| -x)
+    }
+
+    /// Probability of each state to be the next one when the node leaves `state` under the
+    /// parent configuration `u` (the row of the embedded jump chain).
+    ///
+    /// An absorbing state, whose exit rate is zero, moves to itself with probability `1.0`.
+    pub fn transition_probabilities(
+        &self,
+        u: usize,
+        state: usize,
+    ) -> Result<Array1<f64>, ParamsError> {
+        let cim = self.check_rate_indices(u, &[state])?;
+        Ok(self.transition_probabilities_from_cim(cim, u, state))
+    }
+
+    /// Stationary distribution `π` of the node under the parent configuration `u`, i.e. the
+    /// solution of `πQ = 0` with `π·1 = 1`, where `Q` is the CIM of `u`.
+    ///
+    /// The distribution is unique when the states from which the node cannot escape form a single
+    /// closed class; the transient states get probability `0.0`. If there are several closed
+    /// classes (e.g. two absorbing states) the stationary distribution is not unique and an
+    /// [`InvalidCIM`](ParamsError::InvalidCIM) error is returned.
+    pub fn stationary_distribution(&self, u: usize) -> Result<Array1<f64>, ParamsError> {
+        self.check_rate_indices(u, &[])?;
+        let n_states = self.domain.len();
+        let mut q = Array2::<f64>::zeros((n_states, n_states));
+        for ((from, to), x) in q.indexed_iter_mut() {
+            *x = self.rate(u, from, to)?;
+        }
+
+        //reachable[[x, y]] is true when the node can move from x to y.
+        let mut reachable =
+            Array2::from_shape_fn((n_states, n_states), |(x, y)| x == y || q[[x, y]] > 0.0);
+        for k in 0..n_states {
+            for x in 0..n_states {
+                for y in 0..n_states {
+                    reachable[[x, y]] =
+                        reachable[[x, y]] || (reachable[[x, k]] && reachable[[k, y]]);
+                }
+            }
+        }
+        //A closed class is identified by its smallest state.
+        let n_closed_classes = (0..n_states)
+            .filter(|x| (0..n_states).all(|y| !reachable[[*x, y]] || reachable[[y, *x]]))
+            .filter(|x| (0..*x).all(|y| !(reachable[[*x, y]] && reachable[[y, *x]])))
+            .count();
+        if n_closed_classes > 1 {
+            return Err(ParamsError::InvalidCIM(format!(
+                "The CIM of parent configuration {} of node {} has {} closed classes: the \
+                 stationary distribution is not unique",
+                u, self.label, n_closed_classes
+            )));
+        }
+
+        //Solve Q^T π = 0, replacing the last equation with the normalization constraint.
+        let mut a = q.reversed_axes();
+        a.row_mut(n_states - 1).fill(1.0);
+        let mut b = Array1::<f64>::zeros(n_states);
+        b[n_states - 1] = 1.0;
+        Ok(solve_linear_system(a, b))
+    }
+
+    /// Expected time spent in each state before leaving it under the parent configuration `u`,
+    /// i.e. the reciprocal of the exit rates; it is infinite for the absorbing states.
+    pub fn expected_sojourn_times(&self, u: usize) -> Result<Array1<f64>, ParamsError> {
+        self.check_rate_indices(u, &[])?;
+        (0..self.domain.len())
+            .map(|state| {
+                self.exit_rate(u, state)
+                    .map(|x| if x == 0.0 { f64::INFINITY } else { 1.0 / x })
+            })
+            .collect()
+    }
+
+    //Return the CIM, checking that it is set and that `u` and `states` are valid indices.
+    fn check_rate_indices(&self, u: usize, states: &[usize]) -> Result<&Array3<f64>, ParamsError> {
+        let cim = match &self.cim {
+            Some(cim) => cim,
+            None => {
+                warn!("Cim not initialized for node {}", self.get_label());
+                return Err(ParamsError::ParametersNotInitialized(String::from(
+                    "CIM not initialized",
+                )));
+            }
+        };
+        let n_configurations = self.number_of_configurations(cim);
+        if u >= n_configurations {
+            return Err(ParamsError::InvalidIndex(format!(
+                "Parent configuration {} out of range for node {} with {} parent configurations",
+                u, self.label, n_configurations
+            )));
+        }
+        if let Some(state) = states.iter().find(|x| **x >= self.domain.len()) {
+            return Err(ParamsError::InvalidIndex(format!(
+                "State {} out of range for node {} with {} states",
+                state,
+                self.label,
+                self.domain.len()
+            )));
+        }
+        Ok(cim)
+    }
+
+    /// CIM with one matrix for each parent configuration, expanding the groups of parent
+    /// configurations and the noisy-OR parameterization; `None` if the CIM is not set.
+    pub fn get_expanded_cim(&self) -> Option<Array3<f64>> {
+        let cim = self.cim.as_ref()?;
+        let (_, n_states, _) = cim.dim();
+        Some(Array3::from_shape_fn(
+            (self.number_of_configurations(cim), n_states, n_states),
+            |(u, x, y)| cim[[self.get_cim_index(u), x, y]] * self.get_rate_multiplier(u, x),
+        ))
+    }
+
+    //Number of parent configurations described by `cim`.
+    fn number_of_configurations(&self, cim: &Array3<f64>) -> usize {
+        match (&self.configuration_groups, &self.rate_multipliers) {
+            (Some(groups), _) => groups.len(),
+            (None, Some(rate_multipliers)) => {
+                rate_multipliers.iter().map(|x| x.shape()[0]).product()
+            }
+            (None, None) => cim.shape()[0],
+        }
+    }
+
+    /// Getter function for the piecewise-constant CIM.
+    ///
+    /// Each segment is a pair `(t_boundary, cim)`: `cim` is active from `t_boundary` up to the
+    /// boundary of the next segment.
+    pub fn get_piecewise_cim(&self) -> &Option<Vec<(f64, Array3<f64>)>> {
+        debug!("Getting piecewise cim from node {}", self.label);
+        &self.piecewise_cim
+    }
+
+    /// Setter function for a time-inhomogeneous, piecewise-constant CIM.
+    ///
+    /// The first segment must start at `0.0`, the boundaries must be strictly increasing and each
+    /// CIM must be valid (see [`set_cim`](Self::set_cim)). The CIM of the first segment is also
+    /// returned by [`get_cim`](Self::get_cim). The segments replace the noisy-OR parameterization
+    /// and the groups of parent configurations. On error, both the CIMs are replaced with `None`.
+    pub fn set_piecewise_cim(
+        &mut self,
+        piecewise_cim: Vec<(f64, Array3<f64>)>,
+    ) -> Result<(), ParamsError> {
+        debug!("Setting piecewise cim for node {}", self.label);
+        let boundaries_error = match piecewise_cim.first() {
+            None => Some("At least one segment is required"),
+            Some((t, _)) if *t != 0.0 => Some("The first segment must start at 0.0"),
+            _ if piecewise_cim.windows(2).any(|x| x[0].0 >= x[1].0) => {
+                Some("The boundaries must be strictly increasing")
+            }
+            _ => None,
+        };
+        if let Some(message) = boundaries_error {
+            warn!("Invalid piecewise cim for node {}: {}", self.label, message);
+            self.cim = None;
+            self.piecewise_cim = None;
+            return Err(ParamsError::InvalidCIM(String::from(message)));
+        }
+        //Each segment holds one matrix per parent configuration.
+        self.configuration_groups = None;
+        for (_, cim) in piecewise_cim.iter() {
+            self.set_cim(cim.clone())?;
+        }
+        self.set_piecewise_cim_unchecked(piecewise_cim);
+        Ok(())
+    }
+
+    /// Unchecked version of the setter function for the piecewise-constant CIM.
+    pub fn set_piecewise_cim_unchecked(&mut self, piecewise_cim: Vec<(f64, Array3<f64>)>) {
+        debug!("Setting piecewise cim (unchecked) for node {}", self.label);
+        self.cim = piecewise_cim.first().map(|(_, cim)| cim.clone());
+        self.piecewise_cim = Some(piecewise_cim);
+        self.configuration_groups = None;
+        self.rate_multipliers = None;
     }
 
     /// Getter function for transitions.
@@ -164,6 +817,104 @@ impl DiscreteStatesContinousTimeParams {
         debug!("Set residence time for node {}", self.label);
         self.residence_time = Some(residence_time);
     }
+
+    /// Standard errors and Wald confidence intervals of the rates, computed from the stored
+    /// sufficient statistics as done by
+    /// [`mle_confidence_intervals`](crate::parameter_learning::mle_confidence_intervals).
+    ///
+    /// # Arguments
+    ///
+    /// * `p`: confidence level of the intervals (e.g. `0.95`).
+    pub fn get_confidence_intervals(
+        &self,
+        p: f64,
+    ) -> Result<parameter_learning::RateConfidenceIntervals, ParamsError> {
+        match (&self.transitions, &self.residence_time) {
+            (Some(transitions), Some(residence_time)) => Ok(
+                parameter_learning::mle_confidence_intervals(transitions, residence_time, p),
+            ),
+            _ => Err(ParamsError::ParametersNotInitialized(String::from(
+                "Sufficient statistics not initialized",
+            ))),
+        }
+    }
+
+    //Row `state` of the embedded jump chain under the parent configuration `u`, without checking
+    //the indices.
+    fn transition_probabilities_from_cim(
+        &self,
+        cim: &Array3<f64>,
+        u: usize,
+        state: usize,
+    ) -> Array1<f64> {
+        let row = cim.slice(s![self.get_cim_index(u), state, ..]);
+        let lambda = row[state] * -1.0;
+        if lambda * self.get_rate_multiplier(u, state) > 0.0 {
+            let mut probabilities = row.map(|x| x / lambda);
+            probabilities[state] = 0.0;
+            probabilities
+        } else {
+            Array1::from_shape_fn(row.len(), |y| if y == state { 1.0 } else { 0.0 })
+        }
+    }
+
+    fn get_random_state_from_cim(
+        &self,
+        cim: Option<&Array3<f64>>,
+        state: usize,
+        u: usize,
+        rng: &mut ChaCha8Rng,
+    ) -> Result<StateType, ParamsError> {
+        // Generate a random transition given the current state of the node and its parent set.
+        // The method used is described in:
+        // https://en.wikipedia.org/wiki/Multinomial_distribution#Sampling_from_a_multinomial_distribution
+        match cim {
+            Option::Some(cim) => {
+                let probabilities = self.transition_probabilities_from_cim(cim, u, state);
+                let urand: f64 = rng.gen_range(0.0..=1.0);
+
+                // The first state whose cumulative probability reaches urand; the last reachable
+                // state absorbs the rounding errors of the cumulative sum.
+                let mut cumulative = 0.0;
+                let mut next_state = state;
+                for (y, p) in probabilities.iter().enumerate().filter(|(_, p)| **p > 0.0) {
+                    next_state = y;
+                    cumulative += p;
+                    if cumulative >= urand {
+                        break;
+                    }
+                }
+
+                let next_state = StateType::Discrete(next_state);
+                trace!(
+                    "Generate random state. Node: {} - State: {:?}",
+                    self.get_label(),
+                    next_state
+                );
+
+                Ok(next_state)
+            }
+            Option::None => {
+                warn!("Cim not initialized for node {}", self.get_label());
+                Err(ParamsError::ParametersNotInitialized(String::from(
+                    "CIM not initialized",
+                )))
+            }
+        }
+    }
+
+    //CIM active at time t.
+    fn get_cim_at(&self, t: f64) -> Option<&Array3<f64>> {
+        match &self.piecewise_cim {
+            Some(piecewise_cim) => piecewise_cim
+                .iter()
+                .rev()
+                .find(|(boundary, _)| *boundary <= t)
+                .or(piecewise_cim.first())
+                .map(|(_, cim)| cim),
+            None => self.cim.as_ref(),
+        }
+    }
 }
 
 impl ParamsTrait for DiscreteStatesContinousTimeParams {
@@ -173,8 +924,11 @@ impl ParamsTrait for DiscreteStatesContinousTimeParams {
             self.label
         );
         self.cim = Option::None;
+        self.piecewise_cim = Option::None;
         self.transitions = Option::None;
         self.residence_time = Option::None;
+        self.configuration_groups = Option::None;
+        self.rate_multipliers = Option::None;
     }
 
     fn get_random_state_uniform(&self, rng: &mut ChaCha8Rng) -> StateType {
@@ -198,7 +952,9 @@ impl ParamsTrait for DiscreteStatesContinousTimeParams {
         // https://en.wikipedia.org/wiki/Exponential_distribution#Generating_exponential_variates
         match &self.cim {
             Option::Some(cim) => {
-                let lambda = cim[[u, state, state]] * -1.0;
+                let lambda = cim[[self.get_cim_index(u), state, state]]
+                    * -1.0
+                    * self.get_rate_multiplier(u, state);
                 let x: f64 = rng.gen_range(0.0..=1.0);
                 let ret = -x.ln() / lambda;
                 trace!(
@@ -217,64 +973,86 @@ impl ParamsTrait for DiscreteStatesContinousTimeParams {
         }
     }
 
-    fn get_random_state(
+    fn get_random_transition_time(
         &self,
         state: usize,
         u: usize,
+        t: f64,
         rng: &mut ChaCha8Rng,
-    ) -> Result<StateType, ParamsError> {
-        // Generate a random transition given the current state of the node and its parent set.
-        // The method used is described in:
-        // https://en.wikipedia.org/wiki/Multinomial_distribution#Sampling_from_a_multinomial_distribution
-        match &self.cim {
-            Option::Some(cim) => {
-                let lambda = cim[[u, state, state]] * -1.0;
-                let urand: f64 = rng.gen_range(0.0..=1.0);
-
-                let next_state = cim.slice(s![u, state, ..]).map(|x| x / lambda).iter().fold(
-                    (0, 0.0),
-                    |mut acc, ele| {
-                        if &acc.1 + ele < urand && ele > &0.0 {
-                            acc.0 += 1;
-                        }
-                        if ele > &0.0 {
-                            acc.1 += ele;
-                        }
-                        acc
-                    },
-                );
-
-                let next_state = if next_state.0 < state {
-                    next_state.0
-                } else {
-                    next_state.0 + 1
-                };
-
-                let next_state = StateType::Discrete(next_state);
-                trace!(
-                    "Generate random state. Node: {} - State: {:?}",
-                    self.get_label(),
-                    next_state
-                );
-
-                Ok(next_state)
+    ) -> Result<f64, ParamsError> {
+        let piecewise_cim = match &self.piecewise_cim {
+            Some(piecewise_cim) => piecewise_cim,
+            None => {
+                return self
+                    .get_random_residence_time(state, u, rng)
+                    .map(|residence_time| residence_time + t)
             }
-            Option::None => {
-                warn!("Cim not initialized for node {}", self.get_label());
-                Err(ParamsError::ParametersNotInitialized(String::from(
-                    "CIM not initialized",
-                )))
+        };
+        // The rates are constant within each segment: if the sampled transition falls beyond the
+        // end of the active segment, the time is moved to the boundary and, thanks to the
+        // memorylessness of the exponential distribution, a new residence time is sampled with
+        // the rates of the next segment.
+        let u = self.get_cim_index(u);
+        let mut t = t;
+        loop {
+            let segment = piecewise_cim
+                .iter()
+                .rposition(|(boundary, _)| *boundary <= t)
+                .unwrap_or(0);
+            let lambda = piecewise_cim[segment].1[[u, state, state]] * -1.0;
+            let x: f64 = rng.gen_range(0.0..=1.0);
+            //A null exit rate means that the node never leaves the state within the segment.
+            let transition_time = if lambda > 0.0 {
+                t + -x.ln() / lambda
+            } else {
+                f64::INFINITY
+            };
+            match piecewise_cim.get(segment + 1) {
+                Some((boundary, _)) if transition_time >= *boundary => t = *boundary,
+                _ => {
+                    trace!(
+                        "Generate random transition time. Node: {} - Time: {}",
+                        self.get_label(),
+                        transition_time
+                    );
+                    return Ok(transition_time);
+                }
             }
         }
     }
 
+    fn get_random_state(
+        &self,
+        state: usize,
+        u: usize,
+        rng: &mut ChaCha8Rng,
+    ) -> Result<StateType, ParamsError> {
+        self.get_random_state_from_cim(self.cim.as_ref(), state, u, rng)
+    }
+
+    fn get_random_state_at(
+        &self,
+        state: usize,
+        u: usize,
+        t: f64,
+        rng: &mut ChaCha8Rng,
+    ) -> Result<StateType, ParamsError> {
+        self.get_random_state_from_cim(self.get_cim_at(t), state, u, rng)
+    }
+
     fn get_reserved_space_as_parent(&self) -> usize {
         self.domain.len()
     }
 
     fn state_to_index(&self, state: &StateType) -> usize {
-        match state {
-            StateType::Discrete(val) => val.clone() as usize,
+        usize::from(*state)
+    }
+
+    fn get_cim_index(&self, u: usize) -> usize {
+        match (&self.configuration_groups, &self.rate_multipliers) {
+            (Some(groups), _) => groups[u],
+            (None, Some(_)) => 0,
+            (None, None) => u,
         }
     }
 
@@ -300,6 +1078,52 @@ impl ParamsTrait for DiscreteStatesContinousTimeParams {
             return Err(ParamsError::InvalidCIM(message));
         }
 
+        // Check if the cim has one matrix for each group of parent configurations
+        if let Some(n_groups) = self.get_number_of_configuration_groups() {
+            if cim.shape()[0] != n_groups {
+                let message = format!(
+                    "Incompatible shape {:?} with {} groups of parent configurations",
+                    cim.shape(),
+                    n_groups
+                );
+                warn!("{}", message);
+                return Err(ParamsError::InvalidCIM(message));
+            }
+        }
+
+        // Check if the noisy-OR parameterization has a single base cim and a non-negative
+        // multiplier for each state of the parents and of the node
+        if let Some(rate_multipliers) = &self.rate_multipliers {
+            if cim.shape()[0] != 1 {
+                let message = format!(
+                    "Incompatible shape {:?} with a noisy-OR base cim",
+                    cim.shape()
+                );
+                warn!("{}", message);
+                return Err(ParamsError::InvalidCIM(message));
+            }
+            for (i, multipliers) in rate_multipliers.iter().enumerate() {
+                if multipliers.shape()[0] == 0 || multipliers.shape()[1] != domain_size {
+                    let message = format!(
+                        "Incompatible shape {:?} of the rate multipliers of parent {} with domain {}",
+                        multipliers.shape(),
+                        i,
+                        domain_size
+                    );
+                    warn!("{}", message);
+                    return Err(ParamsError::InvalidCIM(message));
+                }
+                if multipliers.iter().any(|x| !x.is_finite() || *x < 0.0) {
+                    let message = format!(
+                        "The rate multipliers of parent {} must be finite and non-negative",
+                        i
+                    );
+                    warn!("{}", message);
+                    return Err(ParamsError::InvalidCIM(message));
+                }
+            }
+        }
+
         // Check if the diagonal of each cim is non-positive
         if cim
             .axis_iter(Axis(0))
@@ -314,19 +1138,20 @@ impl ParamsTrait for DiscreteStatesContinousTimeParams {
             )));
         }
 
-        // Check if each row sum up to 0
-        if cim
-            .sum_axis(Axis(2))
-            .iter()
-            .any(|x| f64::abs(x.clone()) > f64::EPSILON.sqrt())
-        {
-            warn!(
-                "The sum of each row of the cim for node {} must be 0",
-                self.get_label()
-            );
-            return Err(ParamsError::InvalidCIM(String::from(
-                "The sum of each row must be 0",
-            )));
+        // Check if each row sum up to 0, relatively to the largest rate of the row
+        for (u, matrix) in cim.outer_iter().enumerate() {
+            for (x, row) in matrix.outer_iter().enumerate() {
+                let sum = row.sum();
+                let scale = row.iter().fold(0.0, |acc: f64, x| acc.max(x.abs()));
+                if sum.is_nan() || f64::abs(sum) > self.tolerance * scale {
+                    let message = format!(
+                        "The sum of each row must be 0: row {} of parent configuration {} sums to {}",
+                        x, u, sum
+                    );
+                    warn!("{} for node {}", message, self.get_label());
+                    return Err(ParamsError::InvalidCIM(message));
+                }
+            }
         }
 
         return Ok(());
@@ -336,3 +1161,150 @@ impl ParamsTrait for DiscreteStatesContinousTimeParams {
         &self.label
     }
 }
+
+/// Print the label and the domain of the node, followed by the CIM of each parent
+/// configuration as a table with aligned columns.
+///
+/// The parent configurations are named by their index; use
+/// [`Params::display_with_parents`] to name them after the states of the parents. The precision of
+/// the formatter, if any, is used for the rates (e.g. `{:.2}`).
+impl fmt::Display for DiscreteStatesContinousTimeParams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(
+            &ParamsDisplay {
+                param: self,
+                parents: None,
+            },
+            f,
+        )
+    }
+}
+
+/// Helper returned by [`Params::display_with_parents`].
+pub struct ParamsDisplay<'a> {
+    param: &'a DiscreteStatesContinousTimeParams,
+    parents: Option<Vec<&'a Params>>,
+}
+
+impl<'a> ParamsDisplay<'a> {
+    //Name of the `idx`-th matrix of the CIM: the parent configuration or, when the parameters are
+    //tied, the configurations of the group separated by ` | `.
+    fn matrix_name(&self, idx: usize) -> String {
+        if self.param.get_rate_multipliers().is_some() {
+            return String::from("base");
+        }
+        match (self.param.get_configuration_groups(), &self.parents) {
+            (None, _) => self.configuration_name(idx),
+            (Some(_), None) => format!("group {}", idx),
+            (Some(groups), Some(_)) => groups
+                .iter()
+                .enumerate()
+                .filter(|(_, g)| **g == idx)
+                .map(|(u, _)| self.configuration_name(u))
+                .collect::<Vec<String>>()
+                .join(" | "),
+        }
+    }
+
+    //Name of the parent configuration `u`, e.g. `X1=A, X2=B`.
+    fn configuration_name(&self, u: usize) -> String {
+        match &self.parents {
+            None => format!("configuration {}", u),
+            Some(parents) => {
+                let mut stride = 1;
+                parents
+                    .iter()
+                    .map(|parent| {
+                        let cardinality = parent.get_reserved_space_as_parent();
+                        let state = (u / stride) % cardinality;
+                        stride *= cardinality;
+                        match parent {
+                            Params::DiscreteStatesContinousTime(p) => format!(
+                                "{}={}",
+                                p.get_label(),
+                                p.state_label(state).map_or("?", |x| x.as_str())
+                            ),
+                        }
+                    })
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            }
+        }
+    }
+}
+
+impl<'a> fmt::Display for ParamsDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let labels: Vec<&String> = self.param.get_domain().iter().collect();
+        writeln!(
+            f,
+            "{} {{{}}}",
+            self.param.get_label(),
+            labels
+                .iter()
+                .map(|x| x.as_str())
+                .collect::<Vec<&str>>()
+                .join(", ")
+        )?;
+        let cim = match self.param.get_cim() {
+            Some(cim) => cim,
+            None => return writeln!(f, "cim: not set"),
+        };
+
+        let rates: Array3<String> = cim.map(|x| match f.precision() {
+            Some(precision) => format!("{:.*}", precision, x),
+            None => format!("{}", x),
+        });
+        let label_width = labels.iter().map(|x| x.len()).max().unwrap_or(0);
+        let width = rates
+            .iter()
+            .map(|x| x.len())
+            .chain(labels.iter().map(|x| x.len()))
+            .max()
+            .unwrap_or(0);
+
+        for (u, matrix) in rates.outer_iter().enumerate() {
+            let name = self.matrix_name(u);
+            if !name.is_empty() {
+                writeln!(f, "{}", name)?;
+            }
+            write!(f, "  {:label_width$}", "")?;
+            for label in labels.iter() {
+                write!(f, " {:>width$}", label)?;
+            }
+            writeln!(f)?;
+            for (label, row) in labels.iter().zip(matrix.outer_iter()) {
+                write!(f, "  {:label_width$}", label)?;
+                for rate in row.iter() {
+                    write!(f, " {:>width$}", rate)?;
+                }
+                writeln!(f)?;
+            }
+        }
+
+        if let Some(rate_multipliers) = self.param.get_rate_multipliers() {
+            writeln!(f, "rate multipliers")?;
+            for (i, multipliers) in rate_multipliers.iter().enumerate() {
+                for (parent_state, row) in multipliers.outer_iter().enumerate() {
+                    let name = match self.parents.as_ref().and_then(|x| x.get(i)) {
+                        Some(Params::DiscreteStatesContinousTime(p)) => format!(
+                            "{}={}",
+                            p.get_label(),
+                            p.state_label(parent_state).map_or("?", |x| x.as_str())
+                        ),
+                        None => format!("parent {}={}", i, parent_state),
+                    };
+                    write!(f, "  {}:", name)?;
+                    for multiplier in row.iter() {
+                        match f.precision() {
+                            Some(precision) => write!(f, " {:.*}", precision, multiplier)?,
+                            None => write!(f, " {}", multiplier)?,
+                        }
+                    }
+                    writeln!(f)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -3,17 +3,26 @@
 pub mod ctbn;
 pub mod ctmp;
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
 
 use thiserror::Error;
 
-use crate::params;
+use crate::params::{self, ParamsTrait};
 
 /// Error types for trait Network
 #[derive(Error, Debug)]
 pub enum NetworkError {
     #[error("Error during node insertion")]
     NodeInsertionError(String),
+    #[error("Error during node removal")]
+    NodeRemovalError(String),
+    #[error("Unknown node: {0}")]
+    UnknownNode(String),
+    #[error("Invalid model: {0}")]
+    InvalidModel(String),
+    #[error("I/O error: {0}")]
+    Io(String),
 }
 
 /// This type is used to represent a specific realization of a generic NetworkProcess
@@ -33,7 +42,8 @@ pub trait NetworkProcess: Sync {
     ///  # Return
     ///
     ///  * A `Result` containing the `node_idx` automatically assigned if everything is fine,
-    ///    or a `NetworkError` if something went wrong.
+    ///    or a `NetworkError` if something went wrong (e.g. a node with the same label already
+    ///    exists).
     fn add_node(&mut self, n: params::Params) -> Result<usize, NetworkError>;
 
     /// Add a **directed edge** between a two nodes of the network.
@@ -44,12 +54,55 @@ pub trait NetworkProcess: Sync {
     /// * `child` - child node.
     fn add_edge(&mut self, parent: usize, child: usize);
 
+    /// Remove the **directed edge** between two nodes of the network, if it exists.
+    ///
+    /// As for [`add_edge`](NetworkProcess::add_edge), the parameters of the child are reset.
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - parent node.
+    /// * `child` - child node.
+    fn remove_edge(&mut self, parent: usize, child: usize);
+
+    /// Remove a **node** and all its edges from the network.
+    ///
+    /// The nodes following the removed one are shifted down by one index and the parameters of
+    /// its children are reset.
+    ///
+    /// # Arguments
+    ///
+    /// * `node_idx` - index of the node to remove.
+    ///
+    /// # Return
+    ///
+    /// * A `Result` containing, for each old index, the new index of the node (`None` for the
+    ///   removed node), or a `NetworkError` if `node_idx` is not a node of the network.
+    fn remove_node(&mut self, node_idx: usize) -> Result<Vec<Option<usize>>, NetworkError>;
+
+    /// Check whether the **directed edge** `parent -> child` is in the network.
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - parent node.
+    /// * `child` - child node.
+    fn has_edge(&self, parent: usize, child: usize) -> bool;
+
+    /// Iterate over all the **directed edges** of the network as `(parent, child)` pairs,
+    /// ordered by parent and then by child.
+    fn edges(&self) -> impl Iterator<Item = (usize, usize)> + '_;
+
     /// Get all the indices of the nodes contained inside the network.
     fn get_node_indices(&self) -> std::ops::Range<usize>;
 
     /// Get the numbers of nodes contained in the network.
     fn get_number_of_nodes(&self) -> usize;
 
+    /// Get the index of the node labelled `label`, `None` if there is no such node.
+    fn get_node_index_by_label(&self, label: &str) -> Option<usize> {
+        self.get_node_indices()
+            .find(|node| self.get_node(*node).get_label() == label)
+    }
+
     /// Get the **node param**.
     ///
     /// # Arguments
@@ -88,6 +141,23 @@ pub trait NetworkProcess: Sync {
     /// * Index of the `node` relative to the network.
     fn get_param_index_network(&self, node: usize, current_state: &NetworkProcessState) -> usize;
 
+    /// Describe how [`get_param_index_network`](NetworkProcess::get_param_index_network) combines
+    /// the states of the parents of a `node`, so that the index can be computed without scanning
+    /// the whole network.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - selected node.
+    ///
+    /// # Return
+    ///
+    /// * `Some` vector of `(parent, multiplier)` pairs such that the index is the sum of
+    ///   `state_to_index(current_state[parent]) * multiplier`, or `None` if the index is not
+    ///   computed this way.
+    fn get_param_index_multipliers(&self, _node: usize) -> Option<Vec<(usize, usize)>> {
+        None
+    }
+
     /// Compute the index that must be used to access the parameters of a `node`, given a specific
     /// configuration of the network and a generic `parent_set`.
     ///
@@ -129,4 +199,115 @@ pub trait NetworkProcess: Sync {
     ///
     /// * The **children set** of the selected node.
     fn get_children_set(&self, node: usize) -> BTreeSet<usize>;
+
+    /// Get the **Markov blanket** of a given **node**: its parents, its children and the other
+    /// parents of its children.
+    ///
+    /// Given its Markov blanket, the trajectory of the node is independent of the rest of the
+    /// network. The node itself is never part of the blanket, even when the network has cycles.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - node index value.
+    ///
+    /// # Return
+    ///
+    /// * The **Markov blanket** of the selected node.
+    fn get_markov_blanket(&self, node: usize) -> BTreeSet<usize> {
+        self.get_markov_blanket_by_role(node).nodes()
+    }
+
+    /// Get the **Markov blanket** of a given **node**, keeping the co-parents of each child
+    /// separated.
+    ///
+    /// In a CTBN the transition intensities of a child depend on the joint state of all its
+    /// parents, so the co-parents of each child are needed to select the CIM through which the
+    /// child reacts to the transitions of the node; see [`MarkovBlanket`].
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - node index value.
+    fn get_markov_blanket_by_role(&self, node: usize) -> MarkovBlanket {
+        let parents = self.get_parent_set(node);
+        let children = self.get_children_set(node);
+        let co_parents = children
+            .iter()
+            .map(|child| {
+                let mut co_parents = self.get_parent_set(*child);
+                co_parents.remove(&node);
+                (*child, co_parents)
+            })
+            .collect();
+        MarkovBlanket {
+            node,
+            parents,
+            children,
+            co_parents,
+        }
+    }
+}
+
+/// Markov blanket of a node of a `NetworkProcess`, split by the role of each node.
+///
+/// # Attributes
+///
+/// * `node` - the node whose blanket is described.
+/// * `parents` - the parent set of the node, selecting its own CIM.
+/// * `children` - the children set of the node.
+/// * `co_parents` - for each child, its parents other than the node: together with the state of
+///   the node they select the CIM of the child.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MarkovBlanket {
+    pub node: usize,
+    pub parents: BTreeSet<usize>,
+    pub children: BTreeSet<usize>,
+    pub co_parents: BTreeMap<usize, BTreeSet<usize>>,
+}
+
+impl MarkovBlanket {
+    /// All the nodes of the blanket, without the node itself.
+    pub fn nodes(&self) -> BTreeSet<usize> {
+        let mut nodes: BTreeSet<usize> = self
+            .parents
+            .iter()
+            .chain(self.children.iter())
+            .chain(self.co_parents.values().flatten())
+            .copied()
+            .collect();
+        nodes.remove(&self.node);
+        nodes
+    }
+}
+
+/// Write one line describing a node of a `NetworkProcess`.
+///
+/// The line has the form `index: label [cardinality] <- {parent labels}`; with the alternate flag
+/// (`{:#}`) the shape of the CIM (or `not set`) is appended.
+pub(crate) fn fmt_node(
+    f: &mut fmt::Formatter<'_>,
+    node_idx: usize,
+    node: &params::Params,
+    parent_labels: Vec<&String>,
+) -> fmt::Result {
+    write!(
+        f,
+        "{}: {} [{}] <- {{{}}}",
+        node_idx,
+        node.get_label(),
+        node.get_reserved_space_as_parent(),
+        parent_labels
+            .iter()
+            .map(|x| x.as_str())
+            .collect::<Vec<&str>>()
+            .join(", ")
+    )?;
+    if f.alternate() {
+        match node {
+            params::Params::DiscreteStatesContinousTime(p) => match p.get_cim() {
+                Some(cim) => write!(f, " (cim: {:?})", cim.shape())?,
+                None => write!(f, " (cim: not set)")?,
+            },
+        }
+    }
+    writeln!(f)
 }
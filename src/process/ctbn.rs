@@ -1,11 +1,24 @@
 //! Continuous Time Bayesian Network
 
-use std::collections::BTreeSet;
+#[cfg(feature = "npz")]
+use std::collections::BTreeMap;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::Path;
 
-use log::info;
+use log::{info, warn};
 use ndarray::prelude::*;
+#[cfg(feature = "npz")]
+use npyz::npz::{NpzArchive, NpzWriter};
+#[cfg(feature = "npz")]
+use npyz::{NpyFile, Order, WriterBuilder};
+use serde_json::{json, Value};
 
-use crate::params::{DiscreteStatesContinousTimeParams, Params, ParamsTrait, StateType};
+use crate::io::{self, IoError};
+use crate::params::{
+    DiscreteStatesContinousTimeParams, Params, ParamsDisplay, ParamsTrait, StateType,
+};
 use crate::process;
 
 use super::ctmp::CtmpProcess;
@@ -23,27 +36,13 @@ use super::{NetworkProcess, NetworkProcessState};
 /// # Example
 ///
 /// ```rust
-/// use std::collections::BTreeSet;
 /// use reCTBN::process::NetworkProcess;
 /// use reCTBN::params;
 /// use reCTBN::process::ctbn::*;
 ///
-/// //Create the domain for a discrete node
-/// let mut domain = BTreeSet::new();
-/// domain.insert(String::from("A"));
-/// domain.insert(String::from("B"));
-///
-/// //Create the parameters for a discrete node using the domain
-/// let param = params::DiscreteStatesContinousTimeParams::new("X1".to_string(), domain);
-///
-/// //Create the node using the parameters
-/// let X1 = params::Params::DiscreteStatesContinousTime(param);
-///
-/// let mut domain = BTreeSet::new();
-/// domain.insert(String::from("A"));
-/// domain.insert(String::from("B"));
-/// let param = params::DiscreteStatesContinousTimeParams::new("X2".to_string(), domain);
-/// let X2 = params::Params::DiscreteStatesContinousTime(param);
+/// //Create two discrete nodes with states A and B
+/// let X1 = params::discrete_node("X1", &["A", "B"]);
+/// let X2 = params::discrete_node("X2", &["A", "B"]);
 ///
 /// //Initialize a ctbn
 /// let mut net = CtbnNetwork::new();
@@ -59,6 +58,7 @@ use super::{NetworkProcess, NetworkProcessState};
 /// let cs = net.get_children_set(X1);
 /// assert_eq!(&X2, cs.iter().next().unwrap());
 /// ```
+#[derive(Clone, Debug)]
 pub struct CtbnNetwork {
     adj_matrix: Option<Array2<u16>>,
     nodes: Vec<Params>,
@@ -72,8 +72,173 @@ impl CtbnNetwork {
         }
     }
 
+    /// Display the parameters of `node` naming each parent configuration after the states of its
+    /// parents (see [`Params::display_with_parents`]).
+    pub fn display_node(&self, node: usize) -> ParamsDisplay<'_> {
+        let parents = match self.adj_matrix {
+            Some(_) => self
+                .get_parent_set(node)
+                .iter()
+                .map(|x| &self.nodes[*x])
+                .collect(),
+            None => vec![],
+        };
+        self.nodes[node].display_with_parents(parents)
+    }
+
+    /// Labels of the parents of `node`, ordered by node index.
+    pub fn get_parent_labels(&self, node: usize) -> Vec<&String> {
+        self.get_node_indices()
+            .filter(|parent| self.has_edge(*parent, node))
+            .map(|parent| self.nodes[parent].get_label())
+            .collect()
+    }
+
+    /// Labels of the children of `node`, ordered by node index.
+    pub fn get_children_labels(&self, node: usize) -> Vec<&String> {
+        self.get_node_indices()
+            .filter(|child| self.has_edge(node, *child))
+            .map(|child| self.nodes[child].get_label())
+            .collect()
+    }
+
+    /// Add a **directed edge** between the nodes labelled `parent` and `child`.
+    ///
+    /// # Return
+    ///
+    /// * An `UnknownNode` error containing the label if there is no node with such label.
+    pub fn add_edge_by_label(
+        &mut self,
+        parent: &str,
+        child: &str,
+    ) -> Result<(), process::NetworkError> {
+        let index_of = |label: &str| {
+            self.get_node_index_by_label(label).ok_or_else(|| {
+                warn!("There is no node labelled {}", label);
+                process::NetworkError::UnknownNode(label.to_string())
+            })
+        };
+        let (parent, child) = (index_of(parent)?, index_of(child)?);
+        self.add_edge(parent, child);
+        Ok(())
+    }
+
+    /// Check whether the structure of the network is a directed acyclic graph.
+    pub fn is_acyclic(&self) -> bool {
+        self.topological_order().is_some()
+    }
+
+    /// Order the nodes so that every parent comes before its children, using Kahn's algorithm.
+    ///
+    /// Among the nodes that can be placed at each step, the one with the smallest index comes
+    /// first, so the order is deterministic.
+    ///
+    /// # Return
+    ///
+    /// * The nodes in topological order, or `None` if the network has a cycle.
+    pub fn topological_order(&self) -> Option<Vec<usize>> {
+        let mut in_degree: Vec<usize> = vec![0; self.nodes.len()];
+        for (_, child) in self.edges() {
+            in_degree[child] += 1;
+        }
+        let mut ready: BTreeSet<usize> = self
+            .get_node_indices()
+            .filter(|x| in_degree[*x] == 0)
+            .collect();
+        let mut order: Vec<usize> = Vec::with_capacity(self.nodes.len());
+        while let Some(node) = ready.pop_first() {
+            order.push(node);
+            for child in self.get_node_indices() {
+                if self.has_edge(node, child) {
+                    in_degree[child] -= 1;
+                    if in_degree[child] == 0 {
+                        ready.insert(child);
+                    }
+                }
+            }
+        }
+        if order.len() == self.nodes.len() {
+            Some(order)
+        } else {
+            None
+        }
+    }
+
+    /// Decompose the structure of the network in its strongly connected components, using
+    /// Kosaraju's algorithm.
+    ///
+    /// Each node belongs to exactly one component; the network is acyclic if and only if every
+    /// component is a single node without a self loop.
+    ///
+    /// # Return
+    ///
+    /// * The components, each one sorted by node index. The components are in topological
+    ///   order: every edge between two different components goes from an earlier component to
+    ///   a later one.
+    pub fn strongly_connected_components(&self) -> Vec<Vec<usize>> {
+        let n_nodes = self.nodes.len();
+        let mut children: Vec<Vec<usize>> = vec![vec![]; n_nodes];
+        let mut parents: Vec<Vec<usize>> = vec![vec![]; n_nodes];
+        for (parent, child) in self.edges() {
+            children[parent].push(child);
+            parents[child].push(parent);
+        }
+
+        //First pass: order the nodes by finishing time of a depth first search.
+        let mut visited = vec![false; n_nodes];
+        let mut finished: Vec<usize> = Vec::with_capacity(n_nodes);
+        for root in 0..n_nodes {
+            if visited[root] {
+                continue;
+            }
+            visited[root] = true;
+            let mut stack: Vec<(usize, usize)> = vec![(root, 0)];
+            while let Some((node, next_child)) = stack.pop() {
+                match children[node].get(next_child) {
+                    Some(child) => {
+                        stack.push((node, next_child + 1));
+                        if !visited[*child] {
+                            visited[*child] = true;
+                            stack.push((*child, 0));
+                        }
+                    }
+                    None => finished.push(node),
+                }
+            }
+        }
+
+        //Second pass: visit the transposed graph by decreasing finishing time; each search
+        //collects one component.
+        let mut component_of: Vec<Option<usize>> = vec![None; n_nodes];
+        let mut components: Vec<Vec<usize>> = vec![];
+        for root in finished.into_iter().rev() {
+            if component_of[root].is_some() {
+                continue;
+            }
+            component_of[root] = Some(components.len());
+            let mut component = vec![root];
+            let mut stack = vec![root];
+            while let Some(node) = stack.pop() {
+                for parent in parents[node].iter() {
+                    if component_of[*parent].is_none() {
+                        component_of[*parent] = Some(components.len());
+                        component.push(*parent);
+                        stack.push(*parent);
+                    }
+                }
+            }
+            component.sort_unstable();
+            components.push(component);
+        }
+        components
+    }
+
     ///Transform the **CTBN** into a **CTMP**
     ///
+    /// The states of the *CTMP* are numbered as in [`idx_to_state`](Self::idx_to_state); the
+    /// configuration and the label (e.g. `"n1=A|n2=B"`) of each state are available through
+    /// [`CtmpProcess::get_states`] and [`CtmpProcess::get_state_label`].
+    ///
     /// # Return
     ///
     /// * The equivalent *CtmpProcess* computed from the current CtbnNetwork
@@ -89,6 +254,7 @@ impl CtbnNetwork {
         let state_space = variables_domain.product();
         let variables_set = BTreeSet::from_iter(self.get_node_indices());
         let mut amalgamated_cim: Array3<f64> = Array::zeros((1, state_space, state_space));
+        let mut states: Vec<NetworkProcessState> = Vec::with_capacity(state_space);
 
         for idx_current_state in 0..state_space {
             //Compute the state of the ctbn given the state of the ctmp
@@ -116,16 +282,33 @@ impl CtbnNetwork {
                         &next_state_statetype,
                         &variables_set,
                     );
+                    let u = self.get_param_index_network(idx_node, &current_state_statetype);
                     amalgamated_cim[[0, idx_current_state, idx_next_state]] +=
-                        p.get_cim().as_ref().unwrap()[[
-                            self.get_param_index_network(idx_node, &current_state_statetype),
-                            current_state[idx_node],
-                            next_node_state,
-                        ]];
+                        p.rate(u, current_state[idx_node], next_node_state).unwrap();
                 }
             }
+            states.push(current_state_statetype);
         }
 
+        let state_labels = states
+            .iter()
+            .map(|state| {
+                self.nodes
+                    .iter()
+                    .zip(state.iter())
+                    .map(|(node, x)| {
+                        let Params::DiscreteStatesContinousTime(p) = node;
+                        format!(
+                            "{}={}",
+                            node.get_label(),
+                            p.state_label(usize::from(*x)).unwrap()
+                        )
+                    })
+                    .collect::<Vec<String>>()
+                    .join("|")
+            })
+            .collect();
+
         let mut amalgamated_param = DiscreteStatesContinousTimeParams::new(
             "ctmp".to_string(),
             BTreeSet::from_iter((0..state_space).map(|x| x.to_string())),
@@ -137,6 +320,13 @@ impl CtbnNetwork {
 
         ctmp.add_node(Params::DiscreteStatesContinousTime(amalgamated_param))
             .unwrap();
+        ctmp.set_variables(
+            self.nodes
+                .iter()
+                .map(|x| (x.get_label().clone(), x.get_reserved_space_as_parent()))
+                .collect(),
+        );
+        ctmp.set_states(states, state_labels);
         return ctmp;
     }
 
@@ -160,10 +350,701 @@ impl CtbnNetwork {
 
         return array_state;
     }
+
+    /// Compute the index of a configuration of a set of ordered variables; it is the inverse of
+    /// [`idx_to_state`](Self::idx_to_state).
+    ///
+    /// # Arguments
+    ///
+    /// * `variables_domain` - domain of the considered variables
+    /// * `state` - the state of each node
+    ///
+    /// # Return
+    ///
+    /// * The specific configuration of the nodes represented with a single number
+    pub fn state_to_idx(variables_domain: &Array1<usize>, state: &Array1<usize>) -> usize {
+        variables_domain
+            .iter()
+            .zip(state.iter())
+            .rev()
+            .fold(0, |acc, (var, x)| acc * var + x)
+    }
     /// Get the Adjacency Matrix.
     pub fn get_adj_matrix(&self) -> Option<&Array2<u16>> {
         self.adj_matrix.as_ref()
     }
+
+    /// Emit the structure of the network as a GraphML document.
+    ///
+    /// Each node has id `n<index>` and the `label` and `cardinality` data attributes; each edge
+    /// is directed from the parent to the child.
+    pub fn to_graphml(&self) -> String {
+        let mut graphml = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n\
+             \x20   <key id=\"label\" for=\"node\" attr.name=\"label\" attr.type=\"string\"/>\n\
+             \x20   <key id=\"cardinality\" for=\"node\" attr.name=\"cardinality\" attr.type=\"int\"/>\n\
+             \x20   <graph id=\"G\" edgedefault=\"directed\">\n",
+        );
+        for (idx, node) in self.nodes.iter().enumerate() {
+            graphml.push_str(&format!(
+                "        <node id=\"n{}\">\n\
+                 \x20           <data key=\"label\">{}</data>\n\
+                 \x20           <data key=\"cardinality\">{}</data>\n\
+                 \x20       </node>\n",
+                idx,
+                io::escape_xml(node.get_label()),
+                node.get_reserved_space_as_parent()
+            ));
+        }
+        for (parent, child) in self.edges() {
+            graphml.push_str(&format!(
+                "        <edge source=\"n{}\" target=\"n{}\"/>\n",
+                parent, child
+            ));
+        }
+        graphml.push_str("    </graph>\n</graphml>\n");
+        graphml
+    }
+
+    /// Build a network without parameters from the structure described by a GraphML document.
+    ///
+    /// The label of a node is the value of its data attribute named `label`, or its id. The
+    /// cardinality is read from `cardinalities` (keyed by label) or, when missing, from the data
+    /// attribute named `cardinality`; the domain of a node with cardinality `k` contains the
+    /// states `"0"`, ..., `"k-1"`. Nodes are added in document order and unknown attributes are
+    /// ignored.
+    ///
+    /// # Errors
+    ///
+    /// Malformed documents, undirected edges, edges referring to unknown nodes and nodes
+    /// without a valid cardinality.
+    pub fn structure_from_graphml(
+        xml: &str,
+        cardinalities: &HashMap<String, usize>,
+    ) -> Result<CtbnNetwork, IoError> {
+        let invalid = |message: String| IoError::InvalidNetwork(message);
+        let root = io::parse_xml(xml)?;
+        if !root.is("graphml") {
+            return Err(invalid(String::from("missing graphml element")));
+        }
+        let graph = root
+            .child("graph")
+            .ok_or_else(|| invalid(String::from("missing graph element")))?;
+        let directed_by_default = graph.attribute("edgedefault") != Some("undirected");
+
+        //Id of the keys declared for the nodes, by attribute name.
+        let key_of = |attr_name: &str| {
+            root.children_named("key")
+                .find(|x| {
+                    x.attribute("attr.name") == Some(attr_name)
+                        && matches!(x.attribute("for"), None | Some("node") | Some("all"))
+                })
+                .and_then(|x| x.attribute("id"))
+                .unwrap_or(attr_name)
+                .to_string()
+        };
+        let (label_key, cardinality_key) = (key_of("label"), key_of("cardinality"));
+
+        let mut net = CtbnNetwork::new();
+        let mut node_of: HashMap<&str, usize> = HashMap::new();
+        for node in graph.children_named("node") {
+            let id = node
+                .attribute("id")
+                .ok_or_else(|| invalid(String::from("node without id")))?;
+            let data = |key: &str| {
+                node.children_named("data")
+                    .find(|x| x.attribute("key") == Some(key))
+                    .map(|x| x.text.trim().to_string())
+            };
+            let label = data(&label_key).unwrap_or_else(|| id.to_string());
+            let cardinality = match cardinalities.get(&label) {
+                Some(cardinality) => Some(*cardinality),
+                None => data(&cardinality_key).and_then(|x| x.parse::<usize>().ok()),
+            }
+            .filter(|x| *x > 0)
+            .ok_or_else(|| invalid(format!("node {}: missing or invalid cardinality", label)))?;
+            let param = DiscreteStatesContinousTimeParams::new(
+                label.clone(),
+                (0..cardinality).map(|x| x.to_string()).collect(),
+            );
+            if node_of.insert(id, node_of.len()).is_some() {
+                return Err(invalid(format!("node {}: duplicated id {}", label, id)));
+            }
+            net.add_node(Params::DiscreteStatesContinousTime(param))
+                .map_err(|e| invalid(format!("node {}: {}", label, e)))?;
+        }
+
+        net.initialize_adj_matrix();
+        for edge in graph.children_named("edge") {
+            let directed = match edge.attribute("directed") {
+                Some("true") => true,
+                Some("false") => false,
+                _ => directed_by_default,
+            };
+            let endpoint = |name: &str| {
+                edge.attribute(name)
+                    .and_then(|x| node_of.get(x).copied())
+                    .ok_or_else(|| invalid(format!("edge with an invalid {}", name)))
+            };
+            let (parent, child) = (endpoint("source")?, endpoint("target")?);
+            if !directed {
+                return Err(invalid(format!(
+                    "undirected edge between {} and {}",
+                    net.get_node(parent).get_label(),
+                    net.get_node(child).get_label()
+                )));
+            }
+            if parent == child {
+                return Err(invalid(format!(
+                    "self loop on {}",
+                    net.get_node(parent).get_label()
+                )));
+            }
+            net.add_edge(parent, child);
+        }
+        Ok(net)
+    }
+
+    /// Serialize the network in the reCTBN JSON model format (version 1).
+    ///
+    /// The format is a JSON object with the following keys:
+    ///
+    /// * `format` - always `"reCTBN-ctbn"`.
+    /// * `version` - version of the format, currently `1`.
+    /// * `nodes` - list of nodes in index order. Each node is an object with the `label`, the
+    ///   `domain` (state labels in index order, i.e. sorted) and, when set, the `cim` as a nested
+    ///   list with shape `[parent configurations][domain][domain]` and the `piecewise_cim` as a
+    ///   list of `{"start": t, "cim": ...}` segments. Tied parameters also store the
+    ///   `configuration_groups` (the group of each parent configuration) and their CIM has one
+    ///   matrix per group. Noisy-OR parameters store the `rate_multipliers` of each parent and
+    ///   their CIM has the base matrix only.
+    /// * `edges` - list of `[parent, child]` index pairs, ordered by parent and then by child.
+    pub fn to_json_value(&self) -> Value {
+        let nodes: Vec<Value> = self
+            .nodes
+            .iter()
+            .map(|node| match node {
+                Params::DiscreteStatesContinousTime(p) => {
+                    let mut value = json!({
+                        "label": p.get_label(),
+                        "domain": p.get_domain().iter().collect::<Vec<&String>>(),
+                    });
+                    if let Some(groups) = p.get_configuration_groups() {
+                        value["configuration_groups"] = json!(groups.to_vec());
+                    }
+                    if let Some(rate_multipliers) = p.get_rate_multipliers() {
+                        value["rate_multipliers"] = rate_multipliers
+                            .iter()
+                            .map(|x| {
+                                x.outer_iter()
+                                    .map(|row| row.iter().cloned().collect::<Value>())
+                                    .collect::<Value>()
+                            })
+                            .collect();
+                    }
+                    if let Some(cim) = p.get_cim() {
+                        value["cim"] = array3_to_json(cim);
+                    }
+                    if let Some(piecewise_cim) = p.get_piecewise_cim() {
+                        value["piecewise_cim"] = piecewise_cim
+                            .iter()
+                            .map(|(start, cim)| json!({"start": start, "cim": array3_to_json(cim)}))
+                            .collect();
+                    }
+                    value
+                }
+            })
+            .collect();
+        let edges: Vec<Value> = self
+            .edges()
+            .map(|(parent, child)| json!([parent, child]))
+            .collect();
+        json!({
+            "format": "reCTBN-ctbn",
+            "version": 1,
+            "nodes": nodes,
+            "edges": edges,
+        })
+    }
+
+    /// Build a network from a value in the format produced by
+    /// [`to_json_value`](Self::to_json_value).
+    ///
+    /// Every CIM is checked with [`validate_params`](ParamsTrait::validate_params) and its number
+    /// of parent configurations against the parent set; errors name the invalid node.
+    pub fn from_json_value(value: &Value) -> Result<CtbnNetwork, process::NetworkError> {
+        let invalid = |message: String| process::NetworkError::InvalidModel(message);
+        if value["format"] != "reCTBN-ctbn" {
+            return Err(invalid(String::from("unknown format")));
+        }
+        if value["version"] != 1 {
+            return Err(invalid(format!("unsupported version {}", value["version"])));
+        }
+        let nodes = value["nodes"]
+            .as_array()
+            .ok_or_else(|| invalid(String::from("missing list of nodes")))?;
+        let edges = value["edges"]
+            .as_array()
+            .ok_or_else(|| invalid(String::from("missing list of edges")))?;
+
+        let mut net = CtbnNetwork::new();
+        for (idx, node) in nodes.iter().enumerate() {
+            let label = node["label"]
+                .as_str()
+                .ok_or_else(|| invalid(format!("node {}: missing label", idx)))?;
+            let domain: Vec<String> = node["domain"]
+                .as_array()
+                .and_then(|x| x.iter().map(|x| x.as_str().map(String::from)).collect())
+                .ok_or_else(|| invalid(format!("node {} ({}): invalid domain", idx, label)))?;
+            //The index of a state is its position in the ordered domain.
+            if domain.windows(2).any(|x| x[0] >= x[1]) {
+                return Err(invalid(format!(
+                    "node {} ({}): the domain must be sorted and without duplicates",
+                    idx, label
+                )));
+            }
+            let param = DiscreteStatesContinousTimeParams::new(
+                label.to_string(),
+                domain.into_iter().collect(),
+            );
+            net.add_node(Params::DiscreteStatesContinousTime(param))
+                .map_err(|e| invalid(format!("node {} ({}): {}", idx, label, e)))?;
+        }
+
+        net.initialize_adj_matrix();
+        for edge in edges.iter() {
+            let (parent, child) = match edge.as_array().map(|x| x.as_slice()) {
+                Some([parent, child]) => (
+                    parent.as_u64().map(|x| x as usize),
+                    child.as_u64().map(|x| x as usize),
+                ),
+                _ => (None, None),
+            };
+            match (parent, child) {
+                (Some(parent), Some(child))
+                    if parent < nodes.len() && child < nodes.len() && parent != child =>
+                {
+                    net.add_edge(parent, child)
+                }
+                _ => return Err(invalid(format!("invalid edge {}", edge))),
+            }
+        }
+
+        for (idx, node) in nodes.iter().enumerate() {
+            let n_parent_configurations: usize = net
+                .get_parent_set(idx)
+                .iter()
+                .map(|x| net.get_node(*x).get_reserved_space_as_parent())
+                .product();
+            let label = net.get_node(idx).get_label().clone();
+            let node_error =
+                |message: String| invalid(format!("node {} ({}): {}", idx, label, message));
+            let groups = if node["configuration_groups"].is_null() {
+                None
+            } else {
+                let groups: Vec<usize> = node["configuration_groups"]
+                    .as_array()
+                    .and_then(|x| x.iter().map(|g| g.as_u64().map(|g| g as usize)).collect())
+                    .ok_or_else(|| node_error(String::from("malformed configuration groups")))?;
+                if groups.len() != n_parent_configurations {
+                    return Err(node_error(format!(
+                        "{} configuration groups instead of {}",
+                        groups.len(),
+                        n_parent_configurations
+                    )));
+                }
+                Some(Array1::from(groups))
+            };
+            let rate_multipliers = if node["rate_multipliers"].is_null() {
+                None
+            } else {
+                let rate_multipliers: Vec<Array2<f64>> = node["rate_multipliers"]
+                    .as_array()
+                    .and_then(|x| {
+                        x.iter()
+                            .map(|m| {
+                                json_to_array3(&json!([m])).map(|m| m.index_axis_move(Axis(0), 0))
+                            })
+                            .collect()
+                    })
+                    .ok_or_else(|| node_error(String::from("malformed rate multipliers")))?;
+                let parents_cardinality: Vec<usize> = net
+                    .get_parent_set(idx)
+                    .iter()
+                    .map(|x| net.get_node(*x).get_reserved_space_as_parent())
+                    .collect();
+                if rate_multipliers
+                    .iter()
+                    .map(|x| x.shape()[0])
+                    .ne(parents_cardinality.iter().cloned())
+                {
+                    return Err(node_error(String::from(
+                        "the rate multipliers do not match the parents",
+                    )));
+                }
+                Some(rate_multipliers)
+            };
+            let n_matrices = match (&groups, &rate_multipliers) {
+                (Some(groups), _) => groups.iter().max().map_or(0, |x| x + 1),
+                (None, Some(_)) => 1,
+                (None, None) => n_parent_configurations,
+            };
+            let check_cim = |cim: &Array3<f64>| {
+                if cim.shape()[0] != n_matrices {
+                    Err(node_error(format!(
+                        "the cim has {} parent configurations instead of {}",
+                        cim.shape()[0],
+                        n_matrices
+                    )))
+                } else {
+                    Ok(())
+                }
+            };
+
+            let cim = if node["cim"].is_null() {
+                None
+            } else {
+                let cim = json_to_array3(&node["cim"])
+                    .ok_or_else(|| node_error(String::from("malformed cim")))?;
+                check_cim(&cim)?;
+                Some(cim)
+            };
+            let piecewise_cim = if node["piecewise_cim"].is_null() {
+                None
+            } else {
+                let segments = node["piecewise_cim"]
+                    .as_array()
+                    .ok_or_else(|| node_error(String::from("malformed piecewise cim")))?;
+                let mut piecewise_cim = vec![];
+                for segment in segments.iter() {
+                    match (segment["start"].as_f64(), json_to_array3(&segment["cim"])) {
+                        (Some(start), Some(cim)) => {
+                            check_cim(&cim)?;
+                            piecewise_cim.push((start, cim));
+                        }
+                        _ => return Err(node_error(String::from("malformed piecewise cim"))),
+                    }
+                }
+                Some(piecewise_cim)
+            };
+
+            match net.get_node_mut(idx) {
+                Params::DiscreteStatesContinousTime(p) => {
+                    if let Some(groups) = groups {
+                        p.set_configuration_groups(groups)
+                            .map_err(|e| node_error(format!("{:?}", e)))?;
+                    }
+                    let result = match (piecewise_cim, cim, rate_multipliers) {
+                        (Some(piecewise_cim), _, _) => p.set_piecewise_cim(piecewise_cim),
+                        (None, Some(cim), Some(rate_multipliers)) => {
+                            p.set_noisy_or_cim(cim.index_axis_move(Axis(0), 0), rate_multipliers)
+                        }
+                        (None, Some(cim), None) => p.set_cim(cim),
+                        (None, None, _) => Ok(()),
+                    };
+                    result.map_err(|e| node_error(format!("{:?}", e)))?;
+                }
+            }
+        }
+        Ok(net)
+    }
+
+    /// Save the network to `path` in the JSON model format (see
+    /// [`to_json_value`](Self::to_json_value)).
+    pub fn save_json<P: AsRef<Path>>(&self, path: P) -> Result<(), process::NetworkError> {
+        let content = serde_json::to_string_pretty(&self.to_json_value())
+            .map_err(|e| process::NetworkError::Io(e.to_string()))?;
+        fs::write(path, content).map_err(|e| process::NetworkError::Io(e.to_string()))
+    }
+
+    /// Load a network saved with [`save_json`](Self::save_json).
+    pub fn load_json<P: AsRef<Path>>(path: P) -> Result<CtbnNetwork, process::NetworkError> {
+        let content =
+            fs::read_to_string(path).map_err(|e| process::NetworkError::Io(e.to_string()))?;
+        let value: Value = serde_json::from_str(&content)
+            .map_err(|e| process::NetworkError::InvalidModel(e.to_string()))?;
+        CtbnNetwork::from_json_value(&value)
+    }
+
+    /// Save the `cim`, `transitions` and `residence_time` of every node to `path` as a numpy
+    /// `.npz` archive.
+    ///
+    /// The arrays are named `<label>/cim`, `<label>/transitions` and `<label>/residence_time`
+    /// and only the ones set in the node are stored. The CIM has one matrix for each parent
+    /// configuration (see
+    /// [`get_expanded_cim`](DiscreteStatesContinousTimeParams::get_expanded_cim)) and the
+    /// transitions are stored as `uint64`.
+    #[cfg(feature = "npz")]
+    pub fn save_npz<P: AsRef<Path>>(&self, path: P) -> Result<(), process::NetworkError> {
+        let io_error = |e: std::io::Error| process::NetworkError::Io(e.to_string());
+        let mut npz = NpzWriter::create(path).map_err(io_error)?;
+        for node in self.nodes.iter() {
+            match node {
+                Params::DiscreteStatesContinousTime(p) => {
+                    if let Some(cim) = p.get_expanded_cim() {
+                        let name = format!("{}/cim", p.get_label());
+                        write_npz_array(&mut npz, &name, cim.shape(), cim.iter().cloned())
+                            .map_err(io_error)?;
+                    }
+                    if let Some(M) = p.get_transitions() {
+                        let name = format!("{}/transitions", p.get_label());
+                        write_npz_array(&mut npz, &name, M.shape(), M.iter().map(|x| *x as u64))
+                            .map_err(io_error)?;
+                    }
+                    if let Some(T) = p.get_residence_time() {
+                        let name = format!("{}/residence_time", p.get_label());
+                        write_npz_array(&mut npz, &name, T.shape(), T.iter().cloned())
+                            .map_err(io_error)?;
+                    }
+                }
+            }
+        }
+        npz.zip_writer()
+            .finish()
+            .map_err(|e| process::NetworkError::Io(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Load the arrays saved with [`save_npz`](Self::save_npz) into the nodes of the network.
+    ///
+    /// Every array is checked against the domain of its node and the number of configurations
+    /// of its parent set before modifying the network: a mismatched shape, an unknown label or an
+    /// unexpected element type gives `NetworkError::InvalidModel` naming the array. The nodes
+    /// with arrays in the archive drop their configuration groups, since the CIM has one matrix
+    /// for each parent configuration; the other nodes are left unchanged.
+    #[cfg(feature = "npz")]
+    pub fn load_npz<P: AsRef<Path>>(&mut self, path: P) -> Result<(), process::NetworkError> {
+        let io_error = |e: std::io::Error| process::NetworkError::Io(e.to_string());
+        let mut npz = NpzArchive::open(path).map_err(io_error)?;
+        let names: Vec<String> = npz.array_names().map(String::from).collect();
+
+        //Arrays of each node, all checked before modifying the network.
+        let mut arrays: BTreeMap<usize, NpzNodeArrays> = BTreeMap::new();
+        for name in names.iter() {
+            let invalid =
+                |msg: String| process::NetworkError::InvalidModel(format!("{}: {}", name, msg));
+            let (label, field) = name
+                .rsplit_once('/')
+                .ok_or_else(|| invalid(String::from("expected a name <label>/<array>")))?;
+            let node = self
+                .get_node_index_by_label(label)
+                .ok_or_else(|| invalid(format!("no node labelled {}", label)))?;
+            let domain = self.get_node(node).get_reserved_space_as_parent();
+            let n_configurations: usize = self
+                .get_parent_set(node)
+                .iter()
+                .map(|x| self.get_node(*x).get_reserved_space_as_parent())
+                .product();
+            let expected = match field {
+                "cim" | "transitions" => vec![n_configurations, domain, domain],
+                "residence_time" => vec![n_configurations, domain],
+                _ => return Err(invalid(format!("unknown array {}", field))),
+            };
+
+            let npy = npz.by_name(name).map_err(io_error)?.unwrap();
+            let shape: Vec<usize> = npy.shape().iter().map(|x| *x as usize).collect();
+            if shape != expected {
+                return Err(invalid(format!(
+                    "shape {:?} does not match the shape {:?} expected for node {} ({} states, {} \
+                     parent configurations)",
+                    shape, expected, label, domain, n_configurations
+                )));
+            }
+            let entry = arrays.entry(node).or_default();
+            match field {
+                "cim" => entry.0 = Some(read_npz_array(npy, &shape).map_err(invalid)?),
+                "transitions" => {
+                    let M: Array3<u64> = read_npz_array(npy, &shape).map_err(invalid)?;
+                    entry.1 = Some(M.mapv(|x| x as usize));
+                }
+                _ => entry.2 = Some(read_npz_array(npy, &shape).map_err(invalid)?),
+            }
+        }
+
+        for (node, (cim, transitions, residence_time)) in arrays {
+            match self.get_node_mut(node) {
+                Params::DiscreteStatesContinousTime(p) => {
+                    if p.get_configuration_groups().is_some() {
+                        p.reset_configuration_groups();
+                    }
+                    if let Some(cim) = cim {
+                        p.set_cim_unchecked(cim);
+                    }
+                    if let Some(M) = transitions {
+                        p.set_transitions(M);
+                    }
+                    if let Some(T) = residence_time {
+                        p.set_residence_time(T);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn array3_to_json(array: &Array3<f64>) -> Value {
+    array
+        .outer_iter()
+        .map(|matrix| {
+            matrix
+                .outer_iter()
+                .map(|row| row.iter().cloned().collect::<Value>())
+                .collect::<Value>()
+        })
+        .collect()
+}
+
+//Parse a nested list of numbers with a regular shape.
+fn json_to_array3(value: &Value) -> Option<Array3<f64>> {
+    let matrices = value.as_array()?;
+    let n_rows = matrices.first()?.as_array()?.len();
+    let n_cols = matrices.first()?.as_array()?.first()?.as_array()?.len();
+    let mut data = Vec::with_capacity(matrices.len() * n_rows * n_cols);
+    for matrix in matrices.iter() {
+        let rows = matrix.as_array()?;
+        if rows.len() != n_rows {
+            return None;
+        }
+        for row in rows.iter() {
+            let row = row.as_array()?;
+            if row.len() != n_cols {
+                return None;
+            }
+            for x in row.iter() {
+                data.push(x.as_f64()?);
+            }
+        }
+    }
+    Array3::from_shape_vec((matrices.len(), n_rows, n_cols), data).ok()
+}
+
+//CIM, transitions and residence time of a node read by `CtbnNetwork::load_npz`.
+#[cfg(feature = "npz")]
+type NpzNodeArrays = (
+    Option<Array3<f64>>,
+    Option<Array3<usize>>,
+    Option<Array2<f64>>,
+);
+
+#[cfg(feature = "npz")]
+fn write_npz_array<W: std::io::Write + std::io::Seek, T: npyz::AutoSerialize>(
+    npz: &mut NpzWriter<W>,
+    name: &str,
+    shape: &[usize],
+    data: impl IntoIterator<Item = T>,
+) -> std::io::Result<()> {
+    let shape: Vec<u64> = shape.iter().map(|x| *x as u64).collect();
+    let mut writer = npz
+        .array::<T>(name, Default::default())?
+        .default_dtype()
+        .shape(&shape)
+        .begin_nd()?;
+    writer.extend(data)?;
+    writer.finish()
+}
+
+//Read an array whose shape has already been checked, in either memory order.
+#[cfg(feature = "npz")]
+fn read_npz_array<R: std::io::Read, T: npyz::Deserialize, D: Dimension>(
+    npy: NpyFile<R>,
+    shape: &[usize],
+) -> Result<Array<T, D>, String> {
+    let order = npy.order();
+    let data = npy.into_vec::<T>().map_err(|e| e.to_string())?;
+    let array = match order {
+        Order::C => ArrayD::from_shape_vec(shape, data),
+        Order::Fortran => ArrayD::from_shape_vec(shape.f(), data),
+    };
+    array
+        .map_err(|e| e.to_string())?
+        .into_dimensionality::<D>()
+        .map_err(|e| e.to_string())
+}
+
+/// Print one line per node in the form `index: label [cardinality] <- {parent labels}`, followed
+/// by one line per edge in the form `parent -> child`.
+///
+/// The alternate formatter (`{:#}`) also prints the shape of the CIM of each node, or `not set`.
+///
+/// # Example
+///
+/// ```rust
+/// use std::collections::BTreeSet;
+/// use reCTBN::process::NetworkProcess;
+/// use reCTBN::params;
+/// use reCTBN::process::ctbn::*;
+///
+/// let domain: BTreeSet<String> = ["A", "B"].iter().map(|x| x.to_string()).collect();
+/// let mut net = CtbnNetwork::new();
+/// let X1 = net
+///     .add_node(params::Params::DiscreteStatesContinousTime(
+///         params::DiscreteStatesContinousTimeParams::new("X1".to_string(), domain.clone()),
+///     ))
+///     .unwrap();
+/// let X2 = net
+///     .add_node(params::Params::DiscreteStatesContinousTime(
+///         params::DiscreteStatesContinousTimeParams::new("X2".to_string(), domain),
+///     ))
+///     .unwrap();
+/// net.add_edge(X1, X2);
+///
+/// assert_eq!(
+///     "0: X1 [2] <- {}\n\
+///      1: X2 [2] <- {X1}\n\
+///      X1 -> X2\n",
+///     format!("{}", net)
+/// );
+///
+/// //Print the CIM of X2 naming the parent configurations after the states of X1.
+/// if let params::Params::DiscreteStatesContinousTime(p) = net.get_node_mut(X2) {
+///     p.set_cim(ndarray::arr3(&[
+///         [[-1.0, 1.0], [0.5, -0.5]],
+///         [[-10.0, 10.0], [5.0, -5.0]],
+///     ]))
+///     .unwrap();
+/// }
+/// assert_eq!(
+///     concat!(
+///         "X2 {A, B}\n",
+///         "X1=A\n",
+///         "       A    B\n",
+///         "  A   -1    1\n",
+///         "  B  0.5 -0.5\n",
+///         "X1=B\n",
+///         "       A    B\n",
+///         "  A  -10   10\n",
+///         "  B    5   -5\n",
+///     ),
+///     format!("{}", net.display_node(X2))
+/// );
+/// ```
+impl fmt::Display for CtbnNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for node_idx in self.get_node_indices() {
+            let parent_labels = match self.adj_matrix {
+                Some(_) => self
+                    .get_parent_set(node_idx)
+                    .iter()
+                    .map(|x| self.nodes[*x].get_label())
+                    .collect(),
+                None => vec![],
+            };
+            process::fmt_node(f, node_idx, &self.nodes[node_idx], parent_labels)?;
+        }
+        if let Some(adj_matrix) = &self.adj_matrix {
+            for ((parent, child), _) in adj_matrix.indexed_iter().filter(|(_, x)| **x > 0) {
+                writeln!(
+                    f,
+                    "{} -> {}",
+                    self.nodes[parent].get_label(),
+                    self.nodes[child].get_label()
+                )?;
+            }
+        }
+        Ok(())
+    }
 }
 
 impl process::NetworkProcess for CtbnNetwork {
@@ -174,6 +1055,13 @@ impl process::NetworkProcess for CtbnNetwork {
     }
 
     fn add_node(&mut self, mut n: Params) -> Result<usize, process::NetworkError> {
+        if self.get_node_index_by_label(n.get_label()).is_some() {
+            warn!("A node labelled {} already exists", n.get_label());
+            return Err(process::NetworkError::NodeInsertionError(format!(
+                "A node labelled {} already exists",
+                n.get_label()
+            )));
+        }
         n.reset_params();
         self.adj_matrix = Option::None;
         self.nodes.push(n);
@@ -191,6 +1079,63 @@ impl process::NetworkProcess for CtbnNetwork {
         }
     }
 
+    fn remove_edge(&mut self, parent: usize, child: usize) {
+        if let Some(network) = &mut self.adj_matrix {
+            if network[[parent, child]] > 0 {
+                network[[parent, child]] = 0;
+                self.nodes[child].reset_params();
+            }
+        }
+    }
+
+    fn remove_node(
+        &mut self,
+        node_idx: usize,
+    ) -> Result<Vec<Option<usize>>, process::NetworkError> {
+        if node_idx >= self.nodes.len() {
+            warn!("Node {} does not exist", node_idx);
+            return Err(process::NetworkError::NodeRemovalError(format!(
+                "Node {} does not exist in a network with {} nodes",
+                node_idx,
+                self.nodes.len()
+            )));
+        }
+        let mapping: Vec<Option<usize>> = (0..self.nodes.len())
+            .map(|x| match x.cmp(&node_idx) {
+                std::cmp::Ordering::Less => Some(x),
+                std::cmp::Ordering::Equal => None,
+                std::cmp::Ordering::Greater => Some(x - 1),
+            })
+            .collect();
+
+        if let Some(network) = &self.adj_matrix {
+            for child in self.get_children_set(node_idx) {
+                self.nodes[child].reset_params();
+            }
+            let kept: Vec<usize> = (0..self.nodes.len()).filter(|x| *x != node_idx).collect();
+            self.adj_matrix = Some(Array2::from_shape_fn(
+                (kept.len(), kept.len()).f(),
+                |(parent, child)| network[[kept[parent], kept[child]]],
+            ));
+        }
+        self.nodes.remove(node_idx);
+        Ok(mapping)
+    }
+
+    fn has_edge(&self, parent: usize, child: usize) -> bool {
+        self.adj_matrix
+            .as_ref()
+            .is_some_and(|network| network[[parent, child]] > 0)
+    }
+
+    fn edges(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.adj_matrix.iter().flat_map(|network| {
+            network
+                .indexed_iter()
+                .filter_map(|(edge, x)| if *x > 0 { Some(edge) } else { None })
+        })
+    }
+
     fn get_node_indices(&self) -> std::ops::Range<usize> {
         0..self.nodes.len()
     }
@@ -224,6 +1169,20 @@ impl process::NetworkProcess for CtbnNetwork {
             .0
     }
 
+    fn get_param_index_multipliers(&self, node: usize) -> Option<Vec<(usize, usize)>> {
+        let mut multiplier = 1;
+        Some(
+            self.get_parent_set(node)
+                .into_iter()
+                .map(|parent| {
+                    let ret = (parent, multiplier);
+                    multiplier *= self.nodes[parent].get_reserved_space_as_parent();
+                    ret
+                })
+                .collect(),
+        )
+    }
+
     fn get_param_index_from_custom_parent_set(
         &self,
         current_state: &NetworkProcessState,
@@ -1,9 +1,9 @@
 use std::collections::BTreeSet;
+use std::fmt;
 
-use crate::{
-    params::{Params, StateType},
-    process,
-};
+use ndarray::Array1;
+
+use crate::{params::Params, process};
 
 use super::{NetworkProcess, NetworkProcessState};
 use log::warn;
@@ -13,30 +13,22 @@ use log::warn;
 /// * Arguments
 ///
 /// * `param` - An Option containing the parameters of the process
+/// * `variables` - Label and cardinality of the variables encoded by the states of the process,
+///   set when the process is the amalgamation of a CTBN
+/// * `states` - Configuration of the variables encoded by each state of the process, set when
+///   the process is the amalgamation of a CTBN
+/// * `state_labels` - Label of each state of the process, e.g. `"n1=A|n2=B"`, set when the process
+///   is the amalgamation of a CTBN
 ///
 ///```rust
-/// use std::collections::BTreeSet;
 /// use reCTBN::process::NetworkProcess;
 /// use reCTBN::params;
 /// use reCTBN::process::ctbn::*;
 /// use ndarray::arr3;
 ///
-/// //Create the domain for a discrete node
-/// let mut domain = BTreeSet::new();
-/// domain.insert(String::from("A"));
-/// domain.insert(String::from("B"));
-///
-/// //Create the parameters for a discrete node using the domain
-/// let param = params::DiscreteStatesContinousTimeParams::new("X1".to_string(), domain);
-///
-/// //Create the node using the parameters
-/// let X1 = params::Params::DiscreteStatesContinousTime(param);
-///
-/// let mut domain = BTreeSet::new();
-/// domain.insert(String::from("A"));
-/// domain.insert(String::from("B"));
-/// let param = params::DiscreteStatesContinousTimeParams::new("X2".to_string(), domain);
-/// let X2 = params::Params::DiscreteStatesContinousTime(param);
+/// //Create two discrete nodes with states A and B
+/// let X1 = params::discrete_node("X1", &["A", "B"]);
+/// let X2 = params::discrete_node("X2", &["A", "B"]);
 ///
 /// //Initialize a ctbn
 /// let mut net = CtbnNetwork::new();
@@ -76,13 +68,210 @@ use log::warn;
 /// assert_eq!(p_ctmp.shape()[1], 4);
 ///```
 
+#[derive(Debug)]
 pub struct CtmpProcess {
     param: Option<Params>,
+    variables: Option<Vec<(String, usize)>>,
+    states: Option<Vec<NetworkProcessState>>,
+    state_labels: Option<Vec<String>>,
 }
 
 impl CtmpProcess {
     pub fn new() -> CtmpProcess {
-        CtmpProcess { param: None }
+        CtmpProcess {
+            param: None,
+            variables: None,
+            states: None,
+            state_labels: None,
+        }
+    }
+
+    /// Label and cardinality of the variables of the amalgamated CTBN, in node order.
+    ///
+    /// The state with index `i` corresponds to the configuration returned by
+    /// [`CtbnNetwork::idx_to_state`](super::ctbn::CtbnNetwork::idx_to_state).
+    pub fn get_variables(&self) -> &Option<Vec<(String, usize)>> {
+        &self.variables
+    }
+
+    pub(crate) fn set_variables(&mut self, variables: Vec<(String, usize)>) {
+        self.variables = Some(variables);
+    }
+
+    /// Configuration of the variables of the amalgamated CTBN encoded by each state of the
+    /// process; the position in the vector is the index of the state.
+    pub fn get_states(&self) -> Option<&Vec<NetworkProcessState>> {
+        self.states.as_ref()
+    }
+
+    /// Label of the state with index `idx` of the amalgamated CTBN, built from the labels of the
+    /// variables and of their states, e.g. `"n1=A|n2=B"`.
+    ///
+    /// `None` if the process is not an amalgamation or `idx` is out of range.
+    pub fn get_state_label(&self, idx: usize) -> Option<&String> {
+        self.state_labels.as_ref()?.get(idx)
+    }
+
+    pub(crate) fn set_states(
+        &mut self,
+        states: Vec<NetworkProcessState>,
+        state_labels: Vec<String>,
+    ) {
+        self.states = Some(states);
+        self.state_labels = Some(state_labels);
+    }
+
+    /// Index of the state encoding the configuration `state` of the variables of the amalgamated
+    /// CTBN; it is the inverse of [`get_states`](Self::get_states).
+    ///
+    /// `None` if the process is not an amalgamation or `state` is not a valid configuration.
+    pub fn state_to_idx(&self, state: &NetworkProcessState) -> Option<usize> {
+        let variables = self.variables.as_ref()?;
+        if state.len() != variables.len()
+            || state
+                .iter()
+                .zip(variables.iter())
+                .any(|(x, (_, cardinality))| usize::from(*x) >= *cardinality)
+        {
+            return None;
+        }
+        Some(super::ctbn::CtbnNetwork::state_to_idx(
+            &Array1::from_iter(variables.iter().map(|(_, x)| *x)),
+            &Array1::from_iter(state.iter().map(|x| usize::from(*x))),
+        ))
+    }
+
+    /// Emit the process as a CTMC in the PRISM language.
+    ///
+    /// When the process is the amalgamation of a CTBN, each original node becomes a PRISM
+    /// variable (its label is turned into a valid identifier), otherwise the state index is
+    /// stored in a single variable `s`. Each non-zero off-diagonal entry of the CIM becomes a
+    /// command; zero-rate transitions are omitted.
+    pub fn to_prism(&self) -> String {
+        let cim = match self.param.as_ref() {
+            Some(Params::DiscreteStatesContinousTime(p)) => p
+                .get_cim()
+                .as_ref()
+                .expect("The CIM of the CtmpProcess is not set"),
+            None => panic!("Uninitialized CtmpProcess"),
+        };
+        let state_space = cim.shape()[1];
+        let variables = match &self.variables {
+            Some(variables) => variables.clone(),
+            None => vec![(String::from("s"), state_space)],
+        };
+        let names = prism_identifiers(variables.iter().map(|(label, _)| label));
+        let variables_domain = Array1::from_iter(variables.iter().map(|(_, x)| *x));
+        let states: Vec<Array1<usize>> = (0..state_space)
+            .map(|x| super::ctbn::CtbnNetwork::idx_to_state(&variables_domain, x))
+            .collect();
+
+        let mut prism = String::from("ctmc\n\nmodule ctmp\n");
+        for (name, (_, cardinality)) in names.iter().zip(variables.iter()) {
+            prism.push_str(&format!("    {} : [0..{}];\n", name, cardinality - 1));
+        }
+        prism.push('\n');
+        for from in 0..state_space {
+            let guard: Vec<String> = names
+                .iter()
+                .zip(states[from].iter())
+                .map(|(name, x)| format!("{}={}", name, x))
+                .collect();
+            for to in 0..state_space {
+                let rate = cim[[0, from, to]];
+                if from == to || rate == 0.0 {
+                    continue;
+                }
+                let update: Vec<String> = names
+                    .iter()
+                    .zip(states[from].iter().zip(states[to].iter()))
+                    .filter(|(_, (x, y))| x != y)
+                    .map(|(name, (_, y))| format!("({}'={})", name, y))
+                    .collect();
+                prism.push_str(&format!(
+                    "    [] {} -> {} : {};\n",
+                    guard.join(" & "),
+                    rate,
+                    update.join(" & ")
+                ));
+            }
+        }
+        prism.push_str("endmodule\n");
+        prism
+    }
+}
+
+//Turn the labels into distinct PRISM identifiers.
+fn prism_identifiers<'a>(labels: impl Iterator<Item = &'a String>) -> Vec<String> {
+    const KEYWORDS: [&str; 32] = [
+        "A",
+        "bool",
+        "clock",
+        "const",
+        "ctmc",
+        "C",
+        "double",
+        "dtmc",
+        "E",
+        "endinit",
+        "endmodule",
+        "endrewards",
+        "endsystem",
+        "false",
+        "formula",
+        "filter",
+        "func",
+        "F",
+        "global",
+        "G",
+        "init",
+        "invariant",
+        "I",
+        "int",
+        "label",
+        "max",
+        "mdp",
+        "min",
+        "module",
+        "X",
+        "true",
+        "U",
+    ];
+    let mut identifiers: Vec<String> = vec![];
+    for (idx, label) in labels.enumerate() {
+        let mut identifier: String = label
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        if identifier.is_empty()
+            || identifier.starts_with(|c: char| c.is_ascii_digit())
+            || KEYWORDS.contains(&identifier.as_str())
+        {
+            identifier = format!("v_{}", identifier);
+        }
+        if identifiers.contains(&identifier) {
+            identifier = format!("{}_{}", identifier, idx);
+        }
+        identifiers.push(identifier);
+    }
+    identifiers
+}
+
+/// Print the single node of the process in the form `0: label [cardinality] <- {}`.
+///
+/// The alternate formatter (`{:#}`) also prints the shape of the CIM, or `not set`.
+impl fmt::Display for CtmpProcess {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.param {
+            Some(param) => process::fmt_node(f, 0, param, vec![]),
+            None => Ok(()),
+        }
     }
 }
 
@@ -111,6 +300,34 @@ impl NetworkProcess for CtmpProcess {
         unimplemented!("CtmpProcess has only one node")
     }
 
+    fn remove_edge(&mut self, _parent: usize, _child: usize) {
+        warn!("A CTMP cannot have edges");
+        unimplemented!("CtmpProcess has only one node")
+    }
+
+    fn remove_node(
+        &mut self,
+        node_idx: usize,
+    ) -> Result<Vec<Option<usize>>, process::NetworkError> {
+        if node_idx != 0 || self.param.is_none() {
+            warn!("Node {} does not exist", node_idx);
+            return Err(process::NetworkError::NodeRemovalError(format!(
+                "Node {} does not exist",
+                node_idx
+            )));
+        }
+        *self = CtmpProcess::new();
+        Ok(vec![None])
+    }
+
+    fn has_edge(&self, _parent: usize, _child: usize) -> bool {
+        false
+    }
+
+    fn edges(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        std::iter::empty()
+    }
+
     fn get_node_indices(&self) -> std::ops::Range<usize> {
         match self.param {
             None => 0..0,
@@ -143,9 +360,7 @@ impl NetworkProcess for CtmpProcess {
 
     fn get_param_index_network(&self, node: usize, current_state: &NetworkProcessState) -> usize {
         if node == 0 {
-            match current_state[0] {
-                StateType::Discrete(x) => x,
-            }
+            usize::from(current_state[0])
         } else {
             unimplemented!("CtmpProcess has only one node")
         }
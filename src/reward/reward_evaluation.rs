@@ -1,12 +1,15 @@
 /// Evaluate the `RewardFunction` for a `NetworkProcess`
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use log::{info, warn};
 use rayon::prelude::{IntoParallelIterator, ParallelIterator};
+use rayon::ThreadPool;
 use statrs::distribution::ContinuousCDF;
 
 use crate::params::{self, ParamsTrait};
 use crate::process;
+use crate::tools::run_in_thread_pool;
 
 use crate::{
     process::NetworkProcessState,
@@ -108,6 +111,7 @@ pub struct MonteCarloReward {
     end_time: f64,
     reward_criteria: RewardCriteria,
     seed: Option<u64>,
+    thread_pool: Option<Arc<ThreadPool>>,
 }
 
 impl MonteCarloReward {
@@ -126,8 +130,19 @@ impl MonteCarloReward {
             end_time,
             reward_criteria,
             seed,
+            thread_pool: None,
         }
     }
+
+    /// Run the parallel work of `evaluate_state_space` inside `thread_pool` instead of the global
+    /// rayon pool.
+    ///
+    /// The states are evaluated in parallel, one state per task, while the trajectories of a
+    /// state are sampled sequentially.
+    pub fn with_thread_pool(mut self, thread_pool: Arc<ThreadPool>) -> MonteCarloReward {
+        self.thread_pool = Some(thread_pool);
+        self
+    }
 }
 
 impl RewardEvaluation for MonteCarloReward {
@@ -151,24 +166,26 @@ impl RewardEvaluation for MonteCarloReward {
         let n_states: usize = variables_domain.iter().map(|x| x.len()).product();
 
         // Compute the expected reward for each possible configuration of the `NetworkProcess`
-        (0..n_states)
-            .into_par_iter()
-            .map(|s| {
-                let state: process::NetworkProcessState = variables_domain
-                    .iter()
-                    .fold((s, vec![]), |acc, x| {
-                        let mut acc = acc;
-                        let idx_s = acc.0 % x.len();
-                        acc.1.push(x[idx_s].clone());
-                        acc.0 = acc.0 / x.len();
-                        acc
-                    })
-                    .1;
+        run_in_thread_pool(self.thread_pool.as_deref(), || {
+            (0..n_states)
+                .into_par_iter()
+                .map(|s| {
+                    let state: process::NetworkProcessState = variables_domain
+                        .iter()
+                        .fold((s, vec![]), |acc, x| {
+                            let mut acc = acc;
+                            let idx_s = acc.0 % x.len();
+                            acc.1.push(x[idx_s]);
+                            acc.0 = acc.0 / x.len();
+                            acc
+                        })
+                        .1;
 
-                let r = self.evaluate_state(network_process, reward_function, &state);
-                (state, r)
-            })
-            .collect()
+                    let r = self.evaluate_state(network_process, reward_function, &state);
+                    (state, r)
+                })
+                .collect()
+        })
     }
 
     fn evaluate_state<N: crate::process::NetworkProcess, R: super::RewardFunction>(
@@ -1,7 +1,7 @@
 //! Module for dealing with reward functions
 
 use crate::{
-    params::{self, ParamsTrait},
+    params::ParamsTrait,
     process,
     reward::{Reward, RewardFunction},
 };
@@ -39,6 +39,28 @@ impl FactoredRewardFunction {
     pub fn get_instantaneous_reward_mut(&mut self, node_idx: usize) -> &mut ndarray::Array1<f64> {
         &mut self.instantaneous_reward[node_idx]
     }
+
+    /// Same as `get_transition_reward_mut` for the node of `net` labelled `label`, `None` if
+    /// there is no such node.
+    pub fn get_transition_reward_by_label_mut<T: process::NetworkProcess>(
+        &mut self,
+        net: &T,
+        label: &str,
+    ) -> Option<&mut ndarray::Array2<f64>> {
+        net.get_node_index_by_label(label)
+            .map(move |node_idx| self.get_transition_reward_mut(node_idx))
+    }
+
+    /// Same as `get_instantaneous_reward_mut` for the node of `net` labelled `label`, `None` if
+    /// there is no such node.
+    pub fn get_instantaneous_reward_by_label_mut<T: process::NetworkProcess>(
+        &mut self,
+        net: &T,
+        label: &str,
+    ) -> Option<&mut ndarray::Array1<f64>> {
+        net.get_node_index_by_label(label)
+            .map(move |node_idx| self.get_instantaneous_reward_mut(node_idx))
+    }
 }
 
 impl RewardFunction for FactoredRewardFunction {
@@ -50,12 +72,7 @@ impl RewardFunction for FactoredRewardFunction {
         let instantaneous_reward: f64 = current_state
             .iter()
             .enumerate()
-            .map(|(idx, x)| {
-                let x = match x {
-                    params::StateType::Discrete(x) => x,
-                };
-                self.instantaneous_reward[idx][*x]
-            })
+            .map(|(idx, x)| self.instantaneous_reward[idx][usize::from(*x)])
             .sum();
         if let Some(previous_state) = previous_state {
             let transition_reward = previous_state
@@ -63,14 +80,8 @@ impl RewardFunction for FactoredRewardFunction {
                 .zip(current_state.iter())
                 .enumerate()
                 .find_map(|(idx, (p, c))| -> Option<f64> {
-                    let p = match p {
-                        params::StateType::Discrete(p) => p,
-                    };
-                    let c = match c {
-                        params::StateType::Discrete(c) => c,
-                    };
                     if p != c {
-                        Some(self.transition_reward[idx][[*p, *c]])
+                        Some(self.transition_reward[idx][[usize::from(*p), usize::from(*c)]])
                     } else {
                         None
                     }
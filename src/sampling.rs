@@ -1,7 +1,7 @@
 //! Module containing methods for the sampling.
 
 use crate::{
-    params::ParamsTrait,
+    params::{self, ParamsTrait},
     process::{NetworkProcess, NetworkProcessState},
 };
 use rand::SeedableRng;
@@ -40,6 +40,10 @@ pub trait Sampler: Iterator<Item = Sample> {
 ///  * `next_transitions` - next time to transition for each variable in the
 ///                       `NetworkProcess`
 ///  * `initial_state`: - Initial state of the `NetworkProcess`
+///  * `intervention`: - Optional `(node, state)` pair: the node is clamped to the state and never
+///    transitions (do-operator)
+///  * `structure_cache`: - Parameters, parent multipliers and children of each node, computed at
+///    the first sample after a reset
 ///
 ///  # Example
 ///
@@ -121,6 +125,15 @@ where
     current_state: NetworkProcessState,
     next_transitions: Vec<Option<f64>>,
     initial_state: Option<NetworkProcessState>,
+    intervention: Option<(usize, params::StateType)>,
+    structure_cache: Option<StructureCache<'a>>,
+}
+
+//Per node view of the network used in the sampling loop.
+struct StructureCache<'a> {
+    nodes: Vec<&'a params::Params>,
+    param_index_multipliers: Vec<Option<Vec<(usize, usize)>>>,
+    children: Vec<Vec<usize>>,
 }
 
 impl<'a, T: NetworkProcess> ForwardSampler<'a, T> {
@@ -150,10 +163,65 @@ impl<'a, T: NetworkProcess> ForwardSampler<'a, T> {
             current_state: vec![],
             next_transitions: vec![],
             initial_state,
+            intervention: None,
+            structure_cache: None,
         };
         fs.reset();
         return fs;
     }
+
+    /// Set the intervention applied to the generated trajectories and reset the sampler.
+    ///
+    /// # Arguments
+    ///
+    /// * `intervention` - `Some((node, state))` clamps `node` to `state` for the whole trajectory
+    ///   (do-operator); `None` restores the observational regime.
+    pub fn set_intervention(&mut self, intervention: Option<(usize, params::StateType)>) {
+        if let Some((node, state)) = &intervention {
+            if *node >= self.net.get_number_of_nodes() {
+                panic!("Node {} does not exist", node);
+            }
+            let node_params = self.net.get_node(*node);
+            if node_params.state_to_index(state) >= node_params.get_reserved_space_as_parent() {
+                panic!("Invalid state for node {}", node);
+            }
+        }
+        self.intervention = intervention;
+        self.reset();
+    }
+
+    fn build_structure_cache(&self) -> StructureCache<'a> {
+        let net: &'a T = self.net;
+        StructureCache {
+            nodes: net.get_node_indices().map(|x| net.get_node(x)).collect(),
+            param_index_multipliers: net
+                .get_node_indices()
+                .map(|x| net.get_param_index_multipliers(x))
+                .collect(),
+            children: net
+                .get_node_indices()
+                .map(|x| net.get_children_set(x).into_iter().collect())
+                .collect(),
+        }
+    }
+}
+
+//Compute the index of the parameters of `node`, touching only its parents when possible.
+fn param_index<T: NetworkProcess>(
+    net: &T,
+    structure_cache: &StructureCache,
+    node: usize,
+    current_state: &NetworkProcessState,
+) -> usize {
+    match &structure_cache.param_index_multipliers[node] {
+        Some(multipliers) => multipliers
+            .iter()
+            .map(|(parent, multiplier)| {
+                structure_cache.nodes[*parent].state_to_index(&current_state[*parent]) * multiplier
+            })
+            .sum(),
+        None => net.get_param_index_network(node, current_state),
+    }
 }
 
 impl<'a, T: NetworkProcess> Iterator for ForwardSampler<'a, T> {
@@ -167,22 +235,31 @@ impl<'a, T: NetworkProcess> Iterator for ForwardSampler<'a, T> {
         //  All the operation stating from here are required to compute the time and state that
         //  will be returned at the next call of this function.
 
+        if self.structure_cache.is_none() {
+            self.structure_cache = Some(self.build_structure_cache());
+        }
+        let structure_cache = self.structure_cache.as_ref().unwrap();
+
         //Check if there are any node without a next time to transition and sample it from an
         //exponential distribution governed by the main diagonal of the CIM.
         for (idx, val) in self.next_transitions.iter_mut().enumerate() {
             if let None = val {
+                //The intervened node never transitions.
+                if let Some((node, _)) = &self.intervention {
+                    if *node == idx {
+                        *val = Some(f64::INFINITY);
+                        continue;
+                    }
+                }
+                let node = structure_cache.nodes[idx];
                 *val = Some(
-                    self.net
-                        .get_node(idx)
-                        .get_random_residence_time(
-                            self.net
-                                .get_node(idx)
-                                .state_to_index(&self.current_state[idx]),
-                            self.net.get_param_index_network(idx, &self.current_state),
-                            &mut self.rng,
-                        )
-                        .unwrap()
-                        + self.current_time,
+                    node.get_random_transition_time(
+                        node.state_to_index(&self.current_state[idx]),
+                        param_index(self.net, structure_cache, idx, &self.current_state),
+                        self.current_time,
+                        &mut self.rng,
+                    )
+                    .unwrap(),
                 );
             }
         }
@@ -200,15 +277,17 @@ impl<'a, T: NetworkProcess> Iterator for ForwardSampler<'a, T> {
 
         // Generate the new  state of the node from a multinomial distribution governed by the off
         // diagonal parameters of the CIM.
-        self.current_state[next_node_transition] = self
-            .net
-            .get_node(next_node_transition)
-            .get_random_state(
-                self.net
-                    .get_node(next_node_transition)
-                    .state_to_index(&self.current_state[next_node_transition]),
-                self.net
-                    .get_param_index_network(next_node_transition, &self.current_state),
+        let node = structure_cache.nodes[next_node_transition];
+        self.current_state[next_node_transition] = node
+            .get_random_state_at(
+                node.state_to_index(&self.current_state[next_node_transition]),
+                param_index(
+                    self.net,
+                    structure_cache,
+                    next_node_transition,
+                    &self.current_state,
+                ),
+                self.current_time,
                 &mut self.rng,
             )
             .unwrap();
@@ -217,8 +296,8 @@ impl<'a, T: NetworkProcess> Iterator for ForwardSampler<'a, T> {
         self.next_transitions[next_node_transition] = None;
 
         //Reset the next_transition for each child of the transitioning node.
-        for child in self.net.get_children_set(next_node_transition) {
-            self.next_transitions[child] = None;
+        for child in structure_cache.children[next_node_transition].iter() {
+            self.next_transitions[*child] = None;
         }
 
         Some(Sample {
@@ -241,6 +320,11 @@ impl<'a, T: NetworkProcess> Sampler for ForwardSampler<'a, T> {
             }
             Some(is) => self.current_state = is.clone(),
         };
+        if let Some((node, state)) = &self.intervention {
+            self.current_state[*node] = *state;
+        }
         self.next_transitions = self.net.get_node_indices().map(|_| Option::None).collect();
+        //Invalidate the cache; it is rebuilt at the first sample.
+        self.structure_cache = None;
     }
 }
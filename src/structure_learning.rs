@@ -1,10 +1,288 @@
 //! Learn the structure of the network.
 
 pub mod constraint_based_algorithm;
+pub mod evaluation;
+pub mod hybrid_algorithm;
 pub mod hypothesis_test;
 pub mod score_based_algorithm;
 pub mod score_function;
-use crate::{process, tools::Dataset};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::process;
+use crate::tools::{Dataset, DatasetError};
+
+use thiserror::Error;
+
+/// Error types for structure learning
+#[derive(Error, Debug, PartialEq)]
+pub enum StructureLearningError {
+    #[error("Search space too large: {0}")]
+    SearchSpaceTooLarge(String),
+    #[error("Invalid edge constraints: {0}")]
+    InvalidEdgeConstraints(String),
+    #[error("Mismatched networks: {0}")]
+    MismatchedNetworks(String),
+    #[error("Mismatched dataset: {0}")]
+    MismatchedDataset(String),
+    #[error("Invalid ordering: {0}")]
+    InvalidOrdering(String),
+    #[error("Learning interrupted: {0}")]
+    LearningInterrupted(String),
+    #[error("Empty dataset: {0}")]
+    EmptyDataset(String),
+    #[error("Mismatched number of variables: {0}")]
+    MismatchedNumberOfVariables(String),
+    #[error("Invalid state: {0}")]
+    InvalidState(String),
+    #[error("Degenerate test: {0}")]
+    DegenerateTest(String),
+}
+
+//Check that `dataset` is not empty, has a variable for each node of `net` and that the observed
+//states are in the domain of the nodes.
+pub(crate) fn check_dataset_compatibility<T: process::NetworkProcess>(
+    net: &T,
+    dataset: &Dataset,
+) -> Result<(), StructureLearningError> {
+    if dataset.get_trajectories().is_empty() {
+        return Err(StructureLearningError::EmptyDataset(String::from(
+            "the dataset contains no trajectories",
+        )));
+    }
+    dataset.validate_against(net).map_err(|e| match e {
+        DatasetError::MismatchedNetwork(message) => {
+            StructureLearningError::MismatchedNumberOfVariables(message)
+        }
+        DatasetError::InvalidValue(message) => StructureLearningError::InvalidState(message),
+        e => StructureLearningError::InvalidState(e.to_string()),
+    })
+}
+
+/// Maximum number of parents of the nodes of a network.
+///
+/// # Variants
+///
+/// * `ParentLimit::Global` - the same maximum for every node.
+/// * `ParentLimit::PerNode` - the maximum of each node, keyed by node; the nodes not in the map
+///   have no maximum.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParentLimit {
+    Global(usize),
+    PerNode(HashMap<usize, usize>),
+}
+
+impl ParentLimit {
+    /// Maximum number of parents of `node`, `None` if the node has no maximum.
+    pub fn get(&self, node: usize) -> Option<usize> {
+        match self {
+            ParentLimit::Global(max_parent_set) => Some(*max_parent_set),
+            ParentLimit::PerNode(max_parent_sets) => max_parent_sets.get(&node).copied(),
+        }
+    }
+}
+
+impl From<usize> for ParentLimit {
+    fn from(max_parent_set: usize) -> ParentLimit {
+        ParentLimit::Global(max_parent_set)
+    }
+}
+
+//Check that the edge constraints contain no self loops and no edges both required and forbidden.
+pub(crate) fn check_edge_constraints(
+    required_edges: &BTreeSet<(usize, usize)>,
+    forbidden_edges: &BTreeSet<(usize, usize)>,
+) -> Result<(), StructureLearningError> {
+    if let Some((parent, child)) = required_edges
+        .iter()
+        .chain(forbidden_edges.iter())
+        .find(|(parent, child)| parent == child)
+    {
+        return Err(StructureLearningError::InvalidEdgeConstraints(format!(
+            "self loop {} -> {}",
+            parent, child
+        )));
+    }
+    if let Some((parent, child)) = required_edges.intersection(forbidden_edges).next() {
+        return Err(StructureLearningError::InvalidEdgeConstraints(format!(
+            "edge {} -> {} is both required and forbidden",
+            parent, child
+        )));
+    }
+    Ok(())
+}
+
+//Check that the known parent sets refer to nodes of `net` and contain no self loops.
+pub(crate) fn check_known_parent_sets<T: process::NetworkProcess>(
+    net: &T,
+    known_parent_sets: &HashMap<usize, BTreeSet<usize>>,
+) -> Result<(), StructureLearningError> {
+    let n_nodes = net.get_number_of_nodes();
+    for (node, parent_set) in known_parent_sets.iter() {
+        if *node >= n_nodes {
+            return Err(StructureLearningError::InvalidEdgeConstraints(format!(
+                "known parent set of node {}, the network has {} nodes",
+                node, n_nodes
+            )));
+        }
+        if let Some(parent) = parent_set.iter().find(|parent| **parent >= n_nodes) {
+            return Err(StructureLearningError::InvalidEdgeConstraints(format!(
+                "known parent {} of node {}, the network has {} nodes",
+                parent, node, n_nodes
+            )));
+        }
+        if parent_set.contains(node) {
+            return Err(StructureLearningError::InvalidEdgeConstraints(format!(
+                "self loop {} -> {}",
+                node, node
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Diagnostics collected while learning the parent set of a node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeDiagnostics {
+    /// Index of the node.
+    pub node: usize,
+    /// Score of the learned parent set, for the score-based learners.
+    pub score: Option<f64>,
+    /// Number of conditional independence tests executed, for the constraint-based learners.
+    pub n_tests: Option<usize>,
+    /// Number of executed tests rejecting the independence, for the constraint-based learners.
+    pub n_rejected_tests: Option<usize>,
+    /// Adjusted p-value of each tested parent, keyed by parent, for the constraint-based
+    /// learners controlling the false discovery rate.
+    pub adjusted_p_values: Option<BTreeMap<usize, f64>>,
+}
+
+/// Diagnostics of a structure learning run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LearningDiagnostics {
+    /// Diagnostics of each node, ordered by node index.
+    pub nodes: Vec<NodeDiagnostics>,
+    /// Wall-clock time of the run, in seconds.
+    pub elapsed_seconds: f64,
+    /// Number of parameter fits requested from the
+    /// [`Cache`](crate::structure_learning::constraint_based_algorithm::Cache), for the
+    /// constraint-based learners.
+    pub n_parameter_fits: Option<usize>,
+}
+
+/// Token used to cancel a structure learning run from another thread.
+///
+/// The clones of a token share the same flag: the learner keeps a clone and checks it between
+/// the hypothesis tests or the score evaluations, while another thread calls
+/// [`cancel`](CancellationToken::cancel).
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    /// Request the cancellation of the runs using this token.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Observer notified by the structure learners while they run.
+///
+/// The nodes are learned in parallel, hence the methods may be called concurrently from several
+/// threads. Every method has a no-op default, so an implementation only overrides the events it
+/// is interested in.
+pub trait ProgressObserver: Send + Sync {
+    /// Called when the search of the parent set of `node` starts.
+    fn on_node_started(&self, _node: usize) {}
+
+    /// Called when the search of the parent set of `node` ends, with the learned `parent_set`.
+    fn on_node_finished(&self, _node: usize, _parent_set: &BTreeSet<usize>) {}
+
+    /// Called by the constraint-based learners after each conditional independence test.
+    ///
+    /// # Arguments
+    ///
+    /// * `child`: the node whose parent set is learned.
+    /// * `parent`: the candidate parent tested.
+    /// * `separation_set`: the conditioning set of the test.
+    /// * `independent`: the outcome of the test.
+    fn on_test_performed(
+        &self,
+        _child: usize,
+        _parent: usize,
+        _separation_set: &BTreeSet<usize>,
+        _independent: bool,
+    ) {
+    }
+
+    /// Called by the score-based learners after each evaluation of the score function.
+    fn on_score_evaluated(&self, _node: usize, _parent_set: &BTreeSet<usize>, _score: f64) {}
+}
+
+/// `ProgressObserver` counting the events, e.g. to render a progress bar from another thread.
+#[derive(Debug, Default)]
+pub struct ProgressCounter {
+    nodes_started: AtomicUsize,
+    nodes_finished: AtomicUsize,
+    tests_performed: AtomicUsize,
+    scores_evaluated: AtomicUsize,
+}
+
+impl ProgressCounter {
+    pub fn new() -> ProgressCounter {
+        ProgressCounter::default()
+    }
+
+    pub fn get_nodes_started(&self) -> usize {
+        self.nodes_started.load(Ordering::Relaxed)
+    }
+
+    pub fn get_nodes_finished(&self) -> usize {
+        self.nodes_finished.load(Ordering::Relaxed)
+    }
+
+    pub fn get_tests_performed(&self) -> usize {
+        self.tests_performed.load(Ordering::Relaxed)
+    }
+
+    pub fn get_scores_evaluated(&self) -> usize {
+        self.scores_evaluated.load(Ordering::Relaxed)
+    }
+}
+
+impl ProgressObserver for ProgressCounter {
+    fn on_node_started(&self, _node: usize) {
+        self.nodes_started.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn on_node_finished(&self, _node: usize, _parent_set: &BTreeSet<usize>) {
+        self.nodes_finished.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn on_test_performed(
+        &self,
+        _child: usize,
+        _parent: usize,
+        _separation_set: &BTreeSet<usize>,
+        _independent: bool,
+    ) {
+        self.tests_performed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn on_score_evaluated(&self, _node: usize, _parent_set: &BTreeSet<usize>, _score: f64) {
+        self.scores_evaluated.fetch_add(1, Ordering::Relaxed);
+    }
+}
 
 /// It defines the required methods for a _structure learning algorithm_.
 pub trait StructuralLearningAlgorithm {
@@ -18,8 +296,131 @@ pub trait StructuralLearningAlgorithm {
     ///
     /// # Return
     ///
+    /// * Return a `NetworkProcess` with the learned structure, or an error if the dataset is
+    ///   empty, if it does not have a variable for each node of `net` or if it contains a state
+    ///   outside the domain of a node.
+    fn fit_transform<T>(&self, net: T, dataset: &Dataset) -> Result<T, StructureLearningError>
+    where
+        T: process::NetworkProcess,
+    {
+        let mut net = net;
+        self.try_fit_in_place_with_diagnostics(&mut net, dataset)?;
+        Ok(net)
+    }
+
+    /// Learn the structure of a network, panicking if the dataset is not compatible with it
+    ///
+    /// #Arguments
+    ///
+    /// * `net`: a `NetworkProcess` instance
+    /// * `dataset`: instantiation of the `struct tools::Dataset` containing the
+    ///   observations used to learn the struct.
+    ///
+    /// # Return
+    ///
     /// * Return a `NetworkProcess` with the learned structure.
-    fn fit_transform<T>(&self, net: T, dataset: &Dataset) -> T
+    #[deprecated(
+        note = "`fit_transform` now returns a `Result`; this wrapper will be removed in the next release"
+    )]
+    fn fit_transform_or_panic<T>(&self, net: T, dataset: &Dataset) -> T
+    where
+        T: process::NetworkProcess,
+    {
+        match self.fit_transform(net, dataset) {
+            Ok(net) => net,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Learn the structure of a network, collecting the diagnostics of the run
+    ///
+    /// #Arguments
+    ///
+    /// * `net`: a `NetworkProcess` instance
+    /// * `dataset`: instantiation of the `struct tools::Dataset` containing the
+    ///   observations used to learn the struct.
+    ///
+    /// # Return
+    ///
+    /// * Return a `NetworkProcess` with the learned structure and the `LearningDiagnostics` of the
+    ///   run.
+    fn fit_transform_with_diagnostics<T>(
+        &self,
+        net: T,
+        dataset: &Dataset,
+    ) -> (T, LearningDiagnostics)
+    where
+        T: process::NetworkProcess,
+    {
+        let mut net = net;
+        let diagnostics = self.fit_in_place_with_diagnostics(&mut net, dataset);
+        (net, diagnostics)
+    }
+
+    /// Learn the structure of a network, modifying it in place
+    ///
+    /// The previous structure of `net` is discarded.
+    ///
+    /// #Arguments
+    ///
+    /// * `net`: a mutable reference to a `NetworkProcess` instance
+    /// * `dataset`: instantiation of the `struct tools::Dataset` containing the
+    ///   observations used to learn the struct.
+    fn fit_in_place<T>(&self, net: &mut T, dataset: &Dataset)
+    where
+        T: process::NetworkProcess,
+    {
+        self.fit_in_place_with_diagnostics(net, dataset);
+    }
+
+    /// Learn the structure of a network, modifying it in place and collecting the diagnostics of
+    /// the run
+    ///
+    /// The previous structure of `net` is discarded.
+    ///
+    /// #Arguments
+    ///
+    /// * `net`: a mutable reference to a `NetworkProcess` instance
+    /// * `dataset`: instantiation of the `struct tools::Dataset` containing the
+    ///   observations used to learn the struct.
+    ///
+    /// # Return
+    ///
+    /// * Return the `LearningDiagnostics` of the run.
+    fn fit_in_place_with_diagnostics<T>(
+        &self,
+        net: &mut T,
+        dataset: &Dataset,
+    ) -> LearningDiagnostics
     where
         T: process::NetworkProcess;
+
+    /// Learn the structure of a network, modifying it in place and collecting the diagnostics of
+    /// the run, returning an error instead of panicking
+    ///
+    /// The default implementation checks the dataset against `net` and calls
+    /// [`fit_in_place_with_diagnostics`](StructuralLearningAlgorithm::fit_in_place_with_diagnostics).
+    ///
+    /// #Arguments
+    ///
+    /// * `net`: a mutable reference to a `NetworkProcess` instance
+    /// * `dataset`: instantiation of the `struct tools::Dataset` containing the
+    ///   observations used to learn the struct.
+    ///
+    /// # Return
+    ///
+    /// * Return the `LearningDiagnostics` of the run, or an error if the dataset is not
+    ///   compatible with `net`, if the run is interrupted or if the constraints of the learner
+    ///   do not match `net`.
+    fn try_fit_in_place_with_diagnostics<T>(
+        &self,
+        net: &mut T,
+        dataset: &Dataset,
+    ) -> Result<LearningDiagnostics, StructureLearningError>
+    where
+        T: process::NetworkProcess,
+    {
+        check_dataset_compatibility(&*net, dataset)?;
+        Ok(self.fit_in_place_with_diagnostics(net, dataset))
+    }
 }
@@ -2,23 +2,36 @@
 
 use crate::params::Params;
 use itertools::Itertools;
+use log::warn;
+use ndarray::{Array2, Array3, ArrayView2, ArrayView3, Axis};
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use rayon::prelude::ParallelExtend;
-use std::collections::{BTreeSet, HashMap};
+use rayon::ThreadPool;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::mem;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
 use std::usize;
 
 use super::hypothesis_test::*;
-use crate::parameter_learning::ParameterLearning;
+use crate::journal::{Journal, JournalRecord};
+use crate::parameter_learning::{sufficient_statistics, ParameterLearning};
 use crate::process;
-use crate::structure_learning::StructuralLearningAlgorithm;
-use crate::tools::Dataset;
+use crate::structure_learning::{
+    check_dataset_compatibility, check_edge_constraints, check_known_parent_sets,
+    CancellationToken, LearningDiagnostics, NodeDiagnostics, ParentLimit, ProgressObserver,
+    StructuralLearningAlgorithm, StructureLearningError,
+};
+use crate::tools::{run_in_thread_pool, Dataset, SuffStatsCache};
 
 pub struct Cache<'a, P: ParameterLearning> {
     parameter_learning: &'a P,
     cache_persistent_small: HashMap<Option<BTreeSet<usize>>, Params>,
     cache_persistent_big: HashMap<Option<BTreeSet<usize>>, Params>,
     parent_set_size_small: usize,
+    suff_stats_cache: Option<Arc<SuffStatsCache>>,
+    n_fits: usize,
 }
 
 impl<'a, P: ParameterLearning> Cache<'a, P> {
@@ -28,8 +41,49 @@ impl<'a, P: ParameterLearning> Cache<'a, P> {
             cache_persistent_small: HashMap::new(),
             cache_persistent_big: HashMap::new(),
             parent_set_size_small: 0,
+            suff_stats_cache: None,
+            n_fits: 0,
         }
     }
+
+    /// Create a `Cache` that computes the parameters from the sufficient statistics stored in
+    /// `suff_stats_cache` instead of scanning the dataset.
+    pub fn new_with_suff_stats_cache(
+        parameter_learning: &'a P,
+        suff_stats_cache: Arc<SuffStatsCache>,
+    ) -> Cache<'a, P> {
+        let mut cache = Cache::new(parameter_learning);
+        cache.suff_stats_cache = Some(suff_stats_cache);
+        cache
+    }
+
+    fn learn_params<T: process::NetworkProcess>(
+        &self,
+        net: &T,
+        dataset: &Dataset,
+        node: usize,
+        parent_set: Option<BTreeSet<usize>>,
+    ) -> Params {
+        match &self.suff_stats_cache {
+            Some(suff_stats_cache) => {
+                let parent_set = parent_set.unwrap_or_else(|| net.get_parent_set(node));
+                let stats = suff_stats_cache.get_or_compute(net, dataset, node, &parent_set);
+                self.parameter_learning.fit_from_parent_set_statistics(
+                    net,
+                    node,
+                    &parent_set,
+                    stats.0.clone(),
+                    stats.1.clone(),
+                )
+            }
+            None => self.parameter_learning.fit(net, dataset, node, parent_set),
+        }
+    }
+    /// Number of parameter fits requested to the cache, including the ones served from memory.
+    pub fn get_number_of_fits(&self) -> usize {
+        self.n_fits
+    }
+
     pub fn fit<T: process::NetworkProcess>(
         &mut self,
         net: &T,
@@ -37,6 +91,7 @@ impl<'a, P: ParameterLearning> Cache<'a, P> {
         node: usize,
         parent_set: Option<BTreeSet<usize>>,
     ) -> Params {
+        self.n_fits += 1;
         let parent_set_len = parent_set.as_ref().unwrap().len();
         if parent_set_len > self.parent_set_size_small + 1 {
             //self.cache_persistent_small = self.cache_persistent_big;
@@ -54,9 +109,7 @@ impl<'a, P: ParameterLearning> Cache<'a, P> {
                 // not cloning requires a minor and reasoned refactoring across the library
                 Some(params) => params.clone(),
                 None => {
-                    let params =
-                        self.parameter_learning
-                            .fit(net, dataset, node, parent_set.clone());
+                    let params = self.learn_params(net, dataset, node, parent_set.clone());
                     self.cache_persistent_big.insert(parent_set, params.clone());
                     params
                 }
@@ -67,9 +120,7 @@ impl<'a, P: ParameterLearning> Cache<'a, P> {
                 // not cloning requires a minor and reasoned refactoring across the library
                 Some(params) => params.clone(),
                 None => {
-                    let params =
-                        self.parameter_learning
-                            .fit(net, dataset, node, parent_set.clone());
+                    let params = self.learn_params(net, dataset, node, parent_set.clone());
                     self.cache_persistent_small
                         .insert(parent_set, params.clone());
                     params
@@ -79,6 +130,21 @@ impl<'a, P: ParameterLearning> Cache<'a, P> {
     }
 }
 
+/// Separation set of each `(parent, child)` pair removed by CTPC.
+pub type SeparationSets = HashMap<(usize, usize), BTreeSet<usize>>;
+
+//Result of the search of the parent set of a node: the node, its parent set, its diagnostics,
+//the number of parameter fits, the separation sets of the removed parents and the p-value of
+//the test of each parent.
+type NodeSearchResult = (
+    usize,
+    BTreeSet<usize>,
+    NodeDiagnostics,
+    usize,
+    Vec<(usize, BTreeSet<usize>)>,
+    BTreeMap<usize, f64>,
+);
+
 /// Continuous-Time Peter Clark algorithm.
 ///
 /// A method to learn the structure of the network.
@@ -88,6 +154,8 @@ impl<'a, P: ParameterLearning> Cache<'a, P> {
 /// * [`parameter_learning`](crate::parameter_learning) - is the method used to learn the parameters.
 /// * [`Ftest`](crate::structure_learning::hypothesis_test::F) - is the F-test hyppothesis test.
 /// * [`Chi2test`](crate::structure_learning::hypothesis_test::ChiSquare) - is the chi-squared test (χ2 test) hypothesis test.
+/// * `max_separation_set_size` - is the maximum size of the separation sets tested.
+/// * `max_candidate_parents` - is the maximum number of candidate parents of each node.
 /// # Example
 ///
 /// ```rust
@@ -97,7 +165,7 @@ impl<'a, P: ParameterLearning> Cache<'a, P> {
 /// # use reCTBN::tools::trajectory_generator;
 /// # use reCTBN::process::NetworkProcess;
 /// # use reCTBN::process::ctbn::CtbnNetwork;
-/// use reCTBN::parameter_learning::BayesianApproach;
+/// use reCTBN::parameter_learning::{Alpha, BayesianApproach, Tau};
 /// use reCTBN::structure_learning::StructuralLearningAlgorithm;
 /// use reCTBN::structure_learning::hypothesis_test::{F, ChiSquare};
 /// use reCTBN::structure_learning::constraint_based_algorithm::CTPC;
@@ -250,13 +318,20 @@ impl<'a, P: ParameterLearning> Cache<'a, P> {
 /// let f = F::new(1e-6);
 /// let chi_sq = ChiSquare::new(1e-4);
 /// // Use the bayesian approach to learn the parameters
-/// let parameter_learning = BayesianApproach { alpha: 1, tau:1.0 };
+/// let parameter_learning = BayesianApproach {
+///     alpha: Alpha::Constant(1.0),
+///     tau: Tau::Constant(1.0),
+/// };
 ///
 /// //Initialize CTPC
 /// let ctpc = CTPC::new(parameter_learning, f, chi_sq);
 ///
 /// // Learn the structure of the network from the generated trajectory
-/// let net = ctpc.fit_transform(net, &data);
+/// let net = ctpc.fit_transform(net, &data).unwrap();
+///
+/// // Alternatively, the structure can be learned modifying a borrowed network in place
+/// let mut net = net;
+/// ctpc.fit_in_place(&mut net, &data);
 /// #
 /// # // Compare the generated network with the original one
 /// # assert_eq!(BTreeSet::new(), net.get_parent_set(0));
@@ -267,82 +342,930 @@ pub struct CTPC<P: ParameterLearning> {
     parameter_learning: P,
     Ftest: F,
     Chi2test: ChiSquare,
+    max_separation_set_size: Option<usize>,
+    max_candidate_parents: Option<usize>,
+    max_parent_set: Option<ParentLimit>,
+    suff_stats_cache: Option<Arc<SuffStatsCache>>,
+    required_edges: BTreeSet<(usize, usize)>,
+    forbidden_edges: BTreeSet<(usize, usize)>,
+    known_parent_sets: HashMap<usize, BTreeSet<usize>>,
+    journal: Option<Arc<dyn Journal>>,
+    progress_observer: Option<Arc<dyn ProgressObserver>>,
+    cancellation_token: Option<CancellationToken>,
+    thread_pool: Option<Arc<ThreadPool>>,
+    correction: Correction,
+    false_discovery_rate: Option<f64>,
+}
+
+/// Correction of the significance level of the hypothesis tests for multiple testing
+///
+/// # Variants
+///
+/// * `Correction::None` - every test uses the significance level given at construction.
+/// * `Correction::Bonferroni` - the significance level is divided by the number of tests planned
+///   for the current node at the current size of the separation sets, i.e. the number of
+///   candidate parents tested times the number of separation sets of each of them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Correction {
+    None,
+    Bonferroni,
+}
+
+//Benjamini-Hochberg adjustment of `p_values`, returned in the same order.
+fn benjamini_hochberg(p_values: &[f64]) -> Vec<f64> {
+    let m = p_values.len();
+    let mut order: Vec<usize> = (0..m).collect();
+    order.sort_by(|a, b| p_values[*a].total_cmp(&p_values[*b]));
+    let mut adjusted = vec![0.0; m];
+    //Running minimum from the largest p-value down to the smallest one.
+    let mut running_min: f64 = 1.0;
+    for (rank, idx) in order.iter().enumerate().rev() {
+        running_min = running_min.min(p_values[*idx] * m as f64 / (rank + 1) as f64);
+        adjusted[*idx] = running_min;
+    }
+    adjusted
+}
+
+//Binomial coefficient `n` choose `k`, as a float to avoid overflows.
+fn binomial(n: usize, k: usize) -> f64 {
+    if k > n {
+        return 0.0;
+    }
+    (0..k).fold(1.0, |acc, i| acc * (n - i) as f64 / (i + 1) as f64)
 }
 
 impl<P: ParameterLearning> CTPC<P> {
+    /// Create a `CTPC`
+    ///
+    /// # Arguments
+    ///
+    /// * `parameter_learning`: the method used to learn the parameters.
+    /// * `Ftest`: the F-test hypothesis test.
+    /// * `Chi2test`: the chi-squared hypothesis test.
     pub fn new(parameter_learning: P, Ftest: F, Chi2test: ChiSquare) -> CTPC<P> {
         CTPC {
             parameter_learning,
             Ftest,
             Chi2test,
+            max_separation_set_size: None,
+            max_candidate_parents: None,
+            max_parent_set: None,
+            suff_stats_cache: None,
+            required_edges: BTreeSet::new(),
+            forbidden_edges: BTreeSet::new(),
+            known_parent_sets: HashMap::new(),
+            journal: None,
+            progress_observer: None,
+            cancellation_token: None,
+            thread_pool: None,
+            correction: Correction::None,
+            false_discovery_rate: None,
+        }
+    }
+
+    /// Constrain the search with edges known to exist or known not to exist.
+    ///
+    /// Each edge is a `(parent, child)` pair. The forbidden parents are removed from the candidate
+    /// parent set before any test, while the required parents are never tested and are always
+    /// kept, even if the data suggests independence.
+    ///
+    /// # Arguments
+    ///
+    /// * `required_edges`: edges that must be present in the learned structure.
+    /// * `forbidden_edges`: edges that must not be present in the learned structure.
+    ///
+    /// # Errors
+    ///
+    /// * `InvalidEdgeConstraints` if an edge is a self loop or if an edge is both required and
+    ///   forbidden.
+    pub fn with_edge_constraints(
+        mut self,
+        required_edges: BTreeSet<(usize, usize)>,
+        forbidden_edges: BTreeSet<(usize, usize)>,
+    ) -> Result<CTPC<P>, StructureLearningError> {
+        check_edge_constraints(&required_edges, &forbidden_edges)?;
+        self.required_edges = required_edges;
+        self.forbidden_edges = forbidden_edges;
+        Ok(self)
+    }
+
+    /// Cap the size of the separation sets tested.
+    ///
+    /// The search becomes approximate, and much cheaper on large networks. When the cap is hit,
+    /// the skipped tests are logged.
+    pub fn with_max_separation_set_size(mut self, max_separation_set_size: usize) -> CTPC<P> {
+        self.max_separation_set_size = Some(max_separation_set_size);
+        self
+    }
+
+    /// Cap the number of candidate parents of each node.
+    ///
+    /// Before the tests, the candidates are ranked by the likelihood-ratio statistic of the node
+    /// given each single candidate and only the best `max_candidate_parents` are kept. When the
+    /// cap is hit, the skipped candidates are logged.
+    pub fn with_max_candidate_parents(mut self, max_candidate_parents: usize) -> CTPC<P> {
+        self.max_candidate_parents = Some(max_candidate_parents);
+        self
+    }
+
+    /// Cap the number of parents of the nodes, either for all the nodes or node by node.
+    ///
+    /// Before the tests, the candidate parent set of a node is pruned to its maximum number of
+    /// parents as done for `max_candidate_parents`, keeping the required parents and then the
+    /// candidates most dependent on the node. The nodes missing from a [`ParentLimit::PerNode`]
+    /// map have no maximum, and the required parents are kept even beyond it.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_parent_set`: the maximum number of parents of the nodes.
+    pub fn with_max_parent_set(mut self, max_parent_set: ParentLimit) -> CTPC<P> {
+        self.max_parent_set = Some(max_parent_set);
+        self
+    }
+
+    /// Fix the parent sets of the nodes known from domain knowledge.
+    ///
+    /// The nodes in `known_parent_sets` are not searched: their parent set is copied verbatim
+    /// into the learned structure, ignoring the edge constraints, and no hypothesis test is
+    /// performed for them. The parent sets are validated against the network at each run.
+    ///
+    /// # Arguments
+    ///
+    /// * `known_parent_sets`: the parent set of each known node, keyed by node.
+    pub fn with_known_parent_sets(
+        mut self,
+        known_parent_sets: HashMap<usize, BTreeSet<usize>>,
+    ) -> CTPC<P> {
+        self.known_parent_sets = known_parent_sets;
+        self
+    }
+
+    /// Write a [`JournalRecord`] to `journal` at the end of each run.
+    pub fn with_journal(mut self, journal: Arc<dyn Journal>) -> CTPC<P> {
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Notify `progress_observer` of the progress of each run.
+    pub fn with_progress_observer(
+        mut self,
+        progress_observer: Arc<dyn ProgressObserver>,
+    ) -> CTPC<P> {
+        self.progress_observer = Some(progress_observer);
+        self
+    }
+
+    /// Stop the search as soon as `cancellation_token` is cancelled.
+    ///
+    /// The token is checked before each hypothesis test. An interrupted run makes
+    /// [`try_fit_in_place`](CTPC::try_fit_in_place) return an error, while the other methods
+    /// panic.
+    pub fn with_cancellation_token(mut self, cancellation_token: CancellationToken) -> CTPC<P> {
+        self.cancellation_token = Some(cancellation_token);
+        self
+    }
+
+    /// Run the parallel work of each run inside `thread_pool` instead of the global rayon pool.
+    ///
+    /// The parent sets of the nodes are learned in parallel, one node per task. The nested
+    /// parallel work started by a task (e.g. the parallel code of a custom parameter learning)
+    /// runs in the same pool, hence a single-threaded pool makes the whole run sequential.
+    pub fn with_thread_pool(mut self, thread_pool: Arc<ThreadPool>) -> CTPC<P> {
+        self.thread_pool = Some(thread_pool);
+        self
+    }
+
+    /// Correct the significance levels of `Ftest` and `Chi2test` for multiple testing.
+    pub fn with_correction(mut self, correction: Correction) -> CTPC<P> {
+        self.correction = correction;
+        self
+    }
+
+    /// Control the false discovery rate of the learned edges with the Benjamini-Hochberg
+    /// procedure.
+    ///
+    /// The p-value of a tested edge is the smallest p-value of `Ftest` and `Chi2test` over the
+    /// separation sets tested for the edge. Once the search of every node is over, the p-values of
+    /// all the tested edges are adjusted and the learned edges with an adjusted p-value larger
+    /// than `q` are removed. The required edges are never tested. The adjusted p-values are
+    /// reported in the [`NodeDiagnostics`] of each node.
+    ///
+    /// # Arguments
+    ///
+    /// * `q`: the target false discovery rate, in `(0, 1]`.
+    pub fn with_false_discovery_rate(mut self, q: f64) -> CTPC<P> {
+        if !(q > 0.0 && q <= 1.0) {
+            panic!("The false discovery rate must be in (0, 1]");
         }
+        self.false_discovery_rate = Some(q);
+        self
+    }
+
+    /// Create a `CTPC` whose hypothesis tests read the sufficient statistics from a shared store.
+    ///
+    /// The store must be bound to the dataset passed to
+    /// [`fit_transform`](StructuralLearningAlgorithm::fit_transform).
+    pub fn new_with_suff_stats_cache(
+        parameter_learning: P,
+        Ftest: F,
+        Chi2test: ChiSquare,
+        suff_stats_cache: Arc<SuffStatsCache>,
+    ) -> CTPC<P> {
+        let mut ctpc = CTPC::new(parameter_learning, Ftest, Chi2test);
+        ctpc.suff_stats_cache = Some(suff_stats_cache);
+        ctpc
+    }
+}
+
+//Likelihood-ratio statistic of a node given a single parent against the node without parents,
+//computed from the sufficient statistics of the node given the parent.
+fn likelihood_ratio_statistic(M: &Array3<usize>, T: &Array2<f64>) -> f64 {
+    //Maximum log-likelihood of the transitions given the sufficient statistics.
+    let log_likelihood = |M: ArrayView3<usize>, T: ArrayView2<f64>| -> f64 {
+        let mut ll = 0.0;
+        for (M_u, T_u) in M.outer_iter().zip(T.outer_iter()) {
+            for (M_ui, t) in M_u.outer_iter().zip(T_u.iter()) {
+                let m = M_ui.sum() as f64;
+                if m == 0.0 {
+                    continue;
+                }
+                ll += m * (m / t).ln() - m;
+                ll += M_ui
+                    .iter()
+                    .filter(|x| **x > 0)
+                    .map(|x| *x as f64 * (*x as f64 / m).ln())
+                    .sum::<f64>();
+            }
+        }
+        ll
+    };
+    let M_marginal = M.sum_axis(Axis(0)).insert_axis(Axis(0));
+    let T_marginal = T.sum_axis(Axis(0)).insert_axis(Axis(0));
+    2.0 * (log_likelihood(M.view(), T.view())
+        - log_likelihood(M_marginal.view(), T_marginal.view()))
+}
+
+impl<P: ParameterLearning> CTPC<P> {
+    /// Learn the structure of `net` and return, along with it, the separation sets found.
+    ///
+    /// For every candidate parent removed by the search, the separation set that made it
+    /// independent of the child is recorded. The candidates pruned by `max_candidate_parents` or
+    /// by the edge constraints are never tested, hence they have no separation set.
+    ///
+    /// # Arguments
+    ///
+    /// * `net`: the network whose structure is learned; its nodes must match the dataset.
+    /// * `dataset`: the observations used to test the conditional independences.
+    ///
+    /// # Return
+    ///
+    /// * A tuple with the learned network and a map from each removed `(parent, child)` pair to
+    ///   its separation set.
+    pub fn fit_transform_with_sepsets<T>(&self, net: T, dataset: &Dataset) -> (T, SeparationSets)
+    where
+        T: process::NetworkProcess,
+    {
+        let mut net = net;
+        match self.fit_in_place_with_sepsets(&mut net, dataset) {
+            Ok((_, separation_sets)) => (net, separation_sets),
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Learn the structure of a network, modifying it in place
+    ///
+    /// Unlike [`fit_in_place`](StructuralLearningAlgorithm::fit_in_place), an error is returned
+    /// instead of panicking when the run is cancelled through the token given to
+    /// [`with_cancellation_token`](CTPC::with_cancellation_token); in that case `net` holds the
+    /// partial structure learned before the cancellation. An error is also returned if the
+    /// parent sets given to [`with_known_parent_sets`](CTPC::with_known_parent_sets) do not match
+    /// the network, or if the dataset is not compatible with it.
+    ///
+    /// # Arguments
+    ///
+    /// * `net`: a mutable reference to a `NetworkProcess` instance
+    /// * `dataset`: the observations used to learn the structure.
+    ///
+    /// # Return
+    ///
+    /// * The `LearningDiagnostics` of the run.
+    pub fn try_fit_in_place<T>(
+        &self,
+        net: &mut T,
+        dataset: &Dataset,
+    ) -> Result<LearningDiagnostics, StructureLearningError>
+    where
+        T: process::NetworkProcess,
+    {
+        self.fit_in_place_with_sepsets(net, dataset)
+            .map(|(diagnostics, _)| diagnostics)
+    }
+
+    fn fit_in_place_with_sepsets<T>(
+        &self,
+        net: &mut T,
+        dataset: &Dataset,
+    ) -> Result<(LearningDiagnostics, SeparationSets), StructureLearningError>
+    where
+        T: process::NetworkProcess,
+    {
+        check_dataset_compatibility(&*net, dataset)?;
+        check_known_parent_sets(&*net, &self.known_parent_sets)?;
+
+        let start = Instant::now();
+        net.initialize_adj_matrix();
+
+        let required_edges = &self.required_edges;
+        let forbidden_edges = &self.forbidden_edges;
+        let progress_observer = self.progress_observer.as_deref();
+        let cancellation_token = self.cancellation_token.as_ref();
+        let thread_pool = self.thread_pool.as_deref();
+        //Set when a node search stops because of the cancellation.
+        let interrupted = AtomicBool::new(false);
+        let mut learned_parent_sets: Vec<NodeSearchResult> = vec![];
+        run_in_thread_pool(thread_pool, || {
+            learned_parent_sets.par_extend(net.get_node_indices().into_par_iter().map(|child_node| {
+                if let Some(progress_observer) = progress_observer {
+                    progress_observer.on_node_started(child_node);
+                }
+                //The parent set of a known node is not searched.
+                if let Some(parent_set) = self.known_parent_sets.get(&child_node) {
+                    if let Some(progress_observer) = progress_observer {
+                        progress_observer.on_node_finished(child_node, parent_set);
+                    }
+                    let node_diagnostics = NodeDiagnostics {
+                        node: child_node,
+                        score: None,
+                        n_tests: Some(0),
+                        n_rejected_tests: Some(0),
+                        adjusted_p_values: None,
+                    };
+                    return (
+                        child_node,
+                        parent_set.clone(),
+                        node_diagnostics,
+                        0,
+                        vec![],
+                        BTreeMap::new(),
+                    );
+                }
+                let mut cache = match &self.suff_stats_cache {
+                    Some(suff_stats_cache) => Cache::new_with_suff_stats_cache(
+                        &self.parameter_learning,
+                        suff_stats_cache.clone(),
+                    ),
+                    None => Cache::new(&self.parameter_learning),
+                };
+                //The forbidden parents are discarded before testing.
+                let mut candidate_parent_set: BTreeSet<usize> = net
+                    .get_node_indices()
+                    .into_iter()
+                    .filter(|x| x != &child_node && !forbidden_edges.contains(&(*x, child_node)))
+                    .collect();
+                //Keep only the candidates most dependent on the child, required parents first.
+                let max_candidate_parents = match (
+                    self.max_candidate_parents,
+                    self.max_parent_set.as_ref().and_then(|x| x.get(child_node)),
+                ) {
+                    (Some(x), Some(y)) => Some(x.min(y)),
+                    (x, y) => x.or(y),
+                };
+                if let Some(max_candidate_parents) = max_candidate_parents {
+                    if candidate_parent_set.len() > max_candidate_parents {
+                        let mut ranking: Vec<(bool, f64, usize)> = candidate_parent_set
+                            .iter()
+                            .map(|parent_node| {
+                                let parent_set = BTreeSet::from([*parent_node]);
+                                let stats = match &self.suff_stats_cache {
+                                    Some(suff_stats_cache) => suff_stats_cache
+                                        .get_or_compute(&*net, dataset, child_node, &parent_set),
+                                    None => Arc::new(sufficient_statistics(
+                                        &*net,
+                                        dataset,
+                                        child_node,
+                                        &parent_set,
+                                    )),
+                                };
+                                (
+                                    required_edges.contains(&(*parent_node, child_node)),
+                                    likelihood_ratio_statistic(&stats.0, &stats.1),
+                                    *parent_node,
+                                )
+                            })
+                            .collect();
+                        ranking.sort_by(|a, b| b.0.cmp(&a.0).then(b.1.total_cmp(&a.1)));
+                        let pruned: Vec<usize> = ranking
+                            .split_off(max_candidate_parents)
+                            .into_iter()
+                            .filter(|(is_required, _, _)| !is_required)
+                            .map(|(_, _, parent_node)| parent_node)
+                            .collect();
+                        warn!(
+                            "Node {}: candidate parents {:?} pruned, their tests are skipped",
+                            child_node, pruned
+                        );
+                        for parent_node in pruned.iter() {
+                            candidate_parent_set.remove(parent_node);
+                        }
+                    }
+                }
+                let max_separation_set_size = self.max_separation_set_size.unwrap_or(usize::MAX);
+                let mut n_tests = 0;
+                let mut n_rejected_tests = 0;
+                //Separation sets of the removed candidate parents.
+                let mut separation_sets = vec![];
+                //Smallest p-value of each tested candidate parent (only with the FDR control).
+                let mut p_values: BTreeMap<usize, f64> = BTreeMap::new();
+                let mut separation_set_size = 0;
+                'search: while separation_set_size < candidate_parent_set.len()
+                    && separation_set_size <= max_separation_set_size
+                {
+                    let mut candidate_parent_set_TMP = candidate_parent_set.clone();
+                    //Significance levels of the tests at the current separation set size.
+                    let (f_alpha, chi2_alpha) = match self.correction {
+                        Correction::None => (self.Ftest.alpha, self.Chi2test.alpha),
+                        Correction::Bonferroni => {
+                            let n_tested_parents = candidate_parent_set
+                                .iter()
+                                .filter(|x| !required_edges.contains(&(**x, child_node)))
+                                .count();
+                            let n_planned_tests = (n_tested_parents as f64
+                                * binomial(candidate_parent_set.len() - 1, separation_set_size))
+                            .max(1.0);
+                            (
+                                self.Ftest.alpha / n_planned_tests,
+                                self.Chi2test.alpha / n_planned_tests,
+                            )
+                        }
+                    };
+                    for parent_node in candidate_parent_set.iter() {
+                        //The required parents are never removed.
+                        if required_edges.contains(&(*parent_node, child_node)) {
+                            continue;
+                        }
+                        for separation_set in candidate_parent_set
+                            .iter()
+                            .filter(|x| x != &parent_node)
+                            .copied()
+                            .combinations(separation_set_size)
+                        {
+                            if cancellation_token.is_some_and(|x| x.is_cancelled()) {
+                                interrupted.store(true, Ordering::Relaxed);
+                                candidate_parent_set = candidate_parent_set_TMP;
+                                break 'search;
+                            }
+                            let separation_set: BTreeSet<usize> = separation_set.into_iter().collect();
+                            n_tests += 1;
+                            let f_query = IndependenceQuery {
+                                child_node,
+                                parent_node: *parent_node,
+                                separation_set: &separation_set,
+                                alpha: f_alpha,
+                            };
+                            let chi2_query = IndependenceQuery {
+                                alpha: chi2_alpha,
+                                ..f_query
+                            };
+                            let independent = self
+                                .Ftest
+                                .call_with_alpha(&*net, &f_query, dataset, &mut cache)
+                                && self
+                                    .Chi2test
+                                    .call_with_alpha(&*net, &chi2_query, dataset, &mut cache);
+                            if self.false_discovery_rate.is_some() {
+                                let p_value = self
+                                    .Ftest
+                                    .p_value(
+                                        &*net,
+                                        child_node,
+                                        *parent_node,
+                                        &separation_set,
+                                        dataset,
+                                        &mut cache,
+                                    )
+                                    .min(self.Chi2test.p_value(
+                                        &*net,
+                                        child_node,
+                                        *parent_node,
+                                        &separation_set,
+                                        dataset,
+                                        &mut cache,
+                                    ));
+                                let min_p_value = p_values.entry(*parent_node).or_insert(1.0);
+                                *min_p_value = min_p_value.min(p_value);
+                            }
+                            if let Some(progress_observer) = progress_observer {
+                                progress_observer.on_test_performed(
+                                    child_node,
+                                    *parent_node,
+                                    &separation_set,
+                                    independent,
+                                );
+                            }
+                            if independent {
+                                candidate_parent_set_TMP.remove(parent_node);
+                                separation_sets.push((*parent_node, separation_set));
+                                break;
+                            }
+                            n_rejected_tests += 1;
+                        }
+                    }
+                    candidate_parent_set = candidate_parent_set_TMP;
+                    separation_set_size += 1;
+                }
+                if separation_set_size < candidate_parent_set.len() {
+                    warn!(
+                        "Node {}: separation sets of size {} to {} skipped for the candidate parents {:?}",
+                        child_node,
+                        separation_set_size,
+                        candidate_parent_set.len() - 1,
+                        candidate_parent_set
+                    );
+                }
+                if let Some(progress_observer) = progress_observer {
+                    progress_observer.on_node_finished(child_node, &candidate_parent_set);
+                }
+                let node_diagnostics = NodeDiagnostics {
+                    node: child_node,
+                    score: None,
+                    n_tests: Some(n_tests),
+                    n_rejected_tests: Some(n_rejected_tests),
+                    adjusted_p_values: None,
+                };
+                (
+                    child_node,
+                    candidate_parent_set,
+                    node_diagnostics,
+                    cache.get_number_of_fits(),
+                    separation_sets,
+                    p_values,
+                )
+            }))
+        });
+        //Adjust the p-values of all the tested edges and drop the edges not surviving.
+        if let Some(q) = self.false_discovery_rate {
+            let tested_edges: Vec<(usize, usize, f64)> = learned_parent_sets
+                .iter()
+                .flat_map(|(child_node, _, _, _, _, p_values)| {
+                    p_values
+                        .iter()
+                        .map(move |(parent_node, p_value)| (*child_node, *parent_node, *p_value))
+                })
+                .collect();
+            let adjusted_p_values = benjamini_hochberg(
+                &tested_edges
+                    .iter()
+                    .map(|(_, _, p_value)| *p_value)
+                    .collect::<Vec<f64>>(),
+            );
+            let mut nodes_adjusted_p_values: BTreeMap<usize, BTreeMap<usize, f64>> =
+                BTreeMap::new();
+            for ((child_node, parent_node, _), adjusted_p_value) in
+                tested_edges.iter().zip(adjusted_p_values)
+            {
+                nodes_adjusted_p_values
+                    .entry(*child_node)
+                    .or_default()
+                    .insert(*parent_node, adjusted_p_value);
+            }
+            for (child_node, candidate_parent_set, node_diagnostics, _, _, _) in
+                learned_parent_sets.iter_mut()
+            {
+                let node_adjusted_p_values = nodes_adjusted_p_values
+                    .remove(child_node)
+                    .unwrap_or_default();
+                candidate_parent_set.retain(|parent_node| {
+                    node_adjusted_p_values
+                        .get(parent_node)
+                        .is_none_or(|adjusted_p_value| *adjusted_p_value <= q)
+                });
+                node_diagnostics.adjusted_p_values = Some(node_adjusted_p_values);
+            }
+        }
+        let mut nodes_diagnostics = vec![];
+        let mut n_parameter_fits = 0;
+        let mut separation_sets = HashMap::new();
+        for (child_node, candidate_parent_set, node_diagnostics, n_fits, node_separation_sets, _) in
+            learned_parent_sets
+        {
+            for parent_node in candidate_parent_set.iter() {
+                net.add_edge(*parent_node, child_node);
+            }
+            nodes_diagnostics.push(node_diagnostics);
+            n_parameter_fits += n_fits;
+            separation_sets.extend(
+                node_separation_sets
+                    .into_iter()
+                    .map(|(parent_node, separation_set)| {
+                        ((parent_node, child_node), separation_set)
+                    }),
+            );
+        }
+        if interrupted.into_inner() {
+            return Err(StructureLearningError::LearningInterrupted(String::from(
+                "CTPC cancelled",
+            )));
+        }
+        let elapsed_seconds = start.elapsed().as_secs_f64();
+
+        if let Some(journal) = &self.journal {
+            let mut hyperparameters = vec![
+                (
+                    String::from("parameter_learning"),
+                    std::any::type_name::<P>().to_string(),
+                ),
+                (String::from("f_alpha"), self.Ftest.alpha.to_string()),
+                (String::from("chi2_alpha"), self.Chi2test.alpha.to_string()),
+            ];
+            hyperparameters.extend(
+                self.parameter_learning
+                    .hyperparameters()
+                    .into_iter()
+                    .map(|(name, value)| (format!("parameter_learning_{}", name), value)),
+            );
+            if let Some(q) = self.false_discovery_rate {
+                hyperparameters.push((String::from("false_discovery_rate"), q.to_string()));
+            }
+            if self.correction != Correction::None {
+                hyperparameters
+                    .push((String::from("correction"), format!("{:?}", self.correction)));
+            }
+            if let Some(max_separation_set_size) = self.max_separation_set_size {
+                hyperparameters.push((
+                    String::from("max_separation_set_size"),
+                    max_separation_set_size.to_string(),
+                ));
+            }
+            if let Some(max_candidate_parents) = self.max_candidate_parents {
+                hyperparameters.push((
+                    String::from("max_candidate_parents"),
+                    max_candidate_parents.to_string(),
+                ));
+            }
+            match &self.max_parent_set {
+                Some(ParentLimit::Global(max_parent_set)) => hyperparameters
+                    .push((String::from("max_parent_set"), max_parent_set.to_string())),
+                Some(ParentLimit::PerNode(max_parent_sets)) => hyperparameters.push((
+                    String::from("max_parent_set"),
+                    format!("{:?}", BTreeMap::from_iter(max_parent_sets.iter())),
+                )),
+                None => {}
+            }
+            if !self.required_edges.is_empty() {
+                hyperparameters.push((
+                    String::from("required_edges"),
+                    format!("{:?}", self.required_edges),
+                ));
+            }
+            if !self.forbidden_edges.is_empty() {
+                hyperparameters.push((
+                    String::from("forbidden_edges"),
+                    format!("{:?}", self.forbidden_edges),
+                ));
+            }
+            if !self.known_parent_sets.is_empty() {
+                hyperparameters.push((
+                    String::from("known_parent_sets"),
+                    format!("{:?}", BTreeMap::from_iter(self.known_parent_sets.iter())),
+                ));
+            }
+            let record = JournalRecord::new(
+                "CTPC",
+                hyperparameters,
+                dataset,
+                vec![],
+                elapsed_seconds,
+                &*net,
+            );
+            if let Err(e) = journal.record(&record) {
+                warn!("Unable to write the journal record: {}", e);
+            }
+        }
+        Ok((
+            LearningDiagnostics {
+                nodes: nodes_diagnostics,
+                elapsed_seconds,
+                n_parameter_fits: Some(n_parameter_fits),
+            },
+            separation_sets,
+        ))
     }
 }
 
 impl<P: ParameterLearning> StructuralLearningAlgorithm for CTPC<P> {
-    fn fit_transform<T>(&self, net: T, dataset: &Dataset) -> T
+    fn fit_in_place_with_diagnostics<T>(
+        &self,
+        net: &mut T,
+        dataset: &Dataset,
+    ) -> LearningDiagnostics
     where
         T: process::NetworkProcess,
     {
-        //Check the coherence between dataset and network
-        if net.get_number_of_nodes() != dataset.get_trajectories()[0].get_events().shape()[1] {
-            panic!("Dataset and Network must have the same number of variables.")
+        match self.try_fit_in_place(net, dataset) {
+            Ok(diagnostics) => diagnostics,
+            Err(e) => panic!("{}", e),
         }
+    }
 
-        //Make the network mutable.
-        let mut net = net;
+    fn try_fit_in_place_with_diagnostics<T>(
+        &self,
+        net: &mut T,
+        dataset: &Dataset,
+    ) -> Result<LearningDiagnostics, StructureLearningError>
+    where
+        T: process::NetworkProcess,
+    {
+        self.try_fit_in_place(net, dataset)
+    }
+}
 
+/// Continuous-time adaptation of the HITON-PC algorithm.
+///
+/// For each node, the variables marginally dependent on it are ranked by their association
+/// strength (the likelihood-ratio statistic of the node given the variable) and added one at a
+/// time to the candidate parent set. A variable is admitted only if no subset of the current
+/// candidates makes it independent of the node; after each admission, the candidates rendered
+/// independent by a subset containing the new one are removed.
+///
+/// # Arguments
+///
+/// * [`parameter_learning`](crate::parameter_learning) - is the method used to learn the parameters.
+/// * [`Ftest`](crate::structure_learning::hypothesis_test::F) - is the F-test hyppothesis test.
+/// * [`Chi2test`](crate::structure_learning::hypothesis_test::ChiSquare) - is the chi-squared test (χ2 test) hypothesis test.
+/// * `max_separation_set_size` - is the maximum size of the separation sets tested.
+pub struct Hiton<P: ParameterLearning> {
+    parameter_learning: P,
+    Ftest: F,
+    Chi2test: ChiSquare,
+    max_separation_set_size: Option<usize>,
+}
+
+impl<P: ParameterLearning> Hiton<P> {
+    /// Create a `Hiton`
+    ///
+    /// # Arguments
+    ///
+    /// * `parameter_learning`: the method used to learn the parameters.
+    /// * `Ftest`: the F-test hypothesis test.
+    /// * `Chi2test`: the chi-squared hypothesis test.
+    /// * `max_separation_set_size`: maximum size of the separation sets tested.
+    pub fn new(
+        parameter_learning: P,
+        Ftest: F,
+        Chi2test: ChiSquare,
+        max_separation_set_size: Option<usize>,
+    ) -> Hiton<P> {
+        Hiton {
+            parameter_learning,
+            Ftest,
+            Chi2test,
+            max_separation_set_size,
+        }
+    }
+}
+
+impl<P: ParameterLearning> StructuralLearningAlgorithm for Hiton<P> {
+    fn fit_in_place_with_diagnostics<T>(
+        &self,
+        net: &mut T,
+        dataset: &Dataset,
+    ) -> LearningDiagnostics
+    where
+        T: process::NetworkProcess,
+    {
+        match self.try_fit_in_place_with_diagnostics(net, dataset) {
+            Ok(diagnostics) => diagnostics,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    fn try_fit_in_place_with_diagnostics<T>(
+        &self,
+        net: &mut T,
+        dataset: &Dataset,
+    ) -> Result<LearningDiagnostics, StructureLearningError>
+    where
+        T: process::NetworkProcess,
+    {
+        check_dataset_compatibility(&*net, dataset)?;
+
+        let start = Instant::now();
         net.initialize_adj_matrix();
+        let max_separation_set_size = self.max_separation_set_size.unwrap_or(usize::MAX);
 
-        let mut learned_parent_sets: Vec<(usize, BTreeSet<usize>)> = vec![];
+        let mut learned_parent_sets: Vec<(usize, BTreeSet<usize>, NodeDiagnostics, usize)> = vec![];
         learned_parent_sets.par_extend(net.get_node_indices().into_par_iter().map(|child_node| {
             let mut cache = Cache::new(&self.parameter_learning);
-            let mut candidate_parent_set: BTreeSet<usize> = net
+            let mut n_tests = 0;
+            let mut n_rejected_tests = 0;
+            //Test the independence between child_node and parent_node given separation_set.
+            let mut is_independent =
+                |parent_node: usize, separation_set: &BTreeSet<usize>, cache: &mut Cache<P>| {
+                    n_tests += 1;
+                    let independent = self.Ftest.call(
+                        &*net,
+                        child_node,
+                        parent_node,
+                        separation_set,
+                        dataset,
+                        cache,
+                    ) && self.Chi2test.call(
+                        &*net,
+                        child_node,
+                        parent_node,
+                        separation_set,
+                        dataset,
+                        cache,
+                    );
+                    if !independent {
+                        n_rejected_tests += 1;
+                    }
+                    independent
+                };
+
+            //Rank the marginally dependent variables by decreasing association strength.
+            let mut open_list: Vec<(f64, usize)> = net
                 .get_node_indices()
-                .into_iter()
-                .filter(|x| x != &child_node)
+                .filter(|x| *x != child_node)
+                .filter(|x| !is_independent(*x, &BTreeSet::new(), &mut cache))
+                .map(|parent_node| {
+                    let (M, T) = sufficient_statistics(
+                        &*net,
+                        dataset,
+                        child_node,
+                        &BTreeSet::from([parent_node]),
+                    );
+                    (likelihood_ratio_statistic(&M, &T), parent_node)
+                })
                 .collect();
-            let mut separation_set_size = 0;
-            while separation_set_size < candidate_parent_set.len() {
-                let mut candidate_parent_set_TMP = candidate_parent_set.clone();
-                for parent_node in candidate_parent_set.iter() {
-                    for separation_set in candidate_parent_set
-                        .iter()
-                        .filter(|x| x != &parent_node)
-                        .map(|x| *x)
-                        .combinations(separation_set_size)
-                    {
-                        let separation_set = separation_set.into_iter().collect();
-                        if self.Ftest.call(
-                            &net,
-                            child_node,
-                            *parent_node,
-                            &separation_set,
-                            dataset,
-                            &mut cache,
-                        ) && self.Chi2test.call(
-                            &net,
-                            child_node,
-                            *parent_node,
-                            &separation_set,
-                            dataset,
+            open_list.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)));
+
+            let mut candidate_parent_set: BTreeSet<usize> = BTreeSet::new();
+            for (_, new_parent) in open_list {
+                //Grow: admit the variable only if no subset of the candidates separates it.
+                let separated = (1..=max_separation_set_size.min(candidate_parent_set.len()))
+                    .flat_map(|size| candidate_parent_set.iter().copied().combinations(size))
+                    .any(|separation_set| {
+                        is_independent(
+                            new_parent,
+                            &separation_set.into_iter().collect(),
                             &mut cache,
-                        ) {
-                            candidate_parent_set_TMP.remove(parent_node);
-                            break;
-                        }
+                        )
+                    });
+                if separated {
+                    continue;
+                }
+                candidate_parent_set.insert(new_parent);
+                //Shrink: only the separation sets containing the new variable are untested.
+                for parent_node in candidate_parent_set.clone() {
+                    if parent_node == new_parent {
+                        continue;
+                    }
+                    let others: Vec<usize> = candidate_parent_set
+                        .iter()
+                        .copied()
+                        .filter(|x| *x != parent_node && *x != new_parent)
+                        .collect();
+                    let separated = (0..max_separation_set_size.min(others.len() + 1))
+                        .flat_map(|size| others.iter().copied().combinations(size))
+                        .any(|separation_set| {
+                            let mut separation_set: BTreeSet<usize> =
+                                separation_set.into_iter().collect();
+                            separation_set.insert(new_parent);
+                            is_independent(parent_node, &separation_set, &mut cache)
+                        });
+                    if separated {
+                        candidate_parent_set.remove(&parent_node);
                     }
                 }
-                candidate_parent_set = candidate_parent_set_TMP;
-                separation_set_size += 1;
             }
-            (child_node, candidate_parent_set)
+            let node_diagnostics = NodeDiagnostics {
+                node: child_node,
+                score: None,
+                n_tests: Some(n_tests),
+                n_rejected_tests: Some(n_rejected_tests),
+                adjusted_p_values: None,
+            };
+            (
+                child_node,
+                candidate_parent_set,
+                node_diagnostics,
+                cache.get_number_of_fits(),
+            )
         }));
-        for (child_node, candidate_parent_set) in learned_parent_sets {
+        let mut nodes_diagnostics = vec![];
+        let mut n_parameter_fits = 0;
+        for (child_node, candidate_parent_set, node_diagnostics, n_fits) in learned_parent_sets {
             for parent_node in candidate_parent_set.iter() {
                 net.add_edge(*parent_node, child_node);
             }
+            nodes_diagnostics.push(node_diagnostics);
+            n_parameter_fits += n_fits;
         }
-        net
+        Ok(LearningDiagnostics {
+            nodes: nodes_diagnostics,
+            elapsed_seconds: start.elapsed().as_secs_f64(),
+            n_parameter_fits: Some(n_parameter_fits),
+        })
     }
 }
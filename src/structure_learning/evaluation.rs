@@ -0,0 +1,307 @@
+//! Evaluation harness for structure learning experiments.
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::time::Instant;
+
+use crate::params::ParamsTrait;
+use crate::process::ctbn::CtbnNetwork;
+use crate::process::NetworkProcess;
+use crate::structure_learning::{StructuralLearningAlgorithm, StructureLearningError};
+use crate::tools::{trajectory_generator, Dataset};
+
+/// Object safe view of a `StructuralLearningAlgorithm` restricted to `CtbnNetwork`; it allows
+/// different algorithms to be evaluated together.
+pub trait EvaluableAlgorithm {
+    fn learn_structure(&self, net: &mut CtbnNetwork, dataset: &Dataset);
+}
+
+impl<A: StructuralLearningAlgorithm> EvaluableAlgorithm for A {
+    fn learn_structure(&self, net: &mut CtbnNetwork, dataset: &Dataset) {
+        self.fit_in_place(net, dataset);
+    }
+}
+
+/// Configuration of the data generated at each replication.
+///
+/// # Attributes
+///
+/// * `n_trajectories` - number of trajectories generated at each replication.
+/// * `t_end` - ending time of each trajectory.
+/// * `n_replications` - number of replications; each one uses a fresh dataset.
+/// * `seed` - base seed; the seed of each replication is derived from it.
+#[derive(Clone, Debug)]
+pub struct EvaluationConfig {
+    pub n_trajectories: u64,
+    pub t_end: f64,
+    pub n_replications: u64,
+    pub seed: Option<u64>,
+}
+
+/// Mean and (sample) standard deviation of a metric over the replications.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MetricSummary {
+    pub mean: f64,
+    pub std: f64,
+}
+
+impl MetricSummary {
+    fn from_values(values: &[f64]) -> MetricSummary {
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let std = if values.len() > 1 {
+            (values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt()
+        } else {
+            0.0
+        };
+        MetricSummary { mean, std }
+    }
+}
+
+/// Summary of the replications of a single algorithm. The runtime is expressed in seconds.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AlgorithmSummary {
+    pub name: String,
+    pub precision: MetricSummary,
+    pub recall: MetricSummary,
+    pub f1: MetricSummary,
+    pub runtime: MetricSummary,
+}
+
+/// Result of [`evaluate`]: one summary per algorithm, in the order they were given.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EvaluationReport {
+    pub n_replications: u64,
+    pub algorithms: Vec<AlgorithmSummary>,
+}
+
+impl EvaluationReport {
+    /// Serialize the report as CSV, one row per algorithm.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "algorithm,precision_mean,precision_std,recall_mean,recall_std,f1_mean,f1_std,runtime_mean,runtime_std\n",
+        );
+        for a in self.algorithms.iter() {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{},{},{}\n",
+                a.name,
+                a.precision.mean,
+                a.precision.std,
+                a.recall.mean,
+                a.recall.std,
+                a.f1.mean,
+                a.f1.std,
+                a.runtime.mean,
+                a.runtime.std
+            ));
+        }
+        csv
+    }
+}
+
+impl fmt::Display for EvaluationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Replications: {}", self.n_replications)?;
+        for a in self.algorithms.iter() {
+            writeln!(
+                f,
+                "{}: precision {:.3} ± {:.3}, recall {:.3} ± {:.3}, f1 {:.3} ± {:.3}, runtime {:.3}s ± {:.3}s",
+                a.name,
+                a.precision.mean,
+                a.precision.std,
+                a.recall.mean,
+                a.recall.std,
+                a.f1.mean,
+                a.f1.std,
+                a.runtime.mean,
+                a.runtime.std
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Compare the edges of a learned network against the ground truth.
+///
+/// When there are no learned (respectively true) edges the precision (respectively the recall)
+/// is defined as `1.0`.
+///
+/// # Return
+///
+/// * A tuple containing precision, recall and F1 score.
+pub fn edge_metrics(ground_truth: &CtbnNetwork, learned: &CtbnNetwork) -> (f64, f64, f64) {
+    let true_edges: BTreeSet<(usize, usize)> = ground_truth.edges().collect();
+    let learned_edges: BTreeSet<(usize, usize)> = learned.edges().collect();
+    let true_positives = true_edges.intersection(&learned_edges).count() as f64;
+    let precision = if learned_edges.is_empty() {
+        1.0
+    } else {
+        true_positives / learned_edges.len() as f64
+    };
+    let recall = if true_edges.is_empty() {
+        1.0
+    } else {
+        true_positives / true_edges.len() as f64
+    };
+    let f1 = if precision + recall == 0.0 {
+        0.0
+    } else {
+        2.0 * precision * recall / (precision + recall)
+    };
+    (precision, recall, f1)
+}
+
+/// Edge-level differences between a learned network and a reference one.
+///
+/// The edges are `(parent, child)` pairs of node labels. An edge whose direction is inverted in
+/// the learned network appears both in `missing_edges` and in `extra_edges`, and it is also
+/// listed (with the direction of the reference) in `reversed_edges`.
+///
+/// # Attributes
+///
+/// * `true_positives` - number of reference edges present in the learned network.
+/// * `false_positives` - number of learned edges not present in the reference network.
+/// * `false_negatives` - number of reference edges not present in the learned network.
+/// * `shd` - structural Hamming distance: number of edge insertions, deletions and reversals
+///   needed to turn the learned network into the reference one.
+/// * `missing_edges` - reference edges not present in the learned network.
+/// * `extra_edges` - learned edges not present in the reference network.
+/// * `reversed_edges` - reference edges present only with the opposite direction.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StructuralDiff {
+    pub true_positives: usize,
+    pub false_positives: usize,
+    pub false_negatives: usize,
+    pub shd: usize,
+    pub missing_edges: Vec<(String, String)>,
+    pub extra_edges: Vec<(String, String)>,
+    pub reversed_edges: Vec<(String, String)>,
+}
+
+/// Compare the structure of a learned network against a reference one.
+///
+/// # Arguments
+///
+/// * `reference` - the reference network, e.g. the ground truth.
+/// * `learned` - the learned network.
+///
+/// # Errors
+///
+/// * `MismatchedNetworks` if the networks have a different number of nodes or if a node has a
+///   different label in the two networks.
+pub fn structural_diff<R: NetworkProcess, L: NetworkProcess>(
+    reference: &R,
+    learned: &L,
+) -> Result<StructuralDiff, StructureLearningError> {
+    if reference.get_number_of_nodes() != learned.get_number_of_nodes() {
+        return Err(StructureLearningError::MismatchedNetworks(format!(
+            "the reference network has {} nodes, the learned one has {}",
+            reference.get_number_of_nodes(),
+            learned.get_number_of_nodes()
+        )));
+    }
+    for node in reference.get_node_indices() {
+        let reference_label = reference.get_node(node).get_label();
+        let learned_label = learned.get_node(node).get_label();
+        if reference_label != learned_label {
+            return Err(StructureLearningError::MismatchedNetworks(format!(
+                "node {} is labelled {} in the reference network and {} in the learned one",
+                node, reference_label, learned_label
+            )));
+        }
+    }
+    let reference_edges: BTreeSet<(usize, usize)> = reference.edges().collect();
+    let learned_edges: BTreeSet<(usize, usize)> = learned.edges().collect();
+    let missing: Vec<(usize, usize)> = reference_edges
+        .difference(&learned_edges)
+        .copied()
+        .collect();
+    let extra: Vec<(usize, usize)> = learned_edges
+        .difference(&reference_edges)
+        .copied()
+        .collect();
+    let reversed: Vec<(usize, usize)> = missing
+        .iter()
+        .filter(|(parent, child)| extra.contains(&(*child, *parent)))
+        .copied()
+        .collect();
+    let with_labels = |edges: &[(usize, usize)]| {
+        edges
+            .iter()
+            .map(|(parent, child)| {
+                (
+                    reference.get_node(*parent).get_label().clone(),
+                    reference.get_node(*child).get_label().clone(),
+                )
+            })
+            .collect()
+    };
+    Ok(StructuralDiff {
+        true_positives: reference_edges.intersection(&learned_edges).count(),
+        false_positives: extra.len(),
+        false_negatives: missing.len(),
+        shd: missing.len() + extra.len() - reversed.len(),
+        missing_edges: with_labels(&missing),
+        extra_edges: with_labels(&extra),
+        reversed_edges: with_labels(&reversed),
+    })
+}
+
+/// Run `config.n_replications` replications: at each one a fresh dataset is generated from
+/// `ground_truth` and the structure is learned with every algorithm.
+///
+/// # Arguments
+///
+/// * `ground_truth` - a network with structure and parameters defined.
+/// * `config` - configuration of the generated data.
+/// * `algorithms` - the algorithms to evaluate, each one with the name used in the report.
+pub fn evaluate(
+    ground_truth: &CtbnNetwork,
+    config: &EvaluationConfig,
+    algorithms: &[(&str, &dyn EvaluableAlgorithm)],
+) -> EvaluationReport {
+    if config.n_replications == 0 {
+        panic!("At least one replication is required");
+    }
+    //(precision, recall, f1, runtime) of each replication for each algorithm.
+    let mut results: Vec<Vec<(f64, f64, f64, f64)>> = vec![vec![]; algorithms.len()];
+    for replication in 0..config.n_replications {
+        let seed = config.seed.map(|x| x.wrapping_add(replication));
+        let dataset = trajectory_generator(ground_truth, config.n_trajectories, config.t_end, seed);
+        for ((_, algorithm), result) in algorithms.iter().zip(results.iter_mut()) {
+            let mut net = empty_copy(ground_truth);
+            let start = Instant::now();
+            algorithm.learn_structure(&mut net, &dataset);
+            let runtime = start.elapsed().as_secs_f64();
+            let (precision, recall, f1) = edge_metrics(ground_truth, &net);
+            result.push((precision, recall, f1, runtime));
+        }
+    }
+
+    let summarize = |result: &Vec<(f64, f64, f64, f64)>, f: fn(&(f64, f64, f64, f64)) -> f64| {
+        MetricSummary::from_values(&result.iter().map(f).collect::<Vec<f64>>())
+    };
+    EvaluationReport {
+        n_replications: config.n_replications,
+        algorithms: algorithms
+            .iter()
+            .zip(results.iter())
+            .map(|((name, _), result)| AlgorithmSummary {
+                name: name.to_string(),
+                precision: summarize(result, |x| x.0),
+                recall: summarize(result, |x| x.1),
+                f1: summarize(result, |x| x.2),
+                runtime: summarize(result, |x| x.3),
+            })
+            .collect(),
+    }
+}
+
+//Network with the same nodes of `net` and no edges.
+fn empty_copy(net: &CtbnNetwork) -> CtbnNetwork {
+    let mut empty = CtbnNetwork::new();
+    for node in net.get_node_indices() {
+        empty.add_node(net.get_node(node).clone()).unwrap();
+    }
+    empty
+}
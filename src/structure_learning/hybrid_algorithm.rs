@@ -0,0 +1,108 @@
+//! Module containing hybrid algorithms, combining a constraint based and a score based search.
+
+use std::collections::BTreeSet;
+use std::time::Instant;
+
+use log::info;
+
+use crate::parameter_learning::ParameterLearning;
+use crate::process;
+use crate::structure_learning::constraint_based_algorithm::CTPC;
+use crate::structure_learning::score_based_algorithm::HillClimbing;
+use crate::structure_learning::score_function::ScoreFunction;
+use crate::structure_learning::{
+    LearningDiagnostics, NodeDiagnostics, StructuralLearningAlgorithm, StructureLearningError,
+};
+use crate::tools::Dataset;
+
+/// Hybrid learner: a `CTPC` skeleton refined by a `HillClimbing` search.
+///
+/// `CTPC` is run first and the parent set it learns for each node is used as the pool of
+/// candidate parents of the node (see [`HillClimbing::with_candidate_parents`]). The
+/// `HillClimbing` search, restricted to these pools, then returns the final structure. A true
+/// parent missed by `CTPC` can not be recovered, hence the hypothesis tests should be tuned to
+/// keep a superset of the parents (i.e. with small significance levels).
+///
+/// The diagnostics of each node report the score of the `HillClimbing` search and the
+/// hypothesis tests of `CTPC`.
+///
+/// # Arguments
+///
+/// * `ctpc`: the constraint based algorithm providing the candidate parents.
+/// * `hill_climbing`: the score based algorithm learning the final structure.
+pub struct HybridLearner<P: ParameterLearning, S: ScoreFunction> {
+    ctpc: CTPC<P>,
+    hill_climbing: HillClimbing<S>,
+}
+
+impl<P: ParameterLearning, S: ScoreFunction> HybridLearner<P, S> {
+    /// Create a `HybridLearner`
+    ///
+    /// # Arguments
+    ///
+    /// * `ctpc`: the constraint based algorithm providing the candidate parents.
+    /// * `hill_climbing`: the score based algorithm learning the final structure; the pools of
+    ///   candidate parents found by `ctpc` replace the ones it may already have.
+    pub fn new(ctpc: CTPC<P>, hill_climbing: HillClimbing<S>) -> HybridLearner<P, S> {
+        HybridLearner {
+            ctpc,
+            hill_climbing,
+        }
+    }
+}
+
+impl<P: ParameterLearning, S: ScoreFunction> StructuralLearningAlgorithm for HybridLearner<P, S> {
+    fn fit_in_place_with_diagnostics<T>(
+        &self,
+        net: &mut T,
+        dataset: &Dataset,
+    ) -> LearningDiagnostics
+    where
+        T: process::NetworkProcess,
+    {
+        match self.try_fit_in_place_with_diagnostics(net, dataset) {
+            Ok(diagnostics) => diagnostics,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    fn try_fit_in_place_with_diagnostics<T>(
+        &self,
+        net: &mut T,
+        dataset: &Dataset,
+    ) -> Result<LearningDiagnostics, StructureLearningError>
+    where
+        T: process::NetworkProcess,
+    {
+        let start = Instant::now();
+        //Learn the skeleton with the constraint based search.
+        let ctpc_diagnostics = self.ctpc.try_fit_in_place(net, dataset)?;
+        let candidate_parents: Vec<BTreeSet<usize>> = net
+            .get_node_indices()
+            .map(|node| net.get_parent_set(node))
+            .collect();
+        info!("Candidate parents: {:?}", candidate_parents);
+        //Refine the structure with the score based search.
+        let hill_climbing_diagnostics = self.hill_climbing.fit_in_place_with_candidate_parents(
+            net,
+            dataset,
+            Some(&candidate_parents),
+        )?;
+        Ok(LearningDiagnostics {
+            nodes: hill_climbing_diagnostics
+                .nodes
+                .into_iter()
+                .zip(ctpc_diagnostics.nodes)
+                .map(|(hill_climbing, ctpc)| NodeDiagnostics {
+                    node: hill_climbing.node,
+                    score: hill_climbing.score,
+                    n_tests: ctpc.n_tests,
+                    n_rejected_tests: ctpc.n_rejected_tests,
+                    adjusted_p_values: ctpc.adjusted_p_values,
+                })
+                .collect(),
+            elapsed_seconds: start.elapsed().as_secs_f64(),
+            n_parameter_fits: ctpc_diagnostics.n_parameter_fits,
+        })
+    }
+}
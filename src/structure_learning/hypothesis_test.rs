@@ -1,14 +1,29 @@
 //! Module for constraint based algorithms containing hypothesis test algorithms like chi-squared test, F test, etc...
 
+use std::borrow::Cow;
 use std::collections::BTreeSet;
 
-use ndarray::{Array3, Axis};
+use ndarray::{Array3, ArrayView2, Axis};
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
 use statrs::distribution::{ChiSquared, ContinuousCDF, FisherSnedecor};
 
+use crate::journal::fnv1a;
 use crate::params::*;
 use crate::structure_learning::constraint_based_algorithm::Cache;
+use crate::structure_learning::StructureLearningError;
 use crate::{parameter_learning, process, tools::Dataset};
 
+/// A single call of a [`HypothesisTest`]: is `child_node` independent of `parent_node` given
+/// `separation_set`, at the significance level `alpha`?
+pub struct IndependenceQuery<'a> {
+    pub child_node: usize,
+    pub parent_node: usize,
+    pub separation_set: &'a BTreeSet<usize>,
+    pub alpha: f64,
+}
+
 pub trait HypothesisTest {
     fn call<T, P>(
         &self,
@@ -22,6 +37,59 @@ pub trait HypothesisTest {
     where
         T: process::NetworkProcess,
         P: parameter_learning::ParameterLearning;
+
+    /// Perform the test of `query` with its significance level `alpha` instead of the one given
+    /// at construction (e.g. an `alpha` corrected for multiple testing).
+    ///
+    /// The default implementation ignores `alpha` and calls [`call`](HypothesisTest::call).
+    fn call_with_alpha<T, P>(
+        &self,
+        net: &T,
+        query: &IndependenceQuery,
+        dataset: &Dataset,
+        cache: &mut Cache<P>,
+    ) -> bool
+    where
+        T: process::NetworkProcess,
+        P: parameter_learning::ParameterLearning,
+    {
+        self.call(
+            net,
+            query.child_node,
+            query.parent_node,
+            query.separation_set,
+            dataset,
+            cache,
+        )
+    }
+
+    /// p-value of the test, i.e. the probability of observing differences at least as large as
+    /// the ones in the data under the independence hypothesis.
+    ///
+    /// The test compares several distributions (one for each configuration of the separation set
+    /// and each starting state of the child): the p-value is the smallest of their p-values.
+    ///
+    /// The default implementation returns `0.0` if [`call`](HypothesisTest::call) rejects the
+    /// independence and `1.0` otherwise.
+    fn p_value<T, P>(
+        &self,
+        net: &T,
+        child_node: usize,
+        parent_node: usize,
+        separation_set: &BTreeSet<usize>,
+        dataset: &Dataset,
+        cache: &mut Cache<P>,
+    ) -> f64
+    where
+        T: process::NetworkProcess,
+        P: parameter_learning::ParameterLearning,
+    {
+        if self.call(net, child_node, parent_node, separation_set, dataset, cache) {
+            1.0
+        } else {
+            0.0
+        }
+    }
 }
 
 /// Does the chi-squared test (χ2 test).
@@ -29,6 +97,10 @@ pub trait HypothesisTest {
 /// Used to determine if a difference between two sets of data is due to chance, or if it is due to
 /// a relationship (dependence) between the variables.
 ///
+/// The transitions are compared one row (i.e. one starting state) at a time. The degrees of
+/// freedom of a row are the number of target states reached in at least one of the two matrices,
+/// minus one. The rows without transitions in one of the two matrices are skipped.
+///
 /// # Arguments
 ///
 /// * `alpha` - is the significance level, the probability to reject a true null hypothesis;
@@ -36,7 +108,7 @@ pub trait HypothesisTest {
 ///   when there is no actual association.
 
 pub struct ChiSquare {
-    alpha: f64,
+    pub(crate) alpha: f64,
 }
 
 /// Does the F-test.
@@ -51,7 +123,41 @@ pub struct ChiSquare {
 ///   when there is no actual association.
 
 pub struct F {
-    alpha: f64,
+    pub(crate) alpha: f64,
+}
+
+//The matrix `index` of the transitions and of the CIM of a node, one for each parent configuration.
+struct MatrixView<'a> {
+    index: usize,
+    transitions: &'a Array3<usize>,
+    cim: Cow<'a, Array3<f64>>,
+}
+
+impl<'a> MatrixView<'a> {
+    fn new(index: usize, transitions: &'a Array3<usize>, cim: &'a Array3<f64>) -> MatrixView<'a> {
+        MatrixView {
+            index,
+            transitions,
+            cim: Cow::Borrowed(cim),
+        }
+    }
+
+    fn of(params: &'a DiscreteStatesContinousTimeParams, index: usize) -> MatrixView<'a> {
+        let transitions = params.get_transitions().as_ref().unwrap();
+        let cim = params.get_cim().as_ref().unwrap();
+        //A noisy-OR CIM stores only the base matrix: expand it to one matrix for each parent
+        //configuration of the transitions.
+        let cim = if cim.shape()[0] == transitions.shape()[0] {
+            Cow::Borrowed(cim)
+        } else {
+            Cow::Owned(params.get_expanded_cim().unwrap())
+        };
+        MatrixView {
+            index,
+            transitions,
+            cim,
+        }
+    }
 }
 
 impl F {
@@ -82,25 +188,154 @@ impl F {
         M2: &Array3<usize>,
         cim_2: &Array3<f64>,
     ) -> bool {
-        let M1 = M1.index_axis(Axis(0), i).mapv(|x| x as f64);
-        let M2 = M2.index_axis(Axis(0), j).mapv(|x| x as f64);
-        let cim_1 = cim_1.index_axis(Axis(0), i);
-        let cim_2 = cim_2.index_axis(Axis(0), j);
+        self.compare_matrices_with_alpha(
+            &MatrixView::new(i, M1, cim_1),
+            &MatrixView::new(j, M2, cim_2),
+            self.alpha,
+        )
+    }
+
+    /// Compare two matrices extracted from two 3rd-orer tensors, failing if no row can be compared.
+    ///
+    /// The rows without transitions in one of the two matrices carry no evidence and are
+    /// skipped, as in [`compare_matrices`](F::compare_matrices).
+    ///
+    /// # Arguments
+    ///
+    /// * `i` - Position of the matrix of `M1` to compare with `M2`.
+    /// * `M1` - 3rd-order tensor 1.
+    /// * `j` - Position of the matrix of `M2` to compare with `M1`.
+    /// * `M2` - 3rd-order tensor 2.
+    ///
+    /// # Returns
+    ///
+    /// * The result of [`compare_matrices`](F::compare_matrices), or `DegenerateTest` if every
+    ///   row is skipped.
+    pub fn try_compare_matrices(
+        &self,
+        i: usize,
+        M1: &Array3<usize>,
+        cim_1: &Array3<f64>,
+        j: usize,
+        M2: &Array3<usize>,
+        cim_2: &Array3<f64>,
+    ) -> Result<bool, StructureLearningError> {
+        self.compare_rows(
+            &MatrixView::new(i, M1, cim_1),
+            &MatrixView::new(j, M2, cim_2),
+            self.alpha,
+        )
+        .ok_or_else(|| {
+            StructureLearningError::DegenerateTest(format!(
+                "no transitions to compare between the matrices {} and {}",
+                i, j
+            ))
+        })
+    }
+
+    fn compare_matrices_with_alpha(&self, m1: &MatrixView, m2: &MatrixView, alpha: f64) -> bool {
+        //No evidence of a difference without comparable rows.
+        self.compare_rows(m1, m2, alpha).unwrap_or(true)
+    }
+
+    //Compare the rows of two matrices, `None` if no row can be compared.
+    fn compare_rows(&self, m1: &MatrixView, m2: &MatrixView, alpha: f64) -> Option<bool> {
+        let rows_cdf = self.rows_cdf(m1, m2);
+        if rows_cdf.is_empty() {
+            return None;
+        }
+        let lim_sx = alpha / 2.0;
+        let lim_dx = 1.0 - (alpha / 2.0);
+        Some(rows_cdf.into_iter().all(|s| !(s < lim_sx || s > lim_dx)))
+    }
+
+    //Two-sided p-value of the comparison of two matrices, the smallest over the rows.
+    fn matrices_p_value(&self, m1: &MatrixView, m2: &MatrixView) -> f64 {
+        self.rows_cdf(m1, m2)
+            .into_iter()
+            .map(|s| 2.0 * s.min(1.0 - s))
+            .fold(1.0, f64::min)
+    }
+
+    //CDF of the ratio of the exit rates of each row of the two matrices. The rows without
+    //transitions in one of the matrices, or whose ratio is not defined, carry no evidence and
+    //are skipped.
+    fn rows_cdf(&self, m1: &MatrixView, m2: &MatrixView) -> Vec<f64> {
+        let M1 = m1
+            .transitions
+            .index_axis(Axis(0), m1.index)
+            .mapv(|x| x as f64);
+        let M2 = m2
+            .transitions
+            .index_axis(Axis(0), m2.index)
+            .mapv(|x| x as f64);
+        let cim_1 = m1.cim.index_axis(Axis(0), m1.index);
+        let cim_2 = m2.cim.index_axis(Axis(0), m2.index);
         let r1 = M1.sum_axis(Axis(1));
         let r2 = M2.sum_axis(Axis(1));
         let q1 = cim_1.diag();
         let q2 = cim_2.diag();
-        for idx in 0..r1.shape()[0] {
-            let s = q2[idx] / q1[idx];
-            let F = FisherSnedecor::new(r1[idx], r2[idx]).unwrap();
-            let s = F.cdf(s);
-            let lim_sx = self.alpha / 2.0;
-            let lim_dx = 1.0 - (self.alpha / 2.0);
-            if s < lim_sx || s > lim_dx {
-                return false;
-            }
+        (0..r1.shape()[0])
+            .filter_map(|idx| {
+                if r1[idx] == 0.0 || r2[idx] == 0.0 {
+                    return None;
+                }
+                let s = q2[idx] / q1[idx];
+                if !s.is_finite() {
+                    return None;
+                }
+                let F = FisherSnedecor::new(r1[idx], r2[idx]).ok()?;
+                Some(F.cdf(s))
+            })
+            .collect()
+    }
+
+    /// Perform the test, failing if no transitions can be compared.
+    ///
+    /// [`call`](HypothesisTest::call) treats the comparisons without transitions as no evidence
+    /// of dependence; this method returns `DegenerateTest` when every comparison is in this case
+    /// (e.g. for a parent configuration never observed in the dataset).
+    ///
+    /// # Arguments
+    ///
+    /// * `net`: `NetworkProcess` object.
+    /// * `child_node`: the node whose transitions are compared.
+    /// * `parent_node`: the candidate parent tested.
+    /// * `separation_set`: the conditioning set of the test.
+    /// * `dataset`: the dataset used to fit the parameters.
+    /// * `cache`: the cache of the parameters fitted.
+    pub fn try_call<T, P>(
+        &self,
+        net: &T,
+        child_node: usize,
+        parent_node: usize,
+        separation_set: &BTreeSet<usize>,
+        dataset: &Dataset,
+        cache: &mut Cache<P>,
+    ) -> Result<bool, StructureLearningError>
+    where
+        T: process::NetworkProcess,
+        P: parameter_learning::ParameterLearning,
+    {
+        let (P_small, P_big, index_pairs) =
+            fit_nested_models(net, child_node, parent_node, separation_set, dataset, cache);
+        let comparisons: Vec<Option<bool>> = index_pairs
+            .into_iter()
+            .map(|(idx_M_small, idx_M_big)| {
+                self.compare_rows(
+                    &MatrixView::of(&P_small, idx_M_small),
+                    &MatrixView::of(&P_big, idx_M_big),
+                    self.alpha,
+                )
+            })
+            .collect();
+        if comparisons.iter().all(|x| x.is_none()) {
+            return Err(StructureLearningError::DegenerateTest(format!(
+                "no transitions of node {} to compare for the parent {} given {:?}",
+                child_node, parent_node, separation_set
+            )));
         }
-        true
+        Ok(comparisons.into_iter().all(|x| x.unwrap_or(true)))
     }
 }
 
@@ -118,43 +353,67 @@ impl HypothesisTest for F {
         T: process::NetworkProcess,
         P: parameter_learning::ParameterLearning,
     {
-        let P_small = match cache.fit(net, &dataset, child_node, Some(separation_set.clone())) {
-            Params::DiscreteStatesContinousTime(node) => node,
+        let query = IndependenceQuery {
+            child_node,
+            parent_node,
+            separation_set,
+            alpha: self.alpha,
         };
-        let mut extended_separation_set = separation_set.clone();
-        extended_separation_set.insert(parent_node);
+        self.call_with_alpha(net, &query, dataset, cache)
+    }
 
-        let P_big = match cache.fit(
+    fn call_with_alpha<T, P>(
+        &self,
+        net: &T,
+        query: &IndependenceQuery,
+        dataset: &Dataset,
+        cache: &mut Cache<P>,
+    ) -> bool
+    where
+        T: process::NetworkProcess,
+        P: parameter_learning::ParameterLearning,
+    {
+        let (P_small, P_big, index_pairs) = fit_nested_models(
             net,
-            &dataset,
-            child_node,
-            Some(extended_separation_set.clone()),
-        ) {
-            Params::DiscreteStatesContinousTime(node) => node,
-        };
-        let partial_cardinality_product: usize = extended_separation_set
-            .iter()
-            .take_while(|x| **x != parent_node)
-            .map(|x| net.get_node(*x).get_reserved_space_as_parent())
-            .product();
-        for idx_M_big in 0..P_big.get_transitions().as_ref().unwrap().shape()[0] {
-            let idx_M_small: usize = idx_M_big % partial_cardinality_product
-                + (idx_M_big
-                    / (partial_cardinality_product
-                        * net.get_node(parent_node).get_reserved_space_as_parent()))
-                    * partial_cardinality_product;
-            if !self.compare_matrices(
-                idx_M_small,
-                P_small.get_transitions().as_ref().unwrap(),
-                P_small.get_cim().as_ref().unwrap(),
-                idx_M_big,
-                P_big.get_transitions().as_ref().unwrap(),
-                P_big.get_cim().as_ref().unwrap(),
-            ) {
-                return false;
-            }
-        }
-        return true;
+            query.child_node,
+            query.parent_node,
+            query.separation_set,
+            dataset,
+            cache,
+        );
+        index_pairs.into_iter().all(|(idx_M_small, idx_M_big)| {
+            self.compare_matrices_with_alpha(
+                &MatrixView::of(&P_small, idx_M_small),
+                &MatrixView::of(&P_big, idx_M_big),
+                query.alpha,
+            )
+        })
+    }
+
+    fn p_value<T, P>(
+        &self,
+        net: &T,
+        child_node: usize,
+        parent_node: usize,
+        separation_set: &BTreeSet<usize>,
+        dataset: &Dataset,
+        cache: &mut Cache<P>,
+    ) -> f64
+    where
+        T: process::NetworkProcess,
+        P: parameter_learning::ParameterLearning,
+    {
+        let (P_small, P_big, index_pairs) =
+            fit_nested_models(net, child_node, parent_node, separation_set, dataset, cache);
+        index_pairs
+            .into_iter()
+            .map(|(idx_M_small, idx_M_big)| {
+                self.matrices_p_value(
+                    &MatrixView::of(&P_small, idx_M_small),
+                    &MatrixView::of(&P_big, idx_M_big),
+                )
+            })
+            .fold(1.0, f64::min)
     }
 }
 
@@ -184,27 +443,55 @@ impl ChiSquare {
         j: usize,
         M2: &Array3<usize>,
     ) -> bool {
+        self.compare_matrices_with_alpha(i, M1, j, M2, self.alpha)
+    }
+
+    fn compare_matrices_with_alpha(
+        &self,
+        i: usize,
+        M1: &Array3<usize>,
+        j: usize,
+        M2: &Array3<usize>,
+        alpha: f64,
+    ) -> bool {
+        self.rows_cdf(i, M1, j, M2)
+            .into_iter()
+            .all(|x| x < (1.0 - alpha))
+    }
+
+    //p-value of the comparison of two matrices, the smallest over the rows.
+    fn matrices_p_value(&self, i: usize, M1: &Array3<usize>, j: usize, M2: &Array3<usize>) -> f64 {
+        self.rows_cdf(i, M1, j, M2)
+            .into_iter()
+            .map(|x| 1.0 - x)
+            .fold(1.0, f64::min)
+    }
+
+    //CDF of the statistic of each row of the two matrices. The rows without transitions in one
+    //of the matrices, or with transitions to a single state, carry no evidence and are skipped.
+    fn rows_cdf(&self, i: usize, M1: &Array3<usize>, j: usize, M2: &Array3<usize>) -> Vec<f64> {
         // Bregoli, A., Scutari, M. and Stella, F., 2021.
         // A constraint-based algorithm for the structural learning of
         // continuous-time Bayesian networks.
         // International Journal of Approximate Reasoning, 138, pp.105-122.
         // Also: https://www.itl.nist.gov/div898/software/dataplot/refman1/auxillar/chi2samp.htm
-        let M1 = M1.index_axis(Axis(0), i).mapv(|x| x as f64);
-        let M2 = M2.index_axis(Axis(0), j).mapv(|x| x as f64);
-        let K = M1.sum_axis(Axis(1)) / M2.sum_axis(Axis(1));
-        let K = K.mapv(f64::sqrt);
-        // Reshape to column vector.
-        let K = {
-            let n = K.len();
-            K.into_shape((n, 1)).unwrap()
-        };
-        let L = 1.0 / &K;
-        let mut X_2 = (&K * &M2 - &L * &M1).mapv(|a| a.powi(2)) / (&M2 + &M1);
-        X_2.diag_mut().fill(0.0);
-        let X_2 = X_2.sum_axis(Axis(1));
-        let n = ChiSquared::new((X_2.dim() - 1) as f64).unwrap();
-        let ret = X_2.into_iter().all(|x| n.cdf(x) < (1.0 - self.alpha));
-        ret
+        let M1 = M1.index_axis(Axis(0), i);
+        let M2 = M2.index_axis(Axis(0), j);
+        (0..M1.shape()[0])
+            .filter_map(|state| {
+                let a = off_diagonal(&M1, state);
+                let b = off_diagonal(&M2, state);
+                if a.iter().sum::<usize>() == 0 || b.iter().sum::<usize>() == 0 {
+                    return None;
+                }
+                let degrees_of_freedom = row_degrees_of_freedom(&a, &b);
+                if degrees_of_freedom == 0 {
+                    return None;
+                }
+                let n = ChiSquared::new(degrees_of_freedom as f64).unwrap();
+                Some(n.cdf(row_statistic(&a, &b)))
+            })
+            .collect()
     }
 }
 
@@ -222,40 +509,402 @@ impl HypothesisTest for ChiSquare {
         T: process::NetworkProcess,
         P: parameter_learning::ParameterLearning,
     {
-        let P_small = match cache.fit(net, &dataset, child_node, Some(separation_set.clone())) {
-            Params::DiscreteStatesContinousTime(node) => node,
+        let query = IndependenceQuery {
+            child_node,
+            parent_node,
+            separation_set,
+            alpha: self.alpha,
         };
-        let mut extended_separation_set = separation_set.clone();
-        extended_separation_set.insert(parent_node);
+        self.call_with_alpha(net, &query, dataset, cache)
+    }
 
-        let P_big = match cache.fit(
+    fn call_with_alpha<T, P>(
+        &self,
+        net: &T,
+        query: &IndependenceQuery,
+        dataset: &Dataset,
+        cache: &mut Cache<P>,
+    ) -> bool
+    where
+        T: process::NetworkProcess,
+        P: parameter_learning::ParameterLearning,
+    {
+        compare_transitions(
             net,
-            &dataset,
-            child_node,
-            Some(extended_separation_set.clone()),
-        ) {
-            Params::DiscreteStatesContinousTime(node) => node,
-        };
-        let partial_cardinality_product: usize = extended_separation_set
-            .iter()
-            .take_while(|x| **x != parent_node)
-            .map(|x| net.get_node(*x).get_reserved_space_as_parent())
-            .product();
-        for idx_M_big in 0..P_big.get_transitions().as_ref().unwrap().shape()[0] {
+            query.child_node,
+            query.parent_node,
+            query.separation_set,
+            dataset,
+            cache,
+            |i, M1, j, M2| self.compare_matrices_with_alpha(i, M1, j, M2, query.alpha),
+        )
+    }
+
+    fn p_value<T, P>(
+        &self,
+        net: &T,
+        child_node: usize,
+        parent_node: usize,
+        separation_set: &BTreeSet<usize>,
+        dataset: &Dataset,
+        cache: &mut Cache<P>,
+    ) -> f64
+    where
+        T: process::NetworkProcess,
+        P: parameter_learning::ParameterLearning,
+    {
+        let (P_small, P_big, index_pairs) =
+            fit_nested_models(net, child_node, parent_node, separation_set, dataset, cache);
+        index_pairs
+            .into_iter()
+            .map(|(idx_M_small, idx_M_big)| {
+                self.matrices_p_value(
+                    idx_M_small,
+                    P_small.get_transitions().as_ref().unwrap(),
+                    idx_M_big,
+                    P_big.get_transitions().as_ref().unwrap(),
+                )
+            })
+            .fold(1.0, f64::min)
+    }
+}
+
+//Compare the transitions of `child_node` given `separation_set` with the transitions given
+//`separation_set` plus `parent_node`, one parent configuration at a time. Return `true` (i.e.
+//independence) if `compare_matrices` finds every pair of matrices similar.
+fn compare_transitions<T, P, C>(
+    net: &T,
+    child_node: usize,
+    parent_node: usize,
+    separation_set: &BTreeSet<usize>,
+    dataset: &Dataset,
+    cache: &mut Cache<P>,
+    compare_matrices: C,
+) -> bool
+where
+    T: process::NetworkProcess,
+    P: parameter_learning::ParameterLearning,
+    C: Fn(usize, &Array3<usize>, usize, &Array3<usize>) -> bool,
+{
+    let (P_small, P_big, index_pairs) =
+        fit_nested_models(net, child_node, parent_node, separation_set, dataset, cache);
+    index_pairs.into_iter().all(|(idx_M_small, idx_M_big)| {
+        compare_matrices(
+            idx_M_small,
+            P_small.get_transitions().as_ref().unwrap(),
+            idx_M_big,
+            P_big.get_transitions().as_ref().unwrap(),
+        )
+    })
+}
+
+//Fit the parameters of `child_node` given `separation_set` and given `separation_set` plus
+//`parent_node`. Return them with the pairs `(idx_M_small, idx_M_big)` of the matrices to
+//compare, i.e. of the parent configurations agreeing on `separation_set`.
+fn fit_nested_models<T, P>(
+    net: &T,
+    child_node: usize,
+    parent_node: usize,
+    separation_set: &BTreeSet<usize>,
+    dataset: &Dataset,
+    cache: &mut Cache<P>,
+) -> (
+    DiscreteStatesContinousTimeParams,
+    DiscreteStatesContinousTimeParams,
+    Vec<(usize, usize)>,
+)
+where
+    T: process::NetworkProcess,
+    P: parameter_learning::ParameterLearning,
+{
+    let P_small = match cache.fit(net, &dataset, child_node, Some(separation_set.clone())) {
+        Params::DiscreteStatesContinousTime(node) => node,
+    };
+    let mut extended_separation_set = separation_set.clone();
+    extended_separation_set.insert(parent_node);
+
+    let P_big = match cache.fit(
+        net,
+        &dataset,
+        child_node,
+        Some(extended_separation_set.clone()),
+    ) {
+        Params::DiscreteStatesContinousTime(node) => node,
+    };
+    let partial_cardinality_product: usize = extended_separation_set
+        .iter()
+        .take_while(|x| **x != parent_node)
+        .map(|x| net.get_node(*x).get_reserved_space_as_parent())
+        .product();
+    let index_pairs = (0..P_big.get_transitions().as_ref().unwrap().shape()[0])
+        .map(|idx_M_big| {
             let idx_M_small: usize = idx_M_big % partial_cardinality_product
                 + (idx_M_big
                     / (partial_cardinality_product
                         * net.get_node(parent_node).get_reserved_space_as_parent()))
                     * partial_cardinality_product;
-            if !self.compare_matrices(
-                idx_M_small,
-                P_small.get_transitions().as_ref().unwrap(),
-                idx_M_big,
-                P_big.get_transitions().as_ref().unwrap(),
-            ) {
-                return false;
+            (idx_M_small, idx_M_big)
+        })
+        .collect();
+    (P_small, P_big, index_pairs)
+}
+
+//Off-diagonal transitions of the row `state` of a transition matrix.
+fn off_diagonal(M: &ArrayView2<usize>, state: usize) -> Vec<usize> {
+    M.row(state)
+        .iter()
+        .enumerate()
+        .filter(|(to_state, _)| *to_state != state)
+        .map(|(_, x)| *x)
+        .collect()
+}
+
+//Degrees of freedom of the chi-squared statistic of two rows: the number of cells with
+//transitions in at least one of the rows, minus one.
+fn row_degrees_of_freedom(a: &[usize], b: &[usize]) -> usize {
+    a.iter()
+        .zip(b.iter())
+        .filter(|(x, y)| **x + **y > 0)
+        .count()
+        .saturating_sub(1)
+}
+
+//Chi-squared statistic comparing two rows of transition counts, as in `ChiSquare`.
+fn row_statistic(a: &[usize], b: &[usize]) -> f64 {
+    let K = (a.iter().sum::<usize>() as f64 / b.iter().sum::<usize>() as f64).sqrt();
+    a.iter()
+        .zip(b.iter())
+        .filter(|(x, y)| **x + **y > 0)
+        .map(|(x, y)| (K * *y as f64 - *x as f64 / K).powi(2) / (*x + *y) as f64)
+        .sum()
+}
+
+/// Does an exact test on sparse transition counts, falling back to the chi-squared test on the
+/// dense ones.
+///
+/// The matrices are compared one row (i.e. one starting state) at a time. When both rows have
+/// at least `threshold` transitions, the chi-squared statistic is compared with its asymptotic
+/// distribution, exactly as in [`ChiSquare`]. Otherwise the p-value is estimated by Monte Carlo:
+/// the transitions of the two rows are pooled and randomly split `n_permutations` times, and the
+/// p-value is the fraction of splits whose statistic is at least the observed one. Rows without
+/// transitions in one of the two matrices carry no evidence and are skipped.
+///
+/// **Runtime:** every sparse row costs `n_permutations` resamplings of at most `threshold`
+/// transitions, instead of a single evaluation of the chi-squared CDF. Moreover, the smallest
+/// p-value that can be estimated is `1 / (n_permutations + 1)`: `n_permutations` should be
+/// larger than `1 / alpha`, otherwise the sparse rows never reject the independence.
+///
+/// # Arguments
+///
+/// * `alpha` - is the significance level, the probability to reject a true null hypothesis.
+/// * `n_permutations` - is the number of random splits used to estimate the p-value.
+/// * `threshold` - is the minimum number of transitions of a row for the chi-squared
+///   approximation.
+/// * `seed` - is the random seed used to draw the splits.
+pub struct ExactTest {
+    pub(crate) alpha: f64,
+    n_permutations: usize,
+    threshold: usize,
+    seed: Option<u64>,
+}
+
+impl ExactTest {
+    pub fn new(
+        alpha: f64,
+        n_permutations: usize,
+        threshold: usize,
+        seed: Option<u64>,
+    ) -> ExactTest {
+        ExactTest {
+            alpha,
+            n_permutations,
+            threshold,
+            seed,
+        }
+    }
+
+    /// Compare two matrices extracted from two 3rd-orer tensors.
+    ///
+    /// # Arguments
+    ///
+    /// * `i` - Position of the matrix of `M1` to compare with `M2`.
+    /// * `M1` - 3rd-order tensor 1.
+    /// * `j` - Position of the matrix of `M2` to compare with `M1`.
+    /// * `M2` - 3rd-order tensor 2.
+    ///
+    /// # Returns
+    ///
+    /// * `true` - when the matrices `M1` and `M2` are very similar, then **independendent**.
+    /// * `false` - when the matrices `M1` and `M2` are too different, then **dependent**.
+    pub fn compare_matrices(
+        &self,
+        i: usize,
+        M1: &Array3<usize>,
+        j: usize,
+        M2: &Array3<usize>,
+    ) -> bool {
+        self.compare_matrices_with_alpha(i, M1, j, M2, self.alpha)
+    }
+
+    fn compare_matrices_with_alpha(
+        &self,
+        i: usize,
+        M1: &Array3<usize>,
+        j: usize,
+        M2: &Array3<usize>,
+        alpha: f64,
+    ) -> bool {
+        self.matrices_p_value(i, M1, j, M2) > alpha
+    }
+
+    //p-value of the comparison of two matrices, the smallest over the rows with transitions in
+    //both matrices.
+    fn matrices_p_value(&self, i: usize, M1: &Array3<usize>, j: usize, M2: &Array3<usize>) -> f64 {
+        let M1 = M1.index_axis(Axis(0), i);
+        let M2 = M2.index_axis(Axis(0), j);
+        let n_states = M1.shape()[0];
+        let mut min_p_value: f64 = 1.0;
+        for state in 0..n_states {
+            let a = off_diagonal(&M1, state);
+            let b = off_diagonal(&M2, state);
+            let (a_sum, b_sum) = (a.iter().sum::<usize>(), b.iter().sum::<usize>());
+            let degrees_of_freedom = row_degrees_of_freedom(&a, &b);
+            if a_sum == 0 || b_sum == 0 || degrees_of_freedom == 0 {
+                continue;
+            }
+            let statistic = row_statistic(&a, &b);
+            let p_value = if a_sum.min(b_sum) >= self.threshold {
+                let n = ChiSquared::new(degrees_of_freedom as f64).unwrap();
+                1.0 - n.cdf(statistic)
+            } else {
+                let mut rng: ChaCha8Rng = match self.seed {
+                    Some(seed) => SeedableRng::seed_from_u64(comparison_seed(seed, i, j, state)),
+                    None => SeedableRng::from_entropy(),
+                };
+                self.permutation_p_value(&a, &b, statistic, &mut rng)
+            };
+            min_p_value = min_p_value.min(p_value);
+        }
+        min_p_value
+    }
+
+    //Monte Carlo estimate of the probability of a statistic at least as large as `statistic`
+    //when the transitions of the two rows are randomly reassigned.
+    fn permutation_p_value(
+        &self,
+        a: &[usize],
+        b: &[usize],
+        statistic: f64,
+        rng: &mut ChaCha8Rng,
+    ) -> f64 {
+        let totals: Vec<usize> = a.iter().zip(b.iter()).map(|(x, y)| x + y).collect();
+        let mut pooled: Vec<usize> = totals
+            .iter()
+            .enumerate()
+            .flat_map(|(to_state, x)| std::iter::repeat_n(to_state, *x))
+            .collect();
+        //Only the smallest row is drawn, the other one is the remainder.
+        let n_drawn = a.iter().sum::<usize>().min(b.iter().sum::<usize>());
+        let mut n_extreme = 0;
+        for _ in 0..self.n_permutations {
+            let (drawn, _) = pooled.partial_shuffle(rng, n_drawn);
+            let mut small = vec![0; totals.len()];
+            for to_state in drawn.iter() {
+                small[*to_state] += 1;
+            }
+            let large: Vec<usize> = totals
+                .iter()
+                .zip(small.iter())
+                .map(|(t, x)| t - x)
+                .collect();
+            if row_statistic(&small, &large) >= statistic - 1e-9 {
+                n_extreme += 1;
             }
         }
-        return true;
+        (n_extreme + 1) as f64 / (self.n_permutations + 1) as f64
+    }
+}
+
+//Seed of the splits of the row `state` of the matrices `i` and `j`, so that each comparison draws
+//its own splits whatever the order of the tests.
+fn comparison_seed(seed: u64, i: usize, j: usize, state: usize) -> u64 {
+    fnv1a(
+        [seed, i as u64, j as u64, state as u64]
+            .iter()
+            .flat_map(|x| x.to_le_bytes()),
+    )
+}
+
+impl HypothesisTest for ExactTest {
+    fn call<T, P>(
+        &self,
+        net: &T,
+        child_node: usize,
+        parent_node: usize,
+        separation_set: &BTreeSet<usize>,
+        dataset: &Dataset,
+        cache: &mut Cache<P>,
+    ) -> bool
+    where
+        T: process::NetworkProcess,
+        P: parameter_learning::ParameterLearning,
+    {
+        let query = IndependenceQuery {
+            child_node,
+            parent_node,
+            separation_set,
+            alpha: self.alpha,
+        };
+        self.call_with_alpha(net, &query, dataset, cache)
+    }
+
+    fn call_with_alpha<T, P>(
+        &self,
+        net: &T,
+        query: &IndependenceQuery,
+        dataset: &Dataset,
+        cache: &mut Cache<P>,
+    ) -> bool
+    where
+        T: process::NetworkProcess,
+        P: parameter_learning::ParameterLearning,
+    {
+        compare_transitions(
+            net,
+            query.child_node,
+            query.parent_node,
+            query.separation_set,
+            dataset,
+            cache,
+            |i, M1, j, M2| self.compare_matrices_with_alpha(i, M1, j, M2, query.alpha),
+        )
+    }
+
+    fn p_value<T, P>(
+        &self,
+        net: &T,
+        child_node: usize,
+        parent_node: usize,
+        separation_set: &BTreeSet<usize>,
+        dataset: &Dataset,
+        cache: &mut Cache<P>,
+    ) -> f64
+    where
+        T: process::NetworkProcess,
+        P: parameter_learning::ParameterLearning,
+    {
+        let (P_small, P_big, index_pairs) =
+            fit_nested_models(net, child_node, parent_node, separation_set, dataset, cache);
+        index_pairs
+            .into_iter()
+            .map(|(idx_M_small, idx_M_big)| {
+                self.matrices_p_value(
+                    idx_M_small,
+                    P_small.get_transitions().as_ref().unwrap(),
+                    idx_M_big,
+                    P_big.get_transitions().as_ref().unwrap(),
+                )
+            })
+            .fold(1.0, f64::min)
     }
 }
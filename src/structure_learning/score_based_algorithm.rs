@@ -1,14 +1,33 @@
-//! Module containing score based algorithms like Hill Climbing and Tabu Search.
+//! Module containing score based algorithms like Hill Climbing, Tabu Search, Simulated Annealing,
+//! Exhaustive Search and the ordering-based (K2) greedy search.
 
-use log::info;
-use std::collections::BTreeSet;
+use log::{info, warn};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
 
-use crate::structure_learning::score_function::ScoreFunction;
-use crate::structure_learning::StructuralLearningAlgorithm;
-use crate::{process, tools::Dataset};
+use crate::journal::{Journal, JournalRecord};
+use crate::parameter_learning::{Alpha, Tau};
+use crate::process;
+use crate::structure_learning::score_function::{held_out_log_likelihood, ScoreFunction};
+use crate::structure_learning::{
+    check_dataset_compatibility, check_edge_constraints, check_known_parent_sets,
+    CancellationToken, LearningDiagnostics, NodeDiagnostics, ParentLimit, ProgressObserver,
+    StructuralLearningAlgorithm, StructureLearningError,
+};
+use crate::tools::{run_in_thread_pool, Dataset};
 
-use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use itertools::Itertools;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use rayon::iter::{
+    IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator, ParallelIterator,
+};
 use rayon::prelude::ParallelExtend;
+use rayon::ThreadPool;
 
 /// HillClimbing functor
 /// Continuous-Time Peter Clark algorithm.
@@ -20,6 +39,10 @@ use rayon::prelude::ParallelExtend;
 /// * [`parameter_learning`](crate::parameter_learning) - is the method used to learn the parameters.
 /// * [`Ftest`](crate::structure_learning::hypothesis_test::F) - is the F-test hyppothesis test.
 /// * [`Chi2test`](crate::structure_learning::hypothesis_test::ChiSquare) - is the chi-squared test (χ2 test) hypothesis test.
+///
+/// The learned structure does not depend on the parallel execution: in the search of a node a
+/// move is accepted only if it improves the score or, when the scores tie exactly, if it leads
+/// to a smaller parent set or to a lexicographically smaller one of the same size.
 /// # Example
 ///
 /// ```rust
@@ -183,7 +206,11 @@ use rayon::prelude::ParallelExtend;
 /// let hc = HillClimbing::new(bic, None);
 ///
 /// // Learn the structure of the network from the generated trajectory
-/// let net = hc.fit_transform(net, &data);
+/// let net = hc.fit_transform(net, &data).unwrap();
+///
+/// // Alternatively, the structure can be learned modifying a borrowed network in place
+/// let mut net = net;
+/// hc.fit_in_place(&mut net, &data);
 /// #
 /// # // Compare the generated network with the original one
 /// # assert_eq!(BTreeSet::new(), net.get_parent_set(0));
@@ -191,85 +218,1359 @@ use rayon::prelude::ParallelExtend;
 /// # assert_eq!(BTreeSet::from_iter(vec![0, 1]), net.get_parent_set(2));
 /// ````
 pub struct HillClimbing<S: ScoreFunction> {
-    score_function: S,
-    max_parent_set: Option<usize>,
+    score_function: Arc<S>,
+    max_parent_set: Option<ParentLimit>,
+    validation: Option<Validation>,
+    required_edges: BTreeSet<(usize, usize)>,
+    forbidden_edges: BTreeSet<(usize, usize)>,
+    start_from_input_structure: bool,
+    best_first: bool,
+    candidate_parents: Option<Vec<BTreeSet<usize>>>,
+    known_parent_sets: HashMap<usize, BTreeSet<usize>>,
+    journal: Option<Arc<dyn Journal>>,
+    progress_observer: Option<Arc<dyn ProgressObserver>>,
+    cancellation_token: Option<CancellationToken>,
+    thread_pool: Option<Arc<ThreadPool>>,
+}
+
+//Hold-out validation used to stop the search of each node early.
+struct Validation {
+    validation_fraction: f64,
+    tolerance: f64,
+    alpha: Alpha,
+    tau: Tau,
+    seed: Option<u64>,
 }
 
 impl<S: ScoreFunction> HillClimbing<S> {
-    pub fn new(score_function: S, max_parent_set: Option<usize>) -> HillClimbing<S> {
+    /// Create a `HillClimbing`
+    ///
+    /// # Arguments
+    ///
+    /// * `score_function`: the score function, either owned or shared through an `Arc` with other
+    ///   searches (e.g. random restarts or bootstrap replicates running concurrently).
+    /// * `max_parent_set`: maximum number of parents for each node (see
+    ///   [`with_max_parent_set`](HillClimbing::with_max_parent_set) for a maximum per node).
+    pub fn new<SF: Into<Arc<S>>>(
+        score_function: SF,
+        max_parent_set: Option<usize>,
+    ) -> HillClimbing<S> {
         HillClimbing {
-            score_function,
-            max_parent_set,
+            score_function: score_function.into(),
+            max_parent_set: max_parent_set.map(ParentLimit::Global),
+            validation: None,
+            required_edges: BTreeSet::new(),
+            forbidden_edges: BTreeSet::new(),
+            start_from_input_structure: false,
+            best_first: false,
+            candidate_parents: None,
+            known_parent_sets: HashMap::new(),
+            journal: None,
+            progress_observer: None,
+            cancellation_token: None,
+            thread_pool: None,
         }
     }
+
+    /// Create a `HillClimbing` that monitors the held-out likelihood of each node.
+    ///
+    /// The dataset is split once, at trajectory level, into a training and a validation set. The
+    /// score function is evaluated on the training set only, and a move is accepted only if it
+    /// does not decrease the validation log-likelihood (see
+    /// [`held_out_log_likelihood`](crate::structure_learning::score_function::held_out_log_likelihood))
+    /// by more than `tolerance`.
+    ///
+    /// **Note:** the score function must not hold a `SuffStatsCache` bound to the whole dataset;
+    /// a `ScoreCache` bound to it makes the learning panic.
+    ///
+    /// # Arguments
+    ///
+    /// * `score_function`: the score function, either owned or shared through an `Arc`.
+    /// * `max_parent_set`: maximum number of parents for each node.
+    /// * `validation_fraction`: fraction of the trajectories used for validation, in `(0, 1)`.
+    /// * `tolerance`: maximum decrease of the validation log-likelihood allowed for a move.
+    /// * `alpha`, `tau`: hyperparameters of the `BayesianApproach` used to estimate the CIMs
+    ///   evaluated on the validation set.
+    /// * `seed`: random seed used to split the dataset.
+    pub fn with_validation<SF: Into<Arc<S>>, A: Into<Alpha>, U: Into<Tau>>(
+        score_function: SF,
+        max_parent_set: Option<usize>,
+        validation_fraction: f64,
+        tolerance: f64,
+        alpha: A,
+        tau: U,
+        seed: Option<u64>,
+    ) -> HillClimbing<S> {
+        if !(validation_fraction > 0.0 && validation_fraction < 1.0) {
+            panic!("validation_fraction must be in (0, 1)");
+        }
+        HillClimbing {
+            score_function: score_function.into(),
+            max_parent_set: max_parent_set.map(ParentLimit::Global),
+            validation: Some(Validation {
+                validation_fraction,
+                tolerance,
+                alpha: alpha.into(),
+                tau: tau.into(),
+                seed,
+            }),
+            required_edges: BTreeSet::new(),
+            forbidden_edges: BTreeSet::new(),
+            start_from_input_structure: false,
+            best_first: false,
+            candidate_parents: None,
+            known_parent_sets: HashMap::new(),
+            journal: None,
+            progress_observer: None,
+            cancellation_token: None,
+            thread_pool: None,
+        }
+    }
+
+    /// Constrain the search with edges known to exist or known not to exist.
+    ///
+    /// Each edge is a `(parent, child)` pair. The required parents of a node are inserted in its
+    /// parent set before the search and never removed, and they count towards `max_parent_set`.
+    /// The forbidden parents are never proposed.
+    ///
+    /// # Arguments
+    ///
+    /// * `required_edges`: edges that must be present in the learned structure.
+    /// * `forbidden_edges`: edges that must not be present in the learned structure.
+    ///
+    /// # Errors
+    ///
+    /// * `InvalidEdgeConstraints` if an edge is a self loop, if an edge is both required and
+    ///   forbidden or if a node has more required parents than `max_parent_set`.
+    pub fn with_edge_constraints(
+        mut self,
+        required_edges: BTreeSet<(usize, usize)>,
+        forbidden_edges: BTreeSet<(usize, usize)>,
+    ) -> Result<HillClimbing<S>, StructureLearningError> {
+        check_edge_constraints(&required_edges, &forbidden_edges)?;
+        check_required_parents(&required_edges, self.max_parent_set.as_ref())?;
+        self.required_edges = required_edges;
+        self.forbidden_edges = forbidden_edges;
+        Ok(self)
+    }
+
+    /// Set the maximum number of parents of the nodes, either for all the nodes or node by node.
+    ///
+    /// It replaces the `max_parent_set` given at construction, e.g. to allow many parents for
+    /// some hub nodes while restricting the others to a single parent. The nodes missing from a
+    /// [`ParentLimit::PerNode`] map have no maximum.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_parent_set`: the maximum number of parents of the nodes.
+    ///
+    /// # Errors
+    ///
+    /// * `InvalidEdgeConstraints` if a node has more required parents than its maximum.
+    pub fn with_max_parent_set(
+        mut self,
+        max_parent_set: ParentLimit,
+    ) -> Result<HillClimbing<S>, StructureLearningError> {
+        check_required_parents(&self.required_edges, Some(&max_parent_set))?;
+        self.max_parent_set = Some(max_parent_set);
+        Ok(self)
+    }
+
+    /// Start the search of each node from its parent set in the input network.
+    ///
+    /// By default the structure of the input network is discarded and each search starts from
+    /// the empty parent set (plus the required parents). When enabled, the current parents of
+    /// each node are kept as starting point, dropping the forbidden ones and the ones exceeding
+    /// `max_parent_set` (the highest indices are dropped first). This allows to refine a
+    /// structure learned with another algorithm or specified by an expert.
+    ///
+    /// # Arguments
+    ///
+    /// * `start_from_input_structure`: whether to start from the structure of the input network.
+    pub fn with_start_from_input_structure(
+        mut self,
+        start_from_input_structure: bool,
+    ) -> HillClimbing<S> {
+        self.start_from_input_structure = start_from_input_structure;
+        self
+    }
+
+    /// Apply, at each step, the best single-edge modification of the parent set.
+    ///
+    /// By default the candidate parents are visited in order and every improving modification is
+    /// applied as soon as it is found. When enabled, all the modifications of the parent set are
+    /// scored in parallel and only the best improving one is applied. The search trajectory, and
+    /// possibly the learned structure, may differ from the default one.
+    ///
+    /// # Arguments
+    ///
+    /// * `best_first`: whether to apply the best modification instead of the first one.
+    pub fn with_best_first(mut self, best_first: bool) -> HillClimbing<S> {
+        self.best_first = best_first;
+        self
+    }
+
+    /// Restrict the parents proposed for each node to a pool of candidates.
+    ///
+    /// `candidate_parents[node]` holds the only parents that the search may add to `node`, e.g.
+    /// the superset of the parents found by a constraint-based algorithm. The required parents
+    /// are inserted even when they are not in the pool.
+    ///
+    /// # Arguments
+    ///
+    /// * `candidate_parents`: the pool of candidate parents of each node, indexed by node.
+    pub fn with_candidate_parents(
+        mut self,
+        candidate_parents: Vec<BTreeSet<usize>>,
+    ) -> HillClimbing<S> {
+        self.candidate_parents = Some(candidate_parents);
+        self
+    }
+
+    /// Fix the parent sets of the nodes known from domain knowledge.
+    ///
+    /// The nodes in `known_parent_sets` are not searched: their parent set is copied verbatim
+    /// into the learned structure, ignoring `max_parent_set`, the edge constraints and the
+    /// candidate parents, and the score function is never evaluated for them. The parent sets are
+    /// validated against the network at each run.
+    ///
+    /// # Arguments
+    ///
+    /// * `known_parent_sets`: the parent set of each known node, keyed by node.
+    pub fn with_known_parent_sets(
+        mut self,
+        known_parent_sets: HashMap<usize, BTreeSet<usize>>,
+    ) -> HillClimbing<S> {
+        self.known_parent_sets = known_parent_sets;
+        self
+    }
+
+    /// Write a [`JournalRecord`] to `journal` at the end of each run.
+    pub fn with_journal(mut self, journal: Arc<dyn Journal>) -> HillClimbing<S> {
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Notify `progress_observer` of the progress of each run.
+    pub fn with_progress_observer(
+        mut self,
+        progress_observer: Arc<dyn ProgressObserver>,
+    ) -> HillClimbing<S> {
+        self.progress_observer = Some(progress_observer);
+        self
+    }
+
+    /// Stop the search as soon as `cancellation_token` is cancelled.
+    ///
+    /// The token is checked before each move of the search. An interrupted run makes
+    /// [`try_fit_in_place`](HillClimbing::try_fit_in_place) return an error, while the other
+    /// methods panic.
+    pub fn with_cancellation_token(
+        mut self,
+        cancellation_token: CancellationToken,
+    ) -> HillClimbing<S> {
+        self.cancellation_token = Some(cancellation_token);
+        self
+    }
+
+    /// Run the parallel work of each run inside `thread_pool` instead of the global rayon pool.
+    ///
+    /// The parent sets of the nodes are learned in parallel, one node per task. The nested
+    /// parallel work started by a task (the scoring of the candidate parent sets with
+    /// `best_first`, or the parallel code of the score function) runs in the same pool, hence a
+    /// single-threaded pool makes the whole run sequential.
+    pub fn with_thread_pool(mut self, thread_pool: Arc<ThreadPool>) -> HillClimbing<S> {
+        self.thread_pool = Some(thread_pool);
+        self
+    }
+
+    /// Learn the structure of a network, modifying it in place
+    ///
+    /// Unlike [`fit_in_place`](StructuralLearningAlgorithm::fit_in_place), an error is returned
+    /// instead of panicking when the run is cancelled through the token given to
+    /// [`with_cancellation_token`](HillClimbing::with_cancellation_token); in that case `net`
+    /// holds the best parent sets found before the cancellation. An error is also returned if
+    /// the parent sets given to [`with_known_parent_sets`](HillClimbing::with_known_parent_sets)
+    /// do not match the network, or if the dataset is not compatible with it.
+    ///
+    /// # Arguments
+    ///
+    /// * `net`: a mutable reference to a `NetworkProcess` instance
+    /// * `dataset`: the observations used to learn the structure.
+    ///
+    /// # Return
+    ///
+    /// * The `LearningDiagnostics` of the run.
+    pub fn try_fit_in_place<T>(
+        &self,
+        net: &mut T,
+        dataset: &Dataset,
+    ) -> Result<LearningDiagnostics, StructureLearningError>
+    where
+        T: process::NetworkProcess,
+    {
+        self.fit_in_place_with_candidate_parents(net, dataset, None)
+    }
+
+    //Learn the structure restricting the parents of each node to `candidate_parents`, which
+    //takes precedence over the pools given to `with_candidate_parents`.
+    pub(crate) fn fit_in_place_with_candidate_parents<T>(
+        &self,
+        net: &mut T,
+        dataset: &Dataset,
+        candidate_parents: Option<&Vec<BTreeSet<usize>>>,
+    ) -> Result<LearningDiagnostics, StructureLearningError>
+    where
+        T: process::NetworkProcess,
+    {
+        check_dataset_compatibility(&*net, dataset)?;
+        let candidate_parents = candidate_parents.or(self.candidate_parents.as_ref());
+        //Check the coherence between candidate parents and network
+        if let Some(candidate_parents) = candidate_parents {
+            if candidate_parents.len() != net.get_number_of_nodes() {
+                return Err(StructureLearningError::InvalidEdgeConstraints(format!(
+                    "{} pools of candidate parents, the network has {} nodes",
+                    candidate_parents.len(),
+                    net.get_number_of_nodes()
+                )));
+            }
+        }
+        //Check if parent is a candidate parent of node.
+        let is_candidate = |parent: usize, node: usize| {
+            candidate_parents
+                .is_none_or(|candidate_parents| candidate_parents[node].contains(&parent))
+        };
+        check_known_parent_sets(&*net, &self.known_parent_sets)?;
+        let known_parent_sets = &self.known_parent_sets;
+
+        let start = Instant::now();
+        //Keep a reference to the whole dataset: it may be split for the validation.
+        let full_dataset = dataset;
+
+        //Maximum number of parents of each node.
+        let n_nodes = net.get_number_of_nodes();
+        let max_parent_sets: Vec<usize> = net
+            .get_node_indices()
+            .map(|node| {
+                self.max_parent_set
+                    .as_ref()
+                    .and_then(|x| x.get(node))
+                    .unwrap_or(n_nodes)
+            })
+            .collect();
+        let required_edges = &self.required_edges;
+        let forbidden_edges = &self.forbidden_edges;
+        //Compute the starting parent set of each node: the required parents plus, if requested,
+        //the parents in the input network.
+        let initial_parent_sets: Vec<BTreeSet<usize>> = net
+            .get_node_indices()
+            .map(|node| {
+                let mut parent_set: BTreeSet<usize> = required_edges
+                    .iter()
+                    .filter(|(_, child)| *child == node)
+                    .map(|(parent, _)| *parent)
+                    .collect();
+                if self.start_from_input_structure {
+                    for parent in net.get_parent_set(node) {
+                        if parent_set.len() >= max_parent_sets[node] {
+                            break;
+                        }
+                        if !forbidden_edges.contains(&(parent, node)) && is_candidate(parent, node)
+                        {
+                            parent_set.insert(parent);
+                        }
+                    }
+                }
+                parent_set
+            })
+            .collect();
+        //Reset the adj matrix
+        net.initialize_adj_matrix();
+        //Borrow the score function: the per-node searches share it by reference.
+        let score_function: &S = &self.score_function;
+        //Split the dataset if the validation is enabled.
+        let split = self
+            .validation
+            .as_ref()
+            .map(|validation| split_dataset(dataset, validation));
+        let (dataset, validation_set) = match &split {
+            Some((training, validation)) => (training, Some(validation)),
+            None => (dataset, None),
+        };
+        let tolerance = self.validation.as_ref().map_or(0.0, |x| x.tolerance);
+        let validation_prior = self.validation.as_ref().map(|x| (&x.alpha, &x.tau));
+        let best_first = self.best_first;
+        let progress_observer = self.progress_observer.as_deref();
+        let cancellation_token = self.cancellation_token.as_ref();
+        let thread_pool = self.thread_pool.as_deref();
+        //Set when a node search stops because of the cancellation.
+        let interrupted = AtomicBool::new(false);
+        let is_cancelled = || {
+            let is_cancelled = cancellation_token.is_some_and(|x| x.is_cancelled());
+            if is_cancelled {
+                interrupted.store(true, Ordering::Relaxed);
+            }
+            is_cancelled
+        };
+        //Evaluate the score function, notifying the progress observer.
+        let call_score_function = |node: usize, parent_set: &BTreeSet<usize>| -> f64 {
+            let score = score_function.call(&*net, node, parent_set, dataset);
+            if let Some(progress_observer) = progress_observer {
+                progress_observer.on_score_evaluated(node, parent_set, score);
+            }
+            score
+        };
+        let mut learned_parent_sets: Vec<(usize, BTreeSet<usize>, Option<f64>)> = vec![];
+        //Iterate over each node to learn their parent set.
+        run_in_thread_pool(thread_pool, || {
+            learned_parent_sets.par_extend(net.get_node_indices().into_par_iter().map(|node| {
+                info!("Learning node {}", node);
+                if let Some(progress_observer) = progress_observer {
+                    progress_observer.on_node_started(node);
+                }
+                //The parent set of a known node is not searched.
+                if let Some(parent_set) = known_parent_sets.get(&node) {
+                    if let Some(progress_observer) = progress_observer {
+                        progress_observer.on_node_finished(node, parent_set);
+                    }
+                    return (node, parent_set.clone(), None);
+                }
+                let mut parent_set: BTreeSet<usize> = initial_parent_sets[node].clone();
+                let max_parent_set = max_parent_sets[node];
+                //The score is decomposable: a modification of the parent set of node only changes
+                //the score of node. Memoize the scores of the parent sets already visited.
+                let mut scores: HashMap<BTreeSet<usize>, f64> = HashMap::new();
+                let mut score = |parent_set: &BTreeSet<usize>| -> f64 {
+                    *scores
+                        .entry(parent_set.clone())
+                        .or_insert_with(|| call_score_function(node, parent_set))
+                };
+                //Compute the score for the initial parent set
+                let mut current_score = score(&parent_set);
+                //Compute the validation log-likelihood of a parent set (if the validation is enabled).
+                let validation_ll = |parent_set: &BTreeSet<usize>| {
+                    validation_set
+                        .zip(validation_prior)
+                        .map(|(validation, (alpha, tau))| {
+                            held_out_log_likelihood(
+                                &*net,
+                                node,
+                                parent_set,
+                                dataset,
+                                validation,
+                                alpha.clone(),
+                                tau.clone(),
+                            )
+                        })
+                };
+                let mut current_validation_ll = validation_ll(&parent_set);
+                if best_first {
+                    //Iterate until no single-edge modification improves the score.
+                    while !is_cancelled() {
+                        //Enumerate the candidate modifications of the parent set.
+                        let candidates: Vec<BTreeSet<usize>> = net
+                            .get_node_indices()
+                            .filter(|parent| {
+                                *parent != node
+                                    && !required_edges.contains(&(*parent, node))
+                                    && !forbidden_edges.contains(&(*parent, node))
+                                    && is_candidate(*parent, node)
+                                    && (parent_set.contains(parent)
+                                        || parent_set.len() < max_parent_set)
+                            })
+                            .map(|parent| {
+                                let mut tmp_parent_set = parent_set.clone();
+                                if !tmp_parent_set.remove(&parent) {
+                                    tmp_parent_set.insert(parent);
+                                }
+                                tmp_parent_set
+                            })
+                            .collect();
+                        //Score in parallel the modifications not visited yet.
+                        let new_scores: Vec<(BTreeSet<usize>, f64)> = candidates
+                            .par_iter()
+                            .filter(|tmp_parent_set| !scores.contains_key(*tmp_parent_set))
+                            .map(|tmp_parent_set| {
+                                let tmp_score = call_score_function(node, tmp_parent_set);
+                                (tmp_parent_set.clone(), tmp_score)
+                            })
+                            .collect();
+                        scores.extend(new_scores);
+                        let mut moves: Vec<(f64, BTreeSet<usize>)> = candidates
+                            .into_iter()
+                            .map(|tmp_parent_set| (scores[&tmp_parent_set], tmp_parent_set))
+                            .filter(|(tmp_score, tmp_parent_set)| {
+                                compare_moves(
+                                    *tmp_score,
+                                    tmp_parent_set,
+                                    current_score,
+                                    &parent_set,
+                                )
+                                .is_gt()
+                            })
+                            .collect();
+                        //Apply the best improving move that passes the validation.
+                        moves.sort_by(|a, b| compare_moves(b.0, &b.1, a.0, &a.1));
+                        let best_move =
+                            moves.into_iter().find_map(|(tmp_score, tmp_parent_set)| {
+                                let tmp_validation_ll = validation_ll(&tmp_parent_set);
+                                match (tmp_validation_ll, current_validation_ll) {
+                                    (Some(tmp), Some(current)) if tmp < current - tolerance => None,
+                                    _ => Some((tmp_score, tmp_parent_set, tmp_validation_ll)),
+                                }
+                            });
+                        match best_move {
+                            Some((tmp_score, tmp_parent_set, tmp_validation_ll)) => {
+                                current_score = tmp_score;
+                                parent_set = tmp_parent_set;
+                                if tmp_validation_ll.is_some() {
+                                    current_validation_ll = tmp_validation_ll;
+                                }
+                            }
+                            None => break,
+                        }
+                    }
+                } else {
+                    //Iterate until a whole pass over the candidate parents leaves the parent set
+                    //unchanged.
+                    let mut old_parent_set = None;
+                    'search: while old_parent_set.as_ref() != Some(&parent_set) {
+                        //Save the current parent_set.
+                        old_parent_set = Some(parent_set.clone());
+                        //Iterate over each node.
+                        for parent in net.get_node_indices() {
+                            if is_cancelled() {
+                                break 'search;
+                            }
+                            //Continue if the parent and the node are the same or if the edge is
+                            //constrained.
+                            if parent == node
+                                || required_edges.contains(&(parent, node))
+                                || forbidden_edges.contains(&(parent, node))
+                                || !is_candidate(parent, node)
+                            {
+                                continue;
+                            }
+                            //Try to remove parent from the parent_set, otherwise add it.
+                            let mut tmp_parent_set = parent_set.clone();
+                            if !tmp_parent_set.remove(&parent) {
+                                if tmp_parent_set.len() >= max_parent_set {
+                                    continue;
+                                }
+                                tmp_parent_set.insert(parent);
+                            }
+                            //Compute the score with the modified parent_set.
+                            let tmp_score = score(&tmp_parent_set);
+                            let is_improving = compare_moves(
+                                tmp_score,
+                                &tmp_parent_set,
+                                current_score,
+                                &parent_set,
+                            )
+                            .is_gt();
+                            //Compute the validation log-likelihood only for the improving moves.
+                            let tmp_validation_ll = if is_improving {
+                                validation_ll(&tmp_parent_set)
+                            } else {
+                                None
+                            };
+                            //Check that the validation log-likelihood does not degrade too much.
+                            let is_validated = match (tmp_validation_ll, current_validation_ll) {
+                                (Some(tmp), Some(current)) => tmp >= current - tolerance,
+                                _ => true,
+                            };
+                            //Apply the move if it improves the parent set and it is validated.
+                            if is_improving && is_validated {
+                                parent_set = tmp_parent_set;
+                                current_score = tmp_score;
+                                if tmp_validation_ll.is_some() {
+                                    current_validation_ll = tmp_validation_ll;
+                                }
+                            }
+                        }
+                    }
+                }
+                if let Some(progress_observer) = progress_observer {
+                    progress_observer.on_node_finished(node, &parent_set);
+                }
+                (node, parent_set, Some(current_score))
+            }))
+        });
+
+        //Apply the parent sets by node, whatever the order in which the searches completed.
+        learned_parent_sets.sort_by_key(|(node, _, _)| *node);
+        let mut nodes_diagnostics = vec![];
+        for (child_node, candidate_parent_set, score) in learned_parent_sets {
+            for parent_node in candidate_parent_set.iter() {
+                net.add_edge(*parent_node, child_node);
+            }
+            nodes_diagnostics.push(NodeDiagnostics {
+                node: child_node,
+                score,
+                n_tests: None,
+                n_rejected_tests: None,
+                adjusted_p_values: None,
+            });
+        }
+        if interrupted.into_inner() {
+            return Err(StructureLearningError::LearningInterrupted(String::from(
+                "HillClimbing cancelled",
+            )));
+        }
+        let elapsed_seconds = start.elapsed().as_secs_f64();
+
+        if let Some(journal) = &self.journal {
+            let mut hyperparameters = vec![
+                (
+                    String::from("score_function"),
+                    std::any::type_name::<S>().to_string(),
+                ),
+                (
+                    String::from("max_parent_set"),
+                    match &self.max_parent_set {
+                        Some(ParentLimit::Global(max_parent_set)) => {
+                            format!("{:?}", Some(max_parent_set))
+                        }
+                        Some(ParentLimit::PerNode(max_parent_sets)) => {
+                            format!("{:?}", BTreeMap::from_iter(max_parent_sets.iter()))
+                        }
+                        None => format!("{:?}", None::<usize>),
+                    },
+                ),
+            ];
+            if let Some(validation) = &self.validation {
+                hyperparameters.push((
+                    String::from("validation_fraction"),
+                    validation.validation_fraction.to_string(),
+                ));
+                hyperparameters.push((String::from("tolerance"), validation.tolerance.to_string()));
+                hyperparameters.push((
+                    String::from("validation_alpha"),
+                    format!("{:?}", validation.alpha),
+                ));
+                hyperparameters.push((
+                    String::from("validation_tau"),
+                    format!("{:?}", validation.tau),
+                ));
+            }
+            if !self.required_edges.is_empty() {
+                hyperparameters.push((
+                    String::from("required_edges"),
+                    format!("{:?}", self.required_edges),
+                ));
+            }
+            if !self.forbidden_edges.is_empty() {
+                hyperparameters.push((
+                    String::from("forbidden_edges"),
+                    format!("{:?}", self.forbidden_edges),
+                ));
+            }
+            if self.start_from_input_structure {
+                hyperparameters.push((
+                    String::from("start_from_input_structure"),
+                    String::from("true"),
+                ));
+            }
+            if self.best_first {
+                hyperparameters.push((String::from("best_first"), String::from("true")));
+            }
+            if let Some(candidate_parents) = candidate_parents {
+                hyperparameters.push((
+                    String::from("candidate_parents"),
+                    format!("{:?}", candidate_parents),
+                ));
+            }
+            if !self.known_parent_sets.is_empty() {
+                hyperparameters.push((
+                    String::from("known_parent_sets"),
+                    format!("{:?}", BTreeMap::from_iter(self.known_parent_sets.iter())),
+                ));
+            }
+            let record = JournalRecord::new(
+                "HillClimbing",
+                hyperparameters,
+                full_dataset,
+                self.validation.iter().filter_map(|x| x.seed).collect(),
+                elapsed_seconds,
+                &*net,
+            );
+            if let Err(e) = journal.record(&record) {
+                warn!("Unable to write the journal record: {}", e);
+            }
+        }
+        Ok(LearningDiagnostics {
+            nodes: nodes_diagnostics,
+            elapsed_seconds,
+            n_parameter_fits: None,
+        })
+    }
+}
+
+//Order the parent sets visited by the search of a node: by score, the ties broken in favour of
+//the smaller parent set and then of the lexicographically smaller one.
+fn compare_moves(
+    score_a: f64,
+    parent_set_a: &BTreeSet<usize>,
+    score_b: f64,
+    parent_set_b: &BTreeSet<usize>,
+) -> std::cmp::Ordering {
+    score_a
+        .total_cmp(&score_b)
+        .then(parent_set_b.len().cmp(&parent_set_a.len()))
+        .then(parent_set_b.cmp(parent_set_a))
+}
+
+//Check that no node has more required parents than its maximum number of parents.
+fn check_required_parents(
+    required_edges: &BTreeSet<(usize, usize)>,
+    max_parent_set: Option<&ParentLimit>,
+) -> Result<(), StructureLearningError> {
+    if let Some(max_parent_set) = max_parent_set {
+        let mut required_parents: BTreeMap<usize, usize> = BTreeMap::new();
+        for (_, child) in required_edges.iter() {
+            *required_parents.entry(*child).or_insert(0) += 1;
+        }
+        if let Some((child, n_parents, max_parent_set)) = required_parents
+            .into_iter()
+            .filter_map(|(child, n_parents)| {
+                max_parent_set
+                    .get(child)
+                    .map(|max_parent_set| (child, n_parents, max_parent_set))
+            })
+            .find(|(_, n_parents, max_parent_set)| n_parents > max_parent_set)
+        {
+            return Err(StructureLearningError::InvalidEdgeConstraints(format!(
+                "node {} has {} required parents, max_parent_set is {}",
+                child, n_parents, max_parent_set
+            )));
+        }
+    }
+    Ok(())
+}
+
+//Split the trajectories of `dataset` in a training and a validation set.
+fn split_dataset(dataset: &Dataset, validation: &Validation) -> (Dataset, Dataset) {
+    let n_trajectories = dataset.get_trajectories().len();
+    if n_trajectories < 2 {
+        panic!("At least two trajectories are required for the validation split");
+    }
+    let mut rng: ChaCha8Rng = match validation.seed {
+        Some(seed) => SeedableRng::seed_from_u64(seed),
+        None => SeedableRng::from_entropy(),
+    };
+    let mut indices: Vec<usize> = (0..n_trajectories).collect();
+    indices.shuffle(&mut rng);
+    let n_validation = ((n_trajectories as f64 * validation.validation_fraction).round() as usize)
+        .clamp(1, n_trajectories - 1);
+    let select = |idx: &[usize]| {
+        Dataset::new(
+            idx.iter()
+                .map(|x| dataset.get_trajectories()[*x].clone())
+                .collect(),
+        )
+    };
+    (
+        select(&indices[n_validation..]),
+        select(&indices[..n_validation]),
+    )
 }
 
 impl<S: ScoreFunction> StructuralLearningAlgorithm for HillClimbing<S> {
-    fn fit_transform<T>(&self, net: T, dataset: &Dataset) -> T
+    fn fit_in_place_with_diagnostics<T>(
+        &self,
+        net: &mut T,
+        dataset: &Dataset,
+    ) -> LearningDiagnostics
     where
         T: process::NetworkProcess,
     {
-        //Check the coherence between dataset and network
-        if net.get_number_of_nodes() != dataset.get_trajectories()[0].get_events().shape()[1] {
-            panic!("Dataset and Network must have the same number of variables.")
+        match self.try_fit_in_place(net, dataset) {
+            Ok(diagnostics) => diagnostics,
+            Err(e) => panic!("{}", e),
         }
+    }
 
-        //Make the network mutable.
-        let mut net = net;
+    fn try_fit_in_place_with_diagnostics<T>(
+        &self,
+        net: &mut T,
+        dataset: &Dataset,
+    ) -> Result<LearningDiagnostics, StructureLearningError>
+    where
+        T: process::NetworkProcess,
+    {
+        self.try_fit_in_place(net, dataset)
+    }
+}
+
+/// Simulated annealing search over the parent sets of the network.
+///
+/// At every iteration a random node and a random candidate parent are drawn, and the candidate
+/// is toggled in the parent set of the node. Improving moves are always accepted, while a move
+/// decreasing the score by `Δ` is accepted with probability `exp(-Δ / T)`. The temperature `T`
+/// starts at `initial_temperature` and is multiplied by `cooling_rate` after each iteration.
+/// The best parent set visited for each node is returned.
+///
+/// # Arguments
+///
+/// * `score_function`: the score function, either owned or shared through an `Arc`.
+/// * `max_parent_set`: maximum number of parents for each node.
+/// * `initial_temperature`: the starting temperature, must be positive.
+/// * `cooling_rate`: geometric cooling factor, in `(0, 1]`.
+/// * `max_iterations`: number of proposed moves.
+/// * `seed`: random seed used to draw the moves and to accept the worsening ones.
+pub struct SimulatedAnnealing<S: ScoreFunction> {
+    score_function: Arc<S>,
+    max_parent_set: Option<usize>,
+    initial_temperature: f64,
+    cooling_rate: f64,
+    max_iterations: usize,
+    seed: Option<u64>,
+    journal: Option<Arc<dyn Journal>>,
+}
+
+impl<S: ScoreFunction> SimulatedAnnealing<S> {
+    /// Create a `SimulatedAnnealing`
+    ///
+    /// # Arguments
+    ///
+    /// * `score_function`: the score function, either owned or shared through an `Arc`.
+    /// * `max_parent_set`: maximum number of parents for each node.
+    /// * `initial_temperature`: the starting temperature, must be positive.
+    /// * `cooling_rate`: geometric cooling factor, in `(0, 1]`.
+    /// * `max_iterations`: number of proposed moves.
+    /// * `seed`: random seed used to make the search reproducible.
+    pub fn new<SF: Into<Arc<S>>>(
+        score_function: SF,
+        max_parent_set: Option<usize>,
+        initial_temperature: f64,
+        cooling_rate: f64,
+        max_iterations: usize,
+        seed: Option<u64>,
+    ) -> SimulatedAnnealing<S> {
+        if initial_temperature.is_nan() || initial_temperature <= 0.0 {
+            panic!("initial_temperature must be positive");
+        }
+        if !(cooling_rate > 0.0 && cooling_rate <= 1.0) {
+            panic!("cooling_rate must be in (0, 1]");
+        }
+        SimulatedAnnealing {
+            score_function: score_function.into(),
+            max_parent_set,
+            initial_temperature,
+            cooling_rate,
+            max_iterations,
+            seed,
+            journal: None,
+        }
+    }
+
+    /// Write a [`JournalRecord`] to `journal` at the end of each run.
+    pub fn with_journal(mut self, journal: Arc<dyn Journal>) -> SimulatedAnnealing<S> {
+        self.journal = Some(journal);
+        self
+    }
+}
+
+impl<S: ScoreFunction> StructuralLearningAlgorithm for SimulatedAnnealing<S> {
+    fn fit_in_place_with_diagnostics<T>(
+        &self,
+        net: &mut T,
+        dataset: &Dataset,
+    ) -> LearningDiagnostics
+    where
+        T: process::NetworkProcess,
+    {
+        match self.try_fit_in_place_with_diagnostics(net, dataset) {
+            Ok(diagnostics) => diagnostics,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    fn try_fit_in_place_with_diagnostics<T>(
+        &self,
+        net: &mut T,
+        dataset: &Dataset,
+    ) -> Result<LearningDiagnostics, StructureLearningError>
+    where
+        T: process::NetworkProcess,
+    {
+        check_dataset_compatibility(&*net, dataset)?;
+        let start = Instant::now();
+        let n_nodes = net.get_number_of_nodes();
         //Check if the max_parent_set constraint is present.
+        let max_parent_set = self.max_parent_set.unwrap_or(n_nodes);
+        //Reset the adj matrix
+        net.initialize_adj_matrix();
+        let score_function: &S = &self.score_function;
+        let mut rng: ChaCha8Rng = match self.seed {
+            Some(seed) => SeedableRng::seed_from_u64(seed),
+            None => SeedableRng::from_entropy(),
+        };
+
+        //Start from the empty structure.
+        let mut parent_sets: Vec<BTreeSet<usize>> = vec![BTreeSet::new(); n_nodes];
+        let mut scores: Vec<f64> = net
+            .get_node_indices()
+            .map(|node| score_function.call(&*net, node, &parent_sets[node], dataset))
+            .collect();
+        let mut best_parent_sets = parent_sets.clone();
+        let mut best_scores = scores.clone();
+
+        let mut temperature = self.initial_temperature;
+        //A single node has no candidate parents.
+        if n_nodes > 1 {
+            for iteration in 0..self.max_iterations {
+                //Draw a random node and a random candidate parent different from it.
+                let node = rng.gen_range(0..n_nodes);
+                let mut parent = rng.gen_range(0..n_nodes - 1);
+                if parent >= node {
+                    parent += 1;
+                }
+                //Toggle the parent, skipping the moves violating max_parent_set.
+                let is_removed = parent_sets[node].remove(&parent);
+                if !is_removed {
+                    if parent_sets[node].len() >= max_parent_set {
+                        temperature *= self.cooling_rate;
+                        continue;
+                    }
+                    parent_sets[node].insert(parent);
+                }
+                let tmp_score = score_function.call(&*net, node, &parent_sets[node], dataset);
+                let delta = tmp_score - scores[node];
+                //Always accept the improvements, accept the worsening moves with probability
+                //exp(delta / T).
+                let accepted = delta >= 0.0 || rng.gen::<f64>() < (delta / temperature).exp();
+                if accepted {
+                    scores[node] = tmp_score;
+                    if tmp_score > best_scores[node] {
+                        best_scores[node] = tmp_score;
+                        best_parent_sets[node] = parent_sets[node].clone();
+                    }
+                } else if is_removed {
+                    parent_sets[node].insert(parent);
+                } else {
+                    parent_sets[node].remove(&parent);
+                }
+                temperature *= self.cooling_rate;
+                if iteration % 1000 == 0 {
+                    info!(
+                        "Iteration {}: temperature {}, total score {}",
+                        iteration,
+                        temperature,
+                        scores.iter().sum::<f64>()
+                    );
+                }
+            }
+        }
+
+        for (child_node, candidate_parent_set) in best_parent_sets.iter().enumerate() {
+            for parent_node in candidate_parent_set.iter() {
+                net.add_edge(*parent_node, child_node);
+            }
+        }
+        let elapsed_seconds = start.elapsed().as_secs_f64();
+
+        if let Some(journal) = &self.journal {
+            let hyperparameters = vec![
+                (
+                    String::from("score_function"),
+                    std::any::type_name::<S>().to_string(),
+                ),
+                (
+                    String::from("max_parent_set"),
+                    format!("{:?}", self.max_parent_set),
+                ),
+                (
+                    String::from("initial_temperature"),
+                    self.initial_temperature.to_string(),
+                ),
+                (String::from("cooling_rate"), self.cooling_rate.to_string()),
+                (
+                    String::from("max_iterations"),
+                    self.max_iterations.to_string(),
+                ),
+            ];
+            let record = JournalRecord::new(
+                "SimulatedAnnealing",
+                hyperparameters,
+                dataset,
+                self.seed.into_iter().collect(),
+                elapsed_seconds,
+                &*net,
+            );
+            if let Err(e) = journal.record(&record) {
+                warn!("Unable to write the journal record: {}", e);
+            }
+        }
+        Ok(LearningDiagnostics {
+            nodes: best_scores
+                .into_iter()
+                .enumerate()
+                .map(|(node, score)| NodeDiagnostics {
+                    node,
+                    score: Some(score),
+                    n_tests: None,
+                    n_rejected_tests: None,
+                    adjusted_p_values: None,
+                })
+                .collect(),
+            elapsed_seconds,
+            n_parameter_fits: None,
+        })
+    }
+}
+
+/// Exhaustive search of the best parent set of each node.
+///
+/// For each node, every subset of the other nodes with at most `max_parent_set` elements is
+/// scored and the maximum-score subset is kept. The cost is exponential in the number of nodes,
+/// hence the search refuses to run when the number of parent sets to score exceeds
+/// `max_enumeration_size`.
+///
+/// # Arguments
+///
+/// * `score_function`: the score function, either owned or shared through an `Arc`.
+/// * `max_parent_set`: maximum number of parents for each node.
+/// * `max_enumeration_size`: maximum number of parent sets scored over the whole network.
+pub struct ExhaustiveSearch<S: ScoreFunction> {
+    score_function: Arc<S>,
+    max_parent_set: Option<usize>,
+    max_enumeration_size: usize,
+}
+
+impl<S: ScoreFunction> ExhaustiveSearch<S> {
+    /// Create an `ExhaustiveSearch`
+    ///
+    /// # Arguments
+    ///
+    /// * `score_function`: the score function, either owned or shared through an `Arc`.
+    /// * `max_parent_set`: maximum number of parents for each node.
+    /// * `max_enumeration_size`: maximum number of parent sets scored over the whole network.
+    pub fn new<SF: Into<Arc<S>>>(
+        score_function: SF,
+        max_parent_set: Option<usize>,
+        max_enumeration_size: usize,
+    ) -> ExhaustiveSearch<S> {
+        ExhaustiveSearch {
+            score_function: score_function.into(),
+            max_parent_set,
+            max_enumeration_size,
+        }
+    }
+
+    /// Number of parent sets scored for a network with `n_nodes` nodes.
+    ///
+    /// The count saturates at `usize::MAX`.
+    pub fn enumeration_size(&self, n_nodes: usize) -> usize {
+        if n_nodes == 0 {
+            return 0;
+        }
+        let candidates = n_nodes - 1;
+        let max_parent_set = self.max_parent_set.unwrap_or(candidates).min(candidates);
+        //Sum of the binomial coefficients C(candidates, k) for k in 0..=max_parent_set.
+        let mut binomial: usize = 1;
+        let mut per_node: usize = 1;
+        for k in 1..=max_parent_set {
+            //An overflow of the intermediate product would make the division meaningless.
+            binomial = match binomial.checked_mul(candidates - k + 1) {
+                Some(product) => product / k,
+                None => return usize::MAX,
+            };
+            per_node = match per_node.checked_add(binomial) {
+                Some(per_node) => per_node,
+                None => return usize::MAX,
+            };
+        }
+        per_node.checked_mul(n_nodes).unwrap_or(usize::MAX)
+    }
+
+    /// Learn the structure of a network, modifying it in place
+    ///
+    /// Unlike [`fit_in_place`](StructuralLearningAlgorithm::fit_in_place), an error is returned
+    /// instead of panicking when the enumeration exceeds `max_enumeration_size` or when the
+    /// dataset is not compatible with `net`; in both cases `net` is left untouched.
+    ///
+    /// # Arguments
+    ///
+    /// * `net`: a mutable reference to a `NetworkProcess` instance
+    /// * `dataset`: the observations used to learn the structure.
+    ///
+    /// # Return
+    ///
+    /// * The `LearningDiagnostics` of the run.
+    pub fn try_fit_in_place<T>(
+        &self,
+        net: &mut T,
+        dataset: &Dataset,
+    ) -> Result<LearningDiagnostics, StructureLearningError>
+    where
+        T: process::NetworkProcess,
+    {
+        check_dataset_compatibility(&*net, dataset)?;
+        let start = Instant::now();
+        let enumeration_size = self.enumeration_size(net.get_number_of_nodes());
+        if enumeration_size > self.max_enumeration_size {
+            return Err(StructureLearningError::SearchSpaceTooLarge(format!(
+                "{} parent sets to score, the bound is {}",
+                enumeration_size, self.max_enumeration_size
+            )));
+        }
         let max_parent_set = self.max_parent_set.unwrap_or(net.get_number_of_nodes());
         //Reset the adj matrix
         net.initialize_adj_matrix();
-        let mut learned_parent_sets: Vec<(usize, BTreeSet<usize>)> = vec![];
+        let score_function: &S = &self.score_function;
+        let mut learned_parent_sets: Vec<(usize, BTreeSet<usize>, f64)> = vec![];
         //Iterate over each node to learn their parent set.
         learned_parent_sets.par_extend(net.get_node_indices().into_par_iter().map(|node| {
-            //Initialize an empty parent set.
             info!("Learning node {}", node);
-            let mut parent_set: BTreeSet<usize> = BTreeSet::new();
-            //Compute the score for the empty parent set
-            let mut current_score = self.score_function.call(&net, node, &parent_set, dataset);
-            //Set the old score to -\infty.
-            let mut old_score = f64::NEG_INFINITY;
-            //Iterate until convergence
-            while current_score > old_score {
-                //Save the current_score.
-                old_score = current_score;
-                //Iterate over each node.
-                for parent in net.get_node_indices() {
-                    //Continue if the parent and the node are the same.
-                    if parent == node {
-                        continue;
+            let candidates: Vec<usize> = net.get_node_indices().filter(|x| *x != node).collect();
+            let mut best_parent_set: BTreeSet<usize> = BTreeSet::new();
+            let mut best_score = score_function.call(&*net, node, &best_parent_set, dataset);
+            //Enumerate the non-empty subsets of the candidates by increasing size.
+            for size in 1..=max_parent_set.min(candidates.len()) {
+                for parent_set in candidates.iter().copied().combinations(size) {
+                    let parent_set: BTreeSet<usize> = parent_set.into_iter().collect();
+                    let score = score_function.call(&*net, node, &parent_set, dataset);
+                    if score > best_score {
+                        best_score = score;
+                        best_parent_set = parent_set;
                     }
-                    //Try to remove parent from the parent_set.
-                    let is_removed = parent_set.remove(&parent);
-                    //If parent was not in the parent_set add it.
-                    if !is_removed && parent_set.len() < max_parent_set {
+                }
+            }
+            (node, best_parent_set, best_score)
+        }));
+
+        let mut nodes_diagnostics = vec![];
+        for (child_node, candidate_parent_set, score) in learned_parent_sets {
+            for parent_node in candidate_parent_set.iter() {
+                net.add_edge(*parent_node, child_node);
+            }
+            nodes_diagnostics.push(NodeDiagnostics {
+                node: child_node,
+                score: Some(score),
+                n_tests: None,
+                n_rejected_tests: None,
+                adjusted_p_values: None,
+            });
+        }
+        Ok(LearningDiagnostics {
+            nodes: nodes_diagnostics,
+            elapsed_seconds: start.elapsed().as_secs_f64(),
+            n_parameter_fits: None,
+        })
+    }
+}
+
+impl<S: ScoreFunction> StructuralLearningAlgorithm for ExhaustiveSearch<S> {
+    fn fit_in_place_with_diagnostics<T>(
+        &self,
+        net: &mut T,
+        dataset: &Dataset,
+    ) -> LearningDiagnostics
+    where
+        T: process::NetworkProcess,
+    {
+        match self.try_fit_in_place(net, dataset) {
+            Ok(diagnostics) => diagnostics,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    fn try_fit_in_place_with_diagnostics<T>(
+        &self,
+        net: &mut T,
+        dataset: &Dataset,
+    ) -> Result<LearningDiagnostics, StructureLearningError>
+    where
+        T: process::NetworkProcess,
+    {
+        self.try_fit_in_place(net, dataset)
+    }
+}
+
+/// Greedy search of the parent sets constrained by a known ordering of the nodes (K2 algorithm).
+///
+/// The candidate parents of each node are its predecessors in the ordering. Starting from the
+/// empty parent set, the candidate that most improves the score is added until no candidate
+/// improves it or `max_parent_set` is reached. The learned structure is acyclic and depends on
+/// the ordering: an edge that contradicts the ordering can never be learned.
+///
+/// # Arguments
+///
+/// * `score_function`: the score function, either owned or shared through an `Arc`.
+/// * `ordering`: the nodes sorted so that every parent precedes its children.
+/// * `max_parent_set`: maximum number of parents for each node.
+pub struct OrderedGreedySearch<S: ScoreFunction> {
+    score_function: Arc<S>,
+    ordering: Vec<usize>,
+    max_parent_set: Option<usize>,
+    journal: Option<Arc<dyn Journal>>,
+}
+
+impl<S: ScoreFunction> OrderedGreedySearch<S> {
+    /// Create an `OrderedGreedySearch`
+    ///
+    /// # Arguments
+    ///
+    /// * `score_function`: the score function, either owned or shared through an `Arc`.
+    /// * `ordering`: a permutation of the node indices; every parent must precede its children.
+    /// * `max_parent_set`: maximum number of parents for each node.
+    ///
+    /// # Errors
+    ///
+    /// * `InvalidOrdering` if `ordering` is not a permutation of `0..ordering.len()`.
+    pub fn new<SF: Into<Arc<S>>>(
+        score_function: SF,
+        ordering: Vec<usize>,
+        max_parent_set: Option<usize>,
+    ) -> Result<OrderedGreedySearch<S>, StructureLearningError> {
+        let mut seen = vec![false; ordering.len()];
+        for node in ordering.iter() {
+            if *node >= ordering.len() {
+                return Err(StructureLearningError::InvalidOrdering(format!(
+                    "node {} out of range for an ordering of {} nodes",
+                    node,
+                    ordering.len()
+                )));
+            }
+            if seen[*node] {
+                return Err(StructureLearningError::InvalidOrdering(format!(
+                    "node {} appears more than once",
+                    node
+                )));
+            }
+            seen[*node] = true;
+        }
+        Ok(OrderedGreedySearch {
+            score_function: score_function.into(),
+            ordering,
+            max_parent_set,
+            journal: None,
+        })
+    }
+
+    /// Write a [`JournalRecord`] to `journal` at the end of each run.
+    pub fn with_journal(mut self, journal: Arc<dyn Journal>) -> OrderedGreedySearch<S> {
+        self.journal = Some(journal);
+        self
+    }
+}
+
+impl<S: ScoreFunction> StructuralLearningAlgorithm for OrderedGreedySearch<S> {
+    fn fit_in_place_with_diagnostics<T>(
+        &self,
+        net: &mut T,
+        dataset: &Dataset,
+    ) -> LearningDiagnostics
+    where
+        T: process::NetworkProcess,
+    {
+        match self.try_fit_in_place_with_diagnostics(net, dataset) {
+            Ok(diagnostics) => diagnostics,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    fn try_fit_in_place_with_diagnostics<T>(
+        &self,
+        net: &mut T,
+        dataset: &Dataset,
+    ) -> Result<LearningDiagnostics, StructureLearningError>
+    where
+        T: process::NetworkProcess,
+    {
+        check_dataset_compatibility(&*net, dataset)?;
+        //Check the coherence between ordering and network
+        if net.get_number_of_nodes() != self.ordering.len() {
+            return Err(StructureLearningError::InvalidOrdering(format!(
+                "the ordering has {} nodes, the network has {}",
+                self.ordering.len(),
+                net.get_number_of_nodes()
+            )));
+        }
+        let start = Instant::now();
+        //Check if the max_parent_set constraint is present.
+        let max_parent_set = self.max_parent_set.unwrap_or(net.get_number_of_nodes());
+        //Reset the adj matrix
+        net.initialize_adj_matrix();
+        let score_function: &S = &self.score_function;
+        let ordering = &self.ordering;
+        let mut learned_parent_sets: Vec<(usize, BTreeSet<usize>, f64)> = vec![];
+        //Iterate over each node to learn their parent set.
+        learned_parent_sets.par_extend(ordering.par_iter().enumerate().map(|(position, node)| {
+            let node = *node;
+            info!("Learning node {}", node);
+            let mut candidates: BTreeSet<usize> = ordering[..position].iter().copied().collect();
+            let mut parent_set: BTreeSet<usize> = BTreeSet::new();
+            let mut current_score = score_function.call(&*net, node, &parent_set, dataset);
+            //Add the best candidate until no candidate improves the score.
+            while parent_set.len() < max_parent_set {
+                let best = candidates
+                    .iter()
+                    .map(|parent| {
+                        let mut tmp_parent_set = parent_set.clone();
+                        tmp_parent_set.insert(*parent);
+                        let tmp_score = score_function.call(&*net, node, &tmp_parent_set, dataset);
+                        (*parent, tmp_score)
+                    })
+                    .max_by(|a, b| a.1.total_cmp(&b.1));
+                match best {
+                    Some((parent, tmp_score)) if tmp_score > current_score => {
+                        candidates.remove(&parent);
                         parent_set.insert(parent);
-                    }
-                    //Compute the score with the modified parent_set.
-                    let tmp_score = self.score_function.call(&net, node, &parent_set, dataset);
-                    //If tmp_score is worst than current_score revert the change to the parent set
-                    if tmp_score < current_score {
-                        if is_removed {
-                            parent_set.insert(parent);
-                        } else {
-                            parent_set.remove(&parent);
-                        }
-                    }
-                    //Otherwise save the computed score as current_score
-                    else {
                         current_score = tmp_score;
                     }
+                    _ => break,
                 }
             }
-            (node, parent_set)
+            (node, parent_set, current_score)
         }));
 
-        for (child_node, candidate_parent_set) in learned_parent_sets {
+        let mut nodes_diagnostics = vec![];
+        for (child_node, candidate_parent_set, score) in learned_parent_sets {
             for parent_node in candidate_parent_set.iter() {
                 net.add_edge(*parent_node, child_node);
             }
+            nodes_diagnostics.push(NodeDiagnostics {
+                node: child_node,
+                score: Some(score),
+                n_tests: None,
+                n_rejected_tests: None,
+                adjusted_p_values: None,
+            });
+        }
+        //The nodes were learned following the ordering.
+        nodes_diagnostics.sort_by_key(|x| x.node);
+        let elapsed_seconds = start.elapsed().as_secs_f64();
+
+        if let Some(journal) = &self.journal {
+            let hyperparameters = vec![
+                (
+                    String::from("score_function"),
+                    std::any::type_name::<S>().to_string(),
+                ),
+                (String::from("ordering"), format!("{:?}", self.ordering)),
+                (
+                    String::from("max_parent_set"),
+                    format!("{:?}", self.max_parent_set),
+                ),
+            ];
+            let record = JournalRecord::new(
+                "OrderedGreedySearch",
+                hyperparameters,
+                dataset,
+                vec![],
+                elapsed_seconds,
+                &*net,
+            );
+            if let Err(e) = journal.record(&record) {
+                warn!("Unable to write the journal record: {}", e);
+            }
         }
-        return net;
+        Ok(LearningDiagnostics {
+            nodes: nodes_diagnostics,
+            elapsed_seconds,
+            n_parameter_fits: None,
+        })
     }
 }
@@ -1,10 +1,19 @@
 //! Module for score based algorithms containing score functions algorithms like Log Likelihood, BIC, etc...
 
 use std::collections::BTreeSet;
+use std::sync::Arc;
 
 use ndarray::prelude::*;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
 use statrs::function::gamma;
 
+use crate::journal::DatasetFingerprint;
+use crate::parameter_learning::{Alpha, BayesianApproach, ParameterLearning, Tau};
+use crate::process::ctbn::CtbnNetwork;
+use crate::process::NetworkProcess;
+use crate::structure_learning::StructureLearningError;
 use crate::{parameter_learning, params, process, tools};
 use log::debug;
 
@@ -33,10 +42,128 @@ pub trait ScoreFunction: Sync {
         T: process::NetworkProcess;
 }
 
+/// Store of sufficient statistics bound to a single dataset, meant to be shared between several
+/// runs of the score-based learners (e.g. a sweep over `alpha`, `tau` or `max_parent_set`).
+///
+/// The statistics `(M, T)` are keyed by `(node, parent_set)` and do not depend on the
+/// hyperparameters of the score. Unlike a bare [`SuffStatsCache`](tools::SuffStatsCache), the
+/// store holds its dataset and refuses to serve a different one. A query with the held dataset
+/// is accepted right away; any other dataset is accepted only if its [`DatasetFingerprint`]
+/// matches the stored one.
+///
+/// # Example
+///
+/// ```rust
+/// # use std::collections::BTreeSet;
+/// # use std::sync::Arc;
+/// # use ndarray::{arr1, arr2};
+/// # use reCTBN::params;
+/// # use reCTBN::process::{ctbn::CtbnNetwork, NetworkProcess};
+/// # use reCTBN::structure_learning::score_function::{ScoreCache, ScoreFunction, BIC};
+/// # use reCTBN::tools::{Dataset, Trajectory};
+/// # let mut net = CtbnNetwork::new();
+/// # let param = params::DiscreteStatesContinousTimeParams::new(
+/// #     String::from("n1"),
+/// #     BTreeSet::from([String::from("A"), String::from("B")]),
+/// # );
+/// # let n1 = net.add_node(params::Params::DiscreteStatesContinousTime(param)).unwrap();
+/// let trj = Trajectory::new(arr1(&[0.0, 0.1, 0.3]), arr2(&[[0], [1], [1]]));
+/// let dataset = Arc::new(Dataset::new(vec![trj]));
+///
+/// let score_cache = Arc::new(ScoreCache::new(Arc::clone(&dataset)));
+/// for alpha in [1, 2] {
+///     let bic = BIC::new_with_score_cache(alpha, 0.1, Arc::clone(&score_cache));
+///     bic.call(&net, n1, &BTreeSet::new(), &dataset);
+/// }
+/// assert_eq!(1, score_cache.len());
+/// ```
+pub struct ScoreCache {
+    fingerprint: DatasetFingerprint,
+    suff_stats_cache: tools::SuffStatsCache,
+    //Held to keep the address of the dataset from being reused by another one.
+    dataset: Arc<tools::Dataset>,
+}
+
+impl ScoreCache {
+    /// Create an empty `ScoreCache` bound to `dataset`.
+    pub fn new<D: Into<Arc<tools::Dataset>>>(dataset: D) -> ScoreCache {
+        let dataset = dataset.into();
+        ScoreCache {
+            fingerprint: DatasetFingerprint::new(&dataset),
+            suff_stats_cache: tools::SuffStatsCache::new(),
+            dataset,
+        }
+    }
+
+    pub fn get_fingerprint(&self) -> &DatasetFingerprint {
+        &self.fingerprint
+    }
+
+    /// Check that `dataset` is the dataset bound to this store.
+    ///
+    /// The check is immediate for the dataset held by the store; the fingerprint of any other
+    /// dataset is recomputed at each call.
+    ///
+    /// # Errors
+    ///
+    /// * `MismatchedDataset` if the fingerprint of `dataset` differs from the stored one.
+    pub fn check_dataset(&self, dataset: &tools::Dataset) -> Result<(), StructureLearningError> {
+        //The store holds its dataset: no other dataset can live at the same address.
+        if std::ptr::eq(Arc::as_ptr(&self.dataset), dataset) {
+            return Ok(());
+        }
+        let fingerprint = DatasetFingerprint::new(dataset);
+        if fingerprint != self.fingerprint {
+            return Err(StructureLearningError::MismatchedDataset(format!(
+                "the cache is bound to a dataset with fingerprint {:?}, got {:?}",
+                self.fingerprint, fingerprint
+            )));
+        }
+        Ok(())
+    }
+
+    /// Get the sufficient statistics of `node` given `parent_set`, computing them from `dataset`
+    /// if they are not already stored.
+    ///
+    /// # Arguments
+    ///
+    /// * `net`: a `NetworkProcess` instance
+    /// * `dataset`: the dataset bound to this store
+    /// * `node`: the node index for which we want the sufficient statistics
+    /// * `parent_set`: the set of nodes (identified by indices) used as parents of `node`
+    ///
+    /// # Errors
+    ///
+    /// * `MismatchedDataset` if `dataset` is not the dataset bound to this store.
+    pub fn get_or_compute<T: NetworkProcess>(
+        &self,
+        net: &T,
+        dataset: &tools::Dataset,
+        node: usize,
+        parent_set: &BTreeSet<usize>,
+    ) -> Result<Arc<tools::SuffStats>, StructureLearningError> {
+        self.check_dataset(dataset)?;
+        Ok(self
+            .suff_stats_cache
+            .get_or_compute(net, dataset, node, parent_set))
+    }
+
+    /// Number of `(node, parent_set)` keys currently stored.
+    pub fn len(&self) -> usize {
+        self.suff_stats_cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.suff_stats_cache.is_empty()
+    }
+}
+
 /// LogLikelihood for a `NetworkProcess`
 pub struct LogLikelihood {
-    alpha: usize,
-    tau: f64,
+    alpha: Alpha,
+    tau: Tau,
+    suff_stats_cache: Option<Arc<tools::SuffStatsCache>>,
+    score_cache: Option<Arc<ScoreCache>>,
 }
 
 impl LogLikelihood {
@@ -44,14 +171,74 @@ impl LogLikelihood {
     ///
     /// # Arguments
     ///
-    /// * `alpha`: pseudo count (immaginary  number of transitions)
-    /// * `tau`: pseudo residence time (immaginary residence time)
-    pub fn new(alpha: usize, tau: f64) -> LogLikelihood {
+    /// * `alpha`: pseudo count (immaginary  number of transitions), either a constant or an
+    ///   [`Alpha::Matrix`] matching the parent configurations of the scored parent sets. With a
+    ///   matrix, the prior over the residence time of each state uses the mean pseudo count of
+    ///   its transitions.
+    /// * `tau`: pseudo residence time (immaginary residence time), either a constant or a
+    ///   [`Tau::Matrix`] matching the parent configurations of the scored parent sets
+    pub fn new<A: Into<Alpha>, U: Into<Tau>>(alpha: A, tau: U) -> LogLikelihood {
+        let alpha = alpha.into();
+        let tau = tau.into();
+        //Alpha must be >=0.0
+        let is_negative = match &alpha {
+            Alpha::Constant(alpha) => *alpha < 0.0,
+            Alpha::Matrix(alpha) => alpha.iter().any(|x| *x < 0.0),
+        };
+        if is_negative {
+            panic!("alpha must be >=0.0");
+        }
         //Tau must be >=0.0
-        if tau < 0.0 {
+        let is_negative = match &tau {
+            Tau::Constant(tau) => *tau < 0.0,
+            Tau::Matrix(tau) => tau.iter().any(|x| *x < 0.0),
+        };
+        if is_negative {
             panic!("tau must be >=0.0");
         }
-        LogLikelihood { alpha, tau }
+        LogLikelihood {
+            alpha,
+            tau,
+            suff_stats_cache: None,
+            score_cache: None,
+        }
+    }
+
+    /// Create a `struct LogLikelihood` reading the sufficient statistics from a shared store
+    ///
+    /// # Arguments
+    ///
+    /// * `alpha`: pseudo count (immaginary  number of transitions)
+    /// * `tau`: pseudo residence time (immaginary residence time)
+    /// * `suff_stats_cache`: store of sufficient statistics bound to the dataset that will be
+    ///   scored
+    pub fn new_with_suff_stats_cache<A: Into<Alpha>, U: Into<Tau>>(
+        alpha: A,
+        tau: U,
+        suff_stats_cache: Arc<tools::SuffStatsCache>,
+    ) -> LogLikelihood {
+        let mut ll = LogLikelihood::new(alpha, tau);
+        ll.suff_stats_cache = Some(suff_stats_cache);
+        ll
+    }
+
+    /// Create a `struct LogLikelihood` reading the sufficient statistics from a `ScoreCache`
+    ///
+    /// Scoring a dataset different from the one bound to `score_cache` panics.
+    ///
+    /// # Arguments
+    ///
+    /// * `alpha`: pseudo count (immaginary  number of transitions)
+    /// * `tau`: pseudo residence time (immaginary residence time)
+    /// * `score_cache`: store of sufficient statistics bound to the dataset that will be scored
+    pub fn new_with_score_cache<A: Into<Alpha>, U: Into<Tau>>(
+        alpha: A,
+        tau: U,
+        score_cache: Arc<ScoreCache>,
+    ) -> LogLikelihood {
+        let mut ll = LogLikelihood::new(alpha, tau);
+        ll.score_cache = Some(score_cache);
+        ll
     }
 
     fn compute_score<T>(
@@ -60,56 +247,96 @@ impl LogLikelihood {
         node: usize,
         parent_set: &BTreeSet<usize>,
         dataset: &tools::Dataset,
-    ) -> (f64, Array3<usize>)
+    ) -> (f64, (usize, usize, usize))
     where
         T: process::NetworkProcess,
     {
         //Identify the type of node used
         match &net.get_node(node) {
             params::Params::DiscreteStatesContinousTime(_params) => {
+                //The shared stores hold unweighted statistics, thus the weighted ones are always
+                //computed from the dataset.
+                if dataset.get_weights().is_some() {
+                    let (M, T) = parameter_learning::weighted_sufficient_statistics(
+                        net, dataset, node, parent_set,
+                    );
+                    return (self.log_likelihood(&M, &T), M.dim());
+                }
                 //Compute the sufficient statistics M (number of transistions) and T (residence
-                //time)
-                let (M, T) =
-                    parameter_learning::sufficient_statistics(net, dataset, node, parent_set);
-
-                //Scale alpha accordingly to the size of the parent set
-                let alpha = self.alpha as f64 / M.shape()[0] as f64;
-                //Scale tau accordingly to the size of the parent set
-                let tau = self.tau / M.shape()[0] as f64;
-
-                //Compute the log likelihood for q
-                let log_ll_q: f64 = M
-                    .sum_axis(Axis(2))
-                    .iter()
-                    .zip(T.iter())
-                    .map(|(m, t)| {
-                        gamma::ln_gamma(alpha + *m as f64 + 1.0) + (alpha + 1.0) * f64::ln(tau)
-                            - gamma::ln_gamma(alpha + 1.0)
-                            - (alpha + *m as f64 + 1.0) * f64::ln(tau + t)
-                    })
-                    .sum();
-
-                //Compute the log likelihood for theta
-                let log_ll_theta: f64 = M
-                    .outer_iter()
-                    .map(|x| {
-                        x.outer_iter()
-                            .map(|y| {
-                                gamma::ln_gamma(alpha) - gamma::ln_gamma(alpha + y.sum() as f64)
-                                    + y.iter()
-                                        .map(|z| {
-                                            gamma::ln_gamma(alpha + *z as f64)
-                                                - gamma::ln_gamma(alpha)
-                                        })
-                                        .sum::<f64>()
-                            })
-                            .sum::<f64>()
-                    })
-                    .sum();
-                (log_ll_theta + log_ll_q, M)
+                //time), reading them from the shared store when available.
+                let stats = match (&self.score_cache, &self.suff_stats_cache) {
+                    (Some(score_cache), _) => score_cache
+                        .get_or_compute(net, dataset, node, parent_set)
+                        .unwrap_or_else(|e| panic!("{}", e)),
+                    (None, Some(suff_stats_cache)) => {
+                        suff_stats_cache.get_or_compute(net, dataset, node, parent_set)
+                    }
+                    (None, None) => Arc::new(parameter_learning::sufficient_statistics(
+                        net, dataset, node, parent_set,
+                    )),
+                };
+                let (M, T) = (&stats.0, &stats.1);
+                (self.log_likelihood(&M.mapv(|x| x as f64), T), M.dim())
             }
         }
     }
+
+    //Log likelihood of the (possibly weighted) sufficient statistics M and T.
+    fn log_likelihood(&self, M: &Array3<f64>, T: &Array2<f64>) -> f64 {
+        //Scale alpha accordingly to the size of the parent set
+        let alpha = self.alpha.transitions_prior(M.dim());
+        //Pseudo count of each row: the mean over its transitions
+        let n_states = M.shape()[1];
+        let alpha_row = Array2::from_shape_fn((M.shape()[0], n_states), |(u, x)| {
+            if n_states == 1 {
+                return alpha[[u, x, x]];
+            }
+            (0..n_states)
+                .filter(|y| *y != x)
+                .map(|y| alpha[[u, x, y]])
+                .sum::<f64>()
+                / (n_states - 1) as f64
+        });
+        //Scale tau accordingly to the size of the parent set
+        let tau = self.tau.residence_time_prior(T.dim());
+
+        //Compute the log likelihood for q
+        let log_ll_q: f64 = M
+            .sum_axis(Axis(2))
+            .iter()
+            .zip(T.iter())
+            .zip(tau.iter())
+            .zip(alpha_row.iter())
+            .map(|(((m, t), &tau), &alpha)| {
+                gamma::ln_gamma(alpha + *m + 1.0) + (alpha + 1.0) * f64::ln(tau)
+                    - gamma::ln_gamma(alpha + 1.0)
+                    - (alpha + *m + 1.0) * f64::ln(tau + t)
+            })
+            .sum();
+
+        //Compute the log likelihood for theta
+        let log_ll_theta: f64 = M
+            .outer_iter()
+            .zip(alpha.outer_iter())
+            .zip(alpha_row.outer_iter())
+            .map(|((x, alpha), alpha_row)| {
+                x.outer_iter()
+                    .zip(alpha.outer_iter())
+                    .zip(alpha_row.iter())
+                    .map(|((y, alpha), &alpha_row)| {
+                        gamma::ln_gamma(alpha_row) - gamma::ln_gamma(alpha_row + y.sum())
+                            + y.iter()
+                                .zip(alpha.iter())
+                                .map(|(z, &alpha)| {
+                                    gamma::ln_gamma(alpha + *z) - gamma::ln_gamma(alpha)
+                                })
+                                .sum::<f64>()
+                    })
+                    .sum::<f64>()
+            })
+            .sum();
+        log_ll_theta + log_ll_q
+    }
 }
 
 impl ScoreFunction for LogLikelihood {
@@ -143,12 +370,49 @@ impl BIC {
     /// # Arguments
     ///
     /// * `alpha`: pseudo count (immaginary  number of transitions)
-    /// * `tau`: pseudo residence time (immaginary residence time)
-    pub fn new(alpha: usize, tau: f64) -> BIC {
+    /// * `tau`: pseudo residence time (immaginary residence time), see [`LogLikelihood::new`]
+    pub fn new<A: Into<Alpha>, U: Into<Tau>>(alpha: A, tau: U) -> BIC {
         BIC {
             ll: LogLikelihood::new(alpha, tau),
         }
     }
+
+    /// Create a `struct BIC` reading the sufficient statistics from a shared store
+    ///
+    /// # Arguments
+    ///
+    /// * `alpha`: pseudo count (immaginary  number of transitions)
+    /// * `tau`: pseudo residence time (immaginary residence time)
+    /// * `suff_stats_cache`: store of sufficient statistics bound to the dataset that will be
+    ///   scored
+    pub fn new_with_suff_stats_cache<A: Into<Alpha>, U: Into<Tau>>(
+        alpha: A,
+        tau: U,
+        suff_stats_cache: Arc<tools::SuffStatsCache>,
+    ) -> BIC {
+        BIC {
+            ll: LogLikelihood::new_with_suff_stats_cache(alpha, tau, suff_stats_cache),
+        }
+    }
+
+    /// Create a `struct BIC` reading the sufficient statistics from a `ScoreCache`
+    ///
+    /// Scoring a dataset different from the one bound to `score_cache` panics.
+    ///
+    /// # Arguments
+    ///
+    /// * `alpha`: pseudo count (immaginary  number of transitions)
+    /// * `tau`: pseudo residence time (immaginary residence time)
+    /// * `score_cache`: store of sufficient statistics bound to the dataset that will be scored
+    pub fn new_with_score_cache<A: Into<Alpha>, U: Into<Tau>>(
+        alpha: A,
+        tau: U,
+        score_cache: Arc<ScoreCache>,
+    ) -> BIC {
+        BIC {
+            ll: LogLikelihood::new_with_score_cache(alpha, tau, score_cache),
+        }
+    }
 }
 
 impl ScoreFunction for BIC {
@@ -163,18 +427,26 @@ impl ScoreFunction for BIC {
         T: process::NetworkProcess,
     {
         //Compute the log-likelihood
-        let (ll, M) = self.ll.compute_score(net, node, parent_set, dataset);
+        let (ll, shape) = self.ll.compute_score(net, node, parent_set, dataset);
         //Compute the number of parameters
-        let n_parameters = M.shape()[0] * M.shape()[1] * (M.shape()[2] - 1);
+        let n_parameters = shape.0 * shape.1 * (shape.2 - 1);
         //TODO: Optimize this
-        //Compute the sample size
-        let sample_size: usize = dataset
-            .get_trajectories()
-            .iter()
-            .map(|x| x.get_time().len() - 1)
-            .sum();
+        //Compute the sample size, counting each trajectory as many times as its weight
+        let sample_size: f64 = match dataset.get_weights() {
+            None => dataset
+                .get_trajectories()
+                .iter()
+                .map(|x| x.get_time().len() - 1)
+                .sum::<usize>() as f64,
+            Some(weights) => dataset
+                .get_trajectories()
+                .iter()
+                .zip(weights.iter())
+                .map(|(x, w)| w * (x.get_time().len() - 1) as f64)
+                .sum(),
+        };
         //Compute BIC
-        let score = ll - f64::ln(sample_size as f64) / 2.0 * n_parameters as f64;
+        let score = ll - f64::ln(sample_size) / 2.0 * n_parameters as f64;
         debug!(
             "Node: {} - Parentset: {:?} - score: {}",
             node, parent_set, score
@@ -182,3 +454,458 @@ impl ScoreFunction for BIC {
         score
     }
 }
+
+//Log-prior of the parent set of a node.
+type StructurePrior = Box<dyn Fn(usize, &BTreeSet<usize>) -> f64 + Send + Sync>;
+
+/// Score function adding a structural prior to the score of a data-driven `ScoreFunction`
+///
+/// The score of `node` given `parent_set` is `score_function(node, parent_set) +
+/// structure_prior(node, parent_set)`, where `structure_prior` is the log-prior of the parent
+/// set. Any decomposable prior can be encoded, e.g. a per-edge prior probability `p` with
+/// `|_, parent_set| parent_set.len() as f64 * (p / (1.0 - p)).ln()`, or an exponential prior
+/// on the size of the parent set with `|_, parent_set| -lambda * parent_set.len() as f64`.
+///
+/// # Example
+///
+/// ```rust
+/// # use reCTBN::structure_learning::score_based_algorithm::HillClimbing;
+/// # use reCTBN::structure_learning::score_function::{PriorPenalizedScore, BIC};
+/// let score_function = PriorPenalizedScore::new(BIC::new(1, 1.0), |_, parent_set| {
+///     -10.0 * parent_set.len() as f64
+/// });
+/// let hl = HillClimbing::new(score_function, None);
+/// ```
+pub struct PriorPenalizedScore<S: ScoreFunction> {
+    score_function: S,
+    structure_prior: StructurePrior,
+}
+
+impl<S: ScoreFunction> PriorPenalizedScore<S> {
+    /// Create a `struct PriorPenalizedScore`
+    ///
+    /// # Arguments
+    ///
+    /// * `score_function`: score function computing the data term.
+    /// * `structure_prior`: log-prior of a parent set, called with the node and its parent set.
+    pub fn new<F>(score_function: S, structure_prior: F) -> PriorPenalizedScore<S>
+    where
+        F: Fn(usize, &BTreeSet<usize>) -> f64 + Send + Sync + 'static,
+    {
+        PriorPenalizedScore {
+            score_function,
+            structure_prior: Box::new(structure_prior),
+        }
+    }
+}
+
+impl<S: ScoreFunction> ScoreFunction for PriorPenalizedScore<S> {
+    fn call<T>(
+        &self,
+        net: &T,
+        node: usize,
+        parent_set: &BTreeSet<usize>,
+        dataset: &tools::Dataset,
+    ) -> f64
+    where
+        T: process::NetworkProcess,
+    {
+        let score = self.score_function.call(net, node, parent_set, dataset)
+            + (self.structure_prior)(node, parent_set);
+        debug!(
+            "Node: {} - Parentset: {:?} - score: {}",
+            node, parent_set, score
+        );
+        score
+    }
+}
+
+/// Cross-validated log-likelihood for a `NetworkProcess`
+///
+/// The trajectories of the dataset are split in `n_folds` folds. For each fold, the CIM of the
+/// node is fitted on the other folds with `parameter_learning` and the log-likelihood of the
+/// transitions observed in the held-out fold is computed. The score is the average over the
+/// folds.
+///
+/// The rows of the CIM whose parent configuration and state were never observed in the training
+/// folds are taken from the prior (see [`CrossValidatedLL::with_prior`]). A transition observed
+/// in a held-out fold but never in the training folds has probability zero under `MLE`, hence
+/// the score may be `-inf`; `BayesianApproach` does not have this issue.
+///
+/// The folds depend only on the dataset and on the seed, hence all the parent sets of a search
+/// are scored on the same folds.
+pub struct CrossValidatedLL<P: ParameterLearning> {
+    parameter_learning: P,
+    n_folds: usize,
+    seed: u64,
+    prior: BayesianApproach,
+}
+
+impl<P: ParameterLearning> CrossValidatedLL<P> {
+    /// Create a `struct CrossValidatedLL`
+    ///
+    /// # Arguments
+    ///
+    /// * `parameter_learning`: method used to fit the CIM on the training folds (e.g. `MLE` or
+    ///   `BayesianApproach`).
+    /// * `n_folds`: number of folds; it must be at least 2 and not larger than the number of
+    ///   trajectories of the scored dataset.
+    /// * `seed`: seed used to split the trajectories in folds. If `None` a random seed is drawn
+    ///   once, when the score function is created.
+    ///
+    /// The default prior is a `BayesianApproach` with `alpha: Alpha::Constant(1.0)` and
+    /// `tau: Tau::Constant(1.0)`.
+    pub fn new(parameter_learning: P, n_folds: usize, seed: Option<u64>) -> CrossValidatedLL<P> {
+        if n_folds < 2 {
+            panic!("At least two folds are required for the cross-validation");
+        }
+        CrossValidatedLL {
+            parameter_learning,
+            n_folds,
+            seed: seed.unwrap_or_else(rand::random),
+            prior: BayesianApproach {
+                alpha: Alpha::Constant(1.0),
+                tau: Tau::Constant(1.0),
+            },
+        }
+    }
+
+    /// Set the prior used for the parent configurations never observed in the training folds
+    ///
+    /// # Arguments
+    ///
+    /// * `prior`: the rates of an unobserved row are the ones fitted by `prior` without data.
+    pub fn with_prior(mut self, prior: BayesianApproach) -> CrossValidatedLL<P> {
+        self.prior = prior;
+        self
+    }
+
+    //Split the trajectories of `dataset` in `n_folds` folds.
+    fn split_in_folds(&self, dataset: &tools::Dataset) -> Vec<tools::Dataset> {
+        let n_trajectories = dataset.get_trajectories().len();
+        if n_trajectories < self.n_folds {
+            panic!(
+                "At least {} trajectories are required for the cross-validation",
+                self.n_folds
+            );
+        }
+        let mut rng: ChaCha8Rng = SeedableRng::seed_from_u64(self.seed);
+        let mut indices: Vec<usize> = (0..n_trajectories).collect();
+        indices.shuffle(&mut rng);
+        (0..self.n_folds)
+            .map(|fold| {
+                tools::Dataset::new(
+                    indices
+                        .iter()
+                        .skip(fold)
+                        .step_by(self.n_folds)
+                        .map(|x| dataset.get_trajectories()[*x].clone())
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+}
+
+impl<P: ParameterLearning> ScoreFunction for CrossValidatedLL<P> {
+    fn call<T>(
+        &self,
+        net: &T,
+        node: usize,
+        parent_set: &BTreeSet<usize>,
+        dataset: &tools::Dataset,
+    ) -> f64
+    where
+        T: process::NetworkProcess,
+    {
+        let folds_stats: Vec<(Array3<usize>, Array2<f64>)> = self
+            .split_in_folds(dataset)
+            .iter()
+            .map(|fold| parameter_learning::sufficient_statistics(net, fold, node, parent_set))
+            .collect();
+        //The statistics of the training folds are the ones of the whole dataset minus the ones
+        //of the held-out fold.
+        let mut M_total = Array3::zeros(folds_stats[0].0.raw_dim());
+        let mut T_total = Array2::zeros(folds_stats[0].1.raw_dim());
+        for (M, T) in folds_stats.iter() {
+            M_total += M;
+            T_total += T;
+        }
+        let prior_cim = match self.prior.fit_from_parent_set_statistics(
+            net,
+            node,
+            parent_set,
+            Array3::zeros(M_total.raw_dim()),
+            Array2::zeros(T_total.raw_dim()),
+        ) {
+            params::Params::DiscreteStatesContinousTime(params) => {
+                params.get_expanded_cim().unwrap()
+            }
+        };
+
+        let score = folds_stats
+            .iter()
+            .map(|(M, T)| {
+                let T_training = &T_total - T;
+                let mut cim = match self.parameter_learning.fit_from_parent_set_statistics(
+                    net,
+                    node,
+                    parent_set,
+                    &M_total - M,
+                    T_training.clone(),
+                ) {
+                    params::Params::DiscreteStatesContinousTime(params) => {
+                        params.get_expanded_cim().unwrap()
+                    }
+                };
+                //Rows never observed in the training folds are taken from the prior.
+                for ((u, x), t) in T_training.indexed_iter() {
+                    if *t <= 0.0 {
+                        cim.slice_mut(s![u, x, ..])
+                            .assign(&prior_cim.slice(s![u, x, ..]));
+                    }
+                }
+                log_likelihood_from_cim(&cim, M, T)
+            })
+            .sum::<f64>()
+            / self.n_folds as f64;
+        debug!(
+            "Node: {} - Parentset: {:?} - score: {}",
+            node, parent_set, score
+        );
+        score
+    }
+}
+
+/// Log-likelihood of the transitions of `node` observed in `validation`, under the CIM estimated
+/// from `training` with the Bayesian approach.
+///
+/// # Arguments
+///
+/// * `net`: `NetworkProcess` object.
+/// * `node`: node whose transitions are evaluated.
+/// * `parent_set`: parent set of the `node`.
+/// * `training`: dataset used to estimate the CIM.
+/// * `validation`: dataset on which the likelihood is evaluated.
+/// * `alpha`, `tau`: hyperparameters of the `BayesianApproach` used to estimate the CIM.
+pub fn held_out_log_likelihood<T: process::NetworkProcess, A: Into<Alpha>, U: Into<Tau>>(
+    net: &T,
+    node: usize,
+    parent_set: &BTreeSet<usize>,
+    training: &tools::Dataset,
+    validation: &tools::Dataset,
+    alpha: A,
+    tau: U,
+) -> f64 {
+    let (M, T) = parameter_learning::sufficient_statistics(net, training, node, parent_set);
+    let parameter_learning = parameter_learning::BayesianApproach {
+        alpha: alpha.into(),
+        tau: tau.into(),
+    };
+    let cim = match parameter_learning.fit_from_parent_set_statistics(net, node, parent_set, M, T) {
+        params::Params::DiscreteStatesContinousTime(params) => params.get_expanded_cim().unwrap(),
+    };
+    let (M, T) = parameter_learning::sufficient_statistics(net, validation, node, parent_set);
+    log_likelihood_from_cim(&cim, &M, &T)
+}
+
+//Log-likelihood of the sufficient statistics `(M, T)` under `cim`.
+fn log_likelihood_from_cim(cim: &Array3<f64>, M: &Array3<usize>, T: &Array2<f64>) -> f64 {
+    //Residence times contribute with -q_x * t, each transition x -> y with ln(q_xy).
+    cim.indexed_iter()
+        .map(|((u, x, y), q)| {
+            if x == y {
+                *q * T[[u, x]]
+            } else if M[[u, x, y]] > 0 {
+                M[[u, x, y]] as f64 * q.ln()
+            } else {
+                0.0
+            }
+        })
+        .sum()
+}
+
+/// Outcome of a single property verified by [`check_score_properties`].
+///
+/// # Attributes
+///
+/// * `passed` - `true` if the property holds.
+/// * `evidence` - the largest violation observed (`0.0` when the property holds exactly).
+#[derive(Clone, Debug, PartialEq)]
+pub struct PropertyCheck {
+    pub passed: bool,
+    pub evidence: f64,
+}
+
+impl PropertyCheck {
+    fn from_violation(violation: f64, tolerance: f64) -> PropertyCheck {
+        PropertyCheck {
+            passed: violation <= tolerance,
+            evidence: violation,
+        }
+    }
+}
+
+/// Result of [`check_score_properties`].
+///
+/// # Attributes
+///
+/// * `relabeling_invariance` - relative change of the network score when the nodes are relabeled
+///   (i.e. their indices are permuted).
+/// * `additivity` - relative difference between the network score and the sum of the scores of
+///   the families, each computed on the node and its parents only.
+/// * `duplication_monotonicity` - decrease of the magnitude of the score difference between the
+///   parent set of a node and the empty parent set when the dataset is duplicated.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScorePropertyReport {
+    pub relabeling_invariance: PropertyCheck,
+    pub additivity: PropertyCheck,
+    pub duplication_monotonicity: PropertyCheck,
+}
+
+impl ScorePropertyReport {
+    pub fn passed(&self) -> bool {
+        self.relabeling_invariance.passed
+            && self.additivity.passed
+            && self.duplication_monotonicity.passed
+    }
+}
+
+/// Check basic properties that a decomposable score function is expected to satisfy.
+///
+/// # Arguments
+///
+/// * `score`: the score function to check; it must not hold a `SuffStatsCache`, since the
+///   properties are evaluated on modified copies of `dataset`.
+/// * `net`: a network whose structure defines the parent sets used in the checks.
+/// * `dataset`: a dataset compatible with `net`.
+pub fn check_score_properties<S: ScoreFunction>(
+    score: &S,
+    net: &CtbnNetwork,
+    dataset: &tools::Dataset,
+) -> ScorePropertyReport {
+    //Tolerance on the relative error due to the floating point arithmetic.
+    let tolerance = 1e-9;
+    let relative_error = |a: f64, b: f64| f64::abs(a - b) / f64::max(1.0, f64::abs(a));
+    let network_score = |dataset: &tools::Dataset| -> f64 {
+        net.get_node_indices()
+            .map(|node| score.call(net, node, &net.get_parent_set(node), dataset))
+            .sum()
+    };
+
+    //Keep the weights of `dataset` on a dataset with the same trajectories.
+    let with_weights = |other: tools::Dataset| match dataset.get_weights() {
+        Some(weights) => other.with_weights(weights.clone()),
+        None => other,
+    };
+
+    //Relabel the nodes reversing their order.
+    let original_score = network_score(dataset);
+    let perm: Vec<usize> = net.get_node_indices().rev().collect();
+    let (relabeled_net, relabeled) = tools::permute_variables(net, dataset, &perm);
+    let relabeled = with_weights(relabeled);
+    let relabeled_score: f64 = relabeled_net
+        .get_node_indices()
+        .map(|node| {
+            score.call(
+                &relabeled_net,
+                node,
+                &relabeled_net.get_parent_set(node),
+                &relabeled,
+            )
+        })
+        .sum();
+    let relabeling_violation = relative_error(original_score, relabeled_score);
+
+    //Score each family on a network and a dataset restricted to the node and its parents.
+    let family_score = |node: usize| -> f64 {
+        let parent_set = net.get_parent_set(node);
+        let mut family: Vec<usize> = parent_set.iter().copied().collect();
+        family.push(node);
+        family.sort();
+        let family_idx = |x: usize| family.binary_search(&x).unwrap();
+        let mut family_net = CtbnNetwork::new();
+        for x in family.iter() {
+            family_net.add_node(net.get_node(*x).clone()).unwrap();
+        }
+        family_net.initialize_adj_matrix();
+        for parent in parent_set.iter() {
+            family_net.add_edge(family_idx(*parent), family_idx(node));
+        }
+        let family_dataset = with_weights(tools::Dataset::new(
+            dataset
+                .get_trajectories()
+                .iter()
+                .map(|trj| {
+                    let time = trj.get_time().clone();
+                    let events = trj.get_events().select(Axis(1), &family);
+                    let family_trj = match trj.get_intervention() {
+                        Some((x, state)) if family.contains(&x) => {
+                            tools::Trajectory::new_interventional(
+                                time,
+                                events,
+                                (family_idx(x), state),
+                            )
+                        }
+                        _ => tools::Trajectory::new(time, events),
+                    };
+                    if trj.is_censored() {
+                        family_trj.with_censored_end()
+                    } else {
+                        family_trj
+                    }
+                })
+                .collect(),
+        ));
+        score.call(
+            &family_net,
+            family_idx(node),
+            &parent_set.iter().map(|x| family_idx(*x)).collect(),
+            &family_dataset,
+        )
+    };
+    let additivity_violation = relative_error(
+        original_score,
+        net.get_node_indices().map(family_score).sum(),
+    );
+
+    //Duplicating the dataset must strengthen the evidence for (or against) each parent set.
+    let duplicated = tools::Dataset::new(
+        dataset
+            .get_trajectories()
+            .iter()
+            .chain(dataset.get_trajectories().iter())
+            .cloned()
+            .collect(),
+    );
+    let duplicated = match dataset.get_weights() {
+        Some(weights) => duplicated.with_weights(ndarray::concatenate![
+            Axis(0),
+            weights.view(),
+            weights.view()
+        ]),
+        None => duplicated,
+    };
+    let delta = |node: usize, dataset: &tools::Dataset| {
+        score.call(net, node, &net.get_parent_set(node), dataset)
+            - score.call(net, node, &BTreeSet::new(), dataset)
+    };
+    let duplication_violation = net
+        .get_node_indices()
+        .filter(|node| !net.get_parent_set(*node).is_empty())
+        .map(|node| {
+            let (original, duplicated) = (delta(node, dataset), delta(node, &duplicated));
+            if original * duplicated < 0.0 {
+                f64::abs(original - duplicated)
+            } else {
+                f64::max(0.0, f64::abs(original) - f64::abs(duplicated))
+            }
+        })
+        .fold(0.0, f64::max);
+
+    ScorePropertyReport {
+        relabeling_invariance: PropertyCheck::from_violation(relabeling_violation, tolerance),
+        additivity: PropertyCheck::from_violation(additivity_violation, tolerance),
+        duplication_monotonicity: PropertyCheck::from_violation(duplication_violation, tolerance),
+    }
+}
@@ -1,20 +1,74 @@
 //! Contains commonly used methods used across the crate.
 
-use std::ops::{DivAssign, MulAssign, Range};
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
 
-use ndarray::{Array, Array1, Array2, Array3, Axis};
+use ndarray::{s, Array, Array1, Array2, Array3, Axis};
 use rand::{Rng, SeedableRng};
 use rand_chacha::ChaCha8Rng;
+use rayon::ThreadPool;
+use serde_json::{json, Value};
 
+#[cfg(feature = "parquet")]
+use arrow_array::cast::AsArray;
+#[cfg(feature = "parquet")]
+use arrow_array::types::{Float64Type, UInt64Type};
+#[cfg(feature = "parquet")]
+use arrow_array::{Array as _, ArrayRef, Float64Array, RecordBatch, UInt64Array};
+#[cfg(feature = "parquet")]
+use arrow_schema::{DataType, Field, Schema};
+#[cfg(feature = "parquet")]
+use parquet::arrow::{arrow_reader::ParquetRecordBatchReaderBuilder, ArrowWriter};
+
+use crate::parameter_learning::{
+    sufficient_statistics, DatasetStatistics, ParameterLearning, ParameterLearningError,
+};
 use crate::params::ParamsTrait;
+use crate::process::ctbn::CtbnNetwork;
 use crate::process::NetworkProcess;
 use crate::sampling::{ForwardSampler, Sampler};
+use crate::structure_learning::StructuralLearningAlgorithm;
 use crate::{params, process};
 
+use thiserror::Error;
+
+/// Error types for reading and writing datasets
+#[derive(Error, Debug, PartialEq)]
+pub enum DatasetError {
+    #[error("Malformed CSV: {0}")]
+    MalformedCsv(String),
+    #[error("Malformed Parquet: {0}")]
+    MalformedParquet(String),
+    #[error("Missing column: {0}")]
+    MissingColumn(String),
+    #[error("Invalid value: {0}")]
+    InvalidValue(String),
+    #[error("Empty dataset")]
+    EmptyDataset(String),
+    #[error("Mismatched network: {0}")]
+    MismatchedNetwork(String),
+    #[error("I/O error: {0}")]
+    Io(String),
+}
+
 #[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "TrajectoryData")
+)]
 pub struct Trajectory {
     time: Array1<f64>,
     events: Array2<usize>,
+    intervention: Option<(usize, usize)>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "std::ops::Not::not"))]
+    censored: bool,
 }
 
 impl Trajectory {
@@ -24,7 +78,36 @@ impl Trajectory {
         if time.shape()[0] != events.shape()[0] {
             panic!("time.shape[0] must be equal to events.shape[0]");
         }
-        Trajectory { time, events }
+        Trajectory {
+            time,
+            events,
+            intervention: None,
+            censored: false,
+        }
+    }
+
+    /// Create a trajectory generated while `node` was clamped to `state` by an intervention.
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - time instants of the events.
+    /// * `events` - state of each variable at each time instant.
+    /// * `intervention` - the `(node, state)` pair of the intervention.
+    pub fn new_interventional(
+        time: Array1<f64>,
+        events: Array2<usize>,
+        intervention: (usize, usize),
+    ) -> Trajectory {
+        let (node, state) = intervention;
+        if node >= events.shape()[1] {
+            panic!("The intervened node {} does not exist", node);
+        }
+        if events.column(node).iter().any(|x| *x != state) {
+            panic!("The intervened node {} must be clamped to {}", node, state);
+        }
+        let mut trj = Trajectory::new(time, events);
+        trj.intervention = Some(intervention);
+        trj
     }
 
     pub fn get_time(&self) -> &Array1<f64> {
@@ -34,11 +117,78 @@ impl Trajectory {
     pub fn get_events(&self) -> &Array2<usize> {
         &self.events
     }
+
+    /// The `(node, state)` pair of the intervention, `None` for observational trajectories.
+    pub fn get_intervention(&self) -> Option<(usize, usize)> {
+        self.intervention
+    }
+
+    /// Mark the last time instant of the trajectory as a censoring time.
+    ///
+    /// By default the last row of a trajectory is a regular event. The last row of a censored
+    /// trajectory, instead, only records the end of the observation (e.g. an administrative
+    /// cutoff): the time elapsed since the previous row counts as residence time in the previous
+    /// state, but a change of state in the last row is never counted as a transition. A last row
+    /// repeating the previous states, as the one added by [`trajectory_generator`] at `t_end`,
+    /// gives the same sufficient statistics with or without the flag.
+    pub fn with_censored_end(mut self) -> Trajectory {
+        self.censored = true;
+        self
+    }
+
+    /// `true` if the last time instant of the trajectory is a censoring time.
+    pub fn is_censored(&self) -> bool {
+        self.censored
+    }
+
+    /// Stable byte serialization of the trajectory.
+    ///
+    /// The number of rows and columns are followed by the time instants and by the events (in
+    /// row-major order), all encoded as little-endian 64 bit values.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let (n_rows, n_cols) = self.events.dim();
+        let mut bytes = Vec::with_capacity(8 * (2 + n_rows + n_rows * n_cols));
+        bytes.extend_from_slice(&(n_rows as u64).to_le_bytes());
+        bytes.extend_from_slice(&(n_cols as u64).to_le_bytes());
+        for t in self.time.iter() {
+            bytes.extend_from_slice(&t.to_bits().to_le_bytes());
+        }
+        for x in self.events.iter() {
+            bytes.extend_from_slice(&(*x as u64).to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Collapse consecutive rows with identical states.
+    ///
+    /// Each removed row extends the interval of the previous one; the last row, marking the end
+    /// of the trajectory, is always preserved. The sufficient statistics are not affected.
+    pub fn normalize(&self) -> Trajectory {
+        let n_rows = self.time.len();
+        let keep: Vec<usize> = (0..n_rows)
+            .filter(|idx| {
+                *idx == 0 || *idx == n_rows - 1 || self.events.row(*idx) != self.events.row(idx - 1)
+            })
+            .collect();
+        Trajectory {
+            time: self.time.select(Axis(0), &keep),
+            events: self.events.select(Axis(0), &keep),
+            intervention: self.intervention,
+            censored: self.censored,
+        }
+    }
 }
 
 #[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "DatasetData")
+)]
 pub struct Dataset {
     trajectories: Vec<Trajectory>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    weights: Option<Array1<f64>>,
 }
 
 impl Dataset {
@@ -51,12 +201,1262 @@ impl Dataset {
         {
             panic!("All the trajectories mus represents the same number of variables");
         }
-        Dataset { trajectories }
+        Dataset {
+            trajectories,
+            weights: None,
+        }
     }
 
     pub fn get_trajectories(&self) -> &Vec<Trajectory> {
         &self.trajectories
     }
+
+    /// Assign a weight to each trajectory of the dataset.
+    ///
+    /// The weights are honored by the learners working on
+    /// [`weighted_sufficient_statistics`](crate::parameter_learning::weighted_sufficient_statistics):
+    /// a trajectory with weight `2.0` counts as two copies of the same trajectory.
+    ///
+    /// # Arguments
+    ///
+    /// * `weights` - one non-negative, finite weight for each trajectory.
+    pub fn with_weights(mut self, weights: Array1<f64>) -> Dataset {
+        if weights.len() != self.trajectories.len() {
+            panic!("A weight for each trajectory is required");
+        }
+        if weights.iter().any(|x| !x.is_finite() || *x < 0.0) {
+            panic!("The weights must be finite and >= 0.0");
+        }
+        self.weights = Some(weights);
+        self
+    }
+
+    /// Weight of each trajectory, `None` if all the trajectories have the same weight.
+    pub fn get_weights(&self) -> Option<&Array1<f64>> {
+        self.weights.as_ref()
+    }
+
+    /// Check that the dataset can be used to learn the parameters or the structure of `net`.
+    ///
+    /// # Errors
+    ///
+    /// * `DatasetError::MismatchedNetwork` if a trajectory does not have a variable for each node
+    ///   of `net`.
+    /// * `DatasetError::InvalidValue` if a trajectory contains a state outside the domain of a
+    ///   node; the message reports the trajectory index and the label of the node.
+    pub fn validate_against<T: NetworkProcess>(&self, net: &T) -> Result<(), DatasetError> {
+        self.validate_nodes(net, net.get_node_indices())
+    }
+
+    //Same as `validate_against`, checking the states of the given nodes only.
+    pub(crate) fn validate_nodes<T: NetworkProcess, I: Iterator<Item = usize> + Clone>(
+        &self,
+        net: &T,
+        nodes: I,
+    ) -> Result<(), DatasetError> {
+        for (trajectory_id, trj) in self.trajectories.iter().enumerate() {
+            let events = trj.get_events();
+            if net.get_number_of_nodes() != events.shape()[1] {
+                return Err(DatasetError::MismatchedNetwork(format!(
+                    "the network has {} nodes but trajectory {} has {} variables",
+                    net.get_number_of_nodes(),
+                    trajectory_id,
+                    events.shape()[1]
+                )));
+            }
+            for node in nodes.clone() {
+                let cardinality = net.get_node(node).get_reserved_space_as_parent();
+                if let Some((row, state)) = events
+                    .column(node)
+                    .indexed_iter()
+                    .find(|(_, state)| **state >= cardinality)
+                {
+                    return Err(DatasetError::InvalidValue(format!(
+                        "trajectory {}, row {}: state {} of node {} with {} states",
+                        trajectory_id,
+                        row,
+                        state,
+                        net.get_node(node).get_label(),
+                        cardinality
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Build a dataset from panel data, i.e. states observed at fixed time instants.
+    ///
+    /// **This is an approximation**: the transitions happening between two consecutive samples
+    /// are not observed. Each observed state is assumed to hold until the next sample, so the
+    /// whole interval is attributed to it as residence time and at most one transition per
+    /// variable is counted between two samples. The approximation improves as the sampling
+    /// interval becomes small with respect to the expected residence times.
+    ///
+    /// # Arguments
+    ///
+    /// * `times` - strictly increasing sampling instants, shared by all the trajectories.
+    /// * `observations` - one matrix per trajectory, with one row per sampling instant and one
+    ///   column per variable.
+    /// * `collapse` - if `true`, consecutive identical rows are collapsed as in
+    ///   [`Trajectory::normalize`]; the sufficient statistics are the same either way.
+    pub fn from_snapshots(
+        times: Array1<f64>,
+        observations: Vec<Array2<usize>>,
+        collapse: bool,
+    ) -> Dataset {
+        if times.windows(2).into_iter().any(|x| x[0] >= x[1]) {
+            panic!("The sampling times must be strictly increasing");
+        }
+        Dataset::new(
+            observations
+                .into_iter()
+                .map(|events| {
+                    let trj = Trajectory::new(times.clone(), events);
+                    if collapse {
+                        trj.normalize()
+                    } else {
+                        trj
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    /// Read a dataset from CSV.
+    ///
+    /// The first line must be the header. Rows sharing the same trajectory id are collected, in
+    /// order, into the same trajectory; trajectories appear in the order of their first row.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - source of the CSV data.
+    /// * `time_column` - name of the column containing the time instants.
+    /// * `variable_columns` - name of the columns containing the state index of each variable;
+    ///   the order defines the variable indices.
+    /// * `trajectory_id_column` - name of the column identifying the trajectory of each row; when
+    ///   `None` the whole file is a single trajectory.
+    ///
+    /// # Errors
+    ///
+    /// Times that are not strictly increasing within a trajectory, states that are not
+    /// non-negative integers, missing columns and malformed rows are reported with the line and
+    /// the column where they occur.
+    pub fn from_csv<R: io::Read>(
+        reader: R,
+        time_column: &str,
+        variable_columns: &[&str],
+        trajectory_id_column: Option<&str>,
+    ) -> Result<Dataset, DatasetError> {
+        let mut reader = csv::Reader::from_reader(reader);
+        let headers = reader
+            .headers()
+            .map_err(|e| DatasetError::MalformedCsv(e.to_string()))?
+            .clone();
+        let column_index = |name: &str| {
+            headers
+                .iter()
+                .position(|x| x == name)
+                .ok_or_else(|| DatasetError::MissingColumn(name.to_string()))
+        };
+        let time_idx = column_index(time_column)?;
+        let variable_idx = variable_columns
+            .iter()
+            .map(|x| column_index(x))
+            .collect::<Result<Vec<usize>, DatasetError>>()?;
+        let id_idx = trajectory_id_column.map(column_index).transpose()?;
+
+        //Time instants and events of each trajectory, in order of first appearance.
+        let mut ids: HashMap<String, usize> = HashMap::new();
+        let mut trajectories: Vec<(Vec<f64>, Vec<usize>)> = Vec::new();
+        for record in reader.records() {
+            let record = record.map_err(|e| DatasetError::MalformedCsv(e.to_string()))?;
+            let line = record.position().map_or(0, |x| x.line());
+            let field = |idx: usize| record.get(idx).unwrap_or("").trim();
+
+            let id = id_idx.map_or(String::new(), |x| field(x).to_string());
+            let trj_idx = *ids.entry(id).or_insert_with(|| {
+                trajectories.push((Vec::new(), Vec::new()));
+                trajectories.len() - 1
+            });
+            let (time, events) = &mut trajectories[trj_idx];
+
+            let t: f64 = field(time_idx).parse().map_err(|_| {
+                DatasetError::InvalidValue(format!(
+                    "line {}, column {}: \"{}\" is not a number",
+                    line,
+                    time_column,
+                    field(time_idx)
+                ))
+            })?;
+            if let Some(previous) = time.last() {
+                if t.partial_cmp(previous) != Some(Ordering::Greater) {
+                    return Err(DatasetError::InvalidValue(format!(
+                        "line {}, column {}: time {} is not greater than the previous time {}",
+                        line, time_column, t, previous
+                    )));
+                }
+            }
+            time.push(t);
+
+            for (idx, name) in variable_idx.iter().zip(variable_columns.iter()) {
+                let state: usize = field(*idx).parse().map_err(|_| {
+                    DatasetError::InvalidValue(format!(
+                        "line {}, column {}: \"{}\" is not a non-negative integer",
+                        line,
+                        name,
+                        field(*idx)
+                    ))
+                })?;
+                events.push(state);
+            }
+        }
+
+        if trajectories.is_empty() {
+            return Err(DatasetError::EmptyDataset(String::from(
+                "The CSV does not contain any row",
+            )));
+        }
+        let n_variables = variable_columns.len();
+        Ok(Dataset::new(
+            trajectories
+                .into_iter()
+                .map(|(time, events)| {
+                    let n_rows = time.len();
+                    Trajectory::new(
+                        Array1::from_vec(time),
+                        Array2::from_shape_vec((n_rows, n_variables), events).unwrap(),
+                    )
+                })
+                .collect(),
+        ))
+    }
+
+    /// Write the dataset as CSV, one row per event.
+    ///
+    /// The columns are `trajectory_id`, `time` and one column per variable, named `var_<index>`
+    /// or after the labels of the nodes of `net`. Times are written with the shortest
+    /// representation that parses back to the same `f64`, and rows are streamed to `writer`.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - destination of the CSV data.
+    /// * `net` - optional network providing the variable labels; it must have one node per
+    ///   variable.
+    pub fn to_csv<W: io::Write>(
+        &self,
+        writer: W,
+        net: Option<&CtbnNetwork>,
+    ) -> Result<(), DatasetError> {
+        let variable_names = self.variable_names(net)?;
+
+        let io_error = |e: csv::Error| DatasetError::Io(e.to_string());
+        let mut writer = csv::Writer::from_writer(writer);
+        let mut record: Vec<String> = vec![String::from("trajectory_id"), String::from("time")];
+        record.extend(variable_names);
+        writer.write_record(&record).map_err(io_error)?;
+        for (id, trj) in self.trajectories.iter().enumerate() {
+            for (t, events) in trj.get_time().iter().zip(trj.get_events().rows()) {
+                record.clear();
+                record.push(id.to_string());
+                record.push(t.to_string());
+                record.extend(events.iter().map(|x| x.to_string()));
+                writer.write_record(&record).map_err(io_error)?;
+            }
+        }
+        writer.flush().map_err(|e| DatasetError::Io(e.to_string()))
+    }
+
+    //Name of each variable: `var_<index>` or the label of the corresponding node of `net`.
+    fn variable_names(&self, net: Option<&CtbnNetwork>) -> Result<Vec<String>, DatasetError> {
+        let n_variables = self
+            .trajectories
+            .first()
+            .map_or(0, |x| x.get_events().shape()[1]);
+        match net {
+            Some(net) => {
+                if net.get_number_of_nodes() != n_variables {
+                    return Err(DatasetError::MismatchedNetwork(format!(
+                        "the network has {} nodes but the dataset has {} variables",
+                        net.get_number_of_nodes(),
+                        n_variables
+                    )));
+                }
+                Ok(net
+                    .get_node_indices()
+                    .map(|x| net.get_node(x).get_label().clone())
+                    .collect())
+            }
+            None => Ok((0..n_variables).map(|x| format!("var_{}", x)).collect()),
+        }
+    }
+
+    /// Read a dataset from Parquet.
+    ///
+    /// The table is in long format, one row per event, as written by
+    /// [`to_parquet`](Dataset::to_parquet). Rows sharing the same trajectory id are collected, in
+    /// order, into the same trajectory; trajectories appear in the order of their first row. The
+    /// time column may have any numeric type, the variable columns any integer type and the
+    /// trajectory id column any type convertible to a string.
+    ///
+    /// Available with the `parquet` feature.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - source of the Parquet data, e.g. a `std::fs::File`.
+    /// * `time_column` - name of the column containing the time instants.
+    /// * `variable_columns` - name of the columns containing the state index of each variable;
+    ///   the order defines the variable indices.
+    /// * `trajectory_id_column` - name of the column identifying the trajectory of each row; when
+    ///   `None` the whole table is a single trajectory.
+    ///
+    /// # Errors
+    ///
+    /// Times that are not strictly increasing within a trajectory (i.e. unsorted or duplicated),
+    /// negative states and missing values are reported with the row and the column where they
+    /// occur.
+    #[cfg(feature = "parquet")]
+    pub fn from_parquet<R: parquet::file::reader::ChunkReader + 'static>(
+        reader: R,
+        time_column: &str,
+        variable_columns: &[&str],
+        trajectory_id_column: Option<&str>,
+    ) -> Result<Dataset, DatasetError> {
+        let builder = ParquetRecordBatchReaderBuilder::try_new(reader)
+            .map_err(|e| DatasetError::MalformedParquet(e.to_string()))?;
+        let schema = builder.schema().clone();
+        let column_index = |name: &str| {
+            schema
+                .index_of(name)
+                .map_err(|_| DatasetError::MissingColumn(name.to_string()))
+        };
+        let check_type = |idx: usize, name: &str, is_valid: fn(&DataType) -> bool| {
+            let data_type = schema.field(idx).data_type();
+            if is_valid(data_type) {
+                Ok(idx)
+            } else {
+                Err(DatasetError::InvalidValue(format!(
+                    "column {}: unsupported type {}",
+                    name, data_type
+                )))
+            }
+        };
+        let time_idx = check_type(column_index(time_column)?, time_column, |x| x.is_numeric())?;
+        let variable_idx = variable_columns
+            .iter()
+            .map(|x| check_type(column_index(x)?, x, DataType::is_integer))
+            .collect::<Result<Vec<usize>, DatasetError>>()?;
+        let id_idx = trajectory_id_column.map(column_index).transpose()?;
+        let batches = builder
+            .build()
+            .map_err(|e| DatasetError::MalformedParquet(e.to_string()))?;
+
+        //Time instants and events of each trajectory, in order of first appearance.
+        let mut ids: HashMap<String, usize> = HashMap::new();
+        let mut trajectories: Vec<(Vec<f64>, Vec<usize>)> = Vec::new();
+        //Index of the first row of the current batch.
+        let mut first_row = 0;
+        for batch in batches {
+            let batch = batch.map_err(|e| DatasetError::MalformedParquet(e.to_string()))?;
+            let cast = |idx: usize, data_type: &DataType| {
+                arrow_cast::cast(batch.column(idx), data_type)
+                    .map_err(|e| DatasetError::MalformedParquet(e.to_string()))
+            };
+            let missing_value = |row: usize, name: &str| {
+                DatasetError::InvalidValue(format!("row {}, column {}: missing value", row, name))
+            };
+            let time = cast(time_idx, &DataType::Float64)?;
+            let time = time.as_primitive::<Float64Type>();
+            //Negative states become nulls when cast to unsigned integers.
+            let states = variable_idx
+                .iter()
+                .map(|idx| cast(*idx, &DataType::UInt64))
+                .collect::<Result<Vec<ArrayRef>, DatasetError>>()?;
+            let trajectory_ids = id_idx.map(|idx| cast(idx, &DataType::Utf8)).transpose()?;
+
+            for idx in 0..batch.num_rows() {
+                let row = first_row + idx;
+                let id = match (&trajectory_ids, trajectory_id_column) {
+                    (Some(trajectory_ids), Some(name)) => {
+                        if trajectory_ids.is_null(idx) {
+                            return Err(missing_value(row, name));
+                        }
+                        trajectory_ids.as_string::<i32>().value(idx)
+                    }
+                    _ => "",
+                };
+                let trj_idx = match ids.get(id) {
+                    Some(trj_idx) => *trj_idx,
+                    None => {
+                        trajectories.push((Vec::new(), Vec::new()));
+                        ids.insert(id.to_string(), trajectories.len() - 1);
+                        trajectories.len() - 1
+                    }
+                };
+                let (trj_time, events) = &mut trajectories[trj_idx];
+
+                if time.is_null(idx) {
+                    return Err(missing_value(row, time_column));
+                }
+                let t = time.value(idx);
+                if let Some(previous) = trj_time.last() {
+                    if t.partial_cmp(previous) != Some(Ordering::Greater) {
+                        return Err(DatasetError::InvalidValue(format!(
+                            "row {}, column {}: time {} is not greater than the previous time {}",
+                            row, time_column, t, previous
+                        )));
+                    }
+                }
+                trj_time.push(t);
+
+                for ((state, name), original_idx) in states
+                    .iter()
+                    .zip(variable_columns.iter())
+                    .zip(variable_idx.iter())
+                {
+                    if batch.column(*original_idx).is_null(idx) {
+                        return Err(missing_value(row, name));
+                    }
+                    if state.is_null(idx) {
+                        return Err(DatasetError::InvalidValue(format!(
+                            "row {}, column {}: the state is not a non-negative integer",
+                            row, name
+                        )));
+                    }
+                    events.push(state.as_primitive::<UInt64Type>().value(idx) as usize);
+                }
+            }
+            first_row += batch.num_rows();
+        }
+
+        if trajectories.is_empty() {
+            return Err(DatasetError::EmptyDataset(String::from(
+                "The Parquet table does not contain any row",
+            )));
+        }
+        let n_variables = variable_columns.len();
+        Ok(Dataset::new(
+            trajectories
+                .into_iter()
+                .map(|(time, events)| {
+                    let n_rows = time.len();
+                    Trajectory::new(
+                        Array1::from_vec(time),
+                        Array2::from_shape_vec((n_rows, n_variables), events).unwrap(),
+                    )
+                })
+                .collect(),
+        ))
+    }
+
+    /// Write the dataset as Parquet, one row per event.
+    ///
+    /// The columns are `trajectory_id` and `time`, followed by one column per variable named as
+    /// in [`to_csv`](Dataset::to_csv); ids and states are written as unsigned 64-bit integers and
+    /// times as 64-bit floats. The rows are written in batches, so the whole table is never
+    /// materialized in memory.
+    ///
+    /// Available with the `parquet` feature.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - destination of the Parquet data.
+    /// * `net` - optional network providing the variable labels; it must have one node per
+    ///   variable.
+    #[cfg(feature = "parquet")]
+    pub fn to_parquet<W: io::Write + Send>(
+        &self,
+        writer: W,
+        net: Option<&CtbnNetwork>,
+    ) -> Result<(), DatasetError> {
+        //Number of rows written at once.
+        const BATCH_SIZE: usize = 65536;
+        let variable_names = self.variable_names(net)?;
+        let mut fields = vec![
+            Field::new("trajectory_id", DataType::UInt64, false),
+            Field::new("time", DataType::Float64, false),
+        ];
+        fields.extend(
+            variable_names
+                .iter()
+                .map(|x| Field::new(x.as_str(), DataType::UInt64, false)),
+        );
+        let schema = Arc::new(Schema::new(fields));
+
+        let io_error = |e: parquet::errors::ParquetError| DatasetError::Io(e.to_string());
+        let mut writer = ArrowWriter::try_new(writer, schema.clone(), None).map_err(io_error)?;
+        let mut ids: Vec<u64> = Vec::new();
+        let mut times: Vec<f64> = Vec::new();
+        let mut states: Vec<Vec<u64>> = vec![Vec::new(); variable_names.len()];
+        let mut write_batch =
+            |ids: &mut Vec<u64>, times: &mut Vec<f64>, states: &mut Vec<Vec<u64>>| {
+                let mut columns: Vec<ArrayRef> = vec![
+                    Arc::new(UInt64Array::from(std::mem::take(ids))),
+                    Arc::new(Float64Array::from(std::mem::take(times))),
+                ];
+                columns.extend(
+                    states
+                        .iter_mut()
+                        .map(|x| Arc::new(UInt64Array::from(std::mem::take(x))) as ArrayRef),
+                );
+                let batch = RecordBatch::try_new(schema.clone(), columns)
+                    .map_err(|e| DatasetError::Io(e.to_string()))?;
+                writer.write(&batch).map_err(io_error)
+            };
+        for (id, trj) in self.trajectories.iter().enumerate() {
+            ids.extend(std::iter::repeat_n(id as u64, trj.get_time().len()));
+            times.extend(trj.get_time().iter());
+            for (column, events) in states.iter_mut().zip(trj.get_events().columns()) {
+                column.extend(events.iter().map(|x| *x as u64));
+            }
+            if times.len() >= BATCH_SIZE {
+                write_batch(&mut ids, &mut times, &mut states)?;
+            }
+        }
+        if !times.is_empty() {
+            write_batch(&mut ids, &mut times, &mut states)?;
+        }
+        writer.close().map_err(io_error)?;
+        Ok(())
+    }
+
+    /// Summarize the data available for each node of `net`, without fitting any parameter.
+    ///
+    /// The transitions and the exposure of each node are computed with
+    /// [`sufficient_statistics`] and an empty parent set, thus the trajectories generated under an
+    /// intervention on the node are not taken into account. The weights of the trajectories, if
+    /// any, are ignored.
+    pub fn summary<T: NetworkProcess>(&self, net: &T) -> DatasetSummary {
+        let nodes = net
+            .get_node_indices()
+            .map(|node| {
+                let (M, T) = sufficient_statistics(net, self, node, &BTreeSet::new());
+                let exposure = T.sum();
+                let constant_trajectories = self
+                    .trajectories
+                    .iter()
+                    .filter(|trj| {
+                        //The state observed at the censoring time is not an event.
+                        let n_rows = trj.get_time().len() - trj.is_censored() as usize;
+                        let column = trj.get_events().slice(s![..n_rows, node]);
+                        column.iter().all(|x| *x == column[0])
+                    })
+                    .count();
+                NodeSummary {
+                    label: net.get_node(node).get_label().clone(),
+                    transitions: M.sum(),
+                    exposure,
+                    constant_trajectories,
+                    marginal: if exposure > 0.0 {
+                        T.row(0).mapv(|x| x / exposure)
+                    } else {
+                        Array1::zeros(T.shape()[1])
+                    },
+                }
+            })
+            .collect();
+        DatasetSummary {
+            n_trajectories: self.trajectories.len(),
+            observation_time: self
+                .trajectories
+                .iter()
+                .map(|trj| trj.get_time()[trj.get_time().len() - 1] - trj.get_time()[0])
+                .sum(),
+            nodes,
+        }
+    }
+
+    /// Apply [`Trajectory::normalize`] to each trajectory of the dataset.
+    pub fn normalize(&self) -> Dataset {
+        Dataset {
+            trajectories: self.trajectories.iter().map(|x| x.normalize()).collect(),
+            weights: self.weights.clone(),
+        }
+    }
+
+    /// Reconstruct a dataset from a log of state changes.
+    ///
+    /// Each trajectory starts at `t_start` in `initial_state` and ends at `t_end`; every entry
+    /// of `events` moves a variable to a new state. Entries with the same trajectory and time are
+    /// merged into a single row, so the state of the variables is always the full joint state.
+    ///
+    /// # Arguments
+    ///
+    /// * `net` - network providing the labels and the cardinalities of the variables.
+    /// * `trajectories` - bounds and initial state of each trajectory, indexed by trajectory id.
+    /// * `events` - the log; within each trajectory the entries must be sorted by time.
+    ///
+    /// # Errors
+    ///
+    /// Invalid initial states, entries referring to unknown trajectories or variables, states
+    /// exceeding the cardinality of the variable and out-of-order entries are reported with the
+    /// offending record.
+    pub fn from_event_log<T: process::NetworkProcess>(
+        net: &T,
+        trajectories: &[EventLogTrajectory],
+        events: &[EventLogEntry],
+    ) -> Result<Dataset, DatasetError> {
+        if trajectories.is_empty() {
+            return Err(DatasetError::EmptyDataset(String::from(
+                "At least one trajectory is required",
+            )));
+        }
+        let node_of: HashMap<&String, usize> = net
+            .get_node_indices()
+            .map(|x| (net.get_node(x).get_label(), x))
+            .collect();
+        let cardinality = |node: usize| net.get_node(node).get_reserved_space_as_parent();
+
+        //Time instants and joint states of each trajectory.
+        let mut rows: Vec<(Vec<f64>, Vec<Vec<usize>>)> = Vec::with_capacity(trajectories.len());
+        for (id, trj) in trajectories.iter().enumerate() {
+            if trj.initial_state.len() != net.get_number_of_nodes()
+                || trj
+                    .initial_state
+                    .iter()
+                    .enumerate()
+                    .any(|(node, state)| *state >= cardinality(node))
+                || trj.t_start.partial_cmp(&trj.t_end) != Some(Ordering::Less)
+            {
+                return Err(DatasetError::InvalidValue(format!(
+                    "trajectory {}: invalid bounds or initial state {:?}",
+                    id, trj
+                )));
+            }
+            rows.push((vec![trj.t_start], vec![trj.initial_state.clone()]));
+        }
+
+        for (idx, event) in events.iter().enumerate() {
+            let error = |message: &str| {
+                DatasetError::InvalidValue(format!("event {} {:?}: {}", idx, event, message))
+            };
+            let trj = trajectories
+                .get(event.trajectory_id)
+                .ok_or_else(|| error("unknown trajectory"))?;
+            let node = *node_of
+                .get(&event.variable)
+                .ok_or_else(|| error("unknown variable"))?;
+            if event.new_state >= cardinality(node) {
+                return Err(error("state exceeding the cardinality of the variable"));
+            }
+            let (time, states) = &mut rows[event.trajectory_id];
+            let last_time = *time.last().unwrap();
+            if event.time < last_time || event.time <= trj.t_start {
+                return Err(error("out of order"));
+            }
+            if event.time > trj.t_end {
+                return Err(error("after the end of the trajectory"));
+            }
+            if event.time > last_time {
+                let state = states.last().unwrap().clone();
+                time.push(event.time);
+                states.push(state);
+            }
+            states.last_mut().unwrap()[node] = event.new_state;
+        }
+
+        let n_variables = net.get_number_of_nodes();
+        Ok(Dataset::new(
+            rows.into_iter()
+                .zip(trajectories.iter())
+                .map(|((mut time, mut states), trj)| {
+                    //The last row marks the end of the trajectory.
+                    if *time.last().unwrap() < trj.t_end {
+                        let state = states.last().unwrap().clone();
+                        time.push(trj.t_end);
+                        states.push(state);
+                    }
+                    let n_rows = time.len();
+                    Trajectory::new(
+                        Array1::from_vec(time),
+                        Array2::from_shape_vec(
+                            (n_rows, n_variables),
+                            states.into_iter().flatten().collect(),
+                        )
+                        .unwrap(),
+                    )
+                })
+                .collect(),
+        ))
+    }
+
+    /// Convert the dataset into a list of transitions.
+    ///
+    /// A transition is recorded for each variable changing state between two consecutive rows of
+    /// a trajectory. The records are ordered by trajectory, then by time and then by node.
+    ///
+    /// # Arguments
+    ///
+    /// * `net` - optional network used to check the number of variables and their states.
+    /// * `split_simultaneous` - if `true`, a row where several variables change state produces
+    ///   one record per variable; otherwise it is rejected with an error.
+    pub fn to_event_list<T: process::NetworkProcess>(
+        &self,
+        net: Option<&T>,
+        split_simultaneous: bool,
+    ) -> Result<Vec<EventRecord>, DatasetError> {
+        if let Some(net) = net {
+            self.validate_against(net)?;
+        }
+        let mut records = vec![];
+        for (trajectory_id, trj) in self.trajectories.iter().enumerate() {
+            let time = trj.get_time();
+            let events = trj.get_events();
+            //Time at which each variable entered its current state.
+            let mut t_start: Vec<f64> = vec![time[0]; events.shape()[1]];
+            for row in 1..time.len() {
+                let changed: Vec<usize> = (0..events.shape()[1])
+                    .filter(|node| events[[row, *node]] != events[[row - 1, *node]])
+                    .collect();
+                if changed.len() > 1 && !split_simultaneous {
+                    return Err(DatasetError::InvalidValue(format!(
+                        "trajectory {}, row {}: the variables {:?} change state simultaneously",
+                        trajectory_id, row, changed
+                    )));
+                }
+                for node in changed {
+                    records.push(EventRecord {
+                        trajectory_id,
+                        t_start: t_start[node],
+                        t_end: time[row],
+                        node,
+                        from_state: events[[row - 1, node]],
+                        to_state: events[[row, node]],
+                    });
+                    t_start[node] = time[row];
+                }
+            }
+        }
+        Ok(records)
+    }
+}
+
+/// Bounds and initial state of a trajectory read by [`Dataset::from_event_log`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct EventLogTrajectory {
+    pub t_start: f64,
+    pub t_end: f64,
+    pub initial_state: Vec<usize>,
+}
+
+/// A record of the log read by [`Dataset::from_event_log`]: at `time` the variable labelled
+/// `variable` moves to `new_state`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EventLogEntry {
+    pub trajectory_id: usize,
+    pub time: f64,
+    pub variable: String,
+    pub new_state: usize,
+}
+
+/// A single transition of a variable, as returned by [`Dataset::to_event_list`].
+///
+/// # Attributes
+///
+/// * `trajectory_id` - index of the trajectory in the dataset.
+/// * `t_start` - time at which the variable entered `from_state`.
+/// * `t_end` - time of the transition.
+/// * `node` - index of the variable.
+/// * `from_state` - state left by the variable.
+/// * `to_state` - state entered by the variable.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EventRecord {
+    pub trajectory_id: usize,
+    pub t_start: f64,
+    pub t_end: f64,
+    pub node: usize,
+    pub from_state: usize,
+    pub to_state: usize,
+}
+
+/// Write a list of transitions as CSV, with the columns `trajectory_id`, `t_start`, `t_end`,
+/// `node`, `from_state` and `to_state`.
+pub fn event_list_to_csv<W: io::Write>(
+    records: &[EventRecord],
+    writer: W,
+) -> Result<(), DatasetError> {
+    let io_error = |e: csv::Error| DatasetError::Io(e.to_string());
+    let mut writer = csv::Writer::from_writer(writer);
+    writer
+        .write_record([
+            "trajectory_id",
+            "t_start",
+            "t_end",
+            "node",
+            "from_state",
+            "to_state",
+        ])
+        .map_err(io_error)?;
+    for record in records.iter() {
+        writer
+            .write_record([
+                record.trajectory_id.to_string(),
+                record.t_start.to_string(),
+                record.t_end.to_string(),
+                record.node.to_string(),
+                record.from_state.to_string(),
+                record.to_state.to_string(),
+            ])
+            .map_err(io_error)?;
+    }
+    writer.flush().map_err(|e| DatasetError::Io(e.to_string()))
+}
+
+//Unvalidated representations used for deserialization: the conversions apply the same checks of
+//the constructors but return an error instead of panicking.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct TrajectoryData {
+    time: Array1<f64>,
+    events: Array2<usize>,
+    intervention: Option<(usize, usize)>,
+    #[serde(default)]
+    censored: bool,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<TrajectoryData> for Trajectory {
+    type Error = String;
+
+    fn try_from(data: TrajectoryData) -> Result<Trajectory, String> {
+        if data.time.shape()[0] != data.events.shape()[0] {
+            return Err(String::from(
+                "time.shape[0] must be equal to events.shape[0]",
+            ));
+        }
+        if let Some((node, state)) = data.intervention {
+            if node >= data.events.shape()[1] {
+                return Err(format!("The intervened node {} does not exist", node));
+            }
+            if data.events.column(node).iter().any(|x| *x != state) {
+                return Err(format!(
+                    "The intervened node {} must be clamped to {}",
+                    node, state
+                ));
+            }
+        }
+        Ok(Trajectory {
+            time: data.time,
+            events: data.events,
+            intervention: data.intervention,
+            censored: data.censored,
+        })
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct DatasetData {
+    trajectories: Vec<Trajectory>,
+    #[serde(default)]
+    weights: Option<Array1<f64>>,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<DatasetData> for Dataset {
+    type Error = String;
+
+    fn try_from(data: DatasetData) -> Result<Dataset, String> {
+        let trajectories = data.trajectories;
+        if trajectories
+            .iter()
+            .any(|x| trajectories[0].get_events().shape()[1] != x.get_events().shape()[1])
+        {
+            return Err(String::from(
+                "All the trajectories mus represents the same number of variables",
+            ));
+        }
+        if let Some(weights) = &data.weights {
+            if weights.len() != trajectories.len() {
+                return Err(String::from("A weight for each trajectory is required"));
+            }
+            if weights.iter().any(|x| !x.is_finite() || *x < 0.0) {
+                return Err(String::from("The weights must be finite and >= 0.0"));
+            }
+        }
+        Ok(Dataset {
+            trajectories,
+            weights: data.weights,
+        })
+    }
+}
+
+/// Data available for a node of a [`Dataset`], see [`Dataset::summary`].
+///
+/// # Attributes
+///
+/// * `label` - label of the node.
+/// * `transitions` - number of transitions of the node.
+/// * `exposure` - total residence time of the node.
+/// * `constant_trajectories` - number of trajectories in which the node never changes state.
+/// * `marginal` - fraction of the residence time spent in each state; all zeros if the exposure
+///   is zero.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NodeSummary {
+    pub label: String,
+    pub transitions: usize,
+    pub exposure: f64,
+    pub constant_trajectories: usize,
+    pub marginal: Array1<f64>,
+}
+
+/// Result of [`Dataset::summary`]: one summary per node, ordered by node index.
+///
+/// # Attributes
+///
+/// * `n_trajectories` - number of trajectories of the dataset.
+/// * `observation_time` - sum of the durations of the trajectories.
+/// * `nodes` - summary of each node.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DatasetSummary {
+    pub n_trajectories: usize,
+    pub observation_time: f64,
+    pub nodes: Vec<NodeSummary>,
+}
+
+impl fmt::Display for DatasetSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Trajectories: {}, observation time: {:.3}",
+            self.n_trajectories, self.observation_time
+        )?;
+        let width = self
+            .nodes
+            .iter()
+            .map(|x| x.label.len())
+            .chain(std::iter::once(4))
+            .max()
+            .unwrap();
+        writeln!(
+            f,
+            "{:<width$}  {:>11}  {:>10}  {:>8}  marginal",
+            "node",
+            "transitions",
+            "exposure",
+            "constant",
+            width = width
+        )?;
+        for node in self.nodes.iter() {
+            writeln!(
+                f,
+                "{:<width$}  {:>11}  {:>10.3}  {:>8}  [{}]",
+                node.label,
+                node.transitions,
+                node.exposure,
+                node.constant_trajectories,
+                node.marginal
+                    .iter()
+                    .map(|x| format!("{:.3}", x))
+                    .collect::<Vec<String>>()
+                    .join(", "),
+                width = width
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Sufficient statistics `(M, T)` of a node given a parent set: the number of transitions and
+/// the residence time for each parent configuration (see
+/// [`sufficient_statistics`](crate::parameter_learning::sufficient_statistics)).
+pub type SuffStats = (Array3<usize>, Array2<f64>);
+
+/// Key of a [`SuffStatsCache`] entry.
+///
+/// Besides the node and the parent set, the key contains the domain cardinality of the node
+/// followed by the cardinalities of its parents (in ascending order of parent index), so that the
+/// statistics are never reused with an incompatible network.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SuffStatsKey {
+    pub node: usize,
+    pub parent_set: BTreeSet<usize>,
+    pub cardinalities: Vec<usize>,
+}
+
+impl SuffStatsKey {
+    pub fn new<T: NetworkProcess>(
+        net: &T,
+        node: usize,
+        parent_set: &BTreeSet<usize>,
+    ) -> SuffStatsKey {
+        SuffStatsKey {
+            node,
+            parent_set: parent_set.clone(),
+            cardinalities: std::iter::once(node)
+                .chain(parent_set.iter().cloned())
+                .map(|x| net.get_node(x).get_reserved_space_as_parent())
+                .collect(),
+        }
+    }
+}
+
+/// Thread-safe store of precomputed sufficient statistics.
+///
+/// The statistics are keyed by [`SuffStatsKey`] and are computed lazily, the first time they are
+/// requested, from a [`DatasetStatistics`] built with the first request. The store can be saved
+/// to and loaded from disk (see [`save`](Self::save) and [`load`](Self::load)). The store can be
+/// shared (wrapped in an `Arc`) between score functions, hypothesis tests and the per-node
+/// parallel loops of the structure learning algorithms, avoiding multiple passes over the dataset
+/// for the same key.
+///
+/// **Note:** a store is bound to the dataset used to fill it; using the same instance with a
+/// different dataset returns stale statistics.
+///
+/// # Example
+///
+/// ```rust
+/// # use std::collections::BTreeSet;
+/// # use ndarray::{arr1, arr2};
+/// # use reCTBN::params;
+/// # use reCTBN::process::NetworkProcess;
+/// # use reCTBN::process::ctbn::CtbnNetwork;
+/// use reCTBN::tools::{Dataset, SuffStatsCache, Trajectory};
+/// # let mut domain = BTreeSet::new();
+/// # domain.insert(String::from("A"));
+/// # domain.insert(String::from("B"));
+/// # let param = params::DiscreteStatesContinousTimeParams::new("n1".to_string(), domain);
+/// # let mut net = CtbnNetwork::new();
+/// # let n1 = net.add_node(params::Params::DiscreteStatesContinousTime(param)).unwrap();
+/// let trj = Trajectory::new(arr1(&[0.0, 0.1, 0.3]), arr2(&[[0], [1], [1]]));
+/// let dataset = Dataset::new(vec![trj]);
+///
+/// let cache = SuffStatsCache::new();
+/// let stats = cache.get_or_compute(&net, &dataset, n1, &BTreeSet::new());
+/// assert_eq!(1, stats.0[[0, 0, 1]]);
+/// assert_eq!(1, cache.len());
+/// ```
+#[derive(Default)]
+pub struct SuffStatsCache {
+    stats: RwLock<HashMap<SuffStatsKey, Arc<SuffStats>>>,
+    dataset_statistics: RwLock<Option<Arc<DatasetStatistics>>>,
+}
+
+impl SuffStatsCache {
+    pub fn new() -> SuffStatsCache {
+        SuffStatsCache {
+            stats: RwLock::new(HashMap::new()),
+            dataset_statistics: RwLock::new(None),
+        }
+    }
+
+    /// Create a store computing the statistics from an already built `DatasetStatistics`, e.g.
+    /// shared with other stores bound to the same dataset.
+    pub fn with_dataset_statistics(dataset_statistics: Arc<DatasetStatistics>) -> SuffStatsCache {
+        SuffStatsCache {
+            stats: RwLock::new(HashMap::new()),
+            dataset_statistics: RwLock::new(Some(dataset_statistics)),
+        }
+    }
+
+    /// Get the `DatasetStatistics` of `dataset`, building it if it does not exist yet.
+    pub fn get_dataset_statistics(&self, dataset: &Dataset) -> Arc<DatasetStatistics> {
+        if let Some(dataset_statistics) = self.dataset_statistics.read().unwrap().as_ref() {
+            return dataset_statistics.clone();
+        }
+        self.dataset_statistics
+            .write()
+            .unwrap()
+            .get_or_insert_with(|| Arc::new(DatasetStatistics::new(dataset)))
+            .clone()
+    }
+
+    /// Get the sufficient statistics of `node` given `parent_set`, computing them from `dataset`
+    /// if they are not already stored.
+    ///
+    /// # Arguments
+    ///
+    /// * `net`: a `NetworkProcess` instance
+    /// * `dataset`: the dataset bound to this store
+    /// * `node`: the node index for which we want the sufficient statistics
+    /// * `parent_set`: the set of nodes (identified by indices) used as parents of `node`
+    ///
+    /// # Return
+    ///
+    /// * A shared reference to the tuple `(M, T)`.
+    pub fn get_or_compute<T: NetworkProcess>(
+        &self,
+        net: &T,
+        dataset: &Dataset,
+        node: usize,
+        parent_set: &BTreeSet<usize>,
+    ) -> Arc<SuffStats> {
+        let key = SuffStatsKey::new(net, node, parent_set);
+        if let Some(stats) = self.stats.read().unwrap().get(&key) {
+            return stats.clone();
+        }
+        //The statistics are computed without holding the lock; if two threads compute the same
+        //key, the first inserted value is kept.
+        let stats = Arc::new(
+            self.get_dataset_statistics(dataset)
+                .sufficient_statistics(net, node, parent_set),
+        );
+        self.stats
+            .write()
+            .unwrap()
+            .entry(key)
+            .or_insert(stats)
+            .clone()
+    }
+
+    /// Get the sufficient statistics of `node` given `parent_set`, if stored for a compatible
+    /// network.
+    pub fn get<T: NetworkProcess>(
+        &self,
+        net: &T,
+        node: usize,
+        parent_set: &BTreeSet<usize>,
+    ) -> Option<Arc<SuffStats>> {
+        self.stats
+            .read()
+            .unwrap()
+            .get(&SuffStatsKey::new(net, node, parent_set))
+            .cloned()
+    }
+
+    /// Store the statistics for `key`, checking their shape against the cardinalities and
+    /// replacing any previous value.
+    pub fn insert(
+        &self,
+        key: SuffStatsKey,
+        transitions: Array3<usize>,
+        residence_time: Array2<f64>,
+    ) -> Result<(), ParameterLearningError> {
+        if key.cardinalities.len() != key.parent_set.len() + 1 {
+            return Err(ParameterLearningError::InvalidSufficientStatistics(
+                format!(
+                    "Statistics of node {} with {} parents have cardinalities {:?}",
+                    key.node,
+                    key.parent_set.len(),
+                    key.cardinalities
+                ),
+            ));
+        }
+        let n_configurations: usize = key.cardinalities[1..].iter().product();
+        let domain = key.cardinalities[0];
+        if transitions.shape() != [n_configurations, domain, domain]
+            || residence_time.shape() != [n_configurations, domain]
+        {
+            return Err(ParameterLearningError::InvalidSufficientStatistics(
+                format!(
+                "Statistics of node {} with shapes {:?} and {:?} do not match the cardinalities \
+                 {:?}",
+                key.node,
+                transitions.shape(),
+                residence_time.shape(),
+                key.cardinalities
+            ),
+            ));
+        }
+        self.stats
+            .write()
+            .unwrap()
+            .insert(key, Arc::new((transitions, residence_time)));
+        Ok(())
+    }
+
+    /// Number of keys currently stored.
+    pub fn len(&self) -> usize {
+        self.stats.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove all the stored statistics, including the `DatasetStatistics`.
+    pub fn clear(&self) {
+        self.stats.write().unwrap().clear();
+        *self.dataset_statistics.write().unwrap() = None;
+    }
+
+    /// Serialize the stored statistics as JSON.
+    ///
+    /// The document contains a `version` (currently `1`) and a list of `statistics`, ordered by
+    /// key, each one with the fields of the [`SuffStatsKey`] plus `transitions` and
+    /// `residence_time` stored as flat lists in row-major order.
+    pub fn to_json(&self) -> String {
+        let stats = self.stats.read().unwrap();
+        let mut keys: Vec<&SuffStatsKey> = stats.keys().collect();
+        keys.sort();
+        let statistics: Vec<Value> = keys
+            .into_iter()
+            .map(|key| {
+                let (M, T) = &*stats[key];
+                json!({
+                    "node": key.node,
+                    "parent_set": key.parent_set,
+                    "cardinalities": key.cardinalities,
+                    "transitions": M.iter().collect::<Vec<&usize>>(),
+                    "residence_time": T.iter().collect::<Vec<&f64>>(),
+                })
+            })
+            .collect();
+        json!({"version": 1, "statistics": statistics}).to_string()
+    }
+
+    /// Build a store from the JSON produced by [`to_json`](Self::to_json).
+    ///
+    /// The store is bound to the dataset the statistics were computed from: it is up to the
+    /// caller to use it only with that dataset.
+    pub fn from_json(json: &str) -> Result<SuffStatsCache, ParameterLearningError> {
+        let invalid =
+            |message: String| ParameterLearningError::InvalidSufficientStatistics(message);
+        let value: Value = serde_json::from_str(json).map_err(|e| invalid(e.to_string()))?;
+        if value["version"] != 1 {
+            return Err(invalid(format!("Unsupported version {}", value["version"])));
+        }
+        let entries = value["statistics"]
+            .as_array()
+            .ok_or_else(|| invalid(String::from("Missing list of statistics")))?;
+        let usize_list = |value: &Value| -> Option<Vec<usize>> {
+            value
+                .as_array()?
+                .iter()
+                .map(|x| x.as_u64().map(|x| x as usize))
+                .collect()
+        };
+
+        let cache = SuffStatsCache::new();
+        for (idx, entry) in entries.iter().enumerate() {
+            let malformed = || invalid(format!("Malformed entry {}", idx));
+            let key = SuffStatsKey {
+                node: entry["node"].as_u64().ok_or_else(malformed)? as usize,
+                parent_set: usize_list(&entry["parent_set"])
+                    .ok_or_else(malformed)?
+                    .into_iter()
+                    .collect(),
+                cardinalities: usize_list(&entry["cardinalities"]).ok_or_else(malformed)?,
+            };
+            if key.cardinalities.len() != key.parent_set.len() + 1 {
+                return Err(malformed());
+            }
+            let n_configurations: usize = key.cardinalities[1..].iter().product();
+            let domain = key.cardinalities[0];
+            let transitions = usize_list(&entry["transitions"])
+                .and_then(|x| Array3::from_shape_vec((n_configurations, domain, domain), x).ok())
+                .ok_or_else(malformed)?;
+            let residence_time = entry["residence_time"]
+                .as_array()
+                .and_then(|x| x.iter().map(|x| x.as_f64()).collect::<Option<Vec<f64>>>())
+                .and_then(|x| Array2::from_shape_vec((n_configurations, domain), x).ok())
+                .ok_or_else(malformed)?;
+            cache.insert(key, transitions, residence_time)?;
+        }
+        Ok(cache)
+    }
+
+    /// Save the stored statistics to `path` (see [`to_json`](Self::to_json)).
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ParameterLearningError> {
+        fs::write(path, self.to_json()).map_err(|e| ParameterLearningError::Io(e.to_string()))
+    }
+
+    /// Load a store saved with [`save`](Self::save).
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<SuffStatsCache, ParameterLearningError> {
+        let json =
+            fs::read_to_string(path).map_err(|e| ParameterLearningError::Io(e.to_string()))?;
+        SuffStatsCache::from_json(&json)
+    }
 }
 
 pub fn trajectory_generator<T: process::NetworkProcess>(
@@ -65,11 +1465,73 @@ pub fn trajectory_generator<T: process::NetworkProcess>(
     t_end: f64,
     seed: Option<u64>,
 ) -> Dataset {
-    //Tmp growing vector containing generated trajectories.
+    //Random Generator object
+    let mut sampler = ForwardSampler::new(net, seed, None);
+    //Return a dataset object with the sampled trajectories.
+    Dataset::new(sample_trajectories(&mut sampler, n_trajectories, t_end))
+}
+
+/// Intervention regime used by [`interventional_trajectory_generator`].
+///
+/// # Attributes
+///
+/// * `intervention` - `Some((node, state))` clamps `node` to `state` (do-operator); `None`
+///   generates observational trajectories.
+/// * `n_trajectories` - number of trajectories to generate under this regime.
+/// * `t_end` - ending time of each trajectory.
+#[derive(Clone, Debug)]
+pub struct InterventionSpec {
+    pub intervention: Option<(usize, usize)>,
+    pub n_trajectories: u64,
+    pub t_end: f64,
+}
+
+/// Generate a dataset mixing observational and interventional trajectories.
+///
+/// Each trajectory generated under an intervention records it (see
+/// [`Trajectory::get_intervention`]), so that [`sufficient_statistics`] can exclude it when
+/// estimating the parameters of the intervened node.
+///
+/// # Arguments
+///
+/// * `net` - the `NetworkProcess` used to generate the trajectories.
+/// * `specs` - the regimes to sample; the trajectories are stored in the same order.
+/// * `seed` - random seed used to make the generation reproducible.
+pub fn interventional_trajectory_generator<T: process::NetworkProcess>(
+    net: &T,
+    specs: &[InterventionSpec],
+    seed: Option<u64>,
+) -> Dataset {
     let mut trajectories: Vec<Trajectory> = Vec::new();
 
-    //Random Generator object
+    //A single sampler is shared by all the regimes to keep a single random stream.
     let mut sampler = ForwardSampler::new(net, seed, None);
+    for spec in specs {
+        sampler.set_intervention(
+            spec.intervention
+                .map(|(node, state)| (node, params::StateType::Discrete(state))),
+        );
+        let sampled = sample_trajectories(&mut sampler, spec.n_trajectories, spec.t_end);
+        match spec.intervention {
+            None => trajectories.extend(sampled),
+            Some(intervention) => trajectories.extend(
+                sampled
+                    .into_iter()
+                    .map(|trj| Trajectory::new_interventional(trj.time, trj.events, intervention)),
+            ),
+        }
+    }
+    Dataset::new(trajectories)
+}
+
+fn sample_trajectories<S: Sampler>(
+    sampler: &mut S,
+    n_trajectories: u64,
+    t_end: f64,
+) -> Vec<Trajectory> {
+    //Tmp growing vector containing generated trajectories.
+    let mut trajectories: Vec<Trajectory> = Vec::new();
+
     //Each iteration generate one trajectory
     for _ in 0..n_trajectories {
         //History of all the moments in which something changed
@@ -98,20 +1560,504 @@ pub fn trajectory_generator<T: process::NetworkProcess>(
             Array::from_vec(time),
             Array2::from_shape_vec(
                 (events.len(), events.last().unwrap().len()),
-                events
-                    .iter()
-                    .flatten()
-                    .map(|x| match x {
-                        params::StateType::Discrete(x) => x.clone(),
-                    })
-                    .collect(),
+                events.iter().flatten().map(|x| usize::from(*x)).collect(),
             )
             .unwrap(),
         ));
         sampler.reset();
     }
-    //Return a dataset object with the sampled trajectories.
-    Dataset::new(trajectories)
+    trajectories
+}
+
+/// Generate trajectories observed through a noisy sensor.
+///
+/// The true trajectories are sampled with [`trajectory_generator`]; then, at each event, the
+/// observed state of each node is drawn from the row of its confusion matrix corresponding to the
+/// true state. The last row of each trajectory (the one added at `t_end`) repeats the previous
+/// observation.
+///
+/// # Arguments
+///
+/// * `net` - the `NetworkProcess` used to generate the true trajectories.
+/// * `n_trajectories` - number of trajectories to generate.
+/// * `t_end` - ending time of each trajectory.
+/// * `noise` - one confusion matrix per node: `noise[node][[true_state, observed_state]]` is the
+///   probability of observing `observed_state` when the node is in `true_state`. Each matrix must
+///   be row-stochastic and its shape must match the cardinality of the node.
+/// * `seed` - random seed used to make the generation reproducible.
+///
+/// # Return
+///
+/// * A tuple containing the clean `Dataset` and the noisy one; the two datasets share the same
+///   time instants.
+pub fn noisy_trajectory_generator<T: process::NetworkProcess>(
+    net: &T,
+    n_trajectories: u64,
+    t_end: f64,
+    noise: &[Array2<f64>],
+    seed: Option<u64>,
+) -> (Dataset, Dataset) {
+    //Each node must have its own confusion matrix.
+    if noise.len() != net.get_number_of_nodes() {
+        panic!(
+            "Expected {} confusion matrices, got {}",
+            net.get_number_of_nodes(),
+            noise.len()
+        );
+    }
+    for (node, confusion_matrix) in noise.iter().enumerate() {
+        let cardinality = net.get_node(node).get_reserved_space_as_parent();
+        if confusion_matrix.shape() != [cardinality, cardinality] {
+            panic!(
+                "The confusion matrix of node {} must have shape [{}, {}], got {:?}",
+                node,
+                cardinality,
+                cardinality,
+                confusion_matrix.shape()
+            );
+        }
+        if confusion_matrix.iter().any(|x| *x < 0.0)
+            || confusion_matrix
+                .sum_axis(Axis(1))
+                .iter()
+                .any(|x| f64::abs(x - 1.0) > f64::EPSILON.sqrt())
+        {
+            panic!(
+                "The confusion matrix of node {} must be row-stochastic",
+                node
+            );
+        }
+    }
+
+    let clean_dataset = trajectory_generator(net, n_trajectories, t_end, seed);
+
+    //Random Generator object. A different stream is used to avoid any correlation with the
+    //sampler initialized with the same seed.
+    let mut rng: ChaCha8Rng = match seed {
+        Some(seed) => SeedableRng::seed_from_u64(seed),
+        None => SeedableRng::from_entropy(),
+    };
+    rng.set_stream(1);
+
+    let noisy_trajectories = clean_dataset
+        .get_trajectories()
+        .iter()
+        .map(|trj| {
+            let mut events = trj.get_events().clone();
+            let n_events = events.shape()[0];
+            for idx in 0..n_events - 1 {
+                for (node, confusion_matrix) in noise.iter().enumerate() {
+                    let urand: f64 = rng.gen_range(0.0..1.0);
+                    let mut cumulative = 0.0;
+                    let true_state = events[[idx, node]];
+                    let mut observed_state = confusion_matrix.shape()[1] - 1;
+                    for (state, p) in confusion_matrix.row(true_state).iter().enumerate() {
+                        cumulative += p;
+                        if urand < cumulative {
+                            observed_state = state;
+                            break;
+                        }
+                    }
+                    events[[idx, node]] = observed_state;
+                }
+            }
+            //The last row repeats the last observation.
+            if n_events > 1 {
+                let last_observation = events.row(n_events - 2).to_owned();
+                events.row_mut(n_events - 1).assign(&last_observation);
+            }
+            Trajectory {
+                time: trj.get_time().clone(),
+                events,
+                intervention: trj.get_intervention(),
+                censored: trj.is_censored(),
+            }
+        })
+        .collect();
+
+    (clean_dataset, Dataset::new(noisy_trajectories))
+}
+
+/// Render the structure of a network as a Graphviz digraph.
+///
+/// Nodes are identified by their index and labelled with [`ParamsTrait::get_label`]. When a
+/// `reference` network is supplied, edges present in both networks are drawn in black (true
+/// positives), edges present only in `net` in red (false positives) and edges present only in
+/// `reference` as dashed gray lines (missing).
+///
+/// # Arguments
+///
+/// * `net` - the network to render.
+/// * `reference` - optional ground truth with the same nodes of `net`.
+pub fn to_dot<T: process::NetworkProcess>(net: &T, reference: Option<&T>) -> String {
+    if let Some(reference) = reference {
+        if reference.get_number_of_nodes() != net.get_number_of_nodes() {
+            panic!("The reference network must have the same nodes of the network");
+        }
+    }
+    let mut dot = String::from("digraph {\n");
+    for node in net.get_node_indices() {
+        dot.push_str(&format!(
+            "    {} [label=\"{}\"];\n",
+            node,
+            net.get_node(node)
+                .get_label()
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+        ));
+    }
+    for child in net.get_node_indices() {
+        for parent in net.get_node_indices() {
+            let attributes = match (
+                net.has_edge(parent, child),
+                reference.map(|x| x.has_edge(parent, child)),
+            ) {
+                (true, None) => "",
+                (true, Some(true)) => " [color=\"black\"]",
+                (true, Some(false)) => " [color=\"red\"]",
+                (false, Some(true)) => " [color=\"gray\", style=\"dashed\"]",
+                (false, _) => continue,
+            };
+            dot.push_str(&format!("    {} -> {}{};\n", parent, child, attributes));
+        }
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// Learn both the structure and the parameters of a network.
+///
+/// The structure is learned with `structure_algo`, then the parameters of every node are fitted
+/// with `param_algo` using the learned parent sets and, finally, validated.
+///
+/// # Arguments
+///
+/// * `structure_algo` - the structure learning algorithm.
+/// * `param_algo` - the parameter learning method.
+/// * `net_template` - a network containing the nodes to learn; its edges are discarded.
+/// * `dataset` - a dataset compatible with `net_template`.
+///
+/// # Return
+///
+/// * A fully parameterized network, or the first error raised by the parameter validation.
+///
+/// # Panics
+///
+/// * If `dataset` is not compatible with `net_template` (see
+///   [`fit_transform`](StructuralLearningAlgorithm::fit_transform)).
+pub fn learn_model<A: StructuralLearningAlgorithm, P: ParameterLearning>(
+    structure_algo: &A,
+    param_algo: &P,
+    net_template: CtbnNetwork,
+    dataset: &Dataset,
+) -> Result<CtbnNetwork, params::ParamsError> {
+    let mut net = match structure_algo.fit_transform(net_template, dataset) {
+        Ok(net) => net,
+        Err(e) => panic!("{}", e),
+    };
+    let learned_params: Vec<params::Params> = net
+        .get_node_indices()
+        .map(|node| param_algo.fit(&net, dataset, node, None))
+        .collect();
+    for (node, node_params) in learned_params.into_iter().enumerate() {
+        node_params.validate_params()?;
+        *net.get_node_mut(node) = node_params;
+    }
+    Ok(net)
+}
+
+/// Minimum number of transitions that must be observed for a configuration (node, parent
+/// configuration, state) to be compared by [`simulation_check`].
+pub const SIMULATION_CHECK_MIN_TRANSITIONS: usize = 30;
+
+/// Result of [`simulation_check`].
+///
+/// # Attributes
+///
+/// * `max_relative_deviation` - maximum relative deviation between the empirical rates and the
+///   non-zero CIM entries, over the configurations that were sufficiently sampled.
+/// * `worst_entry` - the `(node, parent_configuration, from_state, to_state)` entry achieving
+///   `max_relative_deviation`.
+/// * `under_sampled` - the `(node, parent_configuration, state)` configurations never visited or
+///   with fewer than [`SIMULATION_CHECK_MIN_TRANSITIONS`] transitions; they are not compared.
+#[derive(Clone, Debug)]
+pub struct SimulationCheckReport {
+    pub max_relative_deviation: f64,
+    pub worst_entry: Option<(usize, usize, usize, usize)>,
+    pub under_sampled: Vec<(usize, usize, usize)>,
+}
+
+impl SimulationCheckReport {
+    /// `true` if the maximum relative deviation does not exceed `tolerance`.
+    pub fn passes(&self, tolerance: f64) -> bool {
+        self.max_relative_deviation <= tolerance
+    }
+}
+
+/// Check that the data generated by [`trajectory_generator`] is consistent with the CIMs of
+/// `net`.
+///
+/// The empirical rates `M[u, x, y] / T[u, x]` are computed with [`sufficient_statistics`] using
+/// the parent sets of `net` and compared with the corresponding CIM entries.
+///
+/// # Arguments
+///
+/// * `net` - a network with structure and parameters defined.
+/// * `n_trajectories` - number of trajectories to generate.
+/// * `t_end` - ending time of each trajectory.
+/// * `seed` - random seed used to make the generation reproducible.
+pub fn simulation_check(
+    net: &CtbnNetwork,
+    n_trajectories: u64,
+    t_end: f64,
+    seed: Option<u64>,
+) -> SimulationCheckReport {
+    let dataset = trajectory_generator(net, n_trajectories, t_end, seed);
+    let mut report = SimulationCheckReport {
+        max_relative_deviation: 0.0,
+        worst_entry: None,
+        under_sampled: vec![],
+    };
+    for node in net.get_node_indices() {
+        let params::Params::DiscreteStatesContinousTime(params) = net.get_node(node);
+        if params.get_cim().is_none() {
+            panic!("The CIM of node {} is not initialized", node);
+        }
+        let (M, T) = sufficient_statistics(net, &dataset, node, &net.get_parent_set(node));
+        for ((u, x), t) in T.indexed_iter() {
+            let n_transitions: usize = M.slice(s![u, x, ..]).sum();
+            //Absorbing states are expected to have no transitions.
+            let is_absorbing = params.exit_rate(u, x).unwrap() == 0.0;
+            if *t == 0.0 || (!is_absorbing && n_transitions < SIMULATION_CHECK_MIN_TRANSITIONS) {
+                report.under_sampled.push((node, u, x));
+                continue;
+            }
+            for y in 0..params.get_reserved_space_as_parent() {
+                let expected = params.rate(u, x, y).unwrap();
+                if expected == 0.0 {
+                    continue;
+                }
+                let empirical = if x == y {
+                    -(n_transitions as f64) / t
+                } else {
+                    M[[u, x, y]] as f64 / t
+                };
+                let deviation = f64::abs(empirical - expected) / f64::abs(expected);
+                if deviation > report.max_relative_deviation || report.worst_entry.is_none() {
+                    report.max_relative_deviation = deviation;
+                    report.worst_entry = Some((node, u, x, y));
+                }
+            }
+        }
+    }
+    report
+}
+
+/// Apply a permutation of the variables consistently to a network and to a dataset.
+///
+/// The nodes of the network, its edges and the parent-configuration axis of the parameters of
+/// each node (CIM and, if present, the sufficient statistics) are reindexed, as well as the
+/// columns of the dataset.
+///
+/// # Arguments
+///
+/// * `net` - the network to permute.
+/// * `dataset` - a dataset compatible with `net`.
+/// * `perm` - `perm[i]` is the new index of the variable currently at index `i`.
+///
+/// # Return
+///
+/// * A tuple containing the permuted network and the permuted dataset.
+pub fn permute_variables(
+    net: &CtbnNetwork,
+    dataset: &Dataset,
+    perm: &[usize],
+) -> (CtbnNetwork, Dataset) {
+    let n_nodes = net.get_number_of_nodes();
+    //Check that perm is a permutation of the node indices.
+    let mut inverse_perm = vec![usize::MAX; n_nodes];
+    if perm.len() != n_nodes {
+        panic!("perm must contain {} elements, got {}", n_nodes, perm.len());
+    }
+    for (old_idx, new_idx) in perm.iter().enumerate() {
+        if *new_idx >= n_nodes || inverse_perm[*new_idx] != usize::MAX {
+            panic!("{:?} is not a permutation of the node indices", perm);
+        }
+        inverse_perm[*new_idx] = old_idx;
+    }
+    if let Some(trj) = dataset.get_trajectories().first() {
+        if trj.get_events().shape()[1] != n_nodes {
+            panic!("Dataset and Network must have the same number of variables.")
+        }
+    }
+
+    //Permute the dataset columns.
+    let permuted_dataset = Dataset::new(
+        dataset
+            .get_trajectories()
+            .iter()
+            .map(|trj| {
+                let events = Array2::from_shape_fn(trj.get_events().dim(), |(row, col)| {
+                    trj.get_events()[[row, inverse_perm[col]]]
+                });
+                Trajectory {
+                    time: trj.get_time().clone(),
+                    events,
+                    intervention: trj
+                        .get_intervention()
+                        .map(|(node, state)| (perm[node], state)),
+                    censored: trj.is_censored(),
+                }
+            })
+            .collect(),
+    );
+
+    //Parent sets of the original network (empty if the structure is not initialized).
+    let parent_sets: Vec<BTreeSet<usize>> = net
+        .get_node_indices()
+        .map(|node| match net.get_adj_matrix() {
+            Some(_) => net.get_parent_set(node),
+            None => BTreeSet::new(),
+        })
+        .collect();
+
+    let mut permuted_net = CtbnNetwork::new();
+    for old_idx in inverse_perm.iter() {
+        permuted_net
+            .add_node(net.get_node(*old_idx).clone())
+            .unwrap();
+    }
+    permuted_net.initialize_adj_matrix();
+    for (child, parent_set) in parent_sets.iter().enumerate() {
+        for parent in parent_set.iter() {
+            permuted_net.add_edge(perm[*parent], perm[child]);
+        }
+    }
+
+    //Reindex the parent-configuration axis of the parameters of each node. The index of a
+    //parent configuration is a mixed-radix number whose digits are the states of the parents
+    //ordered by node index, so changing the order of the parents changes the index.
+    for (old_idx, parent_set) in parent_sets.iter().enumerate() {
+        let old_parents: Vec<usize> = parent_set.iter().cloned().collect();
+        let mut new_parents: Vec<usize> = old_parents.clone();
+        new_parents.sort_by_key(|x| perm[*x]);
+        let cardinalities: Vec<usize> = old_parents
+            .iter()
+            .map(|x| net.get_node(*x).get_reserved_space_as_parent())
+            .collect();
+        //Position of each parent (in the old order) inside the new order.
+        let new_position: Vec<usize> = old_parents
+            .iter()
+            .map(|x| new_parents.iter().position(|y| y == x).unwrap())
+            .collect();
+        let n_configurations: usize = cardinalities.iter().product();
+        //old_to_new[u] is the index, in the permuted network, of the parent configuration `u`.
+        let old_to_new: Vec<usize> = (0..n_configurations)
+            .map(|u| {
+                let mut states = vec![0; old_parents.len()];
+                let mut rest = u;
+                for (i, card) in cardinalities.iter().enumerate() {
+                    states[new_position[i]] = rest % card;
+                    rest /= card;
+                }
+                let mut new_u = 0;
+                let mut radix = 1;
+                for (state, parent) in states.iter().zip(new_parents.iter()) {
+                    new_u += state * radix;
+                    radix *= net.get_node(*parent).get_reserved_space_as_parent();
+                }
+                new_u
+            })
+            .collect();
+
+        match (
+            net.get_node(old_idx),
+            permuted_net.get_node_mut(perm[old_idx]),
+        ) {
+            (
+                params::Params::DiscreteStatesContinousTime(old_param),
+                params::Params::DiscreteStatesContinousTime(new_param),
+            ) => {
+                //Tied parameters are indexed by group: only the groups are reindexed.
+                if let Some(groups) = old_param.get_configuration_groups() {
+                    let mut new_groups = groups.clone();
+                    for (u, new_u) in old_to_new.iter().enumerate() {
+                        new_groups[*new_u] = groups[u];
+                    }
+                    new_param.set_configuration_groups(new_groups).unwrap();
+                    if let Some(piecewise_cim) = old_param.get_piecewise_cim() {
+                        new_param.set_piecewise_cim_unchecked(piecewise_cim.clone());
+                    } else if let Some(cim) = old_param.get_cim() {
+                        new_param.set_cim_unchecked(cim.clone());
+                    }
+                    if let Some(transitions) = old_param.get_transitions() {
+                        new_param.set_transitions(transitions.clone());
+                    }
+                    if let Some(residence_time) = old_param.get_residence_time() {
+                        new_param.set_residence_time(residence_time.clone());
+                    }
+                    continue;
+                }
+                let permute_cim = |cim: &Array3<f64>| {
+                    let mut new_cim = cim.clone();
+                    for (u, new_u) in old_to_new.iter().enumerate() {
+                        new_cim
+                            .index_axis_mut(Axis(0), *new_u)
+                            .assign(&cim.index_axis(Axis(0), u));
+                    }
+                    new_cim
+                };
+                //Noisy-OR parameters have one set of multipliers for each parent: only their
+                //order changes, while the sufficient statistics are reindexed below.
+                if let Some(rate_multipliers) = old_param.get_rate_multipliers() {
+                    new_param.set_noisy_or_cim_unchecked(
+                        old_param
+                            .get_cim()
+                            .as_ref()
+                            .unwrap()
+                            .index_axis(Axis(0), 0)
+                            .to_owned(),
+                        new_parents
+                            .iter()
+                            .map(|x| {
+                                rate_multipliers[old_parents.iter().position(|y| y == x).unwrap()]
+                                    .clone()
+                            })
+                            .collect(),
+                    );
+                } else if let Some(piecewise_cim) = old_param.get_piecewise_cim() {
+                    new_param.set_piecewise_cim_unchecked(
+                        piecewise_cim
+                            .iter()
+                            .map(|(boundary, cim)| (*boundary, permute_cim(cim)))
+                            .collect(),
+                    );
+                } else if let Some(cim) = old_param.get_cim() {
+                    new_param.set_cim_unchecked(permute_cim(cim));
+                }
+                if let Some(transitions) = old_param.get_transitions() {
+                    let mut new_transitions = transitions.clone();
+                    for (u, new_u) in old_to_new.iter().enumerate() {
+                        new_transitions
+                            .index_axis_mut(Axis(0), *new_u)
+                            .assign(&transitions.index_axis(Axis(0), u));
+                    }
+                    new_param.set_transitions(new_transitions);
+                }
+                if let Some(residence_time) = old_param.get_residence_time() {
+                    let mut new_residence_time = residence_time.clone();
+                    for (u, new_u) in old_to_new.iter().enumerate() {
+                        new_residence_time
+                            .index_axis_mut(Axis(0), *new_u)
+                            .assign(&residence_time.index_axis(Axis(0), u));
+                    }
+                    new_param.set_residence_time(new_residence_time);
+                }
+            }
+        }
+    }
+
+    (permuted_net, permuted_dataset)
 }
 
 pub trait RandomGraphGenerator {
@@ -172,10 +2118,7 @@ pub trait RandomGraphGenerator {
 /// // Generate the graph directly on the network
 /// structure_generator.generate_graph(&mut net);
 /// # // Count all the edges generated in the network
-/// # let mut edges = 0;
-/// # for node in net.get_node_indices(){
-/// #     edges += net.get_children_set(node).len()
-/// # }
+/// # let edges = net.edges().count();
 /// # // Number of all the nodes in the network
 /// # let nodes = net.get_node_indices().len() as f64;
 /// # let expected_edges = (density * nodes * (nodes - 1.0)).round() as usize;
@@ -326,30 +2269,26 @@ impl RandomParametersGenerator for UniformParametersGenerator {
                 .product();
             match &mut net.get_node_mut(node) {
                 params::Params::DiscreteStatesContinousTime(param) => {
-                    let node_domain_cardinality = param.get_reserved_space_as_parent();
-                    let mut cim = Array3::<f64>::from_shape_fn(
-                        (
-                            parent_set_state_space_cardinality,
-                            node_domain_cardinality,
-                            node_domain_cardinality,
-                        ),
-                        |_| self.rng.gen(),
+                    param.randomize_cim(
+                        parent_set_state_space_cardinality,
+                        &params::Uniform(self.interval.clone()),
+                        &mut self.rng,
                     );
-                    cim.axis_iter_mut(Axis(0)).for_each(|mut x| {
-                        x.diag_mut().fill(0.0);
-                        x.div_assign(&x.sum_axis(Axis(1)).insert_axis(Axis(1)));
-                        let diag = Array1::<f64>::from_shape_fn(node_domain_cardinality, |_| {
-                            self.rng.gen_range(self.interval.clone())
-                        });
-                        x.mul_assign(&diag.clone().insert_axis(Axis(1)));
-                        // Recomputing the diagonal in order to reduce the issues caused by the
-                        // loss of precision when validating the parameters.
-                        let diag_sum = -x.sum_axis(Axis(1));
-                        x.diag_mut().assign(&diag_sum)
-                    });
-                    param.set_cim_unchecked(cim);
                 }
             }
         }
     }
 }
+
+// Run `op` inside `thread_pool`, or in the current context (usually the global rayon pool) when
+// no pool is given. The parallel iterators called by `op` use the same pool.
+pub(crate) fn run_in_thread_pool<R, OP>(thread_pool: Option<&ThreadPool>, op: OP) -> R
+where
+    R: Send,
+    OP: FnOnce() -> R + Send,
+{
+    match thread_pool {
+        Some(thread_pool) => thread_pool.install(op),
+        None => op(),
+    }
+}
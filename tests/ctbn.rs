@@ -1,12 +1,15 @@
 mod utils;
-use std::collections::BTreeSet;
+use std::collections::{BTreeSet, HashMap};
 
 
 use approx::AbsDiffEq;
-use ndarray::arr3;
+use ndarray::{arr1, arr3, s};
 use reCTBN::params::{self, ParamsTrait};
 use reCTBN::process::NetworkProcess;
 use reCTBN::process::{ctbn::*};
+use reCTBN::tools::{
+    trajectory_generator, RandomParametersGenerator, UniformParametersGenerator,
+};
 use utils::generate_discrete_time_continous_node;
 
 #[test]
@@ -24,6 +27,69 @@ fn add_node_to_ctbn() {
     assert_eq!(&String::from("n1"), net.get_node(n1).get_label());
 }
 
+#[test]
+fn add_node_duplicate_label() {
+    let mut net = CtbnNetwork::new();
+    net.add_node(generate_discrete_time_continous_node(String::from("n1"), 2))
+        .unwrap();
+    assert!(matches!(
+        net.add_node(generate_discrete_time_continous_node(String::from("n1"), 3)),
+        Err(reCTBN::process::NetworkError::NodeInsertionError(_))
+    ));
+    assert_eq!(1, net.get_number_of_nodes());
+}
+
+#[test]
+fn get_node_index_by_label() {
+    let mut net = CtbnNetwork::new();
+    let n1 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n1"), 2))
+        .unwrap();
+    let n2 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n2"), 2))
+        .unwrap();
+    assert_eq!(Some(n1), net.get_node_index_by_label("n1"));
+    assert_eq!(Some(n2), net.get_node_index_by_label("n2"));
+    assert_eq!(None, net.get_node_index_by_label("n3"));
+}
+
+#[test]
+fn build_ctbn_by_label() {
+    let mut net = CtbnNetwork::new();
+    net.add_node(params::discrete_node("n1", &["A", "B"])).unwrap();
+    net.add_node(params::discrete_node("n2", &["A", "B", "C"])).unwrap();
+    net.add_node(params::discrete_node("n3", &["A", "B"])).unwrap();
+    assert!(matches!(
+        net.add_node(params::discrete_node("n2", &["A", "B"])),
+        Err(reCTBN::process::NetworkError::NodeInsertionError(_))
+    ));
+
+    // No edges yet.
+    assert!(net.get_parent_labels(0).is_empty());
+    assert!(net.get_children_labels(0).is_empty());
+
+    net.add_edge_by_label("n1", "n2").unwrap();
+    net.add_edge_by_label("n1", "n3").unwrap();
+    net.add_edge_by_label("n2", "n3").unwrap();
+
+    let n3 = net.get_node_index_by_label("n3").unwrap();
+    let n1 = net.get_node_index_by_label("n1").unwrap();
+    assert_eq!(vec!["n1", "n2"], net.get_parent_labels(n3));
+    assert_eq!(vec!["n2", "n3"], net.get_children_labels(n1));
+    assert!(net.get_parent_labels(n1).is_empty());
+    assert!(net.get_children_labels(n3).is_empty());
+
+    match net.add_edge_by_label("n1", "n4") {
+        Err(reCTBN::process::NetworkError::UnknownNode(label)) => assert_eq!("n4", label),
+        _ => panic!("n4 is not a node of the network"),
+    }
+    match net.add_edge_by_label("n0", "n3") {
+        Err(e) => assert_eq!("Unknown node: n0", e.to_string()),
+        Ok(_) => panic!("n0 is not a node of the network"),
+    }
+    assert_eq!(3, net.edges().count());
+}
+
 #[test]
 fn add_edge_to_ctbn() {
     let mut net = CtbnNetwork::new();
@@ -38,6 +104,42 @@ fn add_edge_to_ctbn() {
     assert_eq!(&n2, cs.iter().next().unwrap());
 }
 
+#[test]
+fn has_edge_and_edges() {
+    let mut net = CtbnNetwork::new();
+    let n1 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n1"), 2))
+        .unwrap();
+    let n2 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n2"), 2))
+        .unwrap();
+    let n3 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n3"), 2))
+        .unwrap();
+
+    // No adjacency matrix yet.
+    assert!(net.get_adj_matrix().is_none());
+    assert!(!net.has_edge(n1, n2));
+    assert_eq!(0, net.edges().count());
+
+    net.add_edge(n3, n1);
+    net.add_edge(n1, n2);
+    net.add_edge(n1, n3);
+    assert!(net.has_edge(n1, n2));
+    assert!(!net.has_edge(n2, n1));
+    assert_eq!(vec![(n1, n2), (n1, n3), (n3, n1)], net.edges().collect::<Vec<_>>());
+
+    net.remove_edge(n1, n2);
+    assert!(!net.has_edge(n1, n2));
+    assert_eq!(vec![(n1, n3), (n3, n1)], net.edges().collect::<Vec<_>>());
+
+    // Adding a node discards the structure.
+    net.add_node(generate_discrete_time_continous_node(String::from("n4"), 2))
+        .unwrap();
+    assert!(!net.has_edge(n1, n3));
+    assert_eq!(0, net.edges().count());
+}
+
 #[test]
 fn children_and_parents() {
     let mut net = CtbnNetwork::new();
@@ -54,6 +156,129 @@ fn children_and_parents() {
     assert_eq!(&n1, ps.iter().next().unwrap());
 }
 
+#[test]
+fn remove_edge_from_ctbn() {
+    let mut net = CtbnNetwork::new();
+    let n1 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n1"), 2))
+        .unwrap();
+    let n2 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n2"), 3))
+        .unwrap();
+    let n3 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n3"), 2))
+        .unwrap();
+    net.add_edge(n1, n3);
+    net.add_edge(n2, n3);
+    match &mut net.get_node_mut(n1) {
+        params::Params::DiscreteStatesContinousTime(param) => {
+            assert_eq!(Ok(()), param.set_cim(arr3(&[[[-0.1, 0.1], [1.0, -1.0]]])));
+        }
+    }
+
+    net.remove_edge(n1, n3);
+    assert_eq!(BTreeSet::from([n2]), net.get_parent_set(n3));
+    assert!(net.get_children_set(n1).is_empty());
+    let params::Params::DiscreteStatesContinousTime(p) = net.get_node(n3);
+    assert!(p.get_cim().is_none());
+
+    // Only the remaining parent is used to compute the index.
+    let idx = net.get_param_index_network(
+        n3,
+        &vec![
+            params::StateType::Discrete(1),
+            params::StateType::Discrete(2),
+            params::StateType::Discrete(0),
+        ],
+    );
+    assert_eq!(2, idx);
+
+    // Removing a missing edge does not reset the parameters of the child.
+    net.remove_edge(n3, n1);
+    let params::Params::DiscreteStatesContinousTime(p) = net.get_node(n1);
+    assert!(p.get_cim().is_some());
+}
+
+#[test]
+fn remove_node_from_ctbn() {
+    let mut net = CtbnNetwork::new();
+    let n1 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n1"), 3))
+        .unwrap();
+    let n2 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n2"), 2))
+        .unwrap();
+    let n3 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n3"), 2))
+        .unwrap();
+    let n4 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n4"), 2))
+        .unwrap();
+    net.add_edge(n1, n3);
+    net.add_edge(n2, n3);
+    net.add_edge(n3, n4);
+    net.add_edge(n2, n4);
+    match &mut net.get_node_mut(n1) {
+        params::Params::DiscreteStatesContinousTime(param) => {
+            assert_eq!(
+                Ok(()),
+                param.set_cim(arr3(&[[[-0.2, 0.1, 0.1], [1.0, -2.0, 1.0], [0.5, 0.5, -1.0]]]))
+            );
+        }
+    }
+
+    let mapping = net.remove_node(n2).unwrap();
+    assert_eq!(vec![Some(0), None, Some(1), Some(2)], mapping);
+    let (n1, n3, n4) = (mapping[n1].unwrap(), mapping[n3].unwrap(), mapping[n4].unwrap());
+
+    assert_eq!(3, net.get_number_of_nodes());
+    assert_eq!(Some(n3), net.get_node_index_by_label("n3"));
+    assert_eq!(None, net.get_node_index_by_label("n2"));
+    assert_eq!(BTreeSet::from([n1]), net.get_parent_set(n3));
+    assert_eq!(BTreeSet::from([n3]), net.get_parent_set(n4));
+    assert_eq!(BTreeSet::from([n3]), net.get_children_set(n1));
+    assert_eq!(BTreeSet::from([n4]), net.get_children_set(n3));
+
+    // The children of the removed node are reset, the other nodes keep their parameters.
+    let params::Params::DiscreteStatesContinousTime(p) = net.get_node(n1);
+    assert!(p.get_cim().is_some());
+    let params::Params::DiscreteStatesContinousTime(p) = net.get_node(n3);
+    assert!(p.get_cim().is_none());
+    let params::Params::DiscreteStatesContinousTime(p) = net.get_node(n4);
+    assert!(p.get_cim().is_none());
+
+    let idx = net.get_param_index_network(
+        n3,
+        &vec![
+            params::StateType::Discrete(2),
+            params::StateType::Discrete(0),
+            params::StateType::Discrete(1),
+        ],
+    );
+    assert_eq!(2, idx);
+    let idx = net.get_param_index_network(
+        n4,
+        &vec![
+            params::StateType::Discrete(2),
+            params::StateType::Discrete(1),
+            params::StateType::Discrete(0),
+        ],
+    );
+    assert_eq!(1, idx);
+
+    assert!(net.remove_node(3).is_err());
+
+    // A network without edges keeps having no adjacency matrix.
+    let mut net = CtbnNetwork::new();
+    net.add_node(generate_discrete_time_continous_node(String::from("n1"), 2))
+        .unwrap();
+    net.add_node(generate_discrete_time_continous_node(String::from("n2"), 2))
+        .unwrap();
+    assert_eq!(vec![None, Some(0)], net.remove_node(0).unwrap());
+    assert_eq!(Some(0), net.get_node_index_by_label("n2"));
+    assert!(net.get_adj_matrix().is_none());
+}
+
 #[test]
 fn compute_index_ctbn() {
     let mut net = CtbnNetwork::new();
@@ -240,6 +465,82 @@ fn chain_amalgamation() {
     assert!(p_ctmp.abs_diff_eq(&p_ctmp_handmade, 1e-8));
 }
 
+#[test]
+fn chain_amalgamation_state_labels() {
+    let mut net = CtbnNetwork::new();
+    let n1 = net.add_node(params::discrete_node("n1", &["A", "B"])).unwrap();
+    let n2 = net.add_node(params::discrete_node("n2", &["A", "B"])).unwrap();
+    let n3 = net.add_node(params::discrete_node("n3", &["A", "B"])).unwrap();
+
+    net.add_edge(n1, n2);
+    net.add_edge(n2, n3);
+
+    match &mut net.get_node_mut(n1) {
+        params::Params::DiscreteStatesContinousTime(param) => {
+            assert_eq!(Ok(()), param.set_cim(arr3(&[[[-0.1, 0.1], [1.0, -1.0]]])));
+        }
+    }
+
+    for node in [n2, n3] {
+        match &mut net.get_node_mut(node) {
+            params::Params::DiscreteStatesContinousTime(param) => {
+                assert_eq!(
+                    Ok(()),
+                    param.set_cim(arr3(&[
+                        [[-0.01, 0.01], [5.0, -5.0]],
+                        [[-5.0, 5.0], [0.01, -0.01]]
+                    ]))
+                );
+            }
+        }
+    }
+
+    let ctmp = net.amalgamation();
+    let states = ctmp.get_states().unwrap();
+    assert_eq!(states.len(), 8);
+
+    // The first node changes faster, as in the hand-computed chain amalgamation.
+    let state = |x: [usize; 3]| -> Vec<params::StateType> {
+        x.iter().map(|s| params::StateType::Discrete(*s)).collect()
+    };
+    assert_eq!(states[1], state([1, 0, 0]));
+    assert_eq!(states[6], state([0, 1, 1]));
+    assert_eq!(ctmp.get_state_label(0).unwrap(), "n1=A|n2=A|n3=A");
+    assert_eq!(ctmp.get_state_label(1).unwrap(), "n1=B|n2=A|n3=A");
+    assert_eq!(ctmp.get_state_label(6).unwrap(), "n1=A|n2=B|n3=B");
+    assert_eq!(ctmp.get_state_label(8), None);
+
+    for (idx, s) in states.iter().enumerate() {
+        assert_eq!(ctmp.state_to_idx(s), Some(idx));
+        assert_eq!(
+            CtbnNetwork::state_to_idx(
+                &arr1(&[2, 2, 2]),
+                &CtbnNetwork::idx_to_state(&arr1(&[2, 2, 2]), idx)
+            ),
+            idx
+        );
+    }
+    assert_eq!(ctmp.state_to_idx(&state([0, 2, 0])), None);
+    assert_eq!(ctmp.state_to_idx(&state([0, 1, 0])[..2].to_vec()), None);
+
+    // Entries of the hand-computed chain amalgamation looked up by configuration.
+    let params::Params::DiscreteStatesContinousTime(p_ctmp) = &ctmp.get_node(0);
+    let p_ctmp = p_ctmp.get_cim().as_ref().unwrap();
+    let rate = |from: [usize; 3], to: [usize; 3]| {
+        p_ctmp[[
+            0,
+            ctmp.state_to_idx(&state(from)).unwrap(),
+            ctmp.state_to_idx(&state(to)).unwrap(),
+        ]]
+    };
+    assert_eq!(rate([1, 0, 0], [1, 1, 0]), 5.0);
+    assert_eq!(rate([0, 1, 0], [0, 1, 1]), 5.0);
+    assert_eq!(rate([0, 1, 1], [0, 0, 1]), 5.0);
+    assert_eq!(rate([1, 1, 1], [0, 1, 1]), 1.0);
+    assert_eq!(rate([0, 0, 0], [0, 0, 0]), -0.12);
+    assert_eq!(rate([1, 0, 1], [1, 0, 1]), -11.0);
+}
+
 #[test]
 fn chainfork_amalgamation() {
     let mut net = CtbnNetwork::new();
@@ -374,3 +675,590 @@ fn chainfork_amalgamation() {
 
     assert!(p_ctmp.abs_diff_eq(&p_ctmp_handmade, 1e-8));
 }
+
+#[test]
+fn markov_blanket_chain_and_chainfork() {
+    // Chain n1 -> n2 -> n3, as in `chain_amalgamation`.
+    let mut net = CtbnNetwork::new();
+    let n1 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n1"), 2))
+        .unwrap();
+    let n2 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n2"), 2))
+        .unwrap();
+    let n3 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n3"), 2))
+        .unwrap();
+    net.add_edge(n1, n2);
+    net.add_edge(n2, n3);
+
+    assert_eq!(BTreeSet::from([n2]), net.get_markov_blanket(n1));
+    assert_eq!(BTreeSet::from([n1, n3]), net.get_markov_blanket(n2));
+    assert_eq!(BTreeSet::from([n2]), net.get_markov_blanket(n3));
+
+    // Chain-fork n1 -> n3 <- n2, n3 -> n4, as in `chainfork_amalgamation`.
+    let mut net = CtbnNetwork::new();
+    let n1 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n1"), 2))
+        .unwrap();
+    let n2 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n2"), 2))
+        .unwrap();
+    let n3 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n3"), 2))
+        .unwrap();
+    let n4 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n4"), 2))
+        .unwrap();
+    net.add_edge(n1, n3);
+    net.add_edge(n2, n3);
+    net.add_edge(n3, n4);
+
+    assert_eq!(BTreeSet::from([n2, n3]), net.get_markov_blanket(n1));
+    assert_eq!(BTreeSet::from([n1, n3]), net.get_markov_blanket(n2));
+    assert_eq!(BTreeSet::from([n1, n2, n4]), net.get_markov_blanket(n3));
+    assert_eq!(BTreeSet::from([n3]), net.get_markov_blanket(n4));
+
+    let blanket = net.get_markov_blanket_by_role(n1);
+    assert!(blanket.parents.is_empty());
+    assert_eq!(BTreeSet::from([n3]), blanket.children);
+    assert_eq!(
+        std::collections::BTreeMap::from([(n3, BTreeSet::from([n2]))]),
+        blanket.co_parents
+    );
+
+    // With a cycle n4 -> n1 the node is never part of its own blanket.
+    net.add_edge(n4, n1);
+    assert_eq!(BTreeSet::from([n2, n3, n4]), net.get_markov_blanket(n1));
+    assert_eq!(BTreeSet::from([n1, n3]), net.get_markov_blanket(n4));
+}
+
+fn get_mixed_discrete_net_3_nodes() -> CtbnNetwork {
+    let mut net = CtbnNetwork::new();
+    let n1 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n1"), 3))
+        .unwrap();
+    let n2 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n2"), 3))
+        .unwrap();
+    let n3 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n3"), 4))
+        .unwrap();
+    net.add_edge(n1, n2);
+    net.add_edge(n1, n3);
+    net.add_edge(n2, n3);
+    net
+}
+
+#[test]
+fn topological_order_acyclic_networks() {
+    let net = get_mixed_discrete_net_3_nodes();
+    assert!(net.is_acyclic());
+    assert_eq!(Some(vec![0, 1, 2]), net.topological_order());
+    assert_eq!(
+        vec![vec![0], vec![1], vec![2]],
+        net.strongly_connected_components()
+    );
+
+    // Chain-fork n4 -> n2 <- n3, n2 -> n1, added with reversed indices.
+    let mut net = CtbnNetwork::new();
+    for label in ["n1", "n2", "n3", "n4"] {
+        net.add_node(generate_discrete_time_continous_node(String::from(label), 2))
+            .unwrap();
+    }
+    // Without edges every order is topological.
+    assert!(net.is_acyclic());
+    assert_eq!(Some(vec![0, 1, 2, 3]), net.topological_order());
+    assert_eq!(4, net.strongly_connected_components().len());
+
+    net.add_edge(3, 1);
+    net.add_edge(2, 1);
+    net.add_edge(1, 0);
+    assert!(net.is_acyclic());
+    assert_eq!(Some(vec![2, 3, 1, 0]), net.topological_order());
+    let components = net.strongly_connected_components();
+    assert_eq!(4, components.len());
+    let position = |node: usize| components.iter().position(|x| x == &vec![node]).unwrap();
+    for (parent, child) in net.edges() {
+        assert!(position(parent) < position(child));
+    }
+}
+
+#[test]
+fn topological_order_cyclic_network() {
+    let mut net = CtbnNetwork::new();
+    for label in ["n1", "n2", "n3", "n4"] {
+        net.add_node(generate_discrete_time_continous_node(String::from(label), 2))
+            .unwrap();
+    }
+    net.add_edge(0, 1);
+    net.add_edge(1, 2);
+    net.add_edge(2, 0);
+    net.add_edge(2, 3);
+
+    assert!(!net.is_acyclic());
+    assert_eq!(None, net.topological_order());
+    assert_eq!(
+        vec![vec![0, 1, 2], vec![3]],
+        net.strongly_connected_components()
+    );
+
+    net.remove_edge(2, 0);
+    assert!(net.is_acyclic());
+    assert_eq!(Some(vec![0, 1, 2, 3]), net.topological_order());
+
+    // A self loop is a cycle too.
+    net.add_edge(3, 3);
+    assert!(!net.is_acyclic());
+    assert_eq!(4, net.strongly_connected_components().len());
+}
+
+#[test]
+fn display_ctbn() {
+    let net = get_mixed_discrete_net_3_nodes();
+    assert_eq!(
+        "0: n1 [3] <- {}\n\
+         1: n2 [3] <- {n1}\n\
+         2: n3 [4] <- {n1, n2}\n\
+         n1 -> n2\n\
+         n1 -> n3\n\
+         n2 -> n3\n",
+        format!("{}", net)
+    );
+}
+
+#[test]
+fn display_ctbn_alternate() {
+    let mut net = get_mixed_discrete_net_3_nodes();
+    match &mut net.get_node_mut(0) {
+        params::Params::DiscreteStatesContinousTime(param) => {
+            assert_eq!(
+                Ok(()),
+                param.set_cim(arr3(&[[
+                    [-3.0, 2.0, 1.0],
+                    [1.5, -2.0, 0.5],
+                    [0.4, 0.6, -1.0]
+                ]]))
+            );
+        }
+    }
+    assert_eq!(
+        "0: n1 [3] <- {} (cim: [1, 3, 3])\n\
+         1: n2 [3] <- {n1} (cim: not set)\n\
+         2: n3 [4] <- {n1, n2} (cim: not set)\n\
+         n1 -> n2\n\
+         n1 -> n3\n\
+         n2 -> n3\n",
+        format!("{:#}", net)
+    );
+}
+
+#[test]
+fn display_ctbn_without_adj_matrix() {
+    let mut net = CtbnNetwork::new();
+    net.add_node(generate_discrete_time_continous_node(String::from("n1"), 2))
+        .unwrap();
+    assert_eq!("0: n1 [2] <- {}\n", format!("{}", net));
+}
+
+#[test]
+fn param_index_multipliers_consistent_with_param_index() {
+    let net = get_mixed_discrete_net_3_nodes();
+    assert_eq!(Some(vec![]), net.get_param_index_multipliers(0));
+    assert_eq!(Some(vec![(0, 1)]), net.get_param_index_multipliers(1));
+    assert_eq!(Some(vec![(0, 1), (1, 3)]), net.get_param_index_multipliers(2));
+    for s0 in 0..3 {
+        for s1 in 0..3 {
+            for s2 in 0..4 {
+                let state = vec![
+                    params::StateType::Discrete(s0),
+                    params::StateType::Discrete(s1),
+                    params::StateType::Discrete(s2),
+                ];
+                for node in net.get_node_indices() {
+                    let idx: usize = net
+                        .get_param_index_multipliers(node)
+                        .unwrap()
+                        .iter()
+                        .map(|(parent, multiplier)| {
+                            net.get_node(*parent).state_to_index(&state[*parent]) * multiplier
+                        })
+                        .sum();
+                    assert_eq!(net.get_param_index_network(node, &state), idx);
+                }
+            }
+        }
+    }
+}
+
+fn get_mixed_discrete_net_3_nodes_with_cims() -> CtbnNetwork {
+    let mut net = get_mixed_discrete_net_3_nodes();
+    let mut cim_generator: UniformParametersGenerator =
+        RandomParametersGenerator::new(0.5..3.0, Some(7641630759785120));
+    cim_generator.generate_parameters(&mut net);
+    net
+}
+
+#[test]
+fn json_model_round_trip() {
+    let net = get_mixed_discrete_net_3_nodes_with_cims();
+    let value = net.to_json_value();
+    assert_eq!("reCTBN-ctbn", value["format"]);
+    assert_eq!(serde_json::json!(["0", "1", "2"]), value["nodes"][0]["domain"]);
+    assert_eq!(serde_json::json!([[0, 1], [0, 2], [1, 2]]), value["edges"]);
+
+    let loaded = CtbnNetwork::from_json_value(&value).unwrap();
+    assert_eq!(net.get_number_of_nodes(), loaded.get_number_of_nodes());
+    for node in net.get_node_indices() {
+        assert_eq!(net.get_parent_set(node), loaded.get_parent_set(node));
+        let params::Params::DiscreteStatesContinousTime(p) = net.get_node(node);
+        let params::Params::DiscreteStatesContinousTime(p_loaded) = loaded.get_node(node);
+        assert_eq!(p.get_label(), p_loaded.get_label());
+        assert_eq!(p.get_domain(), p_loaded.get_domain());
+        assert_eq!(p.get_cim(), p_loaded.get_cim());
+    }
+
+    let data = trajectory_generator(&net, 5, 2.0, Some(1));
+    let loaded_data = trajectory_generator(&loaded, 5, 2.0, Some(1));
+    for (trj, loaded_trj) in data
+        .get_trajectories()
+        .iter()
+        .zip(loaded_data.get_trajectories().iter())
+    {
+        assert_eq!(trj.get_time(), loaded_trj.get_time());
+        assert_eq!(trj.get_events(), loaded_trj.get_events());
+    }
+}
+
+#[test]
+fn json_model_save_and_load() {
+    let net = get_mixed_discrete_net_3_nodes_with_cims();
+    let path = std::env::temp_dir().join("reCTBN_json_model_save_and_load.json");
+    net.save_json(&path).unwrap();
+    let loaded = CtbnNetwork::load_json(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    for node in net.get_node_indices() {
+        assert_eq!(net.get_parent_set(node), loaded.get_parent_set(node));
+        assert_eq!(Ok(()), loaded.get_node(node).validate_params());
+    }
+}
+
+#[cfg(feature = "npz")]
+#[test]
+fn npz_save_and_load() {
+    use reCTBN::parameter_learning::{ParameterLearning, MLE};
+
+    let mut net = get_mixed_discrete_net_3_nodes_with_cims();
+    let data = trajectory_generator(&net, 100, 10.0, Some(6347747169756259));
+    MLE {}.fit_all(&mut net, &data);
+    let path = std::env::temp_dir().join("reCTBN_npz_save_and_load.npz");
+    net.save_npz(&path).unwrap();
+
+    let mut loaded = get_mixed_discrete_net_3_nodes();
+    loaded.load_npz(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    for node in net.get_node_indices() {
+        let params::Params::DiscreteStatesContinousTime(p) = net.get_node(node);
+        let params::Params::DiscreteStatesContinousTime(p_loaded) = loaded.get_node(node);
+        assert_eq!(p.get_cim(), p_loaded.get_cim());
+        assert_eq!(p.get_transitions(), p_loaded.get_transitions());
+        assert_eq!(p.get_residence_time(), p_loaded.get_residence_time());
+    }
+}
+
+#[cfg(feature = "npz")]
+#[test]
+fn npz_load_mismatched_domain() {
+    let net = get_mixed_discrete_net_3_nodes_with_cims();
+    let path = std::env::temp_dir().join("reCTBN_npz_load_mismatched_domain.npz");
+    net.save_npz(&path).unwrap();
+
+    //n3 has 3 states instead of 4.
+    let mut other = CtbnNetwork::new();
+    for (label, n_states) in [("n1", 3), ("n2", 3), ("n3", 3)] {
+        other
+            .add_node(generate_discrete_time_continous_node(String::from(label), n_states))
+            .unwrap();
+    }
+    other.add_edge(0, 1);
+    other.add_edge(0, 2);
+    other.add_edge(1, 2);
+    let result = other.load_npz(&path);
+    std::fs::remove_file(&path).unwrap();
+    match result {
+        Err(reCTBN::process::NetworkError::InvalidModel(message)) => {
+            assert!(message.starts_with("n3/cim: shape [9, 4, 4]"), "{}", message)
+        }
+        _ => panic!("The mismatched shape must be rejected"),
+    }
+    //The network is left unchanged.
+    let params::Params::DiscreteStatesContinousTime(p) = other.get_node(0);
+    assert_eq!(&None, p.get_cim());
+}
+
+#[test]
+fn json_model_edgeless_network() {
+    //The adjacency matrix of the network is never initialized.
+    let mut net = CtbnNetwork::new();
+    net.add_node(generate_discrete_time_continous_node(String::from("n1"), 2))
+        .unwrap();
+    net.add_node(generate_discrete_time_continous_node(String::from("n2"), 3))
+        .unwrap();
+    let value = net.to_json_value();
+    assert_eq!(serde_json::json!([]), value["edges"]);
+
+    let path = std::env::temp_dir().join("reCTBN_json_model_edgeless_network.json");
+    net.save_json(&path).unwrap();
+    let loaded = CtbnNetwork::load_json(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(2, loaded.get_number_of_nodes());
+    assert_eq!(0, loaded.edges().count());
+}
+
+#[test]
+fn json_model_invalid_cim() {
+    let net = get_mixed_discrete_net_3_nodes_with_cims();
+
+    let mut value = net.to_json_value();
+    value["nodes"][1]["cim"][0][0][0] = serde_json::json!(1.0);
+    match CtbnNetwork::from_json_value(&value) {
+        Err(reCTBN::process::NetworkError::InvalidModel(message)) => {
+            assert!(message.starts_with("node 1 (n2)"))
+        }
+        _ => panic!("The invalid cim must be rejected"),
+    }
+
+    //The cim of n3 has 9 parent configurations; without the edge from n2 only 3 are expected.
+    let mut value = net.to_json_value();
+    value["edges"] = serde_json::json!([[0, 1], [0, 2]]);
+    match CtbnNetwork::from_json_value(&value) {
+        Err(reCTBN::process::NetworkError::InvalidModel(message)) => {
+            assert!(message.starts_with("node 2 (n3)"))
+        }
+        _ => panic!("The cim with the wrong number of parent configurations must be rejected"),
+    }
+
+    let mut value = net.to_json_value();
+    value["nodes"][0]["domain"] = serde_json::json!(["1", "0", "2"]);
+    assert!(CtbnNetwork::from_json_value(&value).is_err());
+}
+
+#[test]
+fn json_model_configuration_groups() {
+    let mut net = get_mixed_discrete_net_3_nodes_with_cims();
+    let cim = match net.get_node(2) {
+        params::Params::DiscreteStatesContinousTime(p) => p.get_cim().clone().unwrap(),
+    };
+    match net.get_node_mut(2) {
+        params::Params::DiscreteStatesContinousTime(p) => {
+            assert_eq!(Ok(()), p.set_configuration_groups(arr1(&[0, 1, 1, 1, 1, 1, 1, 1, 1])));
+            assert_eq!(Ok(()), p.set_cim(cim.slice(s![0..2, .., ..]).to_owned()));
+        }
+    }
+
+    let value = net.to_json_value();
+    assert_eq!(serde_json::json!([0, 1, 1, 1, 1, 1, 1, 1, 1]), value["nodes"][2]["configuration_groups"]);
+    let loaded = CtbnNetwork::from_json_value(&value).unwrap();
+    let params::Params::DiscreteStatesContinousTime(p) = net.get_node(2);
+    let params::Params::DiscreteStatesContinousTime(p_loaded) = loaded.get_node(2);
+    assert_eq!(p.get_configuration_groups(), p_loaded.get_configuration_groups());
+    assert_eq!(p.get_cim(), p_loaded.get_cim());
+
+    //The groups must cover all the parent configurations.
+    let mut value = net.to_json_value();
+    value["nodes"][2]["configuration_groups"] = serde_json::json!([0, 1, 1]);
+    assert!(CtbnNetwork::from_json_value(&value).is_err());
+}
+
+#[test]
+fn json_model_noisy_or() {
+    let mut net = get_mixed_discrete_net_3_nodes_with_cims();
+    let base = match net.get_node(2) {
+        params::Params::DiscreteStatesContinousTime(p) => {
+            p.get_cim().as_ref().unwrap().index_axis(ndarray::Axis(0), 0).to_owned()
+        }
+    };
+    match net.get_node_mut(2) {
+        params::Params::DiscreteStatesContinousTime(p) => {
+            assert_eq!(
+                Ok(()),
+                p.set_noisy_or_cim(
+                    base,
+                    vec![ndarray::Array2::from_elem((3, 4), 2.0), ndarray::Array2::from_elem((3, 4), 0.5)]
+                )
+            );
+        }
+    }
+
+    let value = net.to_json_value();
+    let loaded = CtbnNetwork::from_json_value(&value).unwrap();
+    let params::Params::DiscreteStatesContinousTime(p) = net.get_node(2);
+    let params::Params::DiscreteStatesContinousTime(p_loaded) = loaded.get_node(2);
+    assert_eq!(p.get_rate_multipliers(), p_loaded.get_rate_multipliers());
+    assert_eq!(p.get_expanded_cim(), p_loaded.get_expanded_cim());
+
+    //One set of multipliers is required for each parent.
+    let mut value = net.to_json_value();
+    value["nodes"][2]["rate_multipliers"].as_array_mut().unwrap().pop();
+    assert!(CtbnNetwork::from_json_value(&value).is_err());
+}
+
+fn get_binary_chain_net(label_1: &str, label_2: &str) -> CtbnNetwork {
+    let mut net = CtbnNetwork::new();
+    let n1 = net
+        .add_node(generate_discrete_time_continous_node(String::from(label_1), 2))
+        .unwrap();
+    let n2 = net
+        .add_node(generate_discrete_time_continous_node(String::from(label_2), 2))
+        .unwrap();
+    net.add_edge(n1, n2);
+
+    match &mut net.get_node_mut(n1) {
+        params::Params::DiscreteStatesContinousTime(param) => {
+            assert_eq!(Ok(()), param.set_cim(arr3(&[[[-0.1, 0.1], [1.0, -1.0]]])));
+        }
+    }
+    match &mut net.get_node_mut(n2) {
+        params::Params::DiscreteStatesContinousTime(param) => {
+            assert_eq!(
+                Ok(()),
+                param.set_cim(arr3(&[
+                    [[-0.01, 0.01], [5.0, -5.0]],
+                    [[-5.0, 5.0], [0.01, -0.01]]
+                ]))
+            );
+        }
+    }
+    net
+}
+
+#[test]
+fn amalgamation_to_prism() {
+    let ctmp = get_binary_chain_net("n1", "n2").amalgamation();
+    assert_eq!(
+        &Some(vec![(String::from("n1"), 2), (String::from("n2"), 2)]),
+        ctmp.get_variables()
+    );
+    assert_eq!(
+        "ctmc
+
+module ctmp
+    n1 : [0..1];
+    n2 : [0..1];
+
+    [] n1=0 & n2=0 -> 0.1 : (n1'=1);
+    [] n1=0 & n2=0 -> 0.01 : (n2'=1);
+    [] n1=1 & n2=0 -> 1 : (n1'=0);
+    [] n1=1 & n2=0 -> 5 : (n2'=1);
+    [] n1=0 & n2=1 -> 5 : (n2'=0);
+    [] n1=0 & n2=1 -> 0.1 : (n1'=1);
+    [] n1=1 & n2=1 -> 0.01 : (n2'=0);
+    [] n1=1 & n2=1 -> 1 : (n1'=0);
+endmodule
+",
+        ctmp.to_prism()
+    );
+}
+
+#[test]
+fn amalgamation_to_prism_identifiers() {
+    let prism = get_binary_chain_net("1 st", "module").amalgamation().to_prism();
+    assert!(prism.contains("    v_1_st : [0..1];\n    v_module : [0..1];\n"));
+    assert!(prism.contains("    [] v_1_st=0 & v_module=0 -> 0.1 : (v_1_st'=1);\n"));
+}
+
+#[test]
+fn graphml_round_trip() {
+    let net = get_mixed_discrete_net_3_nodes();
+    let graphml = net.to_graphml();
+    assert!(graphml.contains(
+        "        <node id=\"n2\">\n            <data key=\"label\">n3</data>\n            <data key=\"cardinality\">4</data>\n        </node>\n"
+    ));
+    assert!(graphml.contains("        <edge source=\"n1\" target=\"n2\"/>\n"));
+
+    let loaded = CtbnNetwork::structure_from_graphml(&graphml, &HashMap::new()).unwrap();
+    assert_eq!(net.get_number_of_nodes(), loaded.get_number_of_nodes());
+    for node in net.get_node_indices() {
+        assert_eq!(net.get_node(node).get_label(), loaded.get_node(node).get_label());
+        assert_eq!(
+            net.get_node(node).get_reserved_space_as_parent(),
+            loaded.get_node(node).get_reserved_space_as_parent()
+        );
+        assert_eq!(net.get_parent_set(node), loaded.get_parent_set(node));
+    }
+}
+
+#[test]
+fn graphml_round_trip_edgeless_network() {
+    //The adjacency matrix of the network is never initialized.
+    let mut net = CtbnNetwork::new();
+    net.add_node(generate_discrete_time_continous_node(String::from("n1"), 2))
+        .unwrap();
+    net.add_node(generate_discrete_time_continous_node(String::from("n2"), 3))
+        .unwrap();
+    let graphml = net.to_graphml();
+    assert!(!graphml.contains("<edge "));
+
+    let loaded = CtbnNetwork::structure_from_graphml(&graphml, &HashMap::new()).unwrap();
+    assert_eq!(2, loaded.get_number_of_nodes());
+    assert_eq!(3, loaded.get_node(1).get_reserved_space_as_parent());
+    assert_eq!(0, loaded.edges().count());
+}
+
+#[test]
+fn structure_from_graphml_extra_attributes() {
+    let graphml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+  <key id="d0" for="node" attr.name="label" attr.type="string"/>
+  <key id="d1" for="node" attr.name="color" attr.type="string"/>
+  <key id="d2" for="node" attr.name="cardinality" attr.type="int"/>
+  <graph id="G" edgedefault="directed">
+    <node id="a"><data key="d0">X</data><data key="d1">red</data><data key="d2">2</data></node>
+    <node id="b"><data key="d0">Y</data></node>
+    <node id="c"/>
+    <edge id="e0" source="a" target="b" weight="1.0"/>
+    <edge source="b" target="c" directed="true"/>
+  </graph>
+</graphml>
+"#;
+    let cardinalities = HashMap::from([(String::from("Y"), 3), (String::from("c"), 4)]);
+    let mut net = CtbnNetwork::structure_from_graphml(graphml, &cardinalities).unwrap();
+    assert_eq!("X", net.get_node(0).get_label());
+    assert_eq!("c", net.get_node(2).get_label());
+    assert_eq!(
+        vec![2, 3, 4],
+        net.get_node_indices()
+            .map(|x| net.get_node(x).get_reserved_space_as_parent())
+            .collect::<Vec<usize>>()
+    );
+    assert_eq!(BTreeSet::from([0]), net.get_parent_set(1));
+    assert_eq!(BTreeSet::from([1]), net.get_parent_set(2));
+
+    let mut cim_generator: UniformParametersGenerator =
+        RandomParametersGenerator::new(0.5..3.0, Some(7641630759785120));
+    cim_generator.generate_parameters(&mut net);
+    let data = trajectory_generator(&net, 2, 1.0, Some(1));
+    assert_eq!(2, data.get_trajectories().len());
+
+    assert!(matches!(
+        CtbnNetwork::structure_from_graphml(graphml, &HashMap::new()),
+        Err(reCTBN::io::IoError::InvalidNetwork(_))
+    ));
+}
+
+#[test]
+fn structure_from_graphml_undirected_edges() {
+    let graphml = get_mixed_discrete_net_3_nodes().to_graphml();
+    let undirected = graphml.replace(
+        "<edge source=\"n1\" target=\"n2\"/>",
+        "<edge source=\"n1\" target=\"n2\" directed=\"false\"/>",
+    );
+    assert_eq!(
+        Err(reCTBN::io::IoError::InvalidNetwork(String::from(
+            "undirected edge between n2 and n3"
+        ))),
+        CtbnNetwork::structure_from_graphml(&undirected, &HashMap::new()).map(|_| ())
+    );
+    let undirected = graphml.replace("edgedefault=\"directed\"", "edgedefault=\"undirected\"");
+    assert!(CtbnNetwork::structure_from_graphml(&undirected, &HashMap::new()).is_err());
+}
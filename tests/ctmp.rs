@@ -38,6 +38,29 @@ fn add_two_nodes_to_ctmp() {
     };
 }
 
+
+#[test]
+fn remove_node_from_ctmp() {
+    let mut net = CtmpProcess::new();
+    assert!(net.remove_node(0).is_err());
+    let n1 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n1"), 2))
+        .unwrap();
+    assert!(net.remove_node(1).is_err());
+    assert_eq!(vec![None], net.remove_node(n1).unwrap());
+    assert_eq!(0, net.get_number_of_nodes());
+}
+
+#[test]
+fn ctmp_has_no_edges() {
+    let mut net = CtmpProcess::new();
+    assert_eq!(0, net.edges().count());
+    let n1 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n1"), 2))
+        .unwrap();
+    assert!(!net.has_edge(n1, n1));
+    assert_eq!(0, net.edges().count());
+}
 #[test]
 #[should_panic]
 fn add_edge_to_ctmp() {
@@ -125,3 +148,48 @@ fn compute_index_from_custom_parent_set_ctmp() {
         &BTreeSet::from([0])
     );
 }
+
+#[test]
+fn display_ctmp() {
+    let mut net = CtmpProcess::new();
+    assert_eq!("", format!("{}", net));
+    let _n1 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n1"), 2))
+        .unwrap();
+    assert_eq!("0: n1 [2] <- {}\n", format!("{}", net));
+    assert_eq!("0: n1 [2] <- {} (cim: not set)\n", format!("{:#}", net));
+}
+
+#[test]
+fn ctmp_to_prism() {
+    let mut net = CtmpProcess::new();
+    let n1 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n1"), 3))
+        .unwrap();
+    match &mut net.get_node_mut(n1) {
+        params::Params::DiscreteStatesContinousTime(param) => {
+            assert_eq!(
+                Ok(()),
+                param.set_cim(ndarray::arr3(&[[
+                    [-3.0, 3.0, 0.0],
+                    [1.5, -2.0, 0.5],
+                    [0.0, 0.6, -0.6]
+                ]]))
+            );
+        }
+    }
+    assert_eq!(
+        "ctmc
+
+module ctmp
+    s : [0..2];
+
+    [] s=0 -> 3 : (s'=1);
+    [] s=1 -> 1.5 : (s'=0);
+    [] s=1 -> 0.5 : (s'=2);
+    [] s=2 -> 0.6 : (s'=1);
+endmodule
+",
+        net.to_prism()
+    );
+}
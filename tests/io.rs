@@ -0,0 +1,138 @@
+use std::collections::BTreeSet;
+
+use ndarray::arr3;
+use reCTBN::io::*;
+use reCTBN::params;
+use reCTBN::process::NetworkProcess;
+
+const NET_3_NODES: &str = r#"<?xml version="1.0"?>
+<!-- Chain X -> Y plus Z with parents Y and X -->
+<BIF VERSION="0.3">
+<NETWORK>
+    <NAME>test</NAME>
+    <VARIABLE TYPE="nature">
+        <NAME>X</NAME>
+        <OUTCOME>low</OUTCOME>
+        <OUTCOME>high</OUTCOME>
+        <PROPERTY>position = (0, 0)</PROPERTY>
+    </VARIABLE>
+    <VARIABLE TYPE="nature">
+        <NAME>Y</NAME>
+        <OUTCOME>0</OUTCOME>
+        <OUTCOME>1</OUTCOME>
+    </VARIABLE>
+    <VARIABLE TYPE="nature">
+        <NAME>Z</NAME>
+        <OUTCOME>0</OUTCOME>
+        <OUTCOME>1</OUTCOME>
+    </VARIABLE>
+    <DEFINITION>
+        <FOR>X</FOR>
+        <TABLE>-1.0 1.0 2.0 -2.0</TABLE>
+    </DEFINITION>
+    <DEFINITION>
+        <FOR>Y</FOR>
+        <GIVEN>X</GIVEN>
+        <TABLE>
+            -1.0 1.0 1.0 -1.0
+            -3.0 3.0 3.0 -3.0
+        </TABLE>
+    </DEFINITION>
+    <DEFINITION>
+        <FOR>Z</FOR>
+        <GIVEN>Y</GIVEN>
+        <GIVEN>X</GIVEN>
+        <TABLE>
+            -1.0 1.0 1.0 -1.0
+            -2.0 2.0 2.0 -2.0
+            -3.0 3.0 3.0 -3.0
+            -4.0 4.0 4.0 -4.0
+        </TABLE>
+    </DEFINITION>
+</NETWORK>
+</BIF>
+"#;
+
+fn get_cim<T: NetworkProcess>(net: &T, node: usize) -> ndarray::Array3<f64> {
+    match net.get_node(node) {
+        params::Params::DiscreteStatesContinousTime(p) => p.get_cim().clone().unwrap(),
+    }
+}
+
+#[test]
+fn parse_xml_ctbn_3_nodes() {
+    let net = parse_xml_ctbn(NET_3_NODES).unwrap();
+    assert_eq!(3, net.get_number_of_nodes());
+    assert_eq!(BTreeSet::new(), net.get_parent_set(0));
+    assert_eq!(BTreeSet::from([0]), net.get_parent_set(1));
+    assert_eq!(BTreeSet::from([0, 1]), net.get_parent_set(2));
+
+    //The states of X are sorted: high has index 0 and low index 1.
+    assert_eq!(arr3(&[[[-2.0, 2.0], [1.0, -1.0]]]), get_cim(&net, 0));
+    assert_eq!(
+        arr3(&[
+            [[-3.0, 3.0], [3.0, -3.0]],
+            [[-1.0, 1.0], [1.0, -1.0]]
+        ]),
+        get_cim(&net, 1)
+    );
+    //Configuration index: X + 2 * Y.
+    assert_eq!(
+        arr3(&[
+            [[-2.0, 2.0], [2.0, -2.0]],
+            [[-1.0, 1.0], [1.0, -1.0]],
+            [[-4.0, 4.0], [4.0, -4.0]],
+            [[-3.0, 3.0], [3.0, -3.0]]
+        ]),
+        get_cim(&net, 2)
+    );
+}
+
+#[test]
+fn read_xml_ctbn_from_file() {
+    let path = std::env::temp_dir().join("reCTBN_read_xml_ctbn_from_file.xml");
+    std::fs::write(&path, NET_3_NODES).unwrap();
+    let net = read_xml_ctbn(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(3, net.get_number_of_nodes());
+    assert!(matches!(
+        read_xml_ctbn(&path),
+        Err(IoError::Io(_))
+    ));
+}
+
+#[test]
+fn parse_xml_ctbn_mismatched_table() {
+    let xml = NET_3_NODES.replace("-4.0 4.0 4.0 -4.0", "");
+    match parse_xml_ctbn(&xml) {
+        Err(IoError::InvalidNetwork(message)) => assert!(message.starts_with("node Z:")),
+        _ => panic!("The table with missing entries must be rejected"),
+    }
+}
+
+#[test]
+fn parse_xml_ctbn_invalid_cim() {
+    let xml = NET_3_NODES.replace("-1.0 1.0 2.0 -2.0", "1.0 1.0 2.0 -2.0");
+    match parse_xml_ctbn(&xml) {
+        Err(IoError::InvalidNetwork(message)) => assert!(message.starts_with("node X:")),
+        _ => panic!("The invalid cim must be rejected"),
+    }
+}
+
+#[test]
+fn parse_xml_ctbn_unknown_parent() {
+    let xml = NET_3_NODES.replace("<GIVEN>Y</GIVEN>", "<GIVEN>W</GIVEN>");
+    assert_eq!(
+        Err(IoError::InvalidNetwork(String::from("node Z: unknown parent W"))),
+        parse_xml_ctbn(&xml).map(|_| ())
+    );
+}
+
+#[test]
+fn parse_xml_ctbn_malformed() {
+    let xml = NET_3_NODES.replace("</VARIABLE>", "</VARIABLES>");
+    assert!(matches!(
+        parse_xml_ctbn(&xml),
+        Err(IoError::MalformedXml(_))
+    ));
+}
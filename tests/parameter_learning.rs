@@ -1,15 +1,25 @@
 #![allow(non_snake_case)]
 
 mod utils;
-use ndarray::arr3;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use ndarray::{arr1, arr2, arr3};
 use reCTBN::process::ctbn::*;
 use reCTBN::process::NetworkProcess;
 use reCTBN::parameter_learning::*;
 use reCTBN::params;
 use reCTBN::params::Params::DiscreteStatesContinousTime;
+use reCTBN::params::ParamsTrait;
+use reCTBN::reward::reward_evaluation::{MonteCarloReward, RewardCriteria};
+use reCTBN::reward::reward_function::FactoredRewardFunction;
+use reCTBN::reward::{RewardEvaluation, RewardFunction};
 use reCTBN::tools::*;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
 use utils::*;
 
+#[macro_use]
 extern crate approx;
 use crate::approx::AbsDiffEq;
 
@@ -129,13 +139,13 @@ fn learn_binary_cim_MLE_gen() {
 
 #[test]
 fn learn_binary_cim_BA() {
-    let ba = BayesianApproach { alpha: 1, tau: 1.0 };
+    let ba = BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) };
     learn_binary_cim(ba);
 }
 
 #[test]
 fn learn_binary_cim_BA_gen() {
-    let ba = BayesianApproach { alpha: 1, tau: 1.0 };
+    let ba = BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) };
     learn_binary_cim_gen(ba);
 }
 
@@ -263,16 +273,67 @@ fn learn_ternary_cim_MLE_gen() {
 
 #[test]
 fn learn_ternary_cim_BA() {
-    let ba = BayesianApproach { alpha: 1, tau: 1.0 };
+    let ba = BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) };
     learn_ternary_cim(ba);
 }
 
 #[test]
 fn learn_ternary_cim_BA_gen() {
-    let ba = BayesianApproach { alpha: 1, tau: 1.0 };
+    let ba = BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) };
     learn_ternary_cim_gen(ba);
 }
 
+#[test]
+fn learn_ternary_cim_SmoothedMLE() {
+    let sm = SmoothedMLE { pseudo_transitions: 1.0, pseudo_time: 1.0 };
+    learn_ternary_cim(sm);
+}
+
+#[test]
+fn learn_ternary_cim_SmoothedMLE_gen() {
+    let sm = SmoothedMLE { pseudo_transitions: 1.0, pseudo_time: 1.0 };
+    learn_ternary_cim_gen(sm);
+}
+
+#[test]
+fn learn_ternary_cim_SmoothedMLE_converges_to_MLE() {
+    let mut net = CtbnNetwork::new();
+    generate_nodes(&mut net, 2, 3);
+    net.add_edge(0, 1);
+    let mut cim_generator: UniformParametersGenerator = RandomParametersGenerator::new(
+        4.0..6.0,
+        Some(6813071588535822)
+    );
+    cim_generator.generate_parameters(&mut net);
+    let data = trajectory_generator(&net, 10, 10.0, Some(6347747169756259));
+
+    let (M, T) = sufficient_statistics(&net, &data, 1, &net.get_parent_set(1));
+    let DiscreteStatesContinousTime(mle) = MLE {}.fit(&net, &data, 1, None);
+    let mle = mle.get_cim().as_ref().unwrap().clone();
+
+    let mut previous_error = f64::INFINITY;
+    for pseudo in [1.0, 1e-2, 1e-4, 1e-6] {
+        let sm = SmoothedMLE { pseudo_transitions: pseudo, pseudo_time: pseudo };
+        let DiscreteStatesContinousTime(p) = sm.fit(&net, &data, 1, None);
+        let cim = p.get_cim().as_ref().unwrap();
+
+        //The pseudo-counts are not spread over the parent configurations.
+        for ((u, x, y), rate) in cim.indexed_iter().filter(|((_, x, y), _)| x != y) {
+            let expected = (M[[u, x, y]] as f64 + pseudo) / (T[[u, x]] + pseudo);
+            assert!(f64::abs(rate - expected) < 1e-12);
+        }
+
+        let error = (cim - &mle).mapv(f64::abs).fold(0.0, |acc: f64, x| acc.max(*x));
+        assert!(error < previous_error);
+        previous_error = error;
+    }
+    assert!(previous_error < 1e-5);
+
+    let sm = SmoothedMLE { pseudo_transitions: 0.0, pseudo_time: 0.0 };
+    let DiscreteStatesContinousTime(p) = sm.fit(&net, &data, 1, None);
+    assert_eq!(p.get_cim().as_ref().unwrap(), &mle);
+}
+
 fn learn_ternary_cim_no_parents<T: ParameterLearning>(pl: T) {
     let mut net = CtbnNetwork::new();
     let n1 = net
@@ -387,13 +448,13 @@ fn learn_ternary_cim_no_parents_MLE_gen() {
 
 #[test]
 fn learn_ternary_cim_no_parents_BA() {
-    let ba = BayesianApproach { alpha: 1, tau: 1.0 };
+    let ba = BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) };
     learn_ternary_cim_no_parents(ba);
 }
 
 #[test]
 fn learn_ternary_cim_no_parents_BA_gen() {
-    let ba = BayesianApproach { alpha: 1, tau: 1.0 };
+    let ba = BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) };
     learn_ternary_cim_no_parents_gen(ba);
 }
 
@@ -637,12 +698,1136 @@ fn learn_mixed_discrete_cim_MLE_gen() {
 
 #[test]
 fn learn_mixed_discrete_cim_BA() {
-    let ba = BayesianApproach { alpha: 1, tau: 1.0 };
+    let ba = BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) };
     learn_mixed_discrete_cim(ba);
 }
 
 #[test]
 fn learn_mixed_discrete_cim_BA_gen() {
-    let ba = BayesianApproach { alpha: 1, tau: 1.0 };
+    let ba = BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) };
     learn_mixed_discrete_cim_gen(ba);
 }
+
+fn get_multiscale_net() -> CtbnNetwork {
+    let mut net = CtbnNetwork::new();
+    let n1 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n1"), 2))
+        .unwrap();
+    let n2 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n2"), 2))
+        .unwrap();
+    net.add_edge(n1, n2);
+
+    match &mut net.get_node_mut(n1) {
+        params::Params::DiscreteStatesContinousTime(param) => {
+            assert_eq!(Ok(()), param.set_cim(arr3(&[[[-3.0, 3.0], [2.0, -2.0]]])));
+        }
+    }
+    match &mut net.get_node_mut(n2) {
+        params::Params::DiscreteStatesContinousTime(param) => {
+            assert_eq!(
+                Ok(()),
+                param.set_cim(arr3(&[
+                    [[-0.01, 0.01], [5.0, -5.0]],
+                    [[-5.0, 5.0], [0.01, -0.01]]
+                ]))
+            );
+        }
+    }
+    net
+}
+
+#[test]
+fn learn_binary_cim_BA_tau_matrix() {
+    let net = get_multiscale_net();
+    let data = trajectory_generator(&net, 2, 1.0, Some(6347747169756259));
+
+    //alpha is split over the 2 parent configurations: the prior rate of each entry is
+    //(alpha / 2) / tau[i, x]
+    let informative = BayesianApproach {
+        alpha: Alpha::Constant(20.0),
+        tau: Tau::Matrix(ndarray::arr2(&[[1000.0, 2.0], [2.0, 1000.0]])),
+    };
+    let uninformative = BayesianApproach {
+        alpha: Alpha::Constant(20.0),
+        tau: Tau::Constant(20.0),
+    };
+
+    let error = |pl: &BayesianApproach| -> f64 {
+        match pl.fit(&net, &data, 1, None) {
+            DiscreteStatesContinousTime(p) => {
+                (p.get_cim().clone().unwrap() - get_cim(&net, 1)).mapv(f64::abs).sum()
+            }
+        }
+    };
+    assert!(error(&informative) < 0.5);
+    assert!(error(&informative) < error(&uninformative));
+}
+
+#[test]
+fn learn_binary_cim_BA_tau_matrix_constant_equivalence() {
+    let net = get_multiscale_net();
+    let data = trajectory_generator(&net, 10, 5.0, Some(6347747169756259));
+
+    let constant = BayesianApproach {
+        alpha: Alpha::Constant(1.0),
+        tau: Tau::Constant(1.0),
+    };
+    let matrix = BayesianApproach {
+        alpha: Alpha::Constant(1.0),
+        tau: Tau::Matrix(ndarray::Array2::from_elem((2, 2), 0.5)),
+    };
+    let (DiscreteStatesContinousTime(constant), DiscreteStatesContinousTime(matrix)) = (
+        constant.fit(&net, &data, 1, None),
+        matrix.fit(&net, &data, 1, None),
+    );
+    assert_eq!(constant.get_cim(), matrix.get_cim());
+}
+
+#[test]
+#[should_panic]
+fn learn_binary_cim_BA_tau_matrix_wrong_shape() {
+    let net = get_multiscale_net();
+    let data = trajectory_generator(&net, 2, 1.0, Some(6347747169756259));
+
+    let pl = BayesianApproach {
+        alpha: Alpha::Constant(1.0),
+        tau: Tau::Matrix(ndarray::Array2::from_elem((1, 2), 1.0)),
+    };
+    pl.fit(&net, &data, 1, None);
+}
+
+#[test]
+fn learn_binary_cim_BA_alpha_posterior_mean() {
+    let mut net = CtbnNetwork::new();
+    let n1 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n1"), 2))
+        .unwrap();
+
+    //One transition 0 -> 1, 0.1 time units in state 0 and 0.2 in state 1
+    let trj = Trajectory::new(
+        ndarray::arr1(&[0.0, 0.1, 0.3]),
+        ndarray::arr2(&[[0], [1], [1]]),
+    );
+    let data = Dataset::new(vec![trj]);
+
+    let fit = |pl: BayesianApproach| match pl.fit(&net, &data, n1, Some(BTreeSet::new())) {
+        DiscreteStatesContinousTime(p) => p.get_cim().clone().unwrap(),
+    };
+
+    //q_xy = (M[x, y] + alpha) / (T[x] + tau)
+    let cim = fit(BayesianApproach {
+        alpha: Alpha::Constant(0.5),
+        tau: Tau::Constant(1.0),
+    });
+    assert!(cim.abs_diff_eq(
+        &arr3(&[[[-1.5 / 1.1, 1.5 / 1.1], [0.5 / 1.2, -0.5 / 1.2]]]),
+        1e-9
+    ));
+
+    //q_xy = (M[x, y] + alpha[x, y]) / (T[x] + tau[x])
+    let cim = fit(BayesianApproach {
+        alpha: Alpha::Matrix(arr3(&[[[0.0, 2.0], [3.0, 0.0]]])),
+        tau: Tau::Matrix(ndarray::arr2(&[[1.0, 2.0]])),
+    });
+    assert!(cim.abs_diff_eq(
+        &arr3(&[[[-3.0 / 1.1, 3.0 / 1.1], [3.0 / 2.2, -3.0 / 2.2]]]),
+        1e-9
+    ));
+
+    //The usize conversion keeps the previous behaviour
+    assert_eq!(
+        fit(BayesianApproach {
+            alpha: 1.into(),
+            tau: Tau::Constant(1.0),
+        }),
+        fit(BayesianApproach {
+            alpha: Alpha::Constant(1.0),
+            tau: Tau::Constant(1.0),
+        })
+    );
+}
+
+#[test]
+#[should_panic]
+fn learn_binary_cim_BA_alpha_matrix_wrong_shape() {
+    let net = get_multiscale_net();
+    let data = trajectory_generator(&net, 2, 1.0, Some(6347747169756259));
+
+    let pl = BayesianApproach {
+        alpha: Alpha::Matrix(ndarray::Array3::from_elem((1, 2, 2), 1.0)),
+        tau: Tau::Constant(1.0),
+    };
+    pl.fit(&net, &data, 1, None);
+}
+
+fn get_binary_net() -> CtbnNetwork {
+    let mut net = CtbnNetwork::new();
+    let n1 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n1"), 2))
+        .unwrap();
+    let n2 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n2"), 2))
+        .unwrap();
+    net.add_edge(n1, n2);
+
+    match &mut net.get_node_mut(n1) {
+        params::Params::DiscreteStatesContinousTime(param) => {
+            assert_eq!(Ok(()), param.set_cim(arr3(&[[[-3.0, 3.0], [2.0, -2.0]]])));
+        }
+    }
+    match &mut net.get_node_mut(n2) {
+        params::Params::DiscreteStatesContinousTime(param) => {
+            assert_eq!(
+                Ok(()),
+                param.set_cim(arr3(&[
+                    [[-1.0, 1.0], [4.0, -4.0]],
+                    [[-6.0, 6.0], [2.0, -2.0]]
+                ]))
+            );
+        }
+    }
+    net
+}
+
+#[test]
+fn learn_binary_cim_BA_posterior() {
+    let net = get_binary_net();
+    let data = trajectory_generator(&net, 100, 100.0, Some(6347747169756259));
+    let ba = BayesianApproach {
+        alpha: Alpha::Constant(1.0),
+        tau: Tau::Constant(1.0),
+    };
+
+    let posterior = ba.fit_posterior(&net, &data, 1, None);
+    assert_eq!(posterior.get_shape().shape(), [2, 2, 2]);
+    assert_eq!(posterior.get_rate().shape(), [2, 2]);
+
+    //The posterior mean is the CIM learned by fit
+    let cim = match ba.fit(&net, &data, 1, None) {
+        DiscreteStatesContinousTime(p) => p.get_cim().clone().unwrap(),
+    };
+    assert!(posterior.mean().abs_diff_eq(&cim, 1e-9));
+    assert!(posterior.mode().abs_diff_eq(&cim, 0.1));
+
+    //The credible intervals contain the generating rates, and the interval on the diagonal
+    //contains the opposite of the exit rate.
+    let (lower, upper) = posterior.credible_interval(0.99);
+    let real_cim = get_cim(&net, 1);
+    for ((idx, real), (l, u)) in real_cim
+        .indexed_iter()
+        .zip(lower.iter().zip(upper.iter()))
+    {
+        assert!(l <= real && real <= u, "{:?}: {} not in [{}, {}]", idx, real, l, u);
+    }
+}
+
+#[test]
+fn learn_binary_cim_BA_posterior_sample() {
+    let net = get_binary_net();
+    let data = trajectory_generator(&net, 5, 1.0, Some(6347747169756259));
+    let ba = BayesianApproach {
+        alpha: Alpha::Constant(1.0),
+        tau: Tau::Constant(1.0),
+    };
+    let posterior = ba.fit_posterior(&net, &data, 1, None);
+
+    let mut rng = ChaCha8Rng::seed_from_u64(6347747169756259);
+    let n_samples = 5000;
+    let mut sum = ndarray::Array3::<f64>::zeros((2, 2, 2));
+    for _ in 0..n_samples {
+        let sample = posterior.sample(&mut rng);
+        //Each draw is a valid CIM
+        match net.get_node(1).clone() {
+            DiscreteStatesContinousTime(mut p) => assert_eq!(Ok(()), p.set_cim(sample.clone())),
+        }
+        sum = sum + sample;
+    }
+    let sample_mean = sum / n_samples as f64;
+    let mean = posterior.mean();
+    assert!(sample_mean.abs_diff_eq(&mean, mean.mapv(f64::abs).sum() * 0.01));
+
+    //A network sampled from the posteriors can be simulated
+    let posteriors: BTreeMap<usize, CIMPosterior> = net
+        .get_node_indices()
+        .map(|node| (node, ba.fit_posterior(&net, &data, node, None)))
+        .collect();
+    let sampled_net = sample_network(&net, &posteriors, &mut rng).unwrap();
+    assert_ne!(get_cim(&sampled_net, 1), get_cim(&net, 1));
+    let sampled_data = trajectory_generator(&sampled_net, 5, 1.0, Some(6347747169756259));
+    assert_eq!(sampled_data.get_trajectories().len(), 5);
+}
+
+#[test]
+fn learn_binary_cim_MLE_confidence_intervals() {
+    let net = get_binary_net();
+    let real_cim = get_cim(&net, 1);
+
+    let n_repetitions = 200;
+    let mut covered = 0;
+    for seed in 0..n_repetitions {
+        let data = trajectory_generator(&net, 10, 10.0, Some(seed));
+        let p = match (MLE {}).fit(&net, &data, 1, None) {
+            DiscreteStatesContinousTime(p) => p,
+        };
+        let intervals = p.get_confidence_intervals(0.95).unwrap();
+        assert_eq!(
+            intervals,
+            mle_confidence_intervals(
+                p.get_transitions().as_ref().unwrap(),
+                p.get_residence_time().as_ref().unwrap(),
+                0.95
+            )
+        );
+        covered += real_cim
+            .iter()
+            .zip(intervals.lower.iter().zip(intervals.upper.iter()))
+            .filter(|(real, (l, u))| l <= real && real <= u)
+            .count();
+    }
+    let coverage = covered as f64 / (n_repetitions as usize * real_cim.len()) as f64;
+    assert!(0.9 < coverage && coverage < 0.99, "coverage: {}", coverage);
+}
+
+#[test]
+fn mle_confidence_intervals_zero_exposure() {
+    let transitions = ndarray::arr3(&[[[0, 2], [0, 0]]]);
+    let residence_time = ndarray::arr2(&[[4.0, 0.0]]);
+    let intervals = mle_confidence_intervals(&transitions, &residence_time, 0.95);
+
+    assert_abs_diff_eq!(intervals.standard_error[[0, 0, 1]], 2.0_f64.sqrt() / 4.0);
+    assert_abs_diff_eq!(intervals.standard_error[[0, 0, 0]], 2.0_f64.sqrt() / 4.0);
+    assert_abs_diff_eq!(
+        intervals.lower[[0, 0, 1]] + intervals.upper[[0, 0, 1]],
+        2.0 * 0.5
+    );
+    assert_abs_diff_eq!(
+        intervals.lower[[0, 0, 0]] + intervals.upper[[0, 0, 0]],
+        -2.0 * 0.5
+    );
+    assert!(intervals.standard_error.slice(ndarray::s![0, 1, ..]).iter().all(|x| x.is_nan()));
+    assert!(intervals.lower.slice(ndarray::s![0, 1, ..]).iter().all(|x| x.is_nan()));
+}
+
+#[test]
+fn fit_all_installs_params() {
+    let net = get_binary_net();
+    let data = trajectory_generator(&net, 100, 10.0, Some(6347747169756259));
+
+    //Same structure, no parameters
+    let mut learned_net = CtbnNetwork::new();
+    for node in net.get_node_indices() {
+        learned_net
+            .add_node(generate_discrete_time_continous_node(
+                net.get_node(node).get_label().clone(),
+                2,
+            ))
+            .unwrap();
+    }
+    learned_net.add_edge(0, 1);
+
+    let summary = MLE {}.fit_all(&mut learned_net, &data);
+    assert_eq!(summary.len(), 2);
+    for (node, node_summary) in summary.iter().enumerate() {
+        assert_eq!(node_summary.node, node);
+        assert!(node_summary.transitions > 0);
+        //Every node is observed over the whole length of the trajectories
+        assert_abs_diff_eq!(node_summary.exposure, 100.0 * 10.0, epsilon = 1e-6);
+        match learned_net.get_node(node) {
+            DiscreteStatesContinousTime(p) => {
+                assert_eq!(
+                    p.get_transitions().as_ref().unwrap().sum(),
+                    node_summary.transitions
+                );
+                assert!(p.get_cim().as_ref().unwrap().abs_diff_eq(&get_cim(&net, node), 0.5));
+            }
+        }
+    }
+
+    //The learned network is ready to be simulated and evaluated
+    let learned_data = trajectory_generator(&learned_net, 10, 1.0, Some(6347747169756259));
+    assert_eq!(learned_data.get_trajectories().len(), 10);
+
+    let mut rf = FactoredRewardFunction::initialize_from_network_process(&learned_net);
+    rf.get_instantaneous_reward_mut(0)
+        .assign(&ndarray::arr1(&[1.0, 1.0]));
+    let mc = MonteCarloReward::new(
+        100,
+        1e-1,
+        1e-1,
+        1.0,
+        RewardCriteria::FiniteHorizon,
+        Some(215),
+    );
+    let s0 = vec![params::StateType::Discrete(0), params::StateType::Discrete(0)];
+    assert_abs_diff_eq!(1.0, mc.evaluate_state(&learned_net, &rf, &s0), epsilon = 1e-6);
+}
+
+#[test]
+fn learn_binary_cim_weighted_equals_duplicated() {
+    let net = get_binary_net();
+    let data = trajectory_generator(&net, 3, 10.0, Some(6347747169756259));
+    let trajectories = data.get_trajectories();
+
+    let weighted = Dataset::new(trajectories.clone()).with_weights(ndarray::arr1(&[2.0, 1.0, 1.0]));
+    let duplicated = Dataset::new(vec![
+        trajectories[0].clone(),
+        trajectories[0].clone(),
+        trajectories[1].clone(),
+        trajectories[2].clone(),
+    ]);
+
+    let (M_w, T_w) = weighted_sufficient_statistics(&net, &weighted, 1, &net.get_parent_set(1));
+    let (M_d, T_d) = sufficient_statistics(&net, &duplicated, 1, &net.get_parent_set(1));
+    assert_eq!(M_w, M_d.mapv(|x| x as f64));
+    assert!(T_w.abs_diff_eq(&T_d, 1e-9));
+
+    let ba = BayesianApproach {
+        alpha: Alpha::Constant(1.0),
+        tau: Tau::Constant(1.0),
+    };
+    for (learned_w, learned_d) in [
+        (MLE {}.fit(&net, &weighted, 1, None), MLE {}.fit(&net, &duplicated, 1, None)),
+        (ba.fit(&net, &weighted, 1, None), ba.fit(&net, &duplicated, 1, None)),
+    ] {
+        let (DiscreteStatesContinousTime(learned_w), DiscreteStatesContinousTime(learned_d)) =
+            (learned_w, learned_d);
+        assert!(learned_w
+            .get_cim()
+            .as_ref()
+            .unwrap()
+            .abs_diff_eq(learned_d.get_cim().as_ref().unwrap(), 1e-9));
+        assert_eq!(learned_w.get_transitions(), learned_d.get_transitions());
+    }
+}
+
+#[test]
+fn learn_binary_cim_unit_weights_unchanged() {
+    let net = get_binary_net();
+    let data = trajectory_generator(&net, 3, 10.0, Some(6347747169756259));
+    let weighted = data.clone().with_weights(ndarray::Array1::ones(3));
+
+    let (M, T) = sufficient_statistics(&net, &data, 1, &net.get_parent_set(1));
+    let (M_w, T_w) = weighted_sufficient_statistics(&net, &weighted, 1, &net.get_parent_set(1));
+    assert_eq!(M.mapv(|x| x as f64), M_w);
+    assert_eq!(T, T_w);
+
+    let (DiscreteStatesContinousTime(learned), DiscreteStatesContinousTime(learned_w)) =
+        (MLE {}.fit(&net, &data, 1, None), MLE {}.fit(&net, &weighted, 1, None));
+    assert_eq!(learned.get_cim(), learned_w.get_cim());
+}
+
+#[test]
+fn online_statistics_equals_batch_MLE() {
+    let net = get_binary_net();
+    let data = trajectory_generator(&net, 10, 10.0, Some(6347747169756259));
+
+    let mut online = OnlineStatistics::new(&net, 1, net.get_parent_set(1));
+    for trj in data.get_trajectories() {
+        online.update(trj);
+    }
+
+    assert_eq!(
+        online.get_sufficient_statistics(),
+        sufficient_statistics(&net, &data, 1, &net.get_parent_set(1))
+    );
+    let (DiscreteStatesContinousTime(online_p), DiscreteStatesContinousTime(batch_p)) =
+        (online.to_params(&net, &MLE {}), MLE {}.fit(&net, &data, 1, None));
+    assert_eq!(online_p.get_cim(), batch_p.get_cim());
+}
+
+#[test]
+fn online_statistics_merge() {
+    let net = get_binary_net();
+    let data = trajectory_generator(&net, 10, 10.0, Some(6347747169756259));
+    let ba = BayesianApproach {
+        alpha: Alpha::Constant(1.0),
+        tau: Tau::Constant(1.0),
+    };
+
+    let mut first = OnlineStatistics::new(&net, 1, net.get_parent_set(1));
+    let mut second = first.clone();
+    let (head, tail) = data.get_trajectories().split_at(4);
+    head.iter().for_each(|trj| first.update(trj));
+    tail.iter().for_each(|trj| second.update(trj));
+    assert_eq!(Ok(()), first.merge(&second));
+
+    let (DiscreteStatesContinousTime(online_p), DiscreteStatesContinousTime(batch_p)) =
+        (first.to_params(&net, &ba), ba.fit(&net, &data, 1, None));
+    assert!(online_p
+        .get_cim()
+        .as_ref()
+        .unwrap()
+        .abs_diff_eq(batch_p.get_cim().as_ref().unwrap(), 1e-10));
+
+    let other_node = OnlineStatistics::new(&net, 0, net.get_parent_set(0));
+    assert!(first.merge(&other_node).is_err());
+}
+
+#[test]
+fn learn_binary_cim_MLE_unobserved_configuration() {
+    let net = get_binary_net();
+    //n1 never leaves its first state, thus n2 is never observed under the second configuration.
+    let data = Dataset::new(vec![Trajectory::new(
+        arr1(&[0.0, 1.0, 3.0, 4.0, 6.0]),
+        arr2(&[[0, 0], [0, 1], [0, 0], [0, 1], [0, 1]]),
+    )]);
+
+    let DiscreteStatesContinousTime(p) = MLE {}.fit(&net, &data, 1, None);
+    assert_eq!(
+        p.get_cim().as_ref().unwrap(),
+        arr3(&[[[-1.0, 1.0], [0.25, -0.25]], [[-1.0, 1.0], [0.25, -0.25]]])
+    );
+    assert_eq!(Ok(()), p.validate_params());
+
+    //The second state of n1 is never visited: its rates are uniform.
+    let DiscreteStatesContinousTime(p) = MLE {}.fit(&net, &data, 0, None);
+    assert_eq!(p.get_cim().as_ref().unwrap(), arr3(&[[[0.0, 0.0], [1.0, -1.0]]]));
+
+    assert!(matches!(
+        MLE {}.try_fit(&net, &data, 1, None),
+        Err(ParameterLearningError::MissingConfiguration(_))
+    ));
+    let data = trajectory_generator(&net, 10, 10.0, Some(6347747169756259));
+    let (DiscreteStatesContinousTime(p), DiscreteStatesContinousTime(p_try)) =
+        (MLE {}.fit(&net, &data, 1, None), MLE {}.try_fit(&net, &data, 1, None).unwrap());
+    assert_eq!(p.get_cim(), p_try.get_cim());
+}
+
+#[test]
+#[should_panic(expected = "trajectory 1, row 1: state 2 of node n2")]
+fn learn_binary_cim_MLE_state_outside_domain() {
+    let net = get_binary_net();
+    let data = Dataset::new(vec![
+        Trajectory::new(arr1(&[0.0, 1.0]), arr2(&[[0, 0], [0, 1]])),
+        Trajectory::new(arr1(&[0.0, 1.0]), arr2(&[[0, 0], [0, 2]])),
+    ]);
+    MLE {}.fit(&net, &data, 1, None);
+}
+
+#[test]
+fn try_fit_state_outside_domain() {
+    let net = get_binary_net();
+    let data = Dataset::new(vec![
+        Trajectory::new(arr1(&[0.0, 1.0]), arr2(&[[0, 0], [0, 1]])),
+        Trajectory::new(arr1(&[0.0, 1.0]), arr2(&[[0, 0], [0, 2]])),
+    ]);
+    let bayesian = BayesianApproach {
+        alpha: Alpha::Constant(1.0),
+        tau: Tau::Constant(1.0),
+    };
+    for result in [
+        MLE {}.try_fit(&net, &data, 1, None),
+        bayesian.try_fit(&net, &data, 1, None),
+    ] {
+        match result {
+            Err(ParameterLearningError::InvalidDataset(e)) => {
+                assert!(e.to_string().contains("trajectory 1, row 1: state 2 of node n2"))
+            }
+            _ => panic!("The state outside the domain must be rejected"),
+        }
+    }
+    //The states of the other node are inside its domain.
+    assert!(bayesian.try_fit(&net, &data, 0, Some(BTreeSet::new())).is_ok());
+}
+
+#[test]
+#[should_panic(expected = "trajectory 1, row 1: state 2 of node n2")]
+fn fit_posterior_state_outside_domain() {
+    let net = get_binary_net();
+    let data = Dataset::new(vec![
+        Trajectory::new(arr1(&[0.0, 1.0]), arr2(&[[0, 0], [0, 1]])),
+        Trajectory::new(arr1(&[0.0, 1.0]), arr2(&[[0, 0], [0, 2]])),
+    ]);
+    let bayesian = BayesianApproach {
+        alpha: Alpha::Constant(1.0),
+        tau: Tau::Constant(1.0),
+    };
+    bayesian.fit_posterior(&net, &data, 1, None);
+}
+
+#[test]
+fn dataset_statistics_equals_sufficient_statistics() {
+    let net = get_sensors_net(2);
+    let specs = [
+        InterventionSpec {
+            intervention: None,
+            n_trajectories: 5,
+            t_end: 10.0,
+        },
+        InterventionSpec {
+            intervention: Some((1, 0)),
+            n_trajectories: 3,
+            t_end: 10.0,
+        },
+    ];
+    let data = interventional_trajectory_generator(&net, &specs, Some(6347747169756259));
+    let weighted = data
+        .clone()
+        .with_weights(ndarray::Array1::from_iter((0..8).map(|x| x as f64 / 2.0)));
+
+    let stats = DatasetStatistics::new(&data);
+    let weighted_stats = DatasetStatistics::new(&weighted);
+    assert_eq!(
+        data.get_trajectories()
+            .iter()
+            .map(|trj| trj.get_time().len())
+            .sum::<usize>(),
+        stats.get_number_of_events()
+    );
+    for node in 0..3 {
+        let others: Vec<usize> = (0..3).filter(|x| *x != node).collect();
+        for parent_set in [vec![], vec![others[0]], vec![others[1]], others.clone()] {
+            let parent_set = BTreeSet::from_iter(parent_set);
+            assert_eq!(
+                sufficient_statistics(&net, &data, node, &parent_set),
+                stats.sufficient_statistics(&net, node, &parent_set)
+            );
+            assert_eq!(
+                weighted_sufficient_statistics(&net, &weighted, node, &parent_set),
+                weighted_stats.weighted_sufficient_statistics(&net, node, &parent_set)
+            );
+        }
+    }
+}
+
+#[test]
+fn sufficient_statistics_censored_end() {
+    let net = get_binary_net();
+    let parent_set = net.get_parent_set(1);
+    let time = arr1(&[0.0, 1.0, 3.0, 4.5]);
+    //n2 changes state at the end of the observation.
+    let events = arr2(&[[0, 0], [0, 1], [1, 1], [1, 0]]);
+
+    let data = Dataset::new(vec![Trajectory::new(time.clone(), events.clone())]);
+    let censored = Dataset::new(vec![Trajectory::new(time, events).with_censored_end()]);
+    assert!(censored.get_trajectories()[0].is_censored());
+
+    let (M, T) = sufficient_statistics(&net, &data, 1, &parent_set);
+    let (M_c, T_c) = sufficient_statistics(&net, &censored, 1, &parent_set);
+    assert_eq!(T, T_c);
+    assert_eq!(1, M[[1, 1, 0]]);
+    let mut expected = M.clone();
+    expected[[1, 1, 0]] = 0;
+    assert_eq!(expected, M_c);
+
+    assert_eq!(
+        (M_c, T_c),
+        DatasetStatistics::new(&censored).sufficient_statistics(&net, 1, &parent_set)
+    );
+    let mut online = OnlineStatistics::new(&net, 1, parent_set.clone());
+    online.update(&censored.get_trajectories()[0].normalize());
+    assert_eq!(
+        sufficient_statistics(&net, &censored, 1, &parent_set),
+        online.get_sufficient_statistics()
+    );
+
+    //A repeated last state is not affected by the censoring.
+    let time = arr1(&[0.0, 1.0, 3.0]);
+    let events = arr2(&[[0, 0], [0, 1], [0, 1]]);
+    let data = Dataset::new(vec![Trajectory::new(time.clone(), events.clone())]);
+    let censored = Dataset::new(vec![Trajectory::new(time, events).with_censored_end()]);
+    assert_eq!(
+        sufficient_statistics(&net, &data, 1, &parent_set),
+        sufficient_statistics(&net, &censored, 1, &parent_set)
+    );
+}
+
+#[test]
+fn fit_by_label() {
+    let net = get_binary_net();
+    let data = trajectory_generator(&net, 10, 10.0, Some(6347747169756259));
+
+    let (DiscreteStatesContinousTime(by_label), DiscreteStatesContinousTime(by_index)) = (
+        MLE {}.fit_by_label(&net, &data, "n2", None).unwrap(),
+        MLE {}.fit(&net, &data, 1, None),
+    );
+    assert_eq!(by_label.get_cim(), by_index.get_cim());
+    assert!(matches!(
+        MLE {}.fit_by_label(&net, &data, "n3", None),
+        Err(ParameterLearningError::UnknownNode(_))
+    ));
+}
+
+//Child with three binary parents whose dynamics only depend on whether any parent is active.
+fn get_any_parent_net(tied: bool) -> CtbnNetwork {
+    let mut net = CtbnNetwork::new();
+    let parents: Vec<usize> = (0..3)
+        .map(|i| {
+            net.add_node(generate_discrete_time_continous_node(format!("p{}", i), 2))
+                .unwrap()
+        })
+        .collect();
+    let child = net
+        .add_node(generate_discrete_time_continous_node(String::from("x"), 2))
+        .unwrap();
+    for parent in parents.iter() {
+        net.add_edge(*parent, child);
+        match &mut net.get_node_mut(*parent) {
+            params::Params::DiscreteStatesContinousTime(param) => {
+                assert_eq!(Ok(()), param.set_cim(arr3(&[[[-0.5, 0.5], [2.0, -2.0]]])));
+            }
+        }
+    }
+
+    let inactive = arr2(&[[-1.0, 1.0], [3.0, -3.0]]);
+    let active = arr2(&[[-5.0, 5.0], [0.5, -0.5]]);
+    match &mut net.get_node_mut(child) {
+        params::Params::DiscreteStatesContinousTime(param) => {
+            if tied {
+                assert_eq!(Ok(()), param.set_configuration_groups(arr1(&[0, 1, 1, 1, 1, 1, 1, 1])));
+                assert_eq!(Ok(()), param.set_cim(ndarray::stack![ndarray::Axis(0), inactive, active]));
+            } else {
+                let mut cim = ndarray::Array3::zeros((8, 2, 2));
+                cim.index_axis_mut(ndarray::Axis(0), 0).assign(&inactive);
+                for u in 1..8 {
+                    cim.index_axis_mut(ndarray::Axis(0), u).assign(&active);
+                }
+                assert_eq!(Ok(()), param.set_cim(cim));
+            }
+        }
+    }
+    net
+}
+
+//Largest relative error of the learned CIM of the child over all the parent configurations.
+fn any_parent_max_relative_error(net: &CtbnNetwork, data: &Dataset) -> f64 {
+    let truth = get_any_parent_net(false);
+    let expected = match truth.get_node(3) {
+        params::Params::DiscreteStatesContinousTime(p) => p.get_cim().clone().unwrap(),
+    };
+    let mle = MLE {};
+    let p = match mle.fit(net, data, 3, None) {
+        params::Params::DiscreteStatesContinousTime(p) => p,
+    };
+    let cim = p.get_cim().as_ref().unwrap();
+    expected
+        .indexed_iter()
+        .map(|((u, x, y), q)| (cim[[p.get_cim_index(u), x, y]] - q).abs() / q.abs())
+        .fold(0.0, f64::max)
+}
+
+#[test]
+fn learn_tied_configurations_MLE() {
+    let tied_net = get_any_parent_net(true);
+    let full_net = get_any_parent_net(false);
+
+    let data = trajectory_generator(&tied_net, 100, 10.0, Some(6347747169756259));
+    let mle = MLE {};
+    let p = match mle.fit(&tied_net, &data, 3, None) {
+        params::Params::DiscreteStatesContinousTime(p) => p,
+    };
+    assert_eq!(p.get_cim().as_ref().unwrap().shape(), [2, 2, 2]);
+    assert_eq!(p.get_transitions().as_ref().unwrap().shape(), [2, 2, 2]);
+    assert_eq!(Ok(()), p.validate_params());
+
+    //With the same trajectories only the tied model recovers all the rates within 20%: the full
+    //model splits the data of the active regime among 7 parent configurations.
+    assert!(any_parent_max_relative_error(&tied_net, &data) < 0.2);
+    assert!(any_parent_max_relative_error(&full_net, &data) > 0.2);
+}
+
+//Child with five binary parents, each one scaling its exit rates.
+fn get_noisy_or_net(compact: bool) -> CtbnNetwork {
+    let mut net = CtbnNetwork::new();
+    let parents: Vec<usize> = (0..5)
+        .map(|i| {
+            net.add_node(generate_discrete_time_continous_node(format!("p{}", i), 2))
+                .unwrap()
+        })
+        .collect();
+    let child = net
+        .add_node(generate_discrete_time_continous_node(String::from("x"), 2))
+        .unwrap();
+    for parent in parents.iter() {
+        net.add_edge(*parent, child);
+        match &mut net.get_node_mut(*parent) {
+            params::Params::DiscreteStatesContinousTime(param) => {
+                assert_eq!(Ok(()), param.set_cim(arr3(&[[[-0.5, 0.5], [1.0, -1.0]]])));
+            }
+        }
+    }
+
+    let mut param = params::DiscreteStatesContinousTimeParams::new(
+        String::from("x"),
+        BTreeSet::from([String::from("0"), String::from("1")]),
+    );
+    assert_eq!(
+        Ok(()),
+        param.set_noisy_or_cim(
+            arr2(&[[-0.5, 0.5], [2.0, -2.0]]),
+            vec![
+                arr2(&[[1.0, 1.0], [3.0, 0.5]]),
+                arr2(&[[1.0, 1.0], [2.0, 1.0]]),
+                arr2(&[[1.0, 1.0], [1.0, 0.25]]),
+                arr2(&[[1.0, 1.0], [1.5, 1.5]]),
+                arr2(&[[1.0, 1.0], [4.0, 2.0]]),
+            ]
+        )
+    );
+    match &mut net.get_node_mut(child) {
+        params::Params::DiscreteStatesContinousTime(p) => {
+            if compact {
+                p.set_noisy_or_cim(
+                    param.get_cim().as_ref().unwrap().index_axis(ndarray::Axis(0), 0).to_owned(),
+                    param.get_rate_multipliers().unwrap().clone(),
+                )
+                .unwrap();
+            } else {
+                p.set_cim(param.get_expanded_cim().unwrap()).unwrap();
+            }
+        }
+    }
+    net
+}
+
+#[test]
+fn noisy_or_samples_match_expanded_cim() {
+    let compact_net = get_noisy_or_net(true);
+    let expanded_net = get_noisy_or_net(false);
+    match expanded_net.get_node(5) {
+        params::Params::DiscreteStatesContinousTime(p) => {
+            assert_eq!(p.get_cim().as_ref().unwrap().shape(), [32, 2, 2]);
+        }
+    }
+
+    //The same seed gives the same trajectories, since the rates of the compact model are
+    //assembled as in the expanded CIM.
+    let data = trajectory_generator(&compact_net, 50, 10.0, Some(6347747169756259));
+    let expanded_data = trajectory_generator(&expanded_net, 50, 10.0, Some(6347747169756259));
+    for (trj, expanded_trj) in data
+        .get_trajectories()
+        .iter()
+        .zip(expanded_data.get_trajectories().iter())
+    {
+        assert_eq!(trj.get_time(), expanded_trj.get_time());
+        assert_eq!(trj.get_events(), expanded_trj.get_events());
+    }
+
+    let ps = compact_net.get_parent_set(5);
+    assert_eq!(
+        sufficient_statistics(&compact_net, &data, 5, &ps),
+        sufficient_statistics(&expanded_net, &expanded_data, 5, &ps)
+    );
+}
+
+#[test]
+fn learn_noisy_or_MLE() {
+    let net = get_noisy_or_net(true);
+    let data = trajectory_generator(&net, 200, 20.0, Some(6347747169756259));
+    let pl = NoisyOrMLE {
+        max_iterations: 1000,
+        tolerance: 1e-9,
+    };
+    let p = match pl.fit(&net, &data, 5, None) {
+        params::Params::DiscreteStatesContinousTime(p) => p,
+    };
+    let expected = match net.get_node(5) {
+        params::Params::DiscreteStatesContinousTime(p) => p.clone(),
+    };
+    assert_eq!(Ok(()), p.validate_params());
+    assert_eq!(p.get_cim().as_ref().unwrap().shape(), [1, 2, 2]);
+    assert_eq!(p.get_transitions().as_ref().unwrap().shape(), [32, 2, 2]);
+    assert!(p
+        .get_cim()
+        .as_ref()
+        .unwrap()
+        .abs_diff_eq(expected.get_cim().as_ref().unwrap(), 0.05));
+    for (learned, true_multipliers) in p
+        .get_rate_multipliers()
+        .unwrap()
+        .iter()
+        .zip(expected.get_rate_multipliers().unwrap().iter())
+    {
+        assert_eq!(learned.row(0), true_multipliers.row(0));
+        assert!(learned
+            .iter()
+            .zip(true_multipliers.iter())
+            .all(|(l, t)| (l - t).abs() / t < 0.1));
+    }
+}
+
+fn get_sensors_net(n_sensors: usize) -> CtbnNetwork {
+    let mut net = CtbnNetwork::new();
+    let source = net
+        .add_node(generate_discrete_time_continous_node(String::from("source"), 2))
+        .unwrap();
+    let sensors: Vec<usize> = (0..n_sensors)
+        .map(|idx| {
+            net.add_node(generate_discrete_time_continous_node(
+                format!("sensor{}", idx),
+                2,
+            ))
+            .unwrap()
+        })
+        .collect();
+    for sensor in sensors {
+        net.add_edge(source, sensor);
+        match &mut net.get_node_mut(sensor) {
+            params::Params::DiscreteStatesContinousTime(param) => {
+                assert_eq!(
+                    Ok(()),
+                    param.set_cim(arr3(&[[[-3.0, 3.0], [0.5, -0.5]], [[-0.5, 0.5], [3.0, -3.0]]]))
+                );
+            }
+        }
+    }
+    match &mut net.get_node_mut(source) {
+        params::Params::DiscreteStatesContinousTime(param) => {
+            assert_eq!(Ok(()), param.set_cim(arr3(&[[[-1.0, 1.0], [2.0, -2.0]]])));
+        }
+    }
+    net
+}
+
+fn get_cim(net: &CtbnNetwork, node: usize) -> ndarray::Array3<f64> {
+    match net.get_node(node) {
+        DiscreteStatesContinousTime(p) => p.get_cim().clone().unwrap(),
+    }
+}
+
+#[test]
+fn tied_parameter_learning_lower_error() {
+    let n_sensors = 6;
+    let net = get_sensors_net(n_sensors);
+    let data = trajectory_generator(&net, 3, 2.0, Some(6347747169756259));
+    let sensors: Vec<usize> = (1..=n_sensors).collect();
+
+    let mut independent_net = get_sensors_net(n_sensors);
+    let independent = TiedParameterLearning::new(MLE {}, TiedNodes::new(vec![]).unwrap());
+    independent.fit_all(&mut independent_net, &data).unwrap();
+
+    let mut tied_net = get_sensors_net(n_sensors);
+    let tied = TiedParameterLearning::new(MLE {}, TiedNodes::new(vec![sensors.clone()]).unwrap());
+    tied.fit_all(&mut tied_net, &data).unwrap();
+
+    //All the sensors share the same CIM.
+    for sensor in sensors.iter() {
+        assert_eq!(get_cim(&tied_net, 1), get_cim(&tied_net, *sensor));
+    }
+    //The source is fitted independently in both cases.
+    assert_eq!(get_cim(&independent_net, 0), get_cim(&tied_net, 0));
+
+    let error = |learned: &CtbnNetwork| -> f64 {
+        sensors
+            .iter()
+            .map(|x| (get_cim(learned, *x) - get_cim(&net, *x)).mapv(f64::abs).sum())
+            .sum()
+    };
+    assert!(error(&tied_net) < error(&independent_net));
+}
+
+#[test]
+fn tied_parameter_learning_weighted_dataset() {
+    let n_sensors = 3;
+    let net = get_sensors_net(n_sensors);
+    let data = trajectory_generator(&net, 3, 2.0, Some(6347747169756259));
+    let sensors: Vec<usize> = (1..=n_sensors).collect();
+    let tied = TiedParameterLearning::new(MLE {}, TiedNodes::new(vec![sensors.clone()]).unwrap());
+
+    //A trajectory with weight 2.0 counts as the same trajectory observed twice.
+    let weighted = data.clone().with_weights(ndarray::arr1(&[2.0, 1.0, 1.0]));
+    let mut trajectories = data.get_trajectories().clone();
+    trajectories.push(trajectories[0].clone());
+    let duplicated = Dataset::new(trajectories);
+
+    let mut weighted_net = get_sensors_net(n_sensors);
+    tied.fit_all(&mut weighted_net, &weighted).unwrap();
+    let mut duplicated_net = get_sensors_net(n_sensors);
+    tied.fit_all(&mut duplicated_net, &duplicated).unwrap();
+    for node in net.get_node_indices() {
+        assert!(get_cim(&weighted_net, node).abs_diff_eq(&get_cim(&duplicated_net, node), 1e-9));
+    }
+}
+
+#[test]
+fn tied_parameter_learning_state_outside_domain() {
+    let net = get_sensors_net(2);
+    let data = Dataset::new(vec![Trajectory::new(
+        arr1(&[0.0, 1.0]),
+        arr2(&[[0, 0, 0], [0, 0, 2]]),
+    )]);
+    let mut tied_net = net.clone();
+    let tied = TiedParameterLearning::new(MLE {}, TiedNodes::new(vec![vec![1, 2]]).unwrap());
+    match tied.fit_all(&mut tied_net, &data) {
+        Err(ParameterLearningError::InvalidDataset(e)) => {
+            assert!(e.to_string().contains("state 2 of node sensor1"), "{}", e)
+        }
+        _ => panic!("The state outside the domain must be rejected"),
+    }
+    //The network is left unchanged.
+    for node in net.get_node_indices() {
+        assert_eq!(get_cim(&net, node), get_cim(&tied_net, node));
+    }
+}
+
+#[test]
+fn tied_parameter_learning_mismatched_nodes() {
+    let mut net = CtbnNetwork::new();
+    let n1 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n1"), 2))
+        .unwrap();
+    let n2 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n2"), 2))
+        .unwrap();
+    let n3 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n3"), 3))
+        .unwrap();
+    net.add_edge(n1, n2);
+    let data = Dataset::new(vec![]);
+
+    //Different domain cardinalities.
+    let tied = TiedParameterLearning::new(MLE {}, TiedNodes::new(vec![vec![n1, n3]]).unwrap());
+    assert!(matches!(
+        tied.fit_all(&mut net, &data),
+        Err(ParameterLearningError::MismatchedTiedNodes(_))
+    ));
+    //Different parent set cardinalities.
+    let tied = TiedParameterLearning::new(MLE {}, TiedNodes::new(vec![vec![n1, n2]]).unwrap());
+    assert!(matches!(
+        tied.fit_all(&mut net, &data),
+        Err(ParameterLearningError::MismatchedTiedNodes(_))
+    ));
+}
+
+#[test]
+fn tied_nodes_overlapping_groups() {
+    assert!(matches!(
+        TiedNodes::new(vec![vec![1, 2], vec![2, 3]]),
+        Err(ParameterLearningError::InvalidTiedNodes(_))
+    ));
+}
+
+#[test]
+fn learn_piecewise_cim() {
+    let mut net = CtbnNetwork::new();
+    let n1 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n1"), 2))
+        .unwrap();
+    net.initialize_adj_matrix();
+    let day = arr3(&[[[-1.0, 1.0], [2.0, -2.0]]]);
+    let night = arr3(&[[[-5.0, 5.0], [0.5, -0.5]]]);
+    match &mut net.get_node_mut(n1) {
+        params::Params::DiscreteStatesContinousTime(param) => {
+            assert_eq!(
+                Ok(()),
+                param.set_piecewise_cim(vec![(0.0, day.clone()), (5.0, night.clone())])
+            );
+        }
+    }
+
+    let data = trajectory_generator(&net, 300, 10.0, Some(6347747169756259));
+    let mle = MLE {};
+    let p = match mle.fit_piecewise(&net, &data, n1, None, &[0.0, 5.0]) {
+        DiscreteStatesContinousTime(p) => p,
+    };
+    let piecewise_cim = p.get_piecewise_cim().as_ref().unwrap();
+    assert_eq!(2, piecewise_cim.len());
+    assert_eq!(5.0, piecewise_cim[1].0);
+    assert!(piecewise_cim[0].1.abs_diff_eq(&day, 0.1));
+    assert!(piecewise_cim[1].1.abs_diff_eq(&night, 0.2));
+
+    //The residence time of each segment covers the whole segment.
+    let stats = piecewise_sufficient_statistics(&net, &data, n1, &BTreeSet::new(), &[0.0, 5.0]);
+    assert_relative_eq!(300.0 * 5.0, stats[0].1.sum(), epsilon = 1e-6);
+    assert_relative_eq!(300.0 * 5.0, stats[1].1.sum(), epsilon = 1e-6);
+}
+
+#[test]
+fn learn_binary_cim_stored_statistics() {
+    let pl = StoredStatisticsLearning::new(MLE {}, Arc::new(SuffStatsCache::new()));
+    learn_binary_cim(pl);
+}
+
+#[test]
+fn stored_statistics_save_and_load() {
+    let net = get_sensors_net(2);
+    let data = trajectory_generator(&net, 10, 5.0, Some(6347747169756259));
+
+    let pl = StoredStatisticsLearning::new(
+        BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) },
+        Arc::new(SuffStatsCache::new()),
+    );
+    for node in net.get_node_indices() {
+        pl.fit(&net, &data, node, None);
+    }
+    pl.fit(&net, &data, 1, Some(BTreeSet::new()));
+    let statistics = pl.get_suff_stats_cache();
+    assert_eq!(4, statistics.len());
+
+    let path = std::env::temp_dir().join("reCTBN_stored_statistics_save_and_load.json");
+    statistics.save(&path).unwrap();
+    let loaded = Arc::new(SuffStatsCache::load(&path).unwrap());
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(statistics.to_json(), loaded.to_json());
+
+    //The loaded statistics answer the fit without looking at the dataset.
+    let empty_data = Dataset::new(vec![Trajectory::new(
+        ndarray::arr1(&[0.0, 1.0]),
+        ndarray::arr2(&[[0, 0, 0], [0, 0, 0]]),
+    )]);
+    let pl_loaded = StoredStatisticsLearning::new(BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) }, loaded);
+    for node in net.get_node_indices() {
+        let expected = BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) }.fit(&net, &data, node, None);
+        let (DiscreteStatesContinousTime(expected), DiscreteStatesContinousTime(learned)) =
+            (expected, pl_loaded.fit(&net, &empty_data, node, None));
+        assert_eq!(expected.get_cim(), learned.get_cim());
+    }
+    assert_eq!(4, pl_loaded.get_suff_stats_cache().len());
+}
+
+#[test]
+fn stored_statistics_weighted_dataset() {
+    let net = get_sensors_net(2);
+    let data = trajectory_generator(&net, 10, 5.0, Some(6347747169756259));
+    let weighted = data
+        .clone()
+        .with_weights(ndarray::Array1::from_iter((0..10).map(|x| x as f64 / 2.0)));
+
+    let pl = StoredStatisticsLearning::new(MLE {}, Arc::new(SuffStatsCache::new()));
+    for node in net.get_node_indices() {
+        let (DiscreteStatesContinousTime(expected), DiscreteStatesContinousTime(learned)) =
+            (MLE {}.fit(&net, &weighted, node, None), pl.fit(&net, &weighted, node, None));
+        assert_eq!(expected.get_cim(), learned.get_cim());
+        assert_eq!(expected.get_residence_time(), learned.get_residence_time());
+    }
+    //The weighted statistics are not stored, thus they never answer an unweighted fit.
+    assert!(pl.get_suff_stats_cache().is_empty());
+    let (DiscreteStatesContinousTime(expected), DiscreteStatesContinousTime(learned)) =
+        (MLE {}.fit(&net, &data, 1, None), pl.fit(&net, &data, 1, None));
+    assert_eq!(expected.get_cim(), learned.get_cim());
+}
+
+#[test]
+fn stored_statistics_incompatible_network() {
+    let net = get_sensors_net(1);
+    let data = trajectory_generator(&net, 10, 5.0, Some(6347747169756259));
+    let statistics = SuffStatsCache::new();
+    statistics.get_or_compute(&net, &data, 1, &BTreeSet::from([0]));
+    assert!(statistics.get(&net, 1, &BTreeSet::from([0])).is_some());
+
+    //Same structure, but the sensor has a different cardinality.
+    let mut other_net = CtbnNetwork::new();
+    other_net
+        .add_node(generate_discrete_time_continous_node(String::from("source"), 2))
+        .unwrap();
+    other_net
+        .add_node(generate_discrete_time_continous_node(String::from("sensor_0"), 3))
+        .unwrap();
+    other_net.add_edge(0, 1);
+    assert!(statistics.get(&other_net, 1, &BTreeSet::from([0])).is_none());
+
+    let key = SuffStatsKey::new(&other_net, 1, &BTreeSet::from([0]));
+    let (M, T) = (*statistics.get(&net, 1, &BTreeSet::from([0])).unwrap()).clone();
+    assert!(matches!(
+        statistics.insert(key, M.clone(), T.clone()),
+        Err(ParameterLearningError::InvalidSufficientStatistics(_))
+    ));
+    //The cardinalities must cover the node and each parent.
+    let key = SuffStatsKey { node: 1, parent_set: BTreeSet::from([0]), cardinalities: vec![] };
+    assert!(matches!(
+        statistics.insert(key, M, T),
+        Err(ParameterLearningError::InvalidSufficientStatistics(_))
+    ));
+    assert!(SuffStatsCache::from_json("{\"version\": 1, \"statistics\": [{}]}").is_err());
+}
@@ -18,12 +18,96 @@ fn create_ternary_discrete_time_continous_param() -> DiscreteStatesContinousTime
     params
 }
 
+#[test]
+fn test_state_type_conversions_and_ordering() {
+    assert_eq!(StateType::Discrete(3), StateType::from(3));
+    assert_eq!(3, usize::from(StateType::Discrete(3)));
+    let state = StateType::Discrete(1);
+    let copied = state;
+    assert_eq!(state, copied);
+    assert!(StateType::Discrete(0) < StateType::Discrete(2));
+
+    //Network states can be used as keys of ordered and hashed maps.
+    let states: Vec<Vec<StateType>> = vec![
+        vec![StateType::from(1), StateType::from(0)],
+        vec![StateType::from(0), StateType::from(1)],
+        vec![StateType::from(0), StateType::from(0)],
+    ];
+    let ordered: std::collections::BTreeMap<Vec<StateType>, usize> =
+        states.iter().cloned().zip(0..).collect();
+    assert_eq!(
+        vec![2, 1, 0],
+        ordered.values().cloned().collect::<Vec<usize>>()
+    );
+    let hashed: std::collections::HashMap<Vec<StateType>, usize> =
+        states.iter().cloned().zip(0..).collect();
+    assert_eq!(Some(&1), hashed.get(&vec![StateType::from(0), StateType::from(1)]));
+}
+
+#[test]
+fn test_new_with_cim() {
+    let domain: std::collections::BTreeSet<String> =
+        ["A", "B"].iter().map(|x| x.to_string()).collect();
+    let cim = array![[[-1.0, 1.0], [2.0, -2.0]]];
+
+    let param =
+        DiscreteStatesContinousTimeParams::new_with_cim("X".to_string(), domain.clone(), cim.clone())
+            .unwrap();
+    assert_eq!(param.get_label(), "X");
+    assert_eq!(param.get_cim(), &Some(cim));
+
+    let invalid = DiscreteStatesContinousTimeParams::new_with_cim(
+        "X".to_string(),
+        domain,
+        array![[[-1.0, 1.0], [2.0, -1.0]]],
+    );
+    assert!(matches!(invalid, Err(ParamsError::InvalidCIM(_))));
+}
+
+#[test]
+fn test_discrete_node() {
+    let node = discrete_node("X", &["B", "A", "C"]);
+    assert_eq!(node.get_label(), "X");
+    assert_eq!(node.get_reserved_space_as_parent(), 3);
+    assert_eq!(node.state_from_label("A"), Some(StateType::Discrete(0)));
+    assert_eq!(node.state_from_label("C"), Some(StateType::Discrete(2)));
+
+    let node = generate_discrete_time_continous_node("Y".to_string(), 4);
+    assert_eq!(node.get_label(), "Y");
+    assert_eq!(node.state_from_label("3"), Some(StateType::Discrete(3)));
+}
+
 #[test]
 fn test_get_label() {
     let param = create_ternary_discrete_time_continous_param();
     assert_eq!(&String::from("A"), param.get_label())
 }
 
+#[test]
+fn test_state_label_and_index() {
+    //The index of a state is its position in the (ordered) domain.
+    let domain: std::collections::BTreeSet<String> = ["LOW", "HIGH", "MEDIUM"]
+        .iter()
+        .map(|x| x.to_string())
+        .collect();
+    let param = DiscreteStatesContinousTimeParams::new("A".to_string(), domain);
+    for (idx, label) in ["HIGH", "LOW", "MEDIUM"].iter().enumerate() {
+        assert_eq!(Some(&label.to_string()), param.state_label(idx));
+        assert_eq!(Some(idx), param.index_of_label(label));
+    }
+    assert_eq!(None, param.state_label(3));
+    assert_eq!(None, param.index_of_label("VERY HIGH"));
+
+    let node = Params::DiscreteStatesContinousTime(param);
+    assert_eq!(Some(StateType::Discrete(1)), node.state_from_label("LOW"));
+    assert_eq!(None, node.state_from_label("VERY HIGH"));
+
+    //Numeric labels are ordered as strings.
+    let param = utils::generate_discrete_time_continous_params("B".to_string(), 11);
+    assert_eq!(Some(&String::from("10")), param.state_label(2));
+    assert_eq!(Some(10), param.index_of_label("9"));
+}
+
 #[test]
 fn test_uniform_generation() {
     #![allow(irrefutable_let_patterns)]
@@ -66,6 +150,105 @@ fn test_random_generation_state() {
     assert_relative_eq!(1.0 / 5.0, zero_freq, epsilon = 0.01);
 }
 
+#[test]
+fn test_rate_accessors() {
+    let mut param = utils::generate_discrete_time_continous_params("A".to_string(), 3);
+    assert!(matches!(param.rate(0, 0, 1), Err(ParamsError::ParametersNotInitialized(_))));
+
+    let cim = array![
+        [[-3.0, 2.0, 1.0], [1.0, -5.0, 4.0], [2.3, 1.7, -4.0]],
+        [[-1.0, 0.0, 1.0], [0.0, 0.0, 0.0], [0.5, 0.5, -1.0]]
+    ];
+    param.set_cim_unchecked(cim);
+    assert_eq!(Ok(4.0), param.rate(0, 1, 2));
+    assert_eq!(Ok(-5.0), param.rate(0, 1, 1));
+    assert_eq!(Ok(5.0), param.exit_rate(0, 1));
+    assert!(matches!(param.rate(2, 0, 1), Err(ParamsError::InvalidIndex(_))));
+    assert!(matches!(param.exit_rate(0, 3), Err(ParamsError::InvalidIndex(_))));
+
+    assert_eq!(Ok(array![0.2, 0.0, 0.8]), param.transition_probabilities(0, 1));
+    assert_eq!(Ok(array![0.5, 0.5, 0.0]), param.transition_probabilities(1, 2));
+    //A zero rate is never chosen and an absorbing state moves to itself.
+    assert_eq!(Ok(array![0.0, 0.0, 1.0]), param.transition_probabilities(1, 0));
+    assert_eq!(Ok(array![0.0, 1.0, 0.0]), param.transition_probabilities(1, 1));
+    assert!(matches!(param.transition_probabilities(0, 5), Err(ParamsError::InvalidIndex(_))));
+
+    //The sampler never chooses a transition with a zero rate.
+    let mut rng = ChaCha8Rng::seed_from_u64(6347747169756259);
+    for _ in 0..100 {
+        assert_eq!(StateType::Discrete(2), param.get_random_state(0, 1, &mut rng).unwrap());
+    }
+}
+
+#[test]
+fn test_rate_accessors_tied_and_noisy_or() {
+    let mut param = utils::generate_discrete_time_continous_params("A".to_string(), 2);
+    param.set_configuration_groups(array![0, 1, 1]).unwrap();
+    param
+        .set_cim(array![[[-1.0, 1.0], [2.0, -2.0]], [[-3.0, 3.0], [4.0, -4.0]]])
+        .unwrap();
+    assert_eq!(Ok(3.0), param.rate(2, 0, 1));
+    assert!(matches!(param.rate(3, 0, 1), Err(ParamsError::InvalidIndex(_))));
+
+    param
+        .set_noisy_or_cim(array![[-1.0, 1.0], [2.0, -2.0]], vec![array![[1.0, 1.0], [3.0, 0.0]]])
+        .unwrap();
+    assert_eq!(Ok(3.0), param.exit_rate(1, 0));
+    assert_eq!(Ok(0.0), param.exit_rate(1, 1));
+    assert_eq!(Ok(array![0.0, 1.0]), param.transition_probabilities(1, 0));
+    assert_eq!(Ok(array![0.0, 1.0]), param.transition_probabilities(1, 1));
+}
+
+#[test]
+fn test_stationary_distribution() {
+    let mut param = utils::generate_discrete_time_continous_params("A".to_string(), 2);
+    // The second CIM has an absorbing state, rejected by `set_cim`.
+    param.set_cim_unchecked(array![[[-3.0, 3.0], [2.0, -2.0]], [[-1.0, 1.0], [0.0, 0.0]]]);
+
+    let pi = param.stationary_distribution(0).unwrap();
+    assert_abs_diff_eq!(pi, array![0.4, 0.6], epsilon = 1e-12);
+    let q = param.get_cim().as_ref().unwrap().index_axis(Axis(0), 0).to_owned();
+    assert_abs_diff_eq!(pi.dot(&q), array![0.0, 0.0], epsilon = 1e-12);
+
+    // A single absorbing state collects all the probability.
+    let pi = param.stationary_distribution(1).unwrap();
+    assert_abs_diff_eq!(pi, array![0.0, 1.0], epsilon = 1e-12);
+
+    assert!(matches!(
+        param.stationary_distribution(2),
+        Err(ParamsError::InvalidIndex(_))
+    ));
+
+    let mut param = create_ternary_discrete_time_continous_param();
+    let pi = param.stationary_distribution(0).unwrap();
+    assert_abs_diff_eq!(pi.sum(), 1.0, epsilon = 1e-12);
+    let q = param.get_cim().as_ref().unwrap().index_axis(Axis(0), 0).to_owned();
+    assert_abs_diff_eq!(pi.dot(&q), array![0.0, 0.0, 0.0], epsilon = 1e-12);
+
+    // Two absorbing states: the stationary distribution is not unique.
+    param.set_cim_unchecked(array![[[0.0, 0.0, 0.0], [1.0, -2.0, 1.0], [0.0, 0.0, 0.0]]]);
+    assert!(matches!(
+        param.stationary_distribution(0),
+        Err(ParamsError::InvalidCIM(_))
+    ));
+}
+
+#[test]
+fn test_expected_sojourn_times() {
+    let mut param = utils::generate_discrete_time_continous_params("A".to_string(), 2);
+    assert!(matches!(
+        param.expected_sojourn_times(0),
+        Err(ParamsError::ParametersNotInitialized(_))
+    ));
+    param.set_cim_unchecked(array![[[-4.0, 4.0], [0.5, -0.5]], [[-1.0, 1.0], [0.0, 0.0]]]);
+
+    assert_eq!(param.expected_sojourn_times(0).unwrap(), array![0.25, 2.0]);
+    assert_eq!(
+        param.expected_sojourn_times(1).unwrap(),
+        array![1.0, f64::INFINITY]
+    );
+}
+
 #[test]
 fn test_random_generation_residence_time() {
     let param = create_ternary_discrete_time_continous_param();
@@ -140,9 +323,234 @@ fn test_validate_params_row_not_sum_to_zero() {
     let cim = array![[[-3.0, 2.0, 1.0], [1.0, -5.0, 4.0], [2.3, 1.701, -4.0]]];
     let result = param.set_cim(cim);
     assert_eq!(
-        Err(ParamsError::InvalidCIM(String::from(
-            "The sum of each row must be 0"
+        Err(ParamsError::InvalidCIM(format!(
+            "The sum of each row must be 0: row 2 of parent configuration 0 sums to {}",
+            2.3 + 1.701 - 4.0
         ))),
         result
     );
 }
+
+#[test]
+fn test_validate_params_valid_cim_with_tiny_values() {
+    let mut param = utils::generate_discrete_time_continous_params("A".to_string(), 3);
+    let cim = array![[[-3e-12, 2e-12, 1e-12], [1e-12, -5e-12, 4e-12], [2.3e-12, 1.7e-12, -4e-12]]];
+    assert_eq!(Ok(()), param.set_cim(cim));
+
+    //An error of 10% of the rates is rejected even if its absolute value is tiny.
+    let cim = array![[[-3e-12, 2e-12, 1e-12], [1e-12, -5e-12, 4e-12], [2.3e-12, 2.1e-12, -4e-12]]];
+    assert!(matches!(param.set_cim(cim), Err(ParamsError::InvalidCIM(_))));
+}
+
+#[test]
+fn test_validate_params_custom_tolerance() {
+    let mut param = utils::generate_discrete_time_continous_params("A".to_string(), 2);
+    let cim = array![[[-0.1, 0.1000001], [0.3, -0.3]]];
+    assert!(matches!(param.set_cim(cim.clone()), Err(ParamsError::InvalidCIM(_))));
+    assert_eq!(Ok(()), param.set_cim_with_tolerance(cim, 1e-5));
+    assert_eq!(1e-5, param.get_tolerance());
+}
+
+#[test]
+fn test_display_params() {
+    let mut param = utils::generate_discrete_time_continous_params("A".to_string(), 2);
+    assert_eq!("A {0, 1}\ncim: not set\n", format!("{}", param));
+
+    param
+        .set_cim(array![[[-1.0, 1.0], [0.25, -0.25]], [[-3.0, 3.0], [2.0, -2.0]]])
+        .unwrap();
+    assert_eq!(
+        concat!(
+            "A {0, 1}\n",
+            "configuration 0\n",
+            "        0     1\n",
+            "  0 -1.00  1.00\n",
+            "  1  0.25 -0.25\n",
+            "configuration 1\n",
+            "        0     1\n",
+            "  0 -3.00  3.00\n",
+            "  1  2.00 -2.00\n",
+        ),
+        format!("{:.2}", param)
+    );
+}
+
+#[test]
+fn test_configuration_groups() {
+    let mut param = utils::generate_discrete_time_continous_params("A".to_string(), 2);
+    assert_eq!(3, param.get_cim_index(3));
+
+    assert!(matches!(
+        param.set_configuration_groups(array![0, 2, 2]),
+        Err(ParamsError::InvalidConfigurationGroups(_))
+    ));
+    assert!(matches!(
+        param.set_configuration_groups(array![]),
+        Err(ParamsError::InvalidConfigurationGroups(_))
+    ));
+
+    assert_eq!(Ok(()), param.set_configuration_groups(array![0, 1, 1, 1]));
+    assert_eq!(Some(2), param.get_number_of_configuration_groups());
+    assert_eq!(0, param.get_cim_index(0));
+    assert_eq!(1, param.get_cim_index(3));
+
+    //One matrix for each group is required.
+    let cim = array![[[-1.0, 1.0], [2.0, -2.0]]];
+    assert!(matches!(param.set_cim(cim), Err(ParamsError::InvalidCIM(_))));
+    let cim = array![[[-1.0, 1.0], [2.0, -2.0]], [[-3.0, 3.0], [4.0, -4.0]]];
+    assert_eq!(Ok(()), param.set_cim(cim));
+
+    param.reset_params();
+    assert_eq!(None, param.get_configuration_groups());
+}
+
+#[test]
+fn test_noisy_or_cim() {
+    let mut param = utils::generate_discrete_time_continous_params("A".to_string(), 2);
+    let base = array![[-1.0, 1.0], [2.0, -2.0]];
+    let rate_multipliers = vec![
+        array![[1.0, 1.0], [3.0, 0.5]],
+        array![[1.0, 1.0], [2.0, 1.0], [0.0, 4.0]],
+    ];
+    assert_eq!(Ok(()), param.set_noisy_or_cim(base.clone(), rate_multipliers.clone()));
+    assert_eq!(0, param.get_cim_index(5));
+    //Parent configuration 5: first parent in state 1, second parent in state 2.
+    assert_eq!(0.0, param.get_rate_multiplier(5, 0));
+    assert_eq!(2.0, param.get_rate_multiplier(5, 1));
+
+    let expanded = param.get_expanded_cim().unwrap();
+    assert_eq!(expanded.shape(), [6, 2, 2]);
+    assert_eq!(expanded.index_axis(Axis(0), 0), base);
+    assert_eq!(expanded.index_axis(Axis(0), 3), array![[-6.0, 6.0], [1.0, -1.0]]);
+
+    //The multipliers must match the domain of the node and be non-negative.
+    let mut wrong_multipliers = rate_multipliers.clone();
+    wrong_multipliers[1][[2, 0]] = -1.0;
+    assert!(matches!(
+        param.set_noisy_or_cim(base.clone(), wrong_multipliers),
+        Err(ParamsError::InvalidCIM(_))
+    ));
+    assert_eq!(None, param.get_rate_multipliers());
+    assert!(matches!(
+        param.set_noisy_or_cim(base.clone(), vec![array![[1.0, 1.0, 1.0]]]),
+        Err(ParamsError::InvalidCIM(_))
+    ));
+
+    //A plain CIM replaces the noisy-OR parameterization.
+    param.set_noisy_or_cim(base.clone(), rate_multipliers).unwrap();
+    param.set_cim(base.insert_axis(Axis(0))).unwrap();
+    assert_eq!(None, param.get_rate_multipliers());
+    assert_eq!(3, param.get_cim_index(3));
+}
+
+#[test]
+fn test_randomize_cim() {
+    let mut rng: ChaCha8Rng = SeedableRng::seed_from_u64(6813071588535822);
+    for cardinality in 2..=6 {
+        let mut param = utils::generate_discrete_time_continous_params("X".to_string(), cardinality);
+
+        param.randomize_cim(3, &Uniform(0.5..7.0), &mut rng);
+        let cim = param.get_cim().as_ref().unwrap();
+        assert_eq!(cim.shape(), [3, cardinality, cardinality]);
+        assert!(cim.axis_iter(Axis(0)).all(|x| x.diag().iter().all(|d| *d < 0.0)));
+        assert_eq!(Ok(()), param.validate_params());
+
+        param.randomize_cim(2, &Gamma { shape: 0.5, rate: 2.0 }, &mut rng);
+        assert_eq!(param.get_cim().as_ref().unwrap().shape(), [2, cardinality, cardinality]);
+        assert_eq!(Ok(()), param.validate_params());
+
+        param.randomize_cim(2, &Gamma { shape: 3.0, rate: 1.0 }, &mut rng);
+        let cim = param.get_cim().as_ref().unwrap();
+        assert!(cim.axis_iter(Axis(0)).all(|x| x.diag().iter().all(|d| *d < 0.0)));
+        assert_eq!(Ok(()), param.validate_params());
+    }
+}
+
+#[test]
+fn test_gamma_rate_distribution_mean() {
+    let mut rng: ChaCha8Rng = SeedableRng::seed_from_u64(6813071588535822);
+    for (shape, rate) in [(0.5, 2.0), (3.0, 1.5)] {
+        let dist = Gamma { shape, rate };
+        let n = 20000;
+        let mean = (0..n).map(|_| dist.sample_rate(&mut rng)).sum::<f64>() / n as f64;
+        assert_relative_eq!(mean, shape / rate, max_relative = 0.05);
+    }
+}
+
+#[test]
+fn test_piecewise_cim() {
+    let mut param = utils::generate_discrete_time_continous_params("A".to_string(), 2);
+    let day = array![[[-1.0, 1.0], [2.0, -2.0]]];
+    let night = array![[[-5.0, 5.0], [0.5, -0.5]]];
+    assert_eq!(
+        Ok(()),
+        param.set_piecewise_cim(vec![(0.0, day.clone()), (12.0, night.clone())])
+    );
+    assert_eq!(&Some(day.clone()), param.get_cim());
+    assert_eq!(2, param.get_piecewise_cim().as_ref().unwrap().len());
+
+    //A homogeneous CIM replaces the segments.
+    param.set_cim(night).unwrap();
+    assert_eq!(&None, param.get_piecewise_cim());
+}
+
+#[test]
+fn test_piecewise_cim_replaces_noisy_or_and_groups() {
+    let mut param = utils::generate_discrete_time_continous_params("A".to_string(), 2);
+    let day = array![[[-1.0, 1.0], [2.0, -2.0]], [[-3.0, 3.0], [4.0, -4.0]]];
+    let night = array![[[-5.0, 5.0], [0.5, -0.5]], [[-2.0, 2.0], [1.0, -1.0]]];
+
+    param
+        .set_noisy_or_cim(array![[-1.0, 1.0], [2.0, -2.0]], vec![array![[1.0, 1.0], [3.0, 0.5]]])
+        .unwrap();
+    param.set_piecewise_cim_unchecked(vec![(0.0, day.clone()), (12.0, night.clone())]);
+    assert_eq!(None, param.get_rate_multipliers());
+    assert_eq!(1.0, param.get_rate_multiplier(1, 0));
+    assert_eq!(Some(day.clone()), param.get_expanded_cim());
+
+    param.set_configuration_groups(array![0, 0]).unwrap();
+    param.set_piecewise_cim_unchecked(vec![(0.0, day.clone()), (12.0, night.clone())]);
+    assert_eq!(None, param.get_configuration_groups());
+    assert_eq!(1, param.get_cim_index(1));
+
+    //The segments are validated against the parent configurations, not against the groups.
+    param.set_configuration_groups(array![0, 0]).unwrap();
+    assert_eq!(
+        Ok(()),
+        param.set_piecewise_cim(vec![(0.0, day.clone()), (12.0, night)])
+    );
+    assert_eq!(None, param.get_configuration_groups());
+    assert_eq!(&Some(day), param.get_cim());
+}
+
+#[test]
+fn test_invalid_piecewise_cim() {
+    let mut param = utils::generate_discrete_time_continous_params("A".to_string(), 2);
+    let day = array![[[-1.0, 1.0], [2.0, -2.0]]];
+    let invalid = array![[[-1.0, 2.0], [2.0, -2.0]]];
+    assert!(param
+        .set_piecewise_cim(vec![(1.0, day.clone())])
+        .is_err());
+    assert!(param
+        .set_piecewise_cim(vec![(0.0, day.clone()), (0.0, day.clone())])
+        .is_err());
+    assert!(param
+        .set_piecewise_cim(vec![(0.0, day.clone()), (12.0, invalid)])
+        .is_err());
+    assert_eq!(&None, param.get_cim());
+    assert_eq!(&None, param.get_piecewise_cim());
+}
+
+#[test]
+fn test_piecewise_transition_time() {
+    let mut param = utils::generate_discrete_time_continous_params("A".to_string(), 2);
+    //The node can leave the state 0 only after t = 1.0.
+    let frozen = array![[[0.0, 0.0], [2.0, -2.0]]];
+    let fast = array![[[-10.0, 10.0], [2.0, -2.0]]];
+    param.set_piecewise_cim_unchecked(vec![(0.0, frozen), (1.0, fast)]);
+    let mut rng = ChaCha8Rng::seed_from_u64(6347747169756259);
+    for _ in 0..100 {
+        let t = param.get_random_transition_time(0, 0, 0.5, &mut rng).unwrap();
+        assert!(t >= 1.0);
+    }
+}
@@ -1,5 +1,7 @@
 mod utils;
 
+use std::sync::Arc;
+
 use approx::assert_abs_diff_eq;
 use ndarray::*;
 use reCTBN::{
@@ -120,3 +122,53 @@ fn simple_factored_reward_function_chain_mc() {
     assert_abs_diff_eq!(2.447, rst[&s000], epsilon = 1e-1);
 
 }
+
+#[test]
+fn simple_factored_reward_function_chain_mc_single_thread_pool() {
+    let mut net = CtbnNetwork::new();
+    let n1 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n1"), 2))
+        .unwrap();
+    let n2 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n2"), 2))
+        .unwrap();
+
+    net.add_edge(n1, n2);
+
+    match &mut net.get_node_mut(n1) {
+        params::Params::DiscreteStatesContinousTime(param) => {
+            param.set_cim(arr3(&[[[-0.1, 0.1], [1.0, -1.0]]])).unwrap();
+        }
+    }
+
+    match &mut net.get_node_mut(n2) {
+        params::Params::DiscreteStatesContinousTime(param) => {
+            param
+                .set_cim(arr3(&[
+                    [[-0.01, 0.01], [5.0, -5.0]],
+                    [[-5.0, 5.0], [0.01, -0.01]],
+                ]))
+                .unwrap();
+        }
+    }
+
+    let mut rf = FactoredRewardFunction::initialize_from_network_process(&net);
+    rf.get_transition_reward_mut(n1)
+        .assign(&arr2(&[[0.0, 1.0], [1.0, 0.0]]));
+    rf.get_transition_reward_mut(n2)
+        .assign(&arr2(&[[0.0, 1.0], [1.0, 0.0]]));
+
+    let mc = MonteCarloReward::new(1000, 1e-1, 1e-1, 10.0, RewardCriteria::FiniteHorizon, Some(215));
+    let rst = mc.evaluate_state_space(&net, &rf);
+
+    let thread_pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(1)
+        .build()
+        .unwrap();
+    let mc = MonteCarloReward::new(1000, 1e-1, 1e-1, 10.0, RewardCriteria::FiniteHorizon, Some(215))
+        .with_thread_pool(Arc::new(thread_pool));
+    let single_thread_rst = mc.evaluate_state_space(&net, &rf);
+
+    assert_eq!(4, single_thread_rst.len());
+    assert_eq!(rst, single_thread_rst);
+}
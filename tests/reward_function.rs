@@ -30,6 +30,28 @@ fn simple_factored_reward_function_binary_node() {
 }
 
 
+#[test]
+fn factored_reward_function_by_label() {
+    let mut net = CtbnNetwork::new();
+    net.add_node(generate_discrete_time_continous_node(String::from("n1"), 2))
+        .unwrap();
+
+    let mut rf = FactoredRewardFunction::initialize_from_network_process(&net);
+    rf.get_transition_reward_by_label_mut(&net, "n1")
+        .unwrap()
+        .assign(&arr2(&[[12.0, 1.0], [2.0, 12.0]]));
+    rf.get_instantaneous_reward_by_label_mut(&net, "n1")
+        .unwrap()
+        .assign(&arr1(&[3.0, 5.0]));
+    assert!(rf.get_transition_reward_by_label_mut(&net, "n2").is_none());
+    assert!(rf.get_instantaneous_reward_by_label_mut(&net, "n2").is_none());
+
+    let s0: NetworkProcessState = vec![params::StateType::Discrete(0)];
+    let s1: NetworkProcessState = vec![params::StateType::Discrete(1)];
+    assert_eq!(rf.call(&s0, Some(&s1)), Reward{transition_reward: 2.0, instantaneous_reward: 3.0});
+}
+
+
 #[test]
 fn simple_factored_reward_function_ternary_node() {
     let mut net = CtbnNetwork::new();
@@ -1,18 +1,30 @@
 #![allow(non_snake_case)]
 
 mod utils;
-use std::collections::BTreeSet;
-
-use ndarray::{arr1, arr2, arr3};
-use reCTBN::parameter_learning::BayesianApproach;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use ndarray::{arr1, arr2, arr3, Array1, Array3};
+use reCTBN::journal::JsonLinesJournal;
+use reCTBN::parameter_learning::{
+    sufficient_statistics, Alpha, BayesianApproach, NoisyOrMLE, Tau, MLE,
+};
 use reCTBN::params;
 use reCTBN::process::ctbn::*;
 use reCTBN::process::NetworkProcess;
 use reCTBN::structure_learning::constraint_based_algorithm::*;
+use reCTBN::structure_learning::evaluation::*;
+use reCTBN::structure_learning::hybrid_algorithm::*;
 use reCTBN::structure_learning::hypothesis_test::*;
 use reCTBN::structure_learning::score_based_algorithm::*;
 use reCTBN::structure_learning::score_function::*;
-use reCTBN::structure_learning::StructuralLearningAlgorithm;
+use reCTBN::structure_learning::{
+    CancellationToken, ParentLimit, ProgressCounter, ProgressObserver, StructuralLearningAlgorithm,
+    StructureLearningError,
+};
 use reCTBN::tools::*;
 use utils::*;
 
@@ -58,6 +70,92 @@ fn simple_bic() {
     );
 }
 
+#[test]
+fn simple_score_test_tau_matrix() {
+    let mut net = CtbnNetwork::new();
+    let n1 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n1"), 2))
+        .unwrap();
+
+    let trj = Trajectory::new(arr1(&[0.0, 0.1, 0.3]), arr2(&[[0], [1], [1]]));
+
+    let dataset = Dataset::new(vec![trj]);
+
+    //A matrix filled with the constant tau scores exactly as the constant tau
+    let ll = LogLikelihood::new(1, Tau::Matrix(arr2(&[[1.0, 1.0]])));
+    assert_abs_diff_eq!(
+        0.04257,
+        ll.call(&net, n1, &BTreeSet::new(), &dataset),
+        epsilon = 1e-3
+    );
+
+    //A per-state tau changes the score
+    let bic = BIC::new(1, Tau::Matrix(arr2(&[[1.0, 10.0]])));
+    assert!(
+        (bic.call(&net, n1, &BTreeSet::new(), &dataset)
+            - BIC::new(1, 1.0).call(&net, n1, &BTreeSet::new(), &dataset))
+        .abs()
+            > 1e-3
+    );
+}
+
+#[test]
+fn simple_score_test_alpha_matrix() {
+    let mut net = CtbnNetwork::new();
+    let n1 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n1"), 2))
+        .unwrap();
+
+    let trj = Trajectory::new(arr1(&[0.0, 0.1, 0.3]), arr2(&[[0], [1], [1]]));
+
+    let dataset = Dataset::new(vec![trj]);
+
+    //A matrix filled with the constant alpha scores exactly as the constant alpha
+    let ll = LogLikelihood::new(Alpha::Matrix(arr3(&[[[1.0, 1.0], [1.0, 1.0]]])), 1.0);
+    assert_abs_diff_eq!(
+        0.04257,
+        ll.call(&net, n1, &BTreeSet::new(), &dataset),
+        epsilon = 1e-3
+    );
+    assert_abs_diff_eq!(
+        LogLikelihood::new(0.5, 1.0).call(&net, n1, &BTreeSet::new(), &dataset),
+        LogLikelihood::new(Alpha::Matrix(arr3(&[[[0.5, 0.5], [0.5, 0.5]]])), 1.0).call(
+            &net,
+            n1,
+            &BTreeSet::new(),
+            &dataset
+        ),
+        epsilon = 1e-9
+    );
+}
+
+#[test]
+fn score_weighted_equals_duplicated() {
+    let mut net = CtbnNetwork::new();
+    let n1 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n1"), 2))
+        .unwrap();
+
+    let trj_1 = Trajectory::new(arr1(&[0.0, 0.1, 0.3]), arr2(&[[0], [1], [1]]));
+    let trj_2 = Trajectory::new(arr1(&[0.0, 0.2, 0.5, 0.6]), arr2(&[[1], [0], [1], [1]]));
+
+    let weighted = Dataset::new(vec![trj_1.clone(), trj_2.clone()]).with_weights(arr1(&[2.0, 1.0]));
+    let duplicated = Dataset::new(vec![trj_1.clone(), trj_1, trj_2]);
+
+    let ll = LogLikelihood::new(1, 1.0);
+    assert_abs_diff_eq!(
+        ll.call(&net, n1, &BTreeSet::new(), &weighted),
+        ll.call(&net, n1, &BTreeSet::new(), &duplicated),
+        epsilon = 1e-9
+    );
+    let bic = BIC::new(1, 1.0);
+    assert_abs_diff_eq!(
+        bic.call(&net, n1, &BTreeSet::new(), &weighted),
+        bic.call(&net, n1, &BTreeSet::new(), &duplicated),
+        epsilon = 1e-9
+    );
+}
+
 fn check_compatibility_between_dataset_and_network<T: StructuralLearningAlgorithm>(sl: T) {
     let mut net = CtbnNetwork::new();
     let n1 = net
@@ -100,7 +198,10 @@ fn check_compatibility_between_dataset_and_network<T: StructuralLearningAlgorith
     let _n1 = net
         .add_node(generate_discrete_time_continous_node(String::from("n1"), 3))
         .unwrap();
-    let _net = sl.fit_transform(net, &data);
+    assert!(matches!(
+        sl.fit_transform(net, &data),
+        Err(StructureLearningError::MismatchedNumberOfVariables(_))
+    ));
 }
 
 fn generate_nodes(
@@ -135,11 +236,13 @@ fn check_compatibility_between_dataset_and_network_gen<T: StructuralLearningAlgo
     let _n1 = net
         .add_node(generate_discrete_time_continous_node(String::from("0"), 3))
         .unwrap();
-    let _net = sl.fit_transform(net, &data);
+    assert!(matches!(
+        sl.fit_transform(net, &data),
+        Err(StructureLearningError::MismatchedNumberOfVariables(_))
+    ));
 }
 
 #[test]
-#[should_panic]
 pub fn check_compatibility_between_dataset_and_network_hill_climbing() {
     let ll = LogLikelihood::new(1, 1.0);
     let hl = HillClimbing::new(ll, None);
@@ -147,13 +250,89 @@ pub fn check_compatibility_between_dataset_and_network_hill_climbing() {
 }
 
 #[test]
-#[should_panic]
 pub fn check_compatibility_between_dataset_and_network_hill_climbing_gen() {
     let ll = LogLikelihood::new(1, 1.0);
     let hl = HillClimbing::new(ll, None);
     check_compatibility_between_dataset_and_network_gen(hl);
 }
 
+#[test]
+pub fn check_compatibility_between_dataset_and_network_ctpc() {
+    let ctpc = CTPC::new(
+        BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) },
+        F::new(1e-6),
+        ChiSquare::new(1e-4),
+    );
+    check_compatibility_between_dataset_and_network(ctpc);
+}
+
+#[test]
+fn fit_transform_state_outside_domain() {
+    let mut net = CtbnNetwork::new();
+    generate_nodes(&mut net, 2, 2);
+    let trj = Trajectory::new(arr1(&[0.0, 0.1, 0.3]), arr2(&[[0, 0], [1, 0], [1, 2]]));
+    let dataset = Dataset::new(vec![trj]);
+    let hl = HillClimbing::new(BIC::new(1, 1.0), None);
+    assert!(matches!(
+        hl.fit_transform(net, &dataset),
+        Err(StructureLearningError::InvalidState(_))
+    ));
+}
+
+#[test]
+fn fit_transform_empty_dataset() {
+    let mut net = CtbnNetwork::new();
+    generate_nodes(&mut net, 2, 2);
+    let hl = HillClimbing::new(BIC::new(1, 1.0), None);
+    assert!(matches!(
+        hl.fit_transform(net, &Dataset::new(vec![])),
+        Err(StructureLearningError::EmptyDataset(_))
+    ));
+}
+
+#[test]
+fn try_fit_in_place_empty_dataset() {
+    let mut net = CtbnNetwork::new();
+    generate_nodes(&mut net, 2, 2);
+    let es = ExhaustiveSearch::new(BIC::new(1, 1.0), None, 100);
+    assert!(matches!(
+        es.try_fit_in_place(&mut net, &Dataset::new(vec![])),
+        Err(StructureLearningError::EmptyDataset(_))
+    ));
+    let ogs = OrderedGreedySearch::new(BIC::new(1, 1.0), vec![0, 1], None).unwrap();
+    assert!(matches!(
+        ogs.try_fit_in_place_with_diagnostics(&mut net, &Dataset::new(vec![])),
+        Err(StructureLearningError::EmptyDataset(_))
+    ));
+}
+
+#[test]
+fn fit_transform_propagates_learner_errors() {
+    let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let hl = HillClimbing::new(BIC::new(1, 1.0), None)
+        .with_known_parent_sets(HashMap::from([(0, BTreeSet::from([5]))]));
+    assert!(matches!(
+        hl.fit_transform(net.clone(), &data),
+        Err(StructureLearningError::InvalidEdgeConstraints(_))
+    ));
+    let ogs = OrderedGreedySearch::new(BIC::new(1, 1.0), vec![0, 1], None).unwrap();
+    assert!(matches!(
+        ogs.fit_transform(net, &data),
+        Err(StructureLearningError::InvalidOrdering(_))
+    ));
+}
+
+#[test]
+#[should_panic]
+#[allow(deprecated)]
+fn fit_transform_or_panic_mismatched_dataset() {
+    let (_, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let mut net = CtbnNetwork::new();
+    generate_nodes(&mut net, 2, 3);
+    let hl = HillClimbing::new(BIC::new(1, 1.0), None);
+    hl.fit_transform_or_panic(net, &data);
+}
+
 fn learn_ternary_net_2_nodes<T: StructuralLearningAlgorithm>(sl: T) {
     let mut net = CtbnNetwork::new();
     let n1 = net
@@ -192,7 +371,7 @@ fn learn_ternary_net_2_nodes<T: StructuralLearningAlgorithm>(sl: T) {
 
     let data = trajectory_generator(&net, 100, 20.0, Some(6347747169756259));
 
-    let net = sl.fit_transform(net, &data);
+    let net = sl.fit_transform(net, &data).unwrap();
     assert_eq!(BTreeSet::from_iter(vec![n1]), net.get_parent_set(n2));
     assert_eq!(BTreeSet::new(), net.get_parent_set(n1));
 }
@@ -209,7 +388,7 @@ fn learn_ternary_net_2_nodes_gen<T: StructuralLearningAlgorithm>(sl: T) {
 
     let data = trajectory_generator(&net, 100, 20.0, Some(6347747169756259));
 
-    let net = sl.fit_transform(net, &data);
+    let net = sl.fit_transform(net, &data).unwrap();
     assert_eq!(BTreeSet::from_iter(vec![0]), net.get_parent_set(1));
     assert_eq!(BTreeSet::new(), net.get_parent_set(0));
 }
@@ -242,7 +421,37 @@ pub fn learn_ternary_net_2_nodes_hill_climbing_bic_gen() {
     learn_ternary_net_2_nodes_gen(hl);
 }
 
-fn get_mixed_discrete_net_3_nodes_with_data() -> (CtbnNetwork, Dataset) {
+#[test]
+pub fn learn_ternary_net_2_nodes_simulated_annealing_bic() {
+    let bic = BIC::new(1, 1.0);
+    let sa = SimulatedAnnealing::new(bic, None, 10.0, 0.95, 200, Some(2487106452));
+    learn_ternary_net_2_nodes(sa);
+}
+
+#[test]
+pub fn learn_ternary_net_2_nodes_simulated_annealing_bic_gen() {
+    let bic = BIC::new(1, 1.0);
+    let sa = SimulatedAnnealing::new(bic, None, 10.0, 0.95, 200, Some(2487106452));
+    learn_ternary_net_2_nodes_gen(sa);
+}
+
+#[test]
+pub fn simulated_annealing_reproducible_with_seed() {
+    let (mut net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let mut learn = |seed| {
+        let bic = BIC::new(1, 1.0);
+        //A high temperature and a slow cooling let many worsening moves through.
+        let sa = SimulatedAnnealing::new(bic, None, 1000.0, 0.99, 50, Some(seed));
+        sa.fit_in_place(&mut net, &data);
+        net.get_node_indices()
+            .map(|node| net.get_parent_set(node))
+            .collect::<Vec<_>>()
+    };
+    let first = learn(1234);
+    assert_eq!(first, learn(1234));
+}
+
+fn get_mixed_discrete_net_3_nodes() -> CtbnNetwork {
     let mut net = CtbnNetwork::new();
     let n1 = net
         .add_node(generate_discrete_time_continous_node(String::from("n1"), 3))
@@ -348,6 +557,11 @@ fn get_mixed_discrete_net_3_nodes_with_data() -> (CtbnNetwork, Dataset) {
         }
     }
 
+    net
+}
+
+fn get_mixed_discrete_net_3_nodes_with_data() -> (CtbnNetwork, Dataset) {
+    let net = get_mixed_discrete_net_3_nodes();
     let data = trajectory_generator(&net, 300, 30.0, Some(6347747169756259));
     return (net, data);
 }
@@ -372,7 +586,7 @@ fn get_mixed_discrete_net_3_nodes_with_data_gen() -> (CtbnNetwork, Dataset) {
 
 fn learn_mixed_discrete_net_3_nodes<T: StructuralLearningAlgorithm>(sl: T) {
     let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
-    let net = sl.fit_transform(net, &data);
+    let net = sl.fit_transform(net, &data).unwrap();
     assert_eq!(BTreeSet::new(), net.get_parent_set(0));
     assert_eq!(BTreeSet::from_iter(vec![0]), net.get_parent_set(1));
     assert_eq!(BTreeSet::from_iter(vec![0, 1]), net.get_parent_set(2));
@@ -380,7 +594,7 @@ fn learn_mixed_discrete_net_3_nodes<T: StructuralLearningAlgorithm>(sl: T) {
 
 fn learn_mixed_discrete_net_3_nodes_gen<T: StructuralLearningAlgorithm>(sl: T) {
     let (net, data) = get_mixed_discrete_net_3_nodes_with_data_gen();
-    let net = sl.fit_transform(net, &data);
+    let net = sl.fit_transform(net, &data).unwrap();
     assert_eq!(BTreeSet::new(), net.get_parent_set(0));
     assert_eq!(BTreeSet::from_iter(vec![0]), net.get_parent_set(1));
     assert_eq!(BTreeSet::from_iter(vec![0, 1]), net.get_parent_set(2));
@@ -414,9 +628,223 @@ pub fn learn_mixed_discrete_net_3_nodes_hill_climbing_bic_gen() {
     learn_mixed_discrete_net_3_nodes_gen(hl);
 }
 
+#[test]
+pub fn learn_mixed_discrete_net_3_nodes_hill_climbing_cross_validated_ll() {
+    let cv_ll = CrossValidatedLL::new(
+        BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) },
+        5,
+        Some(6813071588535822),
+    );
+    let hl = HillClimbing::new(cv_ll, None);
+    learn_mixed_discrete_net_3_nodes(hl);
+}
+
+#[test]
+pub fn learn_mixed_discrete_net_3_nodes_hill_climbing_cross_validated_ll_mle() {
+    let cv_ll = CrossValidatedLL::new(MLE {}, 5, Some(6813071588535822));
+    let hl = HillClimbing::new(cv_ll, None);
+    learn_mixed_discrete_net_3_nodes(hl);
+}
+
+#[test]
+fn cross_validated_ll_prefers_true_parent_set() {
+    let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let cv_ll = CrossValidatedLL::new(
+        BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) },
+        5,
+        Some(6813071588535822),
+    );
+    let true_score = cv_ll.call(&net, 2, &BTreeSet::from([0, 1]), &data);
+    for parent_set in [BTreeSet::new(), BTreeSet::from([0]), BTreeSet::from([1])] {
+        assert!(true_score > cv_ll.call(&net, 2, &parent_set, &data));
+    }
+    //The folds are seeded: the same parent set always gets the same score.
+    assert_eq!(
+        true_score,
+        cv_ll.call(&net, 2, &BTreeSet::from([0, 1]), &data)
+    );
+}
+
+#[test]
+fn cross_validated_ll_unobserved_parent_configuration() {
+    let mut net = CtbnNetwork::new();
+    let n1 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n1"), 2))
+        .unwrap();
+    let n2 = net
+        .add_node(generate_discrete_time_continous_node(String::from("n2"), 2))
+        .unwrap();
+
+    //n2 is clamped to a different state in each trajectory: with two folds, each held-out
+    //parent configuration is never observed in the training fold.
+    let trj_1 = Trajectory::new(
+        arr1(&[0.0, 0.1, 0.3, 0.6]),
+        arr2(&[[0, 0], [1, 0], [0, 0], [0, 0]]),
+    );
+    let trj_2 = Trajectory::new(
+        arr1(&[0.0, 0.2, 0.5, 0.6]),
+        arr2(&[[0, 1], [1, 1], [0, 1], [0, 1]]),
+    );
+    let dataset = Dataset::new(vec![trj_1, trj_2]);
+
+    let cv_ll = CrossValidatedLL::new(MLE {}, 2, Some(6813071588535822));
+    let score = cv_ll.call(&net, n1, &BTreeSet::from([n2]), &dataset);
+    assert!(score.is_finite());
+
+    //Without parents, the rates fitted on one trajectory are used on the other one.
+    let cv_ll =
+        CrossValidatedLL::new(MLE {}, 2, Some(6813071588535822)).with_prior(BayesianApproach {
+            alpha: Alpha::Constant(10.0),
+            tau: Tau::Constant(1.0),
+        });
+    assert!(cv_ll.call(&net, n1, &BTreeSet::new(), &dataset).is_finite());
+    //Under the prior, the unobserved rows have rates alpha / tau = 10.0, whatever the number
+    //of parent configurations: the held-out score only depends on it.
+    let prior_score = cv_ll.call(&net, n1, &BTreeSet::from([n2]), &dataset);
+    //Each trajectory has two transitions and a total residence time of 0.6.
+    assert_abs_diff_eq!(
+        2.0 * (2.0 * 10.0_f64.ln() - 10.0 * 0.6),
+        2.0 * prior_score,
+        epsilon = 1e-9
+    );
+}
+
+#[test]
+pub fn learn_mixed_discrete_net_3_nodes_hill_climbing_bic_flat_prior() {
+    let score_function = PriorPenalizedScore::new(BIC::new(1, 1.0), |_, _| 0.0);
+    let hl = HillClimbing::new(score_function, None);
+    learn_mixed_discrete_net_3_nodes(hl);
+}
+
+#[test]
+fn hill_climbing_bic_sparsity_prior() {
+    let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    //The edge n2 -> n3 is the weakest one: given n1, it improves the BIC of n3 by less than 3000.
+    let score_function = PriorPenalizedScore::new(BIC::new(1, 1.0), |_, parent_set| {
+        -3000.0 * parent_set.len() as f64
+    });
+    let hl = HillClimbing::new(score_function, None);
+    let net = hl.fit_transform(net, &data).unwrap();
+    assert_eq!(BTreeSet::new(), net.get_parent_set(0));
+    assert_eq!(BTreeSet::from_iter(vec![0]), net.get_parent_set(1));
+    assert_eq!(BTreeSet::from_iter(vec![0]), net.get_parent_set(2));
+}
+
+#[test]
+pub fn learn_ternary_net_2_nodes_hill_climbing_bic_best_first() {
+    let bic = BIC::new(1, 1.0);
+    let hl = HillClimbing::new(bic, None).with_best_first(true);
+    learn_ternary_net_2_nodes(hl);
+}
+
+#[test]
+pub fn learn_mixed_discrete_net_3_nodes_hill_climbing_bic_best_first() {
+    let bic = BIC::new(1, 1.0);
+    let hl = HillClimbing::new(bic, None).with_best_first(true);
+    learn_mixed_discrete_net_3_nodes(hl);
+}
+
+#[test]
+pub fn learn_mixed_discrete_net_3_nodes_hill_climbing_bic_best_first_gen() {
+    let bic = BIC::new(1, 1.0);
+    let hl = HillClimbing::new(bic, None).with_best_first(true);
+    learn_mixed_discrete_net_3_nodes_gen(hl);
+}
+
+//Same search as `HillClimbing` (starting from empty parent sets), scoring each visited parent
+//set again at every iteration.
+fn hill_climbing_without_memoization<S: ScoreFunction>(
+    score_function: &S,
+    net: &CtbnNetwork,
+    dataset: &Dataset,
+    best_first: bool,
+) -> Vec<BTreeSet<usize>> {
+    let toggle = |parent_set: &BTreeSet<usize>, parent: usize| {
+        let mut tmp_parent_set = parent_set.clone();
+        if !tmp_parent_set.remove(&parent) {
+            tmp_parent_set.insert(parent);
+        }
+        tmp_parent_set
+    };
+    net.get_node_indices()
+        .map(|node| {
+            let mut parent_set = BTreeSet::new();
+            let mut current_score = score_function.call(net, node, &parent_set, dataset);
+            if best_first {
+                loop {
+                    let (tmp_score, tmp_parent_set) = net
+                        .get_node_indices()
+                        .filter(|parent| *parent != node)
+                        .map(|parent| {
+                            let tmp_parent_set = toggle(&parent_set, parent);
+                            let tmp_score =
+                                score_function.call(net, node, &tmp_parent_set, dataset);
+                            (tmp_score, tmp_parent_set)
+                        })
+                        .max_by(|a, b| a.0.total_cmp(&b.0))
+                        .unwrap();
+                    if tmp_score <= current_score {
+                        break;
+                    }
+                    current_score = tmp_score;
+                    parent_set = tmp_parent_set;
+                }
+            } else {
+                let mut old_parent_set = None;
+                while old_parent_set.as_ref() != Some(&parent_set) {
+                    old_parent_set = Some(parent_set.clone());
+                    for parent in net.get_node_indices().filter(|parent| *parent != node) {
+                        let tmp_parent_set = toggle(&parent_set, parent);
+                        let tmp_score = score_function.call(net, node, &tmp_parent_set, dataset);
+                        if tmp_score > current_score {
+                            current_score = tmp_score;
+                            parent_set = tmp_parent_set;
+                        }
+                    }
+                }
+            }
+            parent_set
+        })
+        .collect()
+}
+
+#[test]
+pub fn hill_climbing_scores_each_parent_set_once() {
+    //BIC without a cache computes the sufficient statistics at each call: the number of calls is
+    //the number of computations of the statistics.
+    for best_first in [false, true] {
+        let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
+        let baseline = CountingScore {
+            score_function: BIC::new(1, 1.0),
+            n_calls: AtomicUsize::new(0),
+        };
+        let expected = hill_climbing_without_memoization(&baseline, &net, &data, best_first);
+        let baseline_computations = baseline.n_calls.load(Ordering::SeqCst);
+
+        let score_function = Arc::new(CountingScore {
+            score_function: BIC::new(1, 1.0),
+            n_calls: AtomicUsize::new(0),
+        });
+        let hl: HillClimbing<CountingScore<BIC>> =
+            HillClimbing::new(Arc::clone(&score_function), None).with_best_first(best_first);
+        let net = hl.fit_transform(net, &data).unwrap();
+        let learned: Vec<BTreeSet<usize>> = net
+            .get_node_indices()
+            .map(|x| net.get_parent_set(x))
+            .collect();
+        assert_eq!(expected, learned);
+        assert_eq!(BTreeSet::from_iter(vec![0, 1]), net.get_parent_set(2));
+        //Each of the 3 nodes has 4 candidate parent sets: with the memoization no parent set is
+        //scored twice, whatever the number of iterations of the search.
+        let computations = score_function.n_calls.load(Ordering::SeqCst);
+        assert!(computations <= 3 * 4);
+        assert!(computations < baseline_computations);
+    }
+}
+
 fn learn_mixed_discrete_net_3_nodes_1_parent_constraint<T: StructuralLearningAlgorithm>(sl: T) {
     let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
-    let net = sl.fit_transform(net, &data);
+    let net = sl.fit_transform(net, &data).unwrap();
     assert_eq!(BTreeSet::new(), net.get_parent_set(0));
     assert_eq!(BTreeSet::from_iter(vec![0]), net.get_parent_set(1));
     assert_eq!(BTreeSet::from_iter(vec![0]), net.get_parent_set(2));
@@ -424,7 +852,7 @@ fn learn_mixed_discrete_net_3_nodes_1_parent_constraint<T: StructuralLearningAlg
 
 fn learn_mixed_discrete_net_3_nodes_1_parent_constraint_gen<T: StructuralLearningAlgorithm>(sl: T) {
     let (net, data) = get_mixed_discrete_net_3_nodes_with_data_gen();
-    let net = sl.fit_transform(net, &data);
+    let net = sl.fit_transform(net, &data).unwrap();
     assert_eq!(BTreeSet::new(), net.get_parent_set(0));
     assert_eq!(BTreeSet::from_iter(vec![0]), net.get_parent_set(1));
     assert_eq!(BTreeSet::from_iter(vec![0]), net.get_parent_set(2));
@@ -452,108 +880,613 @@ pub fn learn_mixed_discrete_net_3_nodes_hill_climbing_bic_1_parent_constraint()
 }
 
 #[test]
-pub fn learn_mixed_discrete_net_3_nodes_hill_climbing_bic_1_parent_constraint_gen() {
-    let bic = BIC::new(1, 1.0);
-    let hl = HillClimbing::new(bic, Some(1));
-    learn_mixed_discrete_net_3_nodes_1_parent_constraint_gen(hl);
+pub fn learn_mixed_discrete_net_3_nodes_hill_climbing_per_node_parent_constraint() {
+    for best_first in [false, true] {
+        let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
+        let hl = HillClimbing::new(BIC::new(1, 1.0), Some(1))
+            .with_best_first(best_first)
+            .with_max_parent_set(ParentLimit::PerNode(HashMap::from([(1, 1), (2, 2)])))
+            .unwrap();
+        let net = hl.fit_transform(net, &data).unwrap();
+        assert_eq!(BTreeSet::new(), net.get_parent_set(0));
+        assert_eq!(BTreeSet::from_iter(vec![0]), net.get_parent_set(1));
+        assert_eq!(BTreeSet::from_iter(vec![0, 1]), net.get_parent_set(2));
+    }
+    //Node 1 is not allowed any parent, node 2 a single one.
+    let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let hl = HillClimbing::new(BIC::new(1, 1.0), None)
+        .with_max_parent_set(ParentLimit::PerNode(HashMap::from([(1, 0), (2, 1)])))
+        .unwrap();
+    let net = hl.fit_transform(net, &data).unwrap();
+    assert_eq!(BTreeSet::new(), net.get_parent_set(1));
+    assert_eq!(BTreeSet::from_iter(vec![0]), net.get_parent_set(2));
 }
 
 #[test]
-pub fn chi_square_compare_matrices() {
-    let i: usize = 1;
-    let M1 = arr3(&[
-        [[0, 2, 3], [4, 0, 6], [7, 8, 0]],
-        [[0, 12, 90], [3, 0, 40], [6, 40, 0]],
-        [[0, 2, 3], [4, 0, 6], [44, 66, 0]],
-    ]);
-    let j: usize = 0;
-    let M2 = arr3(&[[[0, 200, 300], [400, 0, 600], [700, 800, 0]]]);
-    let chi_sq = ChiSquare::new(1e-4);
-    assert!(!chi_sq.compare_matrices(i, &M1, j, &M2));
+pub fn hill_climbing_per_node_parent_constraint_required_edges() {
+    let hl = HillClimbing::new(BIC::new(1, 1.0), None)
+        .with_edge_constraints(BTreeSet::from_iter(vec![(0, 2), (1, 2)]), BTreeSet::new())
+        .unwrap();
+    assert_eq!(
+        Some(StructureLearningError::InvalidEdgeConstraints(
+            String::from("node 2 has 2 required parents, max_parent_set is 1")
+        )),
+        hl.with_max_parent_set(ParentLimit::PerNode(HashMap::from([(1, 0), (2, 1)])))
+            .err()
+    );
 }
 
 #[test]
-pub fn chi_square_compare_matrices_2() {
-    let i: usize = 1;
-    let M1 = arr3(&[
-        [[0, 2, 3], [4, 0, 6], [7, 8, 0]],
-        [[0, 20, 30], [40, 0, 60], [70, 80, 0]],
-        [[0, 2, 3], [4, 0, 6], [44, 66, 0]],
-    ]);
-    let j: usize = 0;
-    let M2 = arr3(&[[[0, 200, 300], [400, 0, 600], [700, 800, 0]]]);
-    let chi_sq = ChiSquare::new(1e-4);
-    assert!(chi_sq.compare_matrices(i, &M1, j, &M2));
+pub fn learn_mixed_discrete_net_3_nodes_ctpc_per_node_parent_constraint() {
+    let ctpc = CTPC::new(
+        BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) },
+        F::new(1e-6),
+        ChiSquare::new(1e-4),
+    )
+    .with_max_parent_set(ParentLimit::PerNode(HashMap::from([(1, 1), (2, 2)])));
+    learn_mixed_discrete_net_3_nodes(ctpc);
+    let ctpc = CTPC::new(
+        BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) },
+        F::new(1e-6),
+        ChiSquare::new(1e-4),
+    )
+    .with_max_parent_set(ParentLimit::PerNode(HashMap::from([(1, 1), (2, 1)])));
+    learn_mixed_discrete_net_3_nodes_1_parent_constraint(ctpc);
 }
 
 #[test]
-pub fn chi_square_compare_matrices_3() {
-    let i: usize = 1;
-    let M1 = arr3(&[
-        [[0, 2, 3], [4, 0, 6], [7, 8, 0]],
-        [[0, 21, 31], [41, 0, 59], [71, 79, 0]],
-        [[0, 2, 3], [4, 0, 6], [44, 66, 0]],
-    ]);
-    let j: usize = 0;
-    let M2 = arr3(&[[[0, 200, 300], [400, 0, 600], [700, 800, 0]]]);
-    let chi_sq = ChiSquare::new(1e-4);
-    assert!(chi_sq.compare_matrices(i, &M1, j, &M2));
+pub fn learn_mixed_discrete_net_3_nodes_hill_climbing_bic_1_parent_constraint_gen() {
+    let bic = BIC::new(1, 1.0);
+    let hl = HillClimbing::new(bic, Some(1));
+    learn_mixed_discrete_net_3_nodes_1_parent_constraint_gen(hl);
 }
 
 #[test]
-pub fn chi_square_call() {
+pub fn learn_mixed_discrete_net_3_nodes_hill_climbing_bic_forbidden_edge() {
     let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
-    let N3: usize = 2;
-    let N2: usize = 1;
-    let N1: usize = 0;
-    let mut separation_set = BTreeSet::new();
-    let parameter_learning = BayesianApproach { alpha: 1, tau: 1.0 };
-    let mut cache = Cache::new(&parameter_learning);
-    let chi_sq = ChiSquare::new(1e-4);
-
-    assert!(chi_sq.call(&net, N1, N3, &separation_set, &data, &mut cache));
-    let mut cache = Cache::new(&parameter_learning);
-    assert!(!chi_sq.call(&net, N3, N1, &separation_set, &data, &mut cache));
-    assert!(!chi_sq.call(&net, N3, N2, &separation_set, &data, &mut cache));
-    separation_set.insert(N1);
-    let mut cache = Cache::new(&parameter_learning);
-    assert!(chi_sq.call(&net, N2, N3, &separation_set, &data, &mut cache));
+    let bic = BIC::new(1, 1.0);
+    let hl = HillClimbing::new(bic, None)
+        .with_edge_constraints(BTreeSet::new(), BTreeSet::from_iter(vec![(0, 1)]))
+        .unwrap();
+    let net = hl.fit_transform(net, &data).unwrap();
+    assert_eq!(BTreeSet::new(), net.get_parent_set(0));
+    //Without its true parent, n2 is best explained by its child n3.
+    assert_eq!(BTreeSet::from_iter(vec![2]), net.get_parent_set(1));
+    assert_eq!(BTreeSet::from_iter(vec![0, 1]), net.get_parent_set(2));
 }
 
 #[test]
-pub fn f_call() {
+pub fn learn_mixed_discrete_net_3_nodes_hill_climbing_bic_required_edges() {
     let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
-    let N3: usize = 2;
-    let N2: usize = 1;
-    let N1: usize = 0;
-    let mut separation_set = BTreeSet::new();
-    let parameter_learning = BayesianApproach { alpha: 1, tau: 1.0 };
-    let mut cache = Cache::new(&parameter_learning);
-    let f = F::new(1e-6);
-
-    assert!(f.call(&net, N1, N3, &separation_set, &data, &mut cache));
-    let mut cache = Cache::new(&parameter_learning);
-    assert!(!f.call(&net, N3, N1, &separation_set, &data, &mut cache));
-    assert!(!f.call(&net, N3, N2, &separation_set, &data, &mut cache));
-    separation_set.insert(N1);
-    let mut cache = Cache::new(&parameter_learning);
-    assert!(f.call(&net, N2, N3, &separation_set, &data, &mut cache));
+    let bic = BIC::new(1, 1.0);
+    let hl = HillClimbing::new(bic, Some(1))
+        .with_edge_constraints(BTreeSet::from_iter(vec![(2, 0), (1, 2)]), BTreeSet::new())
+        .unwrap();
+    let net = hl.fit_transform(net, &data).unwrap();
+    assert_eq!(BTreeSet::from_iter(vec![2]), net.get_parent_set(0));
+    assert_eq!(BTreeSet::from_iter(vec![0]), net.get_parent_set(1));
+    //The required parent fills the only slot allowed by max_parent_set.
+    assert_eq!(BTreeSet::from_iter(vec![1]), net.get_parent_set(2));
 }
 
 #[test]
-pub fn learn_ternary_net_2_nodes_ctpc() {
-    let f = F::new(1e-6);
-    let chi_sq = ChiSquare::new(1e-4);
-    let parameter_learning = BayesianApproach { alpha: 1, tau: 1.0 };
-    let ctpc = CTPC::new(parameter_learning, f, chi_sq);
-    learn_ternary_net_2_nodes(ctpc);
+pub fn hill_climbing_conflicting_edge_constraints() {
+    let hl = HillClimbing::new(BIC::new(1, 1.0), Some(1));
+    assert_eq!(
+        Some(StructureLearningError::InvalidEdgeConstraints(
+            String::from("edge 0 -> 1 is both required and forbidden")
+        )),
+        hl.with_edge_constraints(
+            BTreeSet::from_iter(vec![(0, 1)]),
+            BTreeSet::from_iter(vec![(0, 1), (2, 1)])
+        )
+        .err()
+    );
+    let hl = HillClimbing::new(BIC::new(1, 1.0), Some(1));
+    assert_eq!(
+        Some(StructureLearningError::InvalidEdgeConstraints(
+            String::from("node 2 has 2 required parents, max_parent_set is 1")
+        )),
+        hl.with_edge_constraints(BTreeSet::from_iter(vec![(0, 2), (1, 2)]), BTreeSet::new())
+            .err()
+    );
+    let hl = HillClimbing::new(BIC::new(1, 1.0), None);
+    assert_eq!(
+        Some(StructureLearningError::InvalidEdgeConstraints(
+            String::from("self loop 1 -> 1")
+        )),
+        hl.with_edge_constraints(BTreeSet::new(), BTreeSet::from_iter(vec![(1, 1)]))
+            .err()
+    );
 }
 
-#[test]
+//Score function counting the number of times it is evaluated.
+struct CountingScore<S: ScoreFunction> {
+    score_function: S,
+    n_calls: AtomicUsize,
+}
+
+impl<S: ScoreFunction> ScoreFunction for CountingScore<S> {
+    fn call<T>(&self, net: &T, node: usize, parent_set: &BTreeSet<usize>, dataset: &Dataset) -> f64
+    where
+        T: NetworkProcess,
+    {
+        self.n_calls.fetch_add(1, Ordering::SeqCst);
+        self.score_function.call(net, node, parent_set, dataset)
+    }
+}
+
+#[test]
+pub fn learn_mixed_discrete_net_3_nodes_hill_climbing_bic_warm_start() {
+    let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let score_function = Arc::new(CountingScore {
+        score_function: BIC::new(1, 1.0),
+        n_calls: AtomicUsize::new(0),
+    });
+
+    let hl: HillClimbing<CountingScore<BIC>> = HillClimbing::new(Arc::clone(&score_function), None);
+    let net = hl.fit_transform(net, &data).unwrap();
+    let cold_start_calls = score_function.n_calls.swap(0, Ordering::SeqCst);
+
+    //Refine the learned structure: the search starts from the optimum.
+    let hl: HillClimbing<CountingScore<BIC>> =
+        HillClimbing::new(Arc::clone(&score_function), None).with_start_from_input_structure(true);
+    let net = hl.fit_transform(net, &data).unwrap();
+    let warm_start_calls = score_function.n_calls.load(Ordering::SeqCst);
+
+    assert_eq!(BTreeSet::new(), net.get_parent_set(0));
+    assert_eq!(BTreeSet::from_iter(vec![0]), net.get_parent_set(1));
+    assert_eq!(BTreeSet::from_iter(vec![0, 1]), net.get_parent_set(2));
+    assert!(warm_start_calls < cold_start_calls);
+}
+
+#[test]
+pub fn hill_climbing_warm_start_clamped_to_max_parent_set() {
+    let (mut net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    //Start from a structure with a spurious edge.
+    net.add_edge(2, 0);
+    let hl = HillClimbing::new(BIC::new(1, 1.0), Some(1)).with_start_from_input_structure(true);
+    let net = hl.fit_transform(net, &data).unwrap();
+    assert_eq!(BTreeSet::new(), net.get_parent_set(0));
+    assert_eq!(BTreeSet::from_iter(vec![0]), net.get_parent_set(1));
+    assert_eq!(1, net.get_parent_set(2).len());
+}
+
+#[test]
+pub fn learn_mixed_discrete_net_3_nodes_exhaustive_search_bic() {
+    let bic = BIC::new(1, 1.0);
+    let es = ExhaustiveSearch::new(bic, None, 100);
+    learn_mixed_discrete_net_3_nodes(es);
+}
+
+#[test]
+pub fn learn_mixed_discrete_net_3_nodes_exhaustive_search_bic_1_parent_constraint() {
+    let bic = BIC::new(1, 1.0);
+    let es = ExhaustiveSearch::new(bic, Some(1), 100);
+    learn_mixed_discrete_net_3_nodes_1_parent_constraint(es);
+}
+
+#[test]
+pub fn exhaustive_search_enumeration_size() {
+    let es = ExhaustiveSearch::new(BIC::new(1, 1.0), None, 0);
+    assert_eq!(3 * 4, es.enumeration_size(3));
+    assert_eq!(6 * 32, es.enumeration_size(6));
+    let es = ExhaustiveSearch::new(BIC::new(1, 1.0), Some(1), 0);
+    assert_eq!(6 * 6, es.enumeration_size(6));
+    //The binomial coefficients overflow before the sum does.
+    let es = ExhaustiveSearch::new(BIC::new(1, 1.0), None, 0);
+    assert_eq!(usize::MAX, es.enumeration_size(70));
+    let es = ExhaustiveSearch::new(BIC::new(1, 1.0), Some(40), 0);
+    assert_eq!(usize::MAX, es.enumeration_size(70));
+}
+
+#[test]
+pub fn exhaustive_search_refuses_large_enumeration() {
+    let (mut net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let es = ExhaustiveSearch::new(BIC::new(1, 1.0), None, 11);
+    assert_eq!(
+        Err(StructureLearningError::SearchSpaceTooLarge(String::from(
+            "12 parent sets to score, the bound is 11"
+        ))),
+        es.try_fit_in_place(&mut net, &data)
+    );
+    //The network is left untouched.
+    assert_eq!(BTreeSet::from_iter(vec![0, 1]), net.get_parent_set(2));
+}
+
+#[test]
+pub fn learn_mixed_discrete_net_3_nodes_ordered_greedy_search_bic() {
+    let bic = BIC::new(1, 1.0);
+    let ogs = OrderedGreedySearch::new(bic, vec![0, 1, 2], None).unwrap();
+    learn_mixed_discrete_net_3_nodes(ogs);
+}
+
+#[test]
+pub fn learn_mixed_discrete_net_3_nodes_ordered_greedy_search_bic_gen() {
+    let bic = BIC::new(1, 1.0);
+    let ogs = OrderedGreedySearch::new(bic, vec![0, 1, 2], None).unwrap();
+    learn_mixed_discrete_net_3_nodes_gen(ogs);
+}
+
+#[test]
+pub fn ordered_greedy_search_fewer_score_calls_than_hill_climbing() {
+    let (mut net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let score_function = Arc::new(CountingScore {
+        score_function: BIC::new(1, 1.0),
+        n_calls: AtomicUsize::new(0),
+    });
+
+    let hl: HillClimbing<CountingScore<BIC>> = HillClimbing::new(Arc::clone(&score_function), None);
+    hl.fit_in_place(&mut net, &data);
+    let hill_climbing_calls = score_function.n_calls.swap(0, Ordering::SeqCst);
+
+    let ogs: OrderedGreedySearch<CountingScore<BIC>> =
+        OrderedGreedySearch::new(Arc::clone(&score_function), vec![0, 1, 2], None).unwrap();
+    ogs.fit_in_place(&mut net, &data);
+    let ordered_greedy_search_calls = score_function.n_calls.load(Ordering::SeqCst);
+
+    assert_eq!(BTreeSet::new(), net.get_parent_set(0));
+    assert_eq!(BTreeSet::from_iter(vec![0]), net.get_parent_set(1));
+    assert_eq!(BTreeSet::from_iter(vec![0, 1]), net.get_parent_set(2));
+    assert!(ordered_greedy_search_calls < hill_climbing_calls);
+}
+
+#[test]
+pub fn ordered_greedy_search_wrong_ordering() {
+    let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let ogs = OrderedGreedySearch::new(BIC::new(1, 1.0), vec![2, 1, 0], None).unwrap();
+    let net = ogs.fit_transform(net, &data).unwrap();
+    //n3 comes first, so it loses both its parents; n2 can only choose n3, which acts as a proxy
+    //of n1 and is learned as a reversed edge. n1 has no parents either way.
+    assert_eq!(BTreeSet::new(), net.get_parent_set(0));
+    assert_eq!(BTreeSet::from_iter(vec![2]), net.get_parent_set(1));
+    assert_eq!(BTreeSet::new(), net.get_parent_set(2));
+}
+
+#[test]
+pub fn ordered_greedy_search_invalid_ordering() {
+    for ordering in [vec![0, 1, 1], vec![0, 1, 3]] {
+        assert!(matches!(
+            OrderedGreedySearch::new(BIC::new(1, 1.0), ordering, None),
+            Err(StructureLearningError::InvalidOrdering(_))
+        ));
+    }
+}
+
+#[test]
+fn learn_mixed_discrete_net_3_nodes_ctpc_required_edge_gen() {
+    let (net, _) = get_mixed_discrete_net_3_nodes_with_data_gen();
+    //A short dataset and an aggressive alpha: every edge is dropped without constraints.
+    let data = trajectory_generator(&net, 100, 3.0, Some(6347747169756259));
+    let new_ctpc = || {
+        CTPC::new(
+            BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) },
+            F::new(1e-12),
+            ChiSquare::new(1e-12),
+        )
+    };
+    let net = new_ctpc().fit_transform(net, &data).unwrap();
+    assert_eq!(BTreeSet::new(), net.get_parent_set(2));
+
+    let ctpc = new_ctpc()
+        .with_edge_constraints(BTreeSet::from_iter(vec![(1, 2)]), BTreeSet::new())
+        .unwrap();
+    let net = ctpc.fit_transform(net, &data).unwrap();
+    assert_eq!(BTreeSet::from_iter(vec![1]), net.get_parent_set(2));
+}
+
+#[test]
+fn learn_mixed_discrete_net_3_nodes_ctpc_forbidden_edge() {
+    let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let ctpc = CTPC::new(
+        BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) },
+        F::new(1e-6),
+        ChiSquare::new(1e-4),
+    )
+    .with_edge_constraints(BTreeSet::new(), BTreeSet::from_iter(vec![(0, 2)]))
+    .unwrap();
+    let net = ctpc.fit_transform(net, &data).unwrap();
+    assert_eq!(BTreeSet::new(), net.get_parent_set(0));
+    assert_eq!(BTreeSet::from_iter(vec![0]), net.get_parent_set(1));
+    assert!(!net.get_parent_set(2).contains(&0));
+    assert!(net.get_parent_set(2).contains(&1));
+}
+
+#[test]
+fn ctpc_conflicting_edge_constraints() {
+    let ctpc = CTPC::new(
+        BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) },
+        F::new(1e-6),
+        ChiSquare::new(1e-4),
+    );
+    assert_eq!(
+        Some(StructureLearningError::InvalidEdgeConstraints(
+            String::from("edge 1 -> 2 is both required and forbidden")
+        )),
+        ctpc.with_edge_constraints(
+            BTreeSet::from_iter(vec![(1, 2)]),
+            BTreeSet::from_iter(vec![(1, 2)])
+        )
+        .err()
+    );
+}
+
+#[test]
+pub fn chi_square_compare_matrices() {
+    let i: usize = 1;
+    let M1 = arr3(&[
+        [[0, 2, 3], [4, 0, 6], [7, 8, 0]],
+        [[0, 12, 90], [3, 0, 40], [6, 40, 0]],
+        [[0, 2, 3], [4, 0, 6], [44, 66, 0]],
+    ]);
+    let j: usize = 0;
+    let M2 = arr3(&[[[0, 200, 300], [400, 0, 600], [700, 800, 0]]]);
+    let chi_sq = ChiSquare::new(1e-4);
+    assert!(!chi_sq.compare_matrices(i, &M1, j, &M2));
+}
+
+#[test]
+pub fn chi_square_compare_matrices_2() {
+    let i: usize = 1;
+    let M1 = arr3(&[
+        [[0, 2, 3], [4, 0, 6], [7, 8, 0]],
+        [[0, 20, 30], [40, 0, 60], [70, 80, 0]],
+        [[0, 2, 3], [4, 0, 6], [44, 66, 0]],
+    ]);
+    let j: usize = 0;
+    let M2 = arr3(&[[[0, 200, 300], [400, 0, 600], [700, 800, 0]]]);
+    let chi_sq = ChiSquare::new(1e-4);
+    assert!(chi_sq.compare_matrices(i, &M1, j, &M2));
+}
+
+#[test]
+pub fn chi_square_compare_matrices_3() {
+    let i: usize = 1;
+    let M1 = arr3(&[
+        [[0, 2, 3], [4, 0, 6], [7, 8, 0]],
+        [[0, 21, 31], [41, 0, 59], [71, 79, 0]],
+        [[0, 2, 3], [4, 0, 6], [44, 66, 0]],
+    ]);
+    let j: usize = 0;
+    let M2 = arr3(&[[[0, 200, 300], [400, 0, 600], [700, 800, 0]]]);
+    let chi_sq = ChiSquare::new(1e-4);
+    assert!(chi_sq.compare_matrices(i, &M1, j, &M2));
+}
+
+#[test]
+pub fn chi_square_compare_matrices_degrees_of_freedom() {
+    //Two reachable states from the first state: the statistic of the row (4.44) has one degree
+    //of freedom, hence the independence is rejected at 0.05 (it would be accepted with two).
+    let M1 = arr3(&[[[0, 5, 5], [10, 0, 10], [10, 10, 0]]]);
+    let M2 = arr3(&[[[0, 5, 25], [20, 0, 20], [20, 20, 0]]]);
+    let chi_sq = ChiSquare::new(0.05);
+    assert!(!chi_sq.compare_matrices(0, &M1, 0, &M2));
+}
+
+#[test]
+pub fn chi_square_compare_matrices_structural_zeros() {
+    //The first state only reaches the second one: the row carries no evidence.
+    let M1 = arr3(&[[[0, 10, 0], [5, 0, 5], [5, 5, 0]]]);
+    let M2 = arr3(&[[[0, 40, 0], [20, 0, 20], [20, 20, 0]]]);
+    let chi_sq = ChiSquare::new(0.05);
+    assert!(chi_sq.compare_matrices(0, &M1, 0, &M2));
+
+    //The first state is never left in `M1`: the row is skipped.
+    let M1 = arr3(&[[[0, 0, 0], [5, 0, 5], [5, 5, 0]]]);
+    let M2 = arr3(&[[[0, 5, 50], [20, 0, 20], [20, 20, 0]]]);
+    assert!(chi_sq.compare_matrices(0, &M1, 0, &M2));
+
+    //With four states and a structural zero, the remaining cells are still compared.
+    let M1 = arr3(&[[[0, 30, 30, 0], [5, 0, 5, 5], [5, 5, 0, 5], [5, 5, 5, 0]]]);
+    let M2 = arr3(&[[[0, 10, 50, 0], [5, 0, 5, 5], [5, 5, 0, 5], [5, 5, 5, 0]]]);
+    let chi_sq = ChiSquare::new(1e-3);
+    assert!(!chi_sq.compare_matrices(0, &M1, 0, &M2));
+}
+
+#[test]
+pub fn chi_square_call() {
+    let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let N3: usize = 2;
+    let N2: usize = 1;
+    let N1: usize = 0;
+    let mut separation_set = BTreeSet::new();
+    let parameter_learning = BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) };
+    let mut cache = Cache::new(&parameter_learning);
+    let chi_sq = ChiSquare::new(1e-4);
+
+    assert!(chi_sq.call(&net, N1, N3, &separation_set, &data, &mut cache));
+    let mut cache = Cache::new(&parameter_learning);
+    assert!(!chi_sq.call(&net, N3, N1, &separation_set, &data, &mut cache));
+    assert!(!chi_sq.call(&net, N3, N2, &separation_set, &data, &mut cache));
+    separation_set.insert(N1);
+    let mut cache = Cache::new(&parameter_learning);
+    assert!(chi_sq.call(&net, N2, N3, &separation_set, &data, &mut cache));
+}
+
+#[test]
+pub fn exact_test_compare_dense_matrices() {
+    let M1 = arr3(&[
+        [[0, 2, 3], [4, 0, 6], [7, 8, 0]],
+        [[0, 12, 90], [3, 0, 40], [6, 40, 0]],
+        [[0, 20, 30], [40, 0, 60], [70, 80, 0]],
+        [[0, 21, 31], [41, 0, 59], [71, 79, 0]],
+    ]);
+    let M2 = arr3(&[[[0, 200, 300], [400, 0, 600], [700, 800, 0]]]);
+    let chi_sq = ChiSquare::new(1e-2);
+    //Dense rows: the chi-squared approximation is used.
+    let exact_fallback = ExactTest::new(1e-2, 999, 10, Some(6347747169756259));
+    //Every row is resampled.
+    let exact_permutation = ExactTest::new(1e-2, 999, usize::MAX, Some(6347747169756259));
+    for i in 1..4 {
+        let expected = chi_sq.compare_matrices(i, &M1, 0, &M2);
+        assert_eq!(expected, exact_fallback.compare_matrices(i, &M1, 0, &M2));
+        assert_eq!(expected, exact_permutation.compare_matrices(i, &M1, 0, &M2));
+    }
+    assert!(!exact_permutation.compare_matrices(1, &M1, 0, &M2));
+    assert!(exact_permutation.compare_matrices(2, &M1, 0, &M2));
+}
+
+#[test]
+pub fn exact_test_compare_sparse_matrices() {
+    //A single transition from the first state against seven in the opposite direction: the
+    //asymptotic p-value is 0.018 while the exact one is 0.125.
+    let M1 = arr3(&[[[0, 0, 1], [40, 0, 60], [70, 80, 0]]]);
+    let M2 = arr3(&[[[0, 7, 0], [400, 0, 600], [700, 800, 0]]]);
+    let chi_sq = ChiSquare::new(0.05);
+    let exact = ExactTest::new(0.05, 2000, 10, Some(6347747169756259));
+    assert!(!chi_sq.compare_matrices(0, &M1, 0, &M2));
+    assert!(exact.compare_matrices(0, &M1, 0, &M2));
+
+    //With more transitions the evidence is strong enough for both tests.
+    let M1 = arr3(&[[[0, 0, 8], [40, 0, 60], [70, 80, 0]]]);
+    assert!(!chi_sq.compare_matrices(0, &M1, 0, &M2));
+    assert!(!exact.compare_matrices(0, &M1, 0, &M2));
+}
+
+#[test]
+pub fn exact_test_call() {
+    let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let N3: usize = 2;
+    let N2: usize = 1;
+    let N1: usize = 0;
+    let mut separation_set = BTreeSet::new();
+    let parameter_learning = BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) };
+    let mut cache = Cache::new(&parameter_learning);
+    let exact = ExactTest::new(1e-4, 10000, 10, Some(6347747169756259));
+
+    assert!(exact.call(&net, N1, N3, &separation_set, &data, &mut cache));
+    let mut cache = Cache::new(&parameter_learning);
+    assert!(!exact.call(&net, N3, N1, &separation_set, &data, &mut cache));
+    assert!(!exact.call(&net, N3, N2, &separation_set, &data, &mut cache));
+    separation_set.insert(N1);
+    let mut cache = Cache::new(&parameter_learning);
+    assert!(exact.call(&net, N2, N3, &separation_set, &data, &mut cache));
+}
+
+#[test]
+fn hypothesis_tests_p_value() {
+    let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let parameter_learning = BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) };
+    let cases = [
+        (0, 2, BTreeSet::new()),
+        (2, 0, BTreeSet::new()),
+        (2, 1, BTreeSet::new()),
+        (1, 2, BTreeSet::from([0])),
+    ];
+    let f = F::new(1e-6);
+    let chi_sq = ChiSquare::new(1e-4);
+    let exact = ExactTest::new(1e-4, 10000, 10, Some(6347747169756259));
+    //The independence is rejected exactly when the p-value is not larger than alpha.
+    for (child, parent, separation_set) in cases.iter() {
+        let mut cache = Cache::new(&parameter_learning);
+        let p_value = f.p_value(&net, *child, *parent, separation_set, &data, &mut cache);
+        assert!((0.0..=1.0).contains(&p_value));
+        assert_eq!(
+            f.call(&net, *child, *parent, separation_set, &data, &mut cache),
+            p_value > 1e-6
+        );
+        let p_value = chi_sq.p_value(&net, *child, *parent, separation_set, &data, &mut cache);
+        assert!((0.0..=1.0).contains(&p_value));
+        assert_eq!(
+            chi_sq.call(&net, *child, *parent, separation_set, &data, &mut cache),
+            p_value > 1e-4
+        );
+        let p_value = exact.p_value(&net, *child, *parent, separation_set, &data, &mut cache);
+        assert!((0.0..=1.0).contains(&p_value));
+        assert_eq!(
+            exact.call(&net, *child, *parent, separation_set, &data, &mut cache),
+            p_value > 1e-4
+        );
+    }
+}
+
+#[test]
+pub fn f_call() {
+    let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let N3: usize = 2;
+    let N2: usize = 1;
+    let N1: usize = 0;
+    let mut separation_set = BTreeSet::new();
+    let parameter_learning = BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) };
+    let mut cache = Cache::new(&parameter_learning);
+    let f = F::new(1e-6);
+
+    assert!(f.call(&net, N1, N3, &separation_set, &data, &mut cache));
+    let mut cache = Cache::new(&parameter_learning);
+    assert!(!f.call(&net, N3, N1, &separation_set, &data, &mut cache));
+    assert!(!f.call(&net, N3, N2, &separation_set, &data, &mut cache));
+    separation_set.insert(N1);
+    let mut cache = Cache::new(&parameter_learning);
+    assert!(f.call(&net, N2, N3, &separation_set, &data, &mut cache));
+}
+
+#[test]
+pub fn f_compare_matrices_zero_rows() {
+    //The first state is never left in `M1`: its row is skipped.
+    let M1 = arr3(&[[[0, 0, 0], [5, 0, 5], [5, 5, 0]]]);
+    let cim_1 = arr3(&[[[0.0, 0.0, 0.0], [1.0, -2.0, 1.0], [1.0, 1.0, -2.0]]]);
+    let M2 = arr3(&[[[0, 50, 50], [5, 0, 5], [5, 5, 0]]]);
+    let cim_2 = arr3(&[[[-100.0, 50.0, 50.0], [1.0, -2.0, 1.0], [1.0, 1.0, -2.0]]]);
+    let f = F::new(1e-6);
+    assert!(f.compare_matrices(0, &M1, &cim_1, 0, &M2, &cim_2));
+    assert_eq!(
+        Ok(true),
+        f.try_compare_matrices(0, &M1, &cim_1, 0, &M2, &cim_2)
+    );
+
+    //No transitions at all: the comparison is degenerate.
+    let M1 = Array3::<usize>::zeros((1, 3, 3));
+    let cim_1 = Array3::<f64>::from_elem((1, 3, 3), f64::NAN);
+    assert!(f.compare_matrices(0, &M1, &cim_1, 0, &M2, &cim_2));
+    assert!(matches!(
+        f.try_compare_matrices(0, &M1, &cim_1, 0, &M2, &cim_2),
+        Err(StructureLearningError::DegenerateTest(_))
+    ));
+}
+
+#[test]
+pub fn f_call_tiny_dataset() {
+    let net = get_mixed_discrete_net_3_nodes();
+    //Most of the parent configurations are never observed: the MLE leaves their rows empty.
+    let trj = Trajectory::new(
+        arr1(&[0.0, 0.1, 0.3, 0.4]),
+        arr2(&[[0, 0, 0], [1, 0, 0], [1, 1, 0], [1, 1, 0]]),
+    );
+    let data = Dataset::new(vec![trj]);
+    let parameter_learning = MLE {};
+    let f = F::new(1e-6);
+    for (child, parent) in [(0, 1), (1, 0), (2, 0), (2, 1)] {
+        let mut cache = Cache::new(&parameter_learning);
+        assert!(f.call(&net, child, parent, &BTreeSet::new(), &data, &mut cache));
+        let mut cache = Cache::new(&parameter_learning);
+        assert!(f.p_value(&net, child, parent, &BTreeSet::new(), &data, &mut cache) <= 1.0);
+    }
+    //The third node never leaves its state: there is nothing to compare.
+    let mut cache = Cache::new(&parameter_learning);
+    assert!(matches!(
+        f.try_call(&net, 2, 0, &BTreeSet::new(), &data, &mut cache),
+        Err(StructureLearningError::DegenerateTest(_))
+    ));
+    let mut cache = Cache::new(&parameter_learning);
+    assert!(f
+        .try_call(&net, 1, 0, &BTreeSet::new(), &data, &mut cache)
+        .is_ok());
+}
+
+#[test]
+pub fn learn_ternary_net_2_nodes_ctpc() {
+    let f = F::new(1e-6);
+    let chi_sq = ChiSquare::new(1e-4);
+    let parameter_learning = BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) };
+    let ctpc = CTPC::new(parameter_learning, f, chi_sq);
+    learn_ternary_net_2_nodes(ctpc);
+}
+
+#[test]
 pub fn learn_ternary_net_2_nodes_ctpc_gen() {
     let f = F::new(1e-6);
     let chi_sq = ChiSquare::new(1e-4);
-    let parameter_learning = BayesianApproach { alpha: 1, tau: 1.0 };
+    let parameter_learning = BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) };
     let ctpc = CTPC::new(parameter_learning, f, chi_sq);
     learn_ternary_net_2_nodes_gen(ctpc);
 }
@@ -562,7 +1495,7 @@ pub fn learn_ternary_net_2_nodes_ctpc_gen() {
 fn learn_mixed_discrete_net_3_nodes_ctpc() {
     let f = F::new(1e-6);
     let chi_sq = ChiSquare::new(1e-4);
-    let parameter_learning = BayesianApproach { alpha: 1, tau: 1.0 };
+    let parameter_learning = BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) };
     let ctpc = CTPC::new(parameter_learning, f, chi_sq);
     learn_mixed_discrete_net_3_nodes(ctpc);
 }
@@ -571,7 +1504,1280 @@ fn learn_mixed_discrete_net_3_nodes_ctpc() {
 fn learn_mixed_discrete_net_3_nodes_ctpc_gen() {
     let f = F::new(1e-6);
     let chi_sq = ChiSquare::new(1e-4);
-    let parameter_learning = BayesianApproach { alpha: 1, tau: 1.0 };
+    let parameter_learning = BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) };
     let ctpc = CTPC::new(parameter_learning, f, chi_sq);
     learn_mixed_discrete_net_3_nodes_gen(ctpc);
 }
+
+#[test]
+fn learn_mixed_discrete_net_3_nodes_ctpc_noisy_or_suff_stats_cache() {
+    let f = F::new(1e-6);
+    let chi_sq = ChiSquare::new(1e-4);
+    let parameter_learning = NoisyOrMLE {
+        max_iterations: 100,
+        tolerance: 1e-6,
+    };
+    let suff_stats_cache = Arc::new(SuffStatsCache::new());
+    //The statistics of the candidate parent sets differ from the parent sets of the network.
+    let ctpc = CTPC::new_with_suff_stats_cache(parameter_learning, f, chi_sq, suff_stats_cache.clone());
+    learn_mixed_discrete_net_3_nodes(ctpc);
+    assert!(!suff_stats_cache.is_empty());
+}
+
+#[test]
+fn cross_validated_ll_noisy_or() {
+    let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let cv_ll = CrossValidatedLL::new(
+        NoisyOrMLE {
+            max_iterations: 100,
+            tolerance: 1e-6,
+        },
+        5,
+        Some(6813071588535822),
+    );
+    //The scored parent set is not the one of node 2 in the network.
+    let score = cv_ll.call(&net, 2, &BTreeSet::from([0]), &data);
+    assert!(score.is_finite());
+    assert!(cv_ll.call(&net, 2, &BTreeSet::from([0, 1]), &data) > score);
+}
+
+#[test]
+pub fn learn_ternary_net_2_nodes_hiton() {
+    let f = F::new(1e-6);
+    let chi_sq = ChiSquare::new(1e-4);
+    let parameter_learning = BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) };
+    let hiton = Hiton::new(parameter_learning, f, chi_sq, None);
+    learn_ternary_net_2_nodes(hiton);
+}
+
+#[test]
+pub fn learn_ternary_net_2_nodes_hiton_gen() {
+    let f = F::new(1e-6);
+    let chi_sq = ChiSquare::new(1e-4);
+    let parameter_learning = BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) };
+    let hiton = Hiton::new(parameter_learning, f, chi_sq, None);
+    learn_ternary_net_2_nodes_gen(hiton);
+}
+
+#[test]
+fn learn_mixed_discrete_net_3_nodes_hiton() {
+    let f = F::new(1e-6);
+    let chi_sq = ChiSquare::new(1e-4);
+    let parameter_learning = BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) };
+    let hiton = Hiton::new(parameter_learning, f, chi_sq, None);
+    learn_mixed_discrete_net_3_nodes(hiton);
+}
+
+#[test]
+fn learn_mixed_discrete_net_3_nodes_hiton_gen() {
+    let f = F::new(1e-6);
+    let chi_sq = ChiSquare::new(1e-4);
+    let parameter_learning = BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) };
+    let hiton = Hiton::new(parameter_learning, f, chi_sq, None);
+    learn_mixed_discrete_net_3_nodes_gen(hiton);
+}
+
+#[test]
+fn score_functions_with_suff_stats_cache() {
+    let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let suff_stats_cache = Arc::new(SuffStatsCache::new());
+    let ll = LogLikelihood::new(1, 1.0);
+    let ll_cached = LogLikelihood::new_with_suff_stats_cache(1, 1.0, suff_stats_cache.clone());
+    let bic = BIC::new(1, 1.0);
+    let bic_cached = BIC::new_with_suff_stats_cache(1, 1.0, suff_stats_cache.clone());
+    let parent_set = BTreeSet::from_iter(vec![0, 1]);
+
+    assert_eq!(
+        ll.call(&net, 2, &parent_set, &data),
+        ll_cached.call(&net, 2, &parent_set, &data)
+    );
+    assert_eq!(1, suff_stats_cache.len());
+    // BIC reuses the statistics already computed by the LogLikelihood.
+    assert_eq!(
+        bic.call(&net, 2, &parent_set, &data),
+        bic_cached.call(&net, 2, &parent_set, &data)
+    );
+    assert_eq!(1, suff_stats_cache.len());
+}
+
+#[test]
+pub fn learn_mixed_discrete_net_3_nodes_hill_climbing_bic_suff_stats_cache() {
+    let suff_stats_cache = Arc::new(SuffStatsCache::new());
+    let bic = BIC::new_with_suff_stats_cache(1, 1.0, suff_stats_cache.clone());
+    let hl = HillClimbing::new(bic, None);
+    learn_mixed_discrete_net_3_nodes(hl);
+    assert!(!suff_stats_cache.is_empty());
+}
+
+#[test]
+pub fn hill_climbing_sweep_with_score_cache() {
+    let (mut net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let data = Arc::new(data);
+    let score_cache = Arc::new(ScoreCache::new(Arc::clone(&data)));
+
+    let bic = BIC::new_with_score_cache(1, 1.0, score_cache.clone());
+    HillClimbing::new(bic, None).fit_in_place(&mut net, &data);
+    assert_eq!(BTreeSet::from_iter(vec![0, 1]), net.get_parent_set(2));
+    let n_stats = score_cache.len();
+
+    //The statistics do not depend on the hyperparameters: the next runs compute none.
+    for (alpha, tau) in [(1, 0.1), (2, 1.0)] {
+        HillClimbing::new(BIC::new(alpha, tau), Some(1)).fit_in_place(&mut net, &data);
+        let uncached: Vec<BTreeSet<usize>> = net
+            .get_node_indices()
+            .map(|x| net.get_parent_set(x))
+            .collect();
+        let bic = BIC::new_with_score_cache(alpha, tau, score_cache.clone());
+        HillClimbing::new(bic, Some(1)).fit_in_place(&mut net, &data);
+        let cached: Vec<BTreeSet<usize>> = net
+            .get_node_indices()
+            .map(|x| net.get_parent_set(x))
+            .collect();
+        assert_eq!(uncached, cached);
+    }
+    assert_eq!(n_stats, score_cache.len());
+}
+
+#[test]
+fn score_cache_rejects_other_dataset() {
+    let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let data = Arc::new(data);
+    let score_cache = ScoreCache::new(Arc::clone(&data));
+    assert!(score_cache
+        .get_or_compute(&net, &data, 2, &BTreeSet::new())
+        .is_ok());
+
+    let other_data = trajectory_generator(&net, 10, 1.0, Some(1234));
+    assert!(matches!(
+        score_cache.get_or_compute(&net, &other_data, 2, &BTreeSet::new()),
+        Err(StructureLearningError::MismatchedDataset(_))
+    ));
+    //A copy of the dataset is accepted.
+    let copied_data = (*data).clone();
+    assert!(score_cache.check_dataset(&copied_data).is_ok());
+}
+
+#[test]
+#[should_panic]
+fn bic_with_score_cache_panics_on_other_dataset() {
+    let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let other_data = trajectory_generator(&net, 10, 1.0, Some(1234));
+    let bic = BIC::new_with_score_cache(1, 1.0, Arc::new(ScoreCache::new(data)));
+    bic.call(&net, 2, &BTreeSet::new(), &other_data);
+}
+
+#[test]
+fn learn_mixed_discrete_net_3_nodes_ctpc_suff_stats_cache() {
+    let suff_stats_cache = Arc::new(SuffStatsCache::new());
+    let f = F::new(1e-6);
+    let chi_sq = ChiSquare::new(1e-4);
+    let parameter_learning = BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) };
+    let ctpc = CTPC::new_with_suff_stats_cache(
+        parameter_learning,
+        f,
+        chi_sq,
+        suff_stats_cache.clone(),
+    );
+    learn_mixed_discrete_net_3_nodes(ctpc);
+    assert!(!suff_stats_cache.is_empty());
+}
+
+#[test]
+pub fn chi_square_call_suff_stats_cache() {
+    let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let N3: usize = 2;
+    let N1: usize = 0;
+    let separation_set = BTreeSet::new();
+    let parameter_learning = BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) };
+    let suff_stats_cache = Arc::new(SuffStatsCache::new());
+    let mut cache = Cache::new_with_suff_stats_cache(&parameter_learning, suff_stats_cache.clone());
+    let chi_sq = ChiSquare::new(1e-4);
+
+    assert!(chi_sq.call(&net, N1, N3, &separation_set, &data, &mut cache));
+    let mut cache = Cache::new_with_suff_stats_cache(&parameter_learning, suff_stats_cache.clone());
+    assert!(!chi_sq.call(&net, N3, N1, &separation_set, &data, &mut cache));
+    // (N1, {}), (N1, {N3}), (N3, {}) and (N3, {N1})
+    assert_eq!(4, suff_stats_cache.len());
+}
+
+fn learn_mixed_discrete_net_3_nodes_in_place<T: StructuralLearningAlgorithm>(sl: T) {
+    let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let mut net_in_place = CtbnNetwork::new();
+    generate_nodes(&mut net_in_place, 2, 3);
+    net_in_place
+        .add_node(generate_discrete_time_continous_node(String::from("3"), 4))
+        .unwrap();
+    sl.fit_in_place(&mut net_in_place, &data);
+    let net = sl.fit_transform(net, &data).unwrap();
+    for node in net.get_node_indices() {
+        assert_eq!(net.get_parent_set(node), net_in_place.get_parent_set(node));
+    }
+    assert_eq!(
+        BTreeSet::from_iter(vec![0, 1]),
+        net_in_place.get_parent_set(2)
+    );
+}
+
+#[test]
+pub fn learn_mixed_discrete_net_3_nodes_hill_climbing_bic_in_place() {
+    let bic = BIC::new(1, 1.0);
+    let hl = HillClimbing::new(bic, None);
+    learn_mixed_discrete_net_3_nodes_in_place(hl);
+}
+
+#[test]
+fn learn_mixed_discrete_net_3_nodes_ctpc_in_place() {
+    let f = F::new(1e-6);
+    let chi_sq = ChiSquare::new(1e-4);
+    let parameter_learning = BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) };
+    let ctpc = CTPC::new(parameter_learning, f, chi_sq);
+    learn_mixed_discrete_net_3_nodes_in_place(ctpc);
+}
+
+#[test]
+fn fit_in_place_discards_previous_structure() {
+    let (_, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let mut net = CtbnNetwork::new();
+    generate_nodes(&mut net, 2, 3);
+    net.add_node(generate_discrete_time_continous_node(String::from("3"), 4))
+        .unwrap();
+    net.add_edge(2, 0);
+    let hl = HillClimbing::new(BIC::new(1, 1.0), None);
+    hl.fit_in_place(&mut net, &data);
+    assert_eq!(BTreeSet::new(), net.get_parent_set(0));
+}
+
+fn learn_mixed_discrete_net_3_nodes_permuted<T: StructuralLearningAlgorithm>(sl: T) {
+    let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let perm = [2, 0, 1];
+    let (permuted_net, permuted_data) = permute_variables(&net, &data, &perm);
+    let permuted_net = sl.fit_transform(permuted_net, &permuted_data).unwrap();
+    let net = sl.fit_transform(net, &data).unwrap();
+    for node in net.get_node_indices() {
+        let parent_set: BTreeSet<usize> = permuted_net
+            .get_parent_set(perm[node])
+            .iter()
+            .map(|x| perm.iter().position(|y| y == x).unwrap())
+            .collect();
+        assert_eq!(net.get_parent_set(node), parent_set);
+    }
+}
+
+#[test]
+pub fn learn_mixed_discrete_net_3_nodes_hill_climbing_bic_permuted() {
+    let bic = BIC::new(1, 1.0);
+    let hl = HillClimbing::new(bic, None);
+    learn_mixed_discrete_net_3_nodes_permuted(hl);
+}
+
+#[test]
+fn learn_mixed_discrete_net_3_nodes_ctpc_permuted() {
+    let f = F::new(1e-6);
+    let chi_sq = ChiSquare::new(1e-4);
+    let parameter_learning = BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) };
+    let ctpc = CTPC::new(parameter_learning, f, chi_sq);
+    learn_mixed_discrete_net_3_nodes_permuted(ctpc);
+}
+
+#[test]
+fn learn_mixed_discrete_net_3_nodes_ctpc_loose_caps() {
+    let parameter_learning = BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) };
+    let ctpc = CTPC::new(
+        parameter_learning,
+        F::new(1e-6),
+        ChiSquare::new(1e-4),
+    )
+    .with_max_separation_set_size(2)
+    .with_max_candidate_parents(2);
+    learn_mixed_discrete_net_3_nodes(ctpc);
+}
+
+#[test]
+fn ctpc_max_separation_set_size() {
+    let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let ctpc = CTPC::new(
+        BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) },
+        F::new(1e-6),
+        ChiSquare::new(1e-4),
+    )
+    .with_max_separation_set_size(0);
+    let net = ctpc.fit_transform(net, &data).unwrap();
+    assert_eq!(BTreeSet::new(), net.get_parent_set(0));
+    //Without conditioning on n1, n3 looks like a parent of n2.
+    assert_eq!(BTreeSet::from_iter(vec![0, 2]), net.get_parent_set(1));
+    assert_eq!(BTreeSet::from_iter(vec![0, 1]), net.get_parent_set(2));
+}
+
+#[test]
+fn ctpc_max_candidate_parents() {
+    let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let ctpc = CTPC::new(
+        BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) },
+        F::new(1e-6),
+        ChiSquare::new(1e-4),
+    )
+    .with_max_candidate_parents(1);
+    let net = ctpc.fit_transform(net, &data).unwrap();
+    assert_eq!(BTreeSet::new(), net.get_parent_set(0));
+    assert_eq!(BTreeSet::from_iter(vec![0]), net.get_parent_set(1));
+    assert_eq!(BTreeSet::from_iter(vec![0]), net.get_parent_set(2));
+}
+
+#[test]
+fn hill_climbing_diagnostics() {
+    let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let hl = HillClimbing::new(BIC::new(1, 1.0), None);
+    let (net, diagnostics) = hl.fit_transform_with_diagnostics(net, &data);
+    assert_eq!(BTreeSet::from_iter(vec![0, 1]), net.get_parent_set(2));
+    assert_eq!(3, diagnostics.nodes.len());
+    assert_eq!(None, diagnostics.n_parameter_fits);
+    let bic = BIC::new(1, 1.0);
+    for (node, node_diagnostics) in diagnostics.nodes.iter().enumerate() {
+        assert_eq!(node, node_diagnostics.node);
+        assert_eq!(
+            Some(bic.call(&net, node, &net.get_parent_set(node), &data)),
+            node_diagnostics.score
+        );
+        assert_eq!(None, node_diagnostics.n_tests);
+    }
+    assert!(diagnostics.elapsed_seconds >= 0.0);
+}
+
+#[test]
+fn ctpc_diagnostics() {
+    let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let ctpc = CTPC::new(
+        BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) },
+        F::new(1e-6),
+        ChiSquare::new(1e-4),
+    );
+    let (net, diagnostics) = ctpc.fit_transform_with_diagnostics(net, &data);
+    assert_eq!(BTreeSet::from_iter(vec![0, 1]), net.get_parent_set(2));
+    assert_eq!(3, diagnostics.nodes.len());
+    for (node, node_diagnostics) in diagnostics.nodes.iter().enumerate() {
+        assert_eq!(node, node_diagnostics.node);
+        assert_eq!(None, node_diagnostics.score);
+        let n_tests = node_diagnostics.n_tests.unwrap();
+        let n_rejected_tests = node_diagnostics.n_rejected_tests.unwrap();
+        assert!(n_rejected_tests <= n_tests);
+        //Each kept parent rejected the independence at least once.
+        assert!(n_rejected_tests >= net.get_parent_set(node).len());
+    }
+    //n1 has no parents: both candidates are dropped by the marginal tests.
+    assert_eq!(Some(2), diagnostics.nodes[0].n_tests);
+    assert_eq!(Some(0), diagnostics.nodes[0].n_rejected_tests);
+    assert!(diagnostics.n_parameter_fits.unwrap() > 0);
+}
+
+#[test]
+fn ctpc_separation_sets() {
+    let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let ctpc = CTPC::new(
+        BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) },
+        F::new(1e-6),
+        ChiSquare::new(1e-4),
+    );
+    let (net, separation_sets) = ctpc.fit_transform_with_sepsets(net, &data);
+    assert_eq!(BTreeSet::from_iter(vec![0, 1]), net.get_parent_set(2));
+    //The edges of the true structure are never separated, not even n2 -> n3 given {n1}.
+    for edge in [(0, 1), (0, 2), (1, 2)] {
+        assert!(!separation_sets.contains_key(&edge));
+    }
+    //n1 evolves independently of the other nodes, while n3 is separated from n2 by n1.
+    assert_eq!(Some(&BTreeSet::new()), separation_sets.get(&(1, 0)));
+    assert_eq!(Some(&BTreeSet::new()), separation_sets.get(&(2, 0)));
+    assert_eq!(Some(&BTreeSet::from([0])), separation_sets.get(&(2, 1)));
+    assert_eq!(3, separation_sets.len());
+}
+
+#[test]
+pub fn hill_climbing_candidate_parents() {
+    let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let candidate_parents = vec![BTreeSet::new(), BTreeSet::from([0, 2]), BTreeSet::from([1])];
+    let hl = HillClimbing::new(BIC::new(1, 1.0), None).with_candidate_parents(candidate_parents);
+    let net = hl.fit_transform(net, &data).unwrap();
+    assert_eq!(BTreeSet::new(), net.get_parent_set(0));
+    assert_eq!(BTreeSet::from_iter(vec![0]), net.get_parent_set(1));
+    assert_eq!(BTreeSet::from_iter(vec![1]), net.get_parent_set(2));
+}
+
+#[test]
+pub fn learn_mixed_discrete_net_3_nodes_hybrid() {
+    let ctpc = CTPC::new(
+        BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) },
+        F::new(1e-6),
+        ChiSquare::new(1e-4),
+    );
+    let hl = HillClimbing::new(BIC::new(1, 1.0), None);
+    learn_mixed_discrete_net_3_nodes(HybridLearner::new(ctpc, hl));
+}
+
+#[test]
+pub fn learn_mixed_discrete_net_3_nodes_hybrid_gen() {
+    let ctpc = CTPC::new(
+        BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) },
+        F::new(1e-6),
+        ChiSquare::new(1e-4),
+    );
+    let hl = HillClimbing::new(BIC::new(1, 1.0), None);
+    learn_mixed_discrete_net_3_nodes_gen(HybridLearner::new(ctpc, hl));
+}
+
+#[test]
+pub fn hybrid_diagnostics() {
+    let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let ctpc = CTPC::new(
+        BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) },
+        F::new(1e-6),
+        ChiSquare::new(1e-4),
+    );
+    let hybrid = HybridLearner::new(ctpc, HillClimbing::new(BIC::new(1, 1.0), None));
+    let (_, diagnostics) = hybrid.fit_transform_with_diagnostics(net, &data);
+    assert_eq!(3, diagnostics.nodes.len());
+    for (node, node_diagnostics) in diagnostics.nodes.iter().enumerate() {
+        assert_eq!(node, node_diagnostics.node);
+        assert!(node_diagnostics.score.is_some());
+        assert!(node_diagnostics.n_tests.is_some());
+    }
+    assert!(diagnostics.n_parameter_fits.unwrap() > 0);
+}
+
+#[test]
+pub fn hill_climbing_progress_counter() {
+    let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let score_function = Arc::new(CountingScore {
+        score_function: BIC::new(1, 1.0),
+        n_calls: AtomicUsize::new(0),
+    });
+    let progress_counter = Arc::new(ProgressCounter::new());
+    let hl: HillClimbing<CountingScore<BIC>> = HillClimbing::new(Arc::clone(&score_function), None)
+        .with_progress_observer(progress_counter.clone());
+    let net = hl.fit_transform(net, &data).unwrap();
+    assert_eq!(BTreeSet::from_iter(vec![0, 1]), net.get_parent_set(2));
+    assert_eq!(3, progress_counter.get_nodes_started());
+    assert_eq!(3, progress_counter.get_nodes_finished());
+    assert_eq!(0, progress_counter.get_tests_performed());
+    assert_eq!(
+        score_function.n_calls.load(Ordering::SeqCst),
+        progress_counter.get_scores_evaluated()
+    );
+}
+
+#[test]
+pub fn ctpc_progress_counter() {
+    let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let progress_counter = Arc::new(ProgressCounter::new());
+    let ctpc = CTPC::new(
+        BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) },
+        F::new(1e-6),
+        ChiSquare::new(1e-4),
+    )
+    .with_progress_observer(progress_counter.clone());
+    let (net, diagnostics) = ctpc.fit_transform_with_diagnostics(net, &data);
+    assert_eq!(BTreeSet::from_iter(vec![0, 1]), net.get_parent_set(2));
+    assert_eq!(3, progress_counter.get_nodes_started());
+    assert_eq!(3, progress_counter.get_nodes_finished());
+    assert_eq!(0, progress_counter.get_scores_evaluated());
+    let n_tests: usize = diagnostics.nodes.iter().map(|x| x.n_tests.unwrap()).sum();
+    assert_eq!(n_tests, progress_counter.get_tests_performed());
+}
+
+#[test]
+pub fn hill_climbing_known_parent_sets() {
+    let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let progress_counter = Arc::new(ProgressCounter::new());
+    let hl =
+        HillClimbing::new(BIC::new(1, 1.0), None).with_progress_observer(progress_counter.clone());
+    let mut net = net;
+    hl.fit_in_place(&mut net, &data);
+    let learned_parent_sets: Vec<BTreeSet<usize>> =
+        (0..3).map(|node| net.get_parent_set(node)).collect();
+    let n_scores = progress_counter.get_scores_evaluated();
+
+    let progress_counter = Arc::new(ProgressCounter::new());
+    let hl = HillClimbing::new(BIC::new(1, 1.0), None)
+        .with_known_parent_sets(HashMap::from([(2, BTreeSet::from([0, 1]))]))
+        .with_progress_observer(progress_counter.clone());
+    let diagnostics = hl.fit_in_place_with_diagnostics(&mut net, &data);
+    for node in 0..3 {
+        assert_eq!(learned_parent_sets[node], net.get_parent_set(node));
+    }
+    //The search of the node with the most parents is skipped: its 4 evaluations ({}, {0},
+    //{0, 1} and {1}) are saved, while nodes 0 and 1 still evaluate 3 parent sets each.
+    assert_eq!(10, n_scores);
+    assert_eq!(6, progress_counter.get_scores_evaluated());
+    assert_eq!(3, progress_counter.get_nodes_finished());
+    assert_eq!(None, diagnostics.nodes[2].score);
+}
+
+#[test]
+pub fn ctpc_known_parent_sets() {
+    let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    //The known parent set is copied verbatim, even if the data does not support it.
+    let ctpc = CTPC::new(
+        BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) },
+        F::new(1e-6),
+        ChiSquare::new(1e-4),
+    )
+    .with_known_parent_sets(HashMap::from([(0, BTreeSet::from([2]))]));
+    let (net, diagnostics) = ctpc.fit_transform_with_diagnostics(net, &data);
+    assert_eq!(BTreeSet::from([2]), net.get_parent_set(0));
+    assert_eq!(BTreeSet::from([0]), net.get_parent_set(1));
+    assert_eq!(BTreeSet::from([0, 1]), net.get_parent_set(2));
+    assert_eq!(Some(0), diagnostics.nodes[0].n_tests);
+}
+
+#[test]
+pub fn known_parent_sets_out_of_range() {
+    let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let hl = HillClimbing::new(BIC::new(1, 1.0), None)
+        .with_known_parent_sets(HashMap::from([(2, BTreeSet::from([0, 3]))]));
+    let mut net = net;
+    assert_eq!(
+        Err(StructureLearningError::InvalidEdgeConstraints(
+            String::from("known parent 3 of node 2, the network has 3 nodes")
+        )),
+        hl.try_fit_in_place(&mut net, &data)
+    );
+    let ctpc = CTPC::new(
+        BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) },
+        F::new(1e-6),
+        ChiSquare::new(1e-4),
+    )
+    .with_known_parent_sets(HashMap::from([(5, BTreeSet::new())]));
+    assert_eq!(
+        Err(StructureLearningError::InvalidEdgeConstraints(
+            String::from("known parent set of node 5, the network has 3 nodes")
+        )),
+        ctpc.try_fit_in_place(&mut net, &data)
+    );
+}
+
+#[test]
+pub fn ctpc_progress_observer_events() {
+    //Observer recording the outcome of each test.
+    struct TestRecorder {
+        tests: Mutex<Vec<(usize, usize, BTreeSet<usize>, bool)>>,
+        finished: Mutex<Vec<(usize, BTreeSet<usize>)>>,
+    }
+    impl ProgressObserver for TestRecorder {
+        fn on_node_finished(&self, node: usize, parent_set: &BTreeSet<usize>) {
+            self.finished
+                .lock()
+                .unwrap()
+                .push((node, parent_set.clone()));
+        }
+
+        fn on_test_performed(
+            &self,
+            child: usize,
+            parent: usize,
+            separation_set: &BTreeSet<usize>,
+            independent: bool,
+        ) {
+            self.tests
+                .lock()
+                .unwrap()
+                .push((child, parent, separation_set.clone(), independent));
+        }
+    }
+
+    let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let recorder = Arc::new(TestRecorder {
+        tests: Mutex::new(vec![]),
+        finished: Mutex::new(vec![]),
+    });
+    let ctpc = CTPC::new(
+        BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) },
+        F::new(1e-6),
+        ChiSquare::new(1e-4),
+    )
+    .with_progress_observer(recorder.clone());
+    let net = ctpc.fit_transform(net, &data).unwrap();
+
+    let mut finished = recorder.finished.lock().unwrap().clone();
+    finished.sort();
+    for (node, parent_set) in finished {
+        assert_eq!(net.get_parent_set(node), parent_set);
+    }
+    //n1 has no parents: both candidates are dropped by the marginal tests.
+    let tests = recorder.tests.lock().unwrap();
+    let n1_tests: Vec<&(usize, usize, BTreeSet<usize>, bool)> =
+        tests.iter().filter(|x| x.0 == 0).collect();
+    assert_eq!(2, n1_tests.len());
+    assert!(n1_tests.iter().all(|x| x.2.is_empty() && x.3));
+}
+
+//Network with 20 ternary nodes, random structure and random parameters, and a dataset sampled
+//from it: learning its structure takes several seconds.
+fn get_large_net_with_data() -> (CtbnNetwork, Dataset) {
+    let mut net = CtbnNetwork::new();
+    generate_nodes(&mut net, 20, 3);
+    let mut structure_generator: UniformGraphGenerator =
+        RandomGraphGenerator::new(0.2, Some(7641630759785120));
+    structure_generator.generate_graph(&mut net);
+    let mut cim_generator: UniformParametersGenerator =
+        RandomParametersGenerator::new(0.5..3.0, Some(7641630759785120));
+    cim_generator.generate_parameters(&mut net);
+    let data = trajectory_generator(&net, 100, 10.0, Some(6347747169756259));
+    let mut empty_net = CtbnNetwork::new();
+    generate_nodes(&mut empty_net, 20, 3);
+    (empty_net, data)
+}
+
+#[test]
+fn ctpc_cancellation() {
+    let (mut net, data) = get_large_net_with_data();
+    let cancellation_token = CancellationToken::new();
+    let ctpc = CTPC::new(
+        BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) },
+        F::new(1e-6),
+        ChiSquare::new(1e-4),
+    )
+    .with_cancellation_token(cancellation_token.clone());
+    let (result, cancelled_at) = thread::scope(|s| {
+        let learner = s.spawn(|| ctpc.try_fit_in_place(&mut net, &data));
+        thread::sleep(Duration::from_millis(200));
+        cancellation_token.cancel();
+        let cancelled_at = Instant::now();
+        (learner.join().unwrap(), cancelled_at)
+    });
+    assert!(matches!(
+        result,
+        Err(StructureLearningError::LearningInterrupted(_))
+    ));
+    assert!(cancelled_at.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn hill_climbing_cancellation() {
+    let (mut net, data) = get_large_net_with_data();
+    let cancellation_token = CancellationToken::new();
+    let hl = HillClimbing::new(BIC::new(1, 1.0), None)
+        .with_cancellation_token(cancellation_token.clone());
+    let (result, cancelled_at) = thread::scope(|s| {
+        let learner = s.spawn(|| hl.try_fit_in_place(&mut net, &data));
+        thread::sleep(Duration::from_millis(200));
+        cancellation_token.cancel();
+        let cancelled_at = Instant::now();
+        (learner.join().unwrap(), cancelled_at)
+    });
+    assert!(matches!(
+        result,
+        Err(StructureLearningError::LearningInterrupted(_))
+    ));
+    assert!(cancelled_at.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn ctpc_bonferroni_correction() {
+    //Noisy dataset: few short trajectories and loose significance levels.
+    let mut true_net = CtbnNetwork::new();
+    generate_nodes(&mut true_net, 8, 3);
+    let mut structure_generator: UniformGraphGenerator =
+        RandomGraphGenerator::new(0.2, Some(7641630759785120));
+    structure_generator.generate_graph(&mut true_net);
+    let mut cim_generator: UniformParametersGenerator =
+        RandomParametersGenerator::new(0.5..3.0, Some(7641630759785120));
+    cim_generator.generate_parameters(&mut true_net);
+    let data = trajectory_generator(&true_net, 30, 5.0, Some(6347747169756259));
+    let n_edges = |correction: Correction| {
+        let mut net = CtbnNetwork::new();
+        generate_nodes(&mut net, 8, 3);
+        let ctpc = CTPC::new(
+            BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) },
+            F::new(0.05),
+            ChiSquare::new(0.05),
+        )
+        .with_correction(correction);
+        let net = ctpc.fit_transform(net, &data).unwrap();
+        net.get_node_indices()
+            .map(|node| net.get_parent_set(node).len())
+            .sum::<usize>()
+    };
+    let n_edges_none = n_edges(Correction::None);
+    let n_edges_bonferroni = n_edges(Correction::Bonferroni);
+    assert!(n_edges_none > n_edges_bonferroni);
+}
+
+#[test]
+fn ctpc_false_discovery_rate() {
+    let mut true_net = CtbnNetwork::new();
+    generate_nodes(&mut true_net, 6, 3);
+    let mut structure_generator: UniformGraphGenerator =
+        RandomGraphGenerator::new(0.3, Some(7641630759785120));
+    structure_generator.generate_graph(&mut true_net);
+    let mut cim_generator: UniformParametersGenerator =
+        RandomParametersGenerator::new(0.5..3.0, Some(7641630759785120));
+    cim_generator.generate_parameters(&mut true_net);
+    let data = trajectory_generator(&true_net, 30, 5.0, Some(6347747169756259));
+    let new_ctpc = || {
+        CTPC::new(
+            BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) },
+            F::new(0.05),
+            ChiSquare::new(0.05),
+        )
+    };
+    let n_edges = |net: &CtbnNetwork| {
+        net.get_node_indices()
+            .map(|node| net.get_parent_set(node).len())
+            .sum::<usize>()
+    };
+    let mut net = CtbnNetwork::new();
+    generate_nodes(&mut net, 6, 3);
+    let (net, diagnostics) = new_ctpc().fit_transform_with_diagnostics(net, &data);
+    assert!(diagnostics
+        .nodes
+        .iter()
+        .all(|x| x.adjusted_p_values.is_none()));
+    let n_edges_none = n_edges(&net);
+
+    let mut fdr_net = CtbnNetwork::new();
+    generate_nodes(&mut fdr_net, 6, 3);
+    let (fdr_net, fdr_diagnostics) = new_ctpc()
+        .with_false_discovery_rate(0.05)
+        .fit_transform_with_diagnostics(fdr_net, &data);
+    assert!(n_edges_none > n_edges(&fdr_net));
+    for node in fdr_net.get_node_indices() {
+        let adjusted_p_values = fdr_diagnostics.nodes[node]
+            .adjusted_p_values
+            .as_ref()
+            .unwrap();
+        //Every candidate parent is tested at least once.
+        assert_eq!(5, adjusted_p_values.len());
+        //The FDR control only removes edges: the retained ones survive the adjustment.
+        assert!(fdr_net
+            .get_parent_set(node)
+            .is_subset(&net.get_parent_set(node)));
+        for (parent, adjusted_p_value) in adjusted_p_values.iter() {
+            let retained = fdr_net.get_parent_set(node).contains(parent);
+            assert!(!retained || *adjusted_p_value <= 0.05);
+            if net.get_parent_set(node).contains(parent) {
+                assert_eq!(retained, *adjusted_p_value <= 0.05);
+            }
+        }
+    }
+}
+
+fn single_thread_pool() -> Arc<rayon::ThreadPool> {
+    Arc::new(
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap(),
+    )
+}
+
+#[test]
+fn hill_climbing_single_thread_pool() {
+    let (mut net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let hl = HillClimbing::new(BIC::new(1, 1.0), None);
+    let diagnostics = hl.fit_in_place_with_diagnostics(&mut net, &data);
+    let (mut single_thread_net, _) = get_mixed_discrete_net_3_nodes_with_data();
+    let hl = HillClimbing::new(BIC::new(1, 1.0), None).with_thread_pool(single_thread_pool());
+    let single_thread_diagnostics = hl.fit_in_place_with_diagnostics(&mut single_thread_net, &data);
+    for node in net.get_node_indices() {
+        assert_eq!(
+            net.get_parent_set(node),
+            single_thread_net.get_parent_set(node)
+        );
+        assert_eq!(
+            diagnostics.nodes[node].score,
+            single_thread_diagnostics.nodes[node].score
+        );
+    }
+}
+
+#[test]
+fn hill_climbing_deterministic_ties() {
+    let mut net = CtbnNetwork::new();
+    generate_nodes(&mut net, 3, 2);
+    net.add_edge(0, 2);
+    let mut cim_generator: UniformParametersGenerator =
+        RandomParametersGenerator::new(0.5..3.0, Some(7641630759785120));
+    cim_generator.generate_parameters(&mut net);
+    //Node 1 is a copy of node 0: the parent sets {0} and {1} of node 2 score exactly the same.
+    let data = trajectory_generator(&net, 20, 2.0, Some(6347747169756259));
+    let data = Dataset::new(
+        data.get_trajectories()
+            .iter()
+            .map(|trj| {
+                let mut events = trj.get_events().clone();
+                let copy = events.column(0).to_owned();
+                events.column_mut(1).assign(&copy);
+                Trajectory::new(trj.get_time().clone(), events)
+            })
+            .collect(),
+    );
+    for best_first in [false, true] {
+        let hl = HillClimbing::new(BIC::new(1, 1.0), None).with_best_first(best_first);
+        let mut parent_sets = vec![];
+        for _ in 0..20 {
+            hl.fit_in_place(&mut net, &data);
+            parent_sets.push(
+                net.get_node_indices()
+                    .map(|node| net.get_parent_set(node))
+                    .collect::<Vec<BTreeSet<usize>>>(),
+            );
+        }
+        assert!(parent_sets.iter().all(|x| x == &parent_sets[0]));
+        //The tie is broken in favour of the lexicographically smaller parent set.
+        assert_eq!(BTreeSet::from([0]), net.get_parent_set(2));
+    }
+}
+
+#[test]
+fn ctpc_single_thread_pool() {
+    let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let ctpc = CTPC::new(
+        BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) },
+        F::new(1e-6),
+        ChiSquare::new(1e-4),
+    );
+    let (net, separation_sets) = ctpc.fit_transform_with_sepsets(net, &data);
+    let (single_thread_net, _) = get_mixed_discrete_net_3_nodes_with_data();
+    let ctpc = CTPC::new(
+        BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) },
+        F::new(1e-6),
+        ChiSquare::new(1e-4),
+    )
+    .with_thread_pool(single_thread_pool());
+    let (single_thread_net, single_thread_separation_sets) =
+        ctpc.fit_transform_with_sepsets(single_thread_net, &data);
+    for node in net.get_node_indices() {
+        assert_eq!(
+            net.get_parent_set(node),
+            single_thread_net.get_parent_set(node)
+        );
+    }
+    assert_eq!(separation_sets, single_thread_separation_sets);
+}
+
+#[test]
+pub fn learn_mixed_discrete_net_3_nodes_hill_climbing_bic_shared_score_function() {
+    let bic = Arc::new(BIC::new_with_suff_stats_cache(
+        1,
+        1.0,
+        Arc::new(SuffStatsCache::new()),
+    ));
+    std::thread::scope(|s| {
+        for _ in 0..2 {
+            let hl: HillClimbing<BIC> = HillClimbing::new(Arc::clone(&bic), None);
+            s.spawn(move || learn_mixed_discrete_net_3_nodes(hl));
+        }
+    });
+}
+
+#[test]
+fn evaluate_hill_climbing_and_ctpc() {
+    let net = get_mixed_discrete_net_3_nodes();
+    let hl = HillClimbing::new(BIC::new(1, 1.0), None);
+    let ctpc = CTPC::new(
+        BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) },
+        F::new(1e-6),
+        ChiSquare::new(1e-4),
+    );
+    let config = EvaluationConfig {
+        n_trajectories: 300,
+        t_end: 30.0,
+        n_replications: 2,
+        seed: Some(6347747169756259),
+    };
+    let report = evaluate(&net, &config, &[("HillClimbing", &hl), ("CTPC", &ctpc)]);
+
+    assert_eq!(2, report.algorithms.len());
+    for summary in report.algorithms.iter() {
+        assert_eq!(1.0, summary.precision.mean);
+        assert_eq!(1.0, summary.recall.mean);
+        assert_eq!(1.0, summary.f1.mean);
+        assert_eq!(0.0, summary.f1.std);
+    }
+    assert_eq!(3, report.to_csv().lines().count());
+}
+
+#[test]
+fn edge_metrics_partial_structure() {
+    let net = get_mixed_discrete_net_3_nodes();
+    let mut learned = CtbnNetwork::new();
+    for node in net.get_node_indices() {
+        learned.add_node(net.get_node(node).clone()).unwrap();
+    }
+    learned.add_edge(0, 1);
+    learned.add_edge(2, 1);
+    let (precision, recall, f1) = edge_metrics(&net, &learned);
+    assert_eq!(0.5, precision);
+    assert_relative_eq!(1.0 / 3.0, recall);
+    assert_relative_eq!(0.4, f1);
+}
+
+#[test]
+fn structural_diff_corrupted_copy() {
+    let net = get_mixed_discrete_net_3_nodes();
+    let mut learned = CtbnNetwork::new();
+    for node in net.get_node_indices() {
+        learned.add_node(net.get_node(node).clone()).unwrap();
+    }
+    //Keep n1 -> n2, drop n1 -> n3, reverse n2 -> n3 and add n2 -> n1.
+    learned.add_edge(0, 1);
+    learned.add_edge(2, 1);
+    learned.add_edge(1, 0);
+
+    let edge = |parent: &str, child: &str| (String::from(parent), String::from(child));
+    let diff = structural_diff(&net, &learned).unwrap();
+    assert_eq!(1, diff.true_positives);
+    assert_eq!(2, diff.false_positives);
+    assert_eq!(2, diff.false_negatives);
+    assert_eq!(3, diff.shd);
+    assert_eq!(vec![edge("n1", "n3"), edge("n2", "n3")], diff.missing_edges);
+    assert_eq!(vec![edge("n2", "n1"), edge("n3", "n2")], diff.extra_edges);
+    assert_eq!(vec![edge("n2", "n3")], diff.reversed_edges);
+
+    let diff = structural_diff(&net, &net).unwrap();
+    assert_eq!(3, diff.true_positives);
+    assert_eq!(0, diff.shd);
+    assert!(diff.missing_edges.is_empty() && diff.extra_edges.is_empty());
+}
+
+#[test]
+fn structural_diff_mismatched_networks() {
+    let net = get_mixed_discrete_net_3_nodes();
+    let mut learned = CtbnNetwork::new();
+    generate_nodes(&mut learned, 3, 3);
+    assert_eq!(
+        Err(StructureLearningError::MismatchedNetworks(String::from(
+            "node 0 is labelled n1 in the reference network and 0 in the learned one"
+        ))),
+        structural_diff(&net, &learned)
+    );
+
+    let mut learned = CtbnNetwork::new();
+    learned.add_node(net.get_node(0).clone()).unwrap();
+    assert_eq!(
+        Err(StructureLearningError::MismatchedNetworks(String::from(
+            "the reference network has 3 nodes, the learned one has 1"
+        ))),
+        structural_diff(&net, &learned)
+    );
+}
+
+fn count_false_positives(truth: &CtbnNetwork, learned: &CtbnNetwork) -> usize {
+    truth
+        .get_node_indices()
+        .map(|node| {
+            learned
+                .get_parent_set(node)
+                .difference(&truth.get_parent_set(node))
+                .count()
+        })
+        .sum()
+}
+
+#[test]
+fn hill_climbing_with_validation_fewer_false_positives() {
+    let mut truth = CtbnNetwork::new();
+    generate_nodes(&mut truth, 6, 3);
+    truth.add_edge(0, 1);
+    let mut cim_generator: UniformParametersGenerator =
+        RandomParametersGenerator::new(0.0..7.0, Some(6813071588535822));
+    cim_generator.generate_parameters(&mut truth);
+
+    let plain = HillClimbing::new(LogLikelihood::new(1, 1.0), None);
+    let validated = HillClimbing::with_validation(
+        LogLikelihood::new(1, 1.0),
+        None,
+        0.3,
+        0.0,
+        1,
+        1.0,
+        Some(6347747169756259),
+    );
+    let mut plain_false_positives = 0;
+    let mut validated_false_positives = 0;
+    for seed in [1, 2, 3] {
+        //Deliberately tiny dataset.
+        let data = trajectory_generator(&truth, 10, 1.0, Some(seed));
+        let mut net = CtbnNetwork::new();
+        generate_nodes(&mut net, 6, 3);
+        let net = plain.fit_transform(net, &data).unwrap();
+        plain_false_positives += count_false_positives(&truth, &net);
+        let net = validated.fit_transform(net, &data).unwrap();
+        validated_false_positives += count_false_positives(&truth, &net);
+    }
+    assert!(validated_false_positives < plain_false_positives);
+}
+
+#[test]
+fn held_out_log_likelihood_matrix_prior() {
+    let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let parent_set = BTreeSet::from([0]);
+    let (M, T) = sufficient_statistics(&net, &data, 1, &parent_set);
+    //Matrix priors spreading the same totals as the constant ones give the same likelihood.
+    let n_configurations = M.shape()[0] as f64;
+    let alpha = Alpha::Matrix(Array3::from_elem(M.dim(), 2.0 / n_configurations));
+    let tau = Tau::Matrix(ndarray::Array2::from_elem(T.dim(), 0.5 / n_configurations));
+    assert_abs_diff_eq!(
+        held_out_log_likelihood(&net, 1, &parent_set, &data, &data, 2, 0.5),
+        held_out_log_likelihood(&net, 1, &parent_set, &data, &data, alpha, tau),
+        epsilon = 1e-6
+    );
+}
+
+#[test]
+#[should_panic]
+fn hill_climbing_with_validation_invalid_fraction() {
+    HillClimbing::with_validation(BIC::new(1, 1.0), None, 1.0, 0.0, 1, 1.0, None);
+}
+
+#[test]
+fn learn_model_regenerates_training_statistics() {
+    let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let mut template = CtbnNetwork::new();
+    for node in net.get_node_indices() {
+        template.add_node(net.get_node(node).clone()).unwrap();
+    }
+    let hl = HillClimbing::new(BIC::new(1, 1.0), None);
+    let learned = learn_model(&hl, &MLE {}, template, &data).unwrap();
+
+    let regenerated = trajectory_generator(&learned, 300, 30.0, Some(2));
+    for node in net.get_node_indices() {
+        assert_eq!(net.get_parent_set(node), learned.get_parent_set(node));
+        let parent_set = learned.get_parent_set(node);
+        let (M, T) = sufficient_statistics(&learned, &data, node, &parent_set);
+        let (M_gen, T_gen) = sufficient_statistics(&learned, &regenerated, node, &parent_set);
+        let normalize_M = |M: Array3<usize>| {
+            let M = M.mapv(|x| x as f64);
+            let total = M.sum();
+            M / total
+        };
+        assert_abs_diff_eq!(&T / T.sum(), &T_gen / T_gen.sum(), epsilon = 0.02);
+        assert_abs_diff_eq!(normalize_M(M), normalize_M(M_gen), epsilon = 0.02);
+    }
+}
+
+#[test]
+fn score_properties_log_likelihood() {
+    let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let report = check_score_properties(&LogLikelihood::new(1, 1.0), &net, &data);
+    assert!(report.passed());
+}
+
+#[test]
+fn score_properties_bic() {
+    let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let report = check_score_properties(&BIC::new(1, 1.0), &net, &data);
+    assert!(report.passed());
+}
+
+//Score rewarding the time spent in the first state of the node, weighted by the index of the node.
+struct NodeIndexScore {}
+
+impl ScoreFunction for NodeIndexScore {
+    fn call<T: NetworkProcess>(
+        &self,
+        net: &T,
+        node: usize,
+        parent_set: &BTreeSet<usize>,
+        dataset: &Dataset,
+    ) -> f64 {
+        let (_, T) = sufficient_statistics(net, dataset, node, parent_set);
+        (node + 1) as f64 * T.column(0).sum()
+    }
+}
+
+//Score depending on the number of nodes of the whole network.
+struct NetworkSizeScore {}
+
+impl ScoreFunction for NetworkSizeScore {
+    fn call<T: NetworkProcess>(
+        &self,
+        net: &T,
+        node: usize,
+        parent_set: &BTreeSet<usize>,
+        dataset: &Dataset,
+    ) -> f64 {
+        let (_, T) = sufficient_statistics(net, dataset, node, parent_set);
+        net.get_number_of_nodes() as f64 * T.sum()
+    }
+}
+
+#[test]
+fn score_properties_violated() {
+    let (net, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let report = check_score_properties(&NodeIndexScore {}, &net, &data);
+    assert!(!report.passed());
+    assert!(!report.relabeling_invariance.passed);
+    assert!(report.relabeling_invariance.evidence > 0.0);
+
+    let report = check_score_properties(&NetworkSizeScore {}, &net, &data);
+    assert!(!report.passed());
+    assert!(report.relabeling_invariance.passed);
+    assert!(!report.additivity.passed);
+    assert!(report.additivity.evidence > 0.0);
+}
+
+#[test]
+fn score_properties_interventional_weighted_dataset() {
+    let (net, _) = get_mixed_discrete_net_3_nodes_with_data();
+    let data = interventional_trajectory_generator(
+        &net,
+        &[
+            InterventionSpec {
+                intervention: None,
+                n_trajectories: 20,
+                t_end: 30.0,
+            },
+            InterventionSpec {
+                intervention: Some((1, 0)),
+                n_trajectories: 10,
+                t_end: 30.0,
+            },
+        ],
+        Some(6347747169756259),
+    );
+    //Censor every other trajectory and weight them unevenly.
+    let trajectories: Vec<Trajectory> = data
+        .get_trajectories()
+        .iter()
+        .enumerate()
+        .map(|(idx, trj)| if idx % 2 == 0 { trj.clone().with_censored_end() } else { trj.clone() })
+        .collect();
+    let n_trajectories = trajectories.len();
+    let data = Dataset::new(trajectories)
+        .with_weights(Array1::from_shape_fn(n_trajectories, |x| 1.0 + (x % 3) as f64));
+    let report = check_score_properties(&BIC::new(1, 1.0), &net, &data);
+    assert!(report.passed());
+}
+
+fn check_journal_records<T: StructuralLearningAlgorithm>(
+    sl: T,
+    journal_path: &std::path::Path,
+    algorithm: &str,
+) -> Vec<serde_json::Value> {
+    let (_, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let mut net = CtbnNetwork::new();
+    generate_nodes(&mut net, 2, 3);
+    net.add_node(generate_discrete_time_continous_node(String::from("3"), 4))
+        .unwrap();
+    let net = sl.fit_transform(net, &data).unwrap();
+    let net = sl.fit_transform(net, &data).unwrap();
+
+    let content = std::fs::read_to_string(journal_path).unwrap();
+    std::fs::remove_file(journal_path).unwrap();
+    let records: Vec<serde_json::Value> = content
+        .lines()
+        .map(|x| serde_json::from_str(x).unwrap())
+        .collect();
+    assert_eq!(2, records.len());
+    for record in records.iter() {
+        assert_eq!(algorithm, record["algorithm"]);
+        assert_eq!(env!("CARGO_PKG_VERSION"), record["version"]);
+        assert_eq!(300, record["dataset"]["n_trajectories"]);
+        assert_eq!(records[0]["dataset"]["hash"], record["dataset"]["hash"]);
+        for child in net.get_node_indices() {
+            for parent in net.get_node_indices() {
+                let expected = net.get_parent_set(child).contains(&parent) as u64;
+                assert_eq!(expected, record["adjacency_matrix"][parent][child]);
+            }
+        }
+    }
+    records
+}
+
+#[test]
+fn hill_climbing_journal() {
+    let journal_path = std::env::temp_dir().join("reCTBN_hill_climbing_journal.jsonl");
+    let _ = std::fs::remove_file(&journal_path);
+    let journal = Arc::new(JsonLinesJournal::new(&journal_path).unwrap());
+    let hl = HillClimbing::new(BIC::new(1, 1.0), None).with_journal(journal);
+    check_journal_records(hl, &journal_path, "HillClimbing");
+}
+
+#[test]
+fn ctpc_journal() {
+    let journal_path = std::env::temp_dir().join("reCTBN_ctpc_journal.jsonl");
+    let _ = std::fs::remove_file(&journal_path);
+    let journal = Arc::new(JsonLinesJournal::new(&journal_path).unwrap());
+    let ctpc = CTPC::new(
+        BayesianApproach { alpha: Alpha::Constant(1.0), tau: Tau::Constant(1.0) },
+        F::new(1e-6),
+        ChiSquare::new(1e-4),
+    )
+    .with_journal(journal);
+    let records = check_journal_records(ctpc, &journal_path, "CTPC");
+    for record in records.iter() {
+        let hyperparameters = &record["hyperparameters"];
+        assert_eq!("Constant(1.0)", hyperparameters["parameter_learning_alpha"]);
+        assert_eq!("Constant(1.0)", hyperparameters["parameter_learning_tau"]);
+    }
+}
+
+#[test]
+fn to_dot_mixed_discrete_net_3_nodes() {
+    let net = get_mixed_discrete_net_3_nodes();
+    assert_eq!(
+        "digraph {\n    0 [label=\"n1\"];\n    1 [label=\"n2\"];\n    2 [label=\"n3\"];\n    0 -> 1;\n    0 -> 2;\n    1 -> 2;\n}\n",
+        to_dot(&net, None)
+    );
+}
+
+#[test]
+fn to_dot_mixed_discrete_net_3_nodes_with_reference() {
+    let reference = get_mixed_discrete_net_3_nodes();
+    let mut net = CtbnNetwork::new();
+    generate_nodes(&mut net, 2, 3);
+    net.add_node(generate_discrete_time_continous_node(String::from("3"), 4))
+        .unwrap();
+    net.add_edge(0, 1);
+    net.add_edge(2, 1);
+    assert_eq!(
+        "digraph {\n    0 [label=\"0\"];\n    1 [label=\"1\"];\n    2 [label=\"3\"];\n    0 -> 1 [color=\"black\"];\n    2 -> 1 [color=\"red\"];\n    0 -> 2 [color=\"gray\", style=\"dashed\"];\n    1 -> 2 [color=\"gray\", style=\"dashed\"];\n}\n",
+        to_dot(&net, Some(&reference))
+    );
+}
+
+#[test]
+fn to_dot_and_structural_diff_without_structure() {
+    let mut net = CtbnNetwork::new();
+    generate_nodes(&mut net, 2, 2);
+    let reference = get_mixed_discrete_net_3_nodes();
+    let mut learned = CtbnNetwork::new();
+    for node in reference.get_node_indices() {
+        learned.add_node(reference.get_node(node).clone()).unwrap();
+    }
+    assert_eq!(
+        "digraph {\n    0 [label=\"0\"];\n    1 [label=\"1\"];\n}\n",
+        to_dot(&net, None)
+    );
+    let diff = structural_diff(&reference, &learned).unwrap();
+    assert_eq!(0, diff.true_positives);
+    assert_eq!(3, diff.false_negatives);
+    assert_eq!(0, diff.false_positives);
+}
+
+#[test]
+fn learn_mixed_discrete_net_3_nodes_hill_climbing_from_snapshots() {
+    let (_, data) = get_mixed_discrete_net_3_nodes_with_data();
+    let times = ndarray::Array1::range(0.0, 30.0, 0.02);
+    let observations = data
+        .get_trajectories()
+        .iter()
+        .map(|trj| {
+            let rows: Vec<usize> = times
+                .iter()
+                .map(|t| trj.get_time().iter().rposition(|x| x <= t).unwrap())
+                .collect();
+            trj.get_events().select(ndarray::Axis(0), &rows)
+        })
+        .collect();
+    let snapshots = Dataset::from_snapshots(times, observations, true);
+
+    let mut net = CtbnNetwork::new();
+    generate_nodes(&mut net, 2, 3);
+    net.add_node(generate_discrete_time_continous_node(String::from("3"), 4))
+        .unwrap();
+    let hl = HillClimbing::new(BIC::new(1, 1.0), None);
+    let net = hl.fit_transform(net, &snapshots).unwrap();
+    assert_eq!(BTreeSet::new(), net.get_parent_set(0));
+    assert_eq!(BTreeSet::from_iter(vec![0]), net.get_parent_set(1));
+    assert_eq!(BTreeSet::from_iter(vec![0, 1]), net.get_parent_set(2));
+}
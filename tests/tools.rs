@@ -1,6 +1,8 @@
 use std::ops::Range;
 
-use ndarray::{arr1, arr2, arr3};
+use ndarray::{arr1, arr2, arr3, Array2, Axis};
+use approx::AbsDiffEq;
+use reCTBN::parameter_learning::*;
 use reCTBN::params::ParamsTrait;
 use reCTBN::process::ctbn::*;
 use reCTBN::process::ctmp::*;
@@ -89,6 +91,70 @@ fn dataset_wrong_shape() {
     Dataset::new(vec![t1, t2]);
 }
 
+#[test]
+fn dataset_validate_against() {
+    let mut net = CtbnNetwork::new();
+    net.add_node(utils::generate_discrete_time_continous_node(String::from("n1"), 2))
+        .unwrap();
+    net.add_node(utils::generate_discrete_time_continous_node(String::from("n2"), 3))
+        .unwrap();
+
+    let t1 = Trajectory::new(arr1(&[0.0, 0.2]), arr2(&[[0, 2], [1, 2]]));
+    let t2 = Trajectory::new(arr1(&[0.0, 0.2]), arr2(&[[0, 0], [2, 0]]));
+    assert_eq!(Ok(()), Dataset::new(vec![t1.clone()]).validate_against(&net));
+
+    match Dataset::new(vec![t1, t2]).validate_against(&net) {
+        Err(DatasetError::InvalidValue(message)) => {
+            assert!(message.contains("trajectory 1"));
+            assert!(message.contains("node n1"));
+        }
+        _ => panic!("the state 2 of n1 must be rejected"),
+    }
+
+    let t3 = Trajectory::new(arr1(&[0.0, 0.2]), arr2(&[[0], [1]]));
+    assert!(matches!(
+        Dataset::new(vec![t3]).validate_against(&net),
+        Err(DatasetError::MismatchedNetwork(_))
+    ));
+}
+
+#[test]
+fn dataset_summary() {
+    let mut net = CtbnNetwork::new();
+    net.add_node(utils::generate_discrete_time_continous_node(String::from("n1"), 2))
+        .unwrap();
+    net.add_node(utils::generate_discrete_time_continous_node(String::from("n2"), 3))
+        .unwrap();
+    let dataset = Dataset::new(vec![
+        Trajectory::new(arr1(&[0.0, 0.1, 0.3]), arr2(&[[0, 2], [1, 2], [1, 2]])),
+        Trajectory::new(arr1(&[0.0, 0.5]), arr2(&[[1, 0], [1, 1]])),
+    ]);
+
+    let summary = dataset.summary(&net);
+    assert_eq!(2, summary.n_trajectories);
+    assert!(summary.observation_time.abs_diff_eq(&0.8, 1e-12));
+
+    let n1 = &summary.nodes[0];
+    assert_eq!("n1", n1.label);
+    assert_eq!(1, n1.transitions);
+    assert!(n1.exposure.abs_diff_eq(&0.8, 1e-12));
+    assert_eq!(1, n1.constant_trajectories);
+    assert!(n1.marginal.abs_diff_eq(&arr1(&[0.1 / 0.8, 0.7 / 0.8]), 1e-12));
+
+    let n2 = &summary.nodes[1];
+    assert_eq!(1, n2.transitions);
+    assert_eq!(1, n2.constant_trajectories);
+    assert!(n2.marginal.abs_diff_eq(&arr1(&[0.5 / 0.8, 0.0, 0.3 / 0.8]), 1e-12));
+
+    assert_eq!(
+        "Trajectories: 2, observation time: 0.800\n\
+         node  transitions    exposure  constant  marginal\n\
+         n1              1       0.800         1  [0.125, 0.875]\n\
+         n2              1       0.800         1  [0.625, 0.000, 0.375]\n",
+        summary.to_string()
+    );
+}
+
 #[test]
 #[should_panic]
 fn uniform_graph_generator_wrong_density_1() {
@@ -249,3 +315,909 @@ fn uniform_parameters_generator_right_densities_ctmp() {
         );
     }
 }
+
+fn get_binary_chain_net() -> CtbnNetwork {
+    let mut net = CtbnNetwork::new();
+    let n1 = net
+        .add_node(utils::generate_discrete_time_continous_node(
+            String::from("n1"),
+            2,
+        ))
+        .unwrap();
+    let n2 = net
+        .add_node(utils::generate_discrete_time_continous_node(
+            String::from("n2"),
+            3,
+        ))
+        .unwrap();
+    net.add_edge(n1, n2);
+
+    match &mut net.get_node_mut(n1) {
+        params::Params::DiscreteStatesContinousTime(param) => {
+            assert_eq!(Ok(()), param.set_cim(arr3(&[[[-3.0, 3.0], [2.0, -2.0]]])));
+        }
+    }
+
+    match &mut net.get_node_mut(n2) {
+        params::Params::DiscreteStatesContinousTime(param) => {
+            assert_eq!(
+                Ok(()),
+                param.set_cim(arr3(&[
+                    [[-1.0, 0.5, 0.5], [3.0, -4.0, 1.0], [0.9, 0.1, -1.0]],
+                    [[-6.0, 2.0, 4.0], [1.5, -2.0, 0.5], [3.0, 1.0, -4.0]],
+                ]))
+            );
+        }
+    }
+    net
+}
+
+#[test]
+fn noisy_trajectory_generator_confusion_frequencies() {
+    let net = get_binary_chain_net();
+    let noise = vec![
+        arr2(&[[0.9, 0.1], [0.2, 0.8]]),
+        arr2(&[[1.0, 0.0, 0.0], [0.1, 0.7, 0.2], [0.0, 0.3, 0.7]]),
+    ];
+    let (clean, noisy) = noisy_trajectory_generator(&net, 200, 10.0, &noise, Some(6347747169756259));
+
+    assert_eq!(clean.get_trajectories().len(), noisy.get_trajectories().len());
+    for (node, confusion_matrix) in noise.iter().enumerate() {
+        let mut counts = Array2::<f64>::zeros(confusion_matrix.dim());
+        for (c, n) in clean.get_trajectories().iter().zip(noisy.get_trajectories().iter()) {
+            assert_eq!(c.get_time(), n.get_time());
+            let n_events = c.get_events().shape()[0];
+            for idx in 0..n_events - 1 {
+                counts[[c.get_events()[[idx, node]], n.get_events()[[idx, node]]]] += 1.0;
+            }
+        }
+        let frequencies = &counts / &counts.sum_axis(Axis(1)).insert_axis(Axis(1));
+        assert!(frequencies.abs_diff_eq(confusion_matrix, 0.02));
+    }
+}
+
+#[test]
+fn noisy_trajectory_generator_identity_noise() {
+    let net = get_binary_chain_net();
+    let noise = vec![Array2::eye(2), Array2::eye(3)];
+    let (clean, noisy) = noisy_trajectory_generator(&net, 10, 10.0, &noise, Some(6347747169756259));
+    for (c, n) in clean.get_trajectories().iter().zip(noisy.get_trajectories().iter()) {
+        assert_eq!(c.get_events(), n.get_events());
+    }
+}
+
+#[test]
+#[should_panic]
+fn noisy_trajectory_generator_not_stochastic() {
+    let net = get_binary_chain_net();
+    let noise = vec![arr2(&[[0.9, 0.2], [0.2, 0.8]]), Array2::eye(3)];
+    noisy_trajectory_generator(&net, 10, 10.0, &noise, Some(6347747169756259));
+}
+
+#[test]
+#[should_panic]
+fn noisy_trajectory_generator_wrong_shape() {
+    let net = get_binary_chain_net();
+    let noise = vec![Array2::eye(2), Array2::eye(2)];
+    noisy_trajectory_generator(&net, 10, 10.0, &noise, Some(6347747169756259));
+}
+
+#[test]
+fn permute_variables_reindexes_cims() {
+    let mut net = CtbnNetwork::new();
+    let a = net
+        .add_node(utils::generate_discrete_time_continous_node(String::from("a"), 2))
+        .unwrap();
+    let b = net
+        .add_node(utils::generate_discrete_time_continous_node(String::from("b"), 3))
+        .unwrap();
+    let c = net
+        .add_node(utils::generate_discrete_time_continous_node(String::from("c"), 2))
+        .unwrap();
+    net.add_edge(a, c);
+    net.add_edge(b, c);
+    net.add_edge(c, a);
+    let mut cim_generator: UniformParametersGenerator =
+        RandomParametersGenerator::new(1.0..7.0, Some(6813071588535822));
+    cim_generator.generate_parameters(&mut net);
+    let data = trajectory_generator(&net, 5, 1.0, Some(6347747169756259));
+
+    let perm = [2, 0, 1];
+    let (permuted_net, permuted_data) = permute_variables(&net, &data, &perm);
+
+    for node in net.get_node_indices() {
+        assert_eq!(
+            net.get_node(node).get_label(),
+            permuted_net.get_node(perm[node]).get_label()
+        );
+        let permuted_parents: std::collections::BTreeSet<usize> =
+            net.get_parent_set(node).iter().map(|x| perm[*x]).collect();
+        assert_eq!(permuted_parents, permuted_net.get_parent_set(perm[node]));
+    }
+
+    for (trj, permuted_trj) in data
+        .get_trajectories()
+        .iter()
+        .zip(permuted_data.get_trajectories().iter())
+    {
+        for node in net.get_node_indices() {
+            assert_eq!(
+                trj.get_events().column(node),
+                permuted_trj.get_events().column(perm[node])
+            );
+        }
+    }
+
+    // Every joint state must select the same intensities in both networks.
+    for s in 0..12 {
+        let state: Vec<params::StateType> = vec![s % 2, (s / 2) % 3, s / 6]
+            .into_iter()
+            .map(params::StateType::Discrete)
+            .collect();
+        let mut permuted_state = state.clone();
+        for node in net.get_node_indices() {
+            permuted_state[perm[node]] = state[node].clone();
+        }
+        for node in net.get_node_indices() {
+            let params::Params::DiscreteStatesContinousTime(p) = net.get_node(node);
+            let params::Params::DiscreteStatesContinousTime(permuted_p) =
+                permuted_net.get_node(perm[node]);
+            let u = net.get_param_index_network(node, &state);
+            let permuted_u = permuted_net.get_param_index_network(perm[node], &permuted_state);
+            assert_eq!(
+                p.get_cim().as_ref().unwrap().index_axis(Axis(0), u),
+                permuted_p
+                    .get_cim()
+                    .as_ref()
+                    .unwrap()
+                    .index_axis(Axis(0), permuted_u)
+            );
+        }
+    }
+}
+
+#[test]
+#[should_panic]
+fn permute_variables_not_a_permutation() {
+    let net = get_binary_chain_net();
+    let data = trajectory_generator(&net, 5, 1.0, Some(6347747169756259));
+    permute_variables(&net, &data, &[1, 1]);
+}
+
+#[test]
+fn interventional_trajectory_generator_clamps_node() {
+    let net = get_binary_chain_net();
+    let specs = vec![
+        InterventionSpec {
+            intervention: None,
+            n_trajectories: 5,
+            t_end: 5.0,
+        },
+        InterventionSpec {
+            intervention: Some((0, 1)),
+            n_trajectories: 5,
+            t_end: 5.0,
+        },
+    ];
+    let data = interventional_trajectory_generator(&net, &specs, Some(6347747169756259));
+
+    assert_eq!(10, data.get_trajectories().len());
+    for trj in data.get_trajectories()[..5].iter() {
+        assert_eq!(None, trj.get_intervention());
+    }
+    for trj in data.get_trajectories()[5..].iter() {
+        assert_eq!(Some((0, 1)), trj.get_intervention());
+        assert!(trj.get_events().column(0).iter().all(|x| *x == 1));
+        assert_eq!(5.0, *trj.get_time().last().unwrap());
+    }
+}
+
+#[test]
+fn interventional_dataset_skips_intervened_node() {
+    let net = get_binary_chain_net();
+    let specs = vec![
+        InterventionSpec {
+            intervention: None,
+            n_trajectories: 100,
+            t_end: 10.0,
+        },
+        InterventionSpec {
+            intervention: Some((1, 2)),
+            n_trajectories: 100,
+            t_end: 10.0,
+        },
+    ];
+    let mixed = interventional_trajectory_generator(&net, &specs, Some(6347747169756259));
+    let observational = Dataset::new(
+        mixed
+            .get_trajectories()
+            .iter()
+            .filter(|trj| trj.get_intervention().is_none())
+            .cloned()
+            .collect(),
+    );
+
+    let mle = MLE {};
+    let get_cim = |data: &Dataset| match mle.fit(&net, data, 1, None) {
+        params::Params::DiscreteStatesContinousTime(p) => p.get_cim().clone().unwrap(),
+    };
+    assert_eq!(get_cim(&observational), get_cim(&mixed));
+}
+
+#[test]
+#[should_panic]
+fn interventional_trajectory_generator_invalid_state() {
+    let net = get_binary_chain_net();
+    let specs = vec![InterventionSpec {
+        intervention: Some((0, 2)),
+        n_trajectories: 1,
+        t_end: 1.0,
+    }];
+    interventional_trajectory_generator(&net, &specs, Some(6347747169756259));
+}
+
+#[test]
+fn normalize_collapses_repeated_rows() {
+    let trj = Trajectory::new(
+        arr1(&[0.0, 0.5, 1.0, 1.5, 2.0]),
+        arr2(&[[0, 1], [0, 1], [1, 1], [1, 1], [1, 1]]),
+    );
+    let normalized = trj.normalize();
+    assert_eq!(&arr1(&[0.0, 1.0, 2.0]), normalized.get_time());
+    assert_eq!(&arr2(&[[0, 1], [1, 1], [1, 1]]), normalized.get_events());
+}
+
+#[test]
+fn normalize_preserves_sufficient_statistics() {
+    let net = get_binary_chain_net();
+    let data = trajectory_generator(&net, 20, 5.0, Some(6347747169756259));
+
+    //Duplicate each row in the middle of its interval.
+    let redundant = Dataset::new(
+        data.get_trajectories()
+            .iter()
+            .map(|trj| {
+                let n_rows = trj.get_time().len();
+                let mut time = vec![];
+                let mut rows = vec![];
+                for idx in 0..n_rows {
+                    time.push(trj.get_time()[idx]);
+                    rows.push(idx);
+                    if idx < n_rows - 1 {
+                        time.push((trj.get_time()[idx] + trj.get_time()[idx + 1]) / 2.0);
+                        rows.push(idx);
+                    }
+                }
+                Trajectory::new(
+                    ndarray::Array1::from_vec(time),
+                    trj.get_events().select(Axis(0), &rows),
+                )
+            })
+            .collect(),
+    );
+    let normalized = redundant.normalize();
+
+    for (trj, normalized_trj) in data.get_trajectories().iter().zip(normalized.get_trajectories()) {
+        assert_eq!(trj.get_events(), normalized_trj.get_events());
+    }
+    for node in net.get_node_indices() {
+        let parent_set = net.get_parent_set(node);
+        let (m, t) = sufficient_statistics(&net, &redundant, node, &parent_set);
+        let (m_norm, t_norm) = sufficient_statistics(&net, &normalized, node, &parent_set);
+        let (m_data, t_data) = sufficient_statistics(&net, &data, node, &parent_set);
+        assert_eq!(m, m_norm);
+        assert_eq!(m_data, m_norm);
+        assert_abs_diff_eq!(t, t_norm, epsilon = 1e-9);
+        assert_abs_diff_eq!(t_data, t_norm, epsilon = 1e-9);
+    }
+}
+
+#[test]
+fn simulation_check_correct_network() {
+    let net = get_binary_chain_net();
+    let report = simulation_check(&net, 300, 30.0, Some(6347747169756259));
+    assert!(report.passes(0.1));
+    assert!(report.under_sampled.is_empty());
+}
+
+#[test]
+fn simulation_check_misspecified_cim() {
+    let mut net = get_binary_chain_net();
+    //The off-diagonal entries do not sum to the exit rate: the sampler uses the diagonal.
+    match &mut net.get_node_mut(0) {
+        params::Params::DiscreteStatesContinousTime(param) => {
+            param.set_cim_unchecked(arr3(&[[[-1.0, 3.0], [2.0, -2.0]]]));
+        }
+    }
+    let report = simulation_check(&net, 100, 20.0, Some(6347747169756259));
+    assert!(!report.passes(0.2));
+    assert_eq!(Some((0, 0, 0, 1)), report.worst_entry);
+}
+
+#[test]
+fn simulation_check_under_sampled() {
+    let net = get_binary_chain_net();
+    let report = simulation_check(&net, 1, 1.0, Some(6347747169756259));
+    assert!(!report.under_sampled.is_empty());
+}
+
+#[test]
+fn dataset_fingerprint() {
+    let net = get_binary_chain_net();
+    let data = trajectory_generator(&net, 5, 5.0, Some(6347747169756259));
+    let same_data = trajectory_generator(&net, 5, 5.0, Some(6347747169756259));
+    let other_data = trajectory_generator(&net, 5, 5.0, Some(1));
+    let fingerprint = reCTBN::journal::DatasetFingerprint::new(&data);
+
+    assert_eq!(5, fingerprint.n_trajectories);
+    assert_eq!(
+        data.get_trajectories()
+            .iter()
+            .map(|x| x.get_time().len())
+            .sum::<usize>(),
+        fingerprint.n_events
+    );
+    assert_eq!(fingerprint, reCTBN::journal::DatasetFingerprint::new(&same_data));
+    assert_ne!(
+        fingerprint.hash,
+        reCTBN::journal::DatasetFingerprint::new(&other_data).hash
+    );
+}
+
+#[test]
+fn trajectory_to_bytes() {
+    let trj = Trajectory::new(arr1(&[0.0, 1.5]), arr2(&[[0, 1], [1, 1]]));
+    let bytes = trj.to_bytes();
+    assert_eq!(8 * (2 + 2 + 4), bytes.len());
+    assert_eq!(&2u64.to_le_bytes(), &bytes[0..8]);
+    assert_eq!(&1.5f64.to_bits().to_le_bytes(), &bytes[24..32]);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn dataset_serde_round_trip() {
+    let net = get_binary_chain_net();
+    let data = trajectory_generator(&net, 4, 5.0, Some(6347747169756259));
+    let json = serde_json::to_string(&data).unwrap();
+    let restored: Dataset = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(data.get_trajectories().len(), restored.get_trajectories().len());
+    for (trj, restored_trj) in data
+        .get_trajectories()
+        .iter()
+        .zip(restored.get_trajectories().iter())
+    {
+        assert!(trj.get_time().abs_diff_eq(restored_trj.get_time(), 1e-12));
+        assert_eq!(trj.get_events(), restored_trj.get_events());
+        assert_eq!(trj.get_intervention(), restored_trj.get_intervention());
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn dataset_serde_rejects_mismatched_variables() {
+    let data = Dataset::new(vec![
+        Trajectory::new(arr1(&[0.0, 1.0]), arr2(&[[0, 1], [1, 1]])),
+        Trajectory::new(arr1(&[0.0, 1.0]), arr2(&[[0, 1], [0, 0]])),
+    ]);
+    let mut value = serde_json::to_value(&data).unwrap();
+    let other = serde_json::to_value(&Trajectory::new(
+        arr1(&[0.0, 1.0]),
+        arr2(&[[0, 1, 0], [1, 1, 0]]),
+    ))
+    .unwrap();
+    value["trajectories"][1] = other;
+    assert!(serde_json::from_value::<Dataset>(value).is_err());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn trajectory_serde_rejects_corrupted_shape() {
+    let trj = Trajectory::new(arr1(&[0.0, 1.0, 2.0]), arr2(&[[0, 1], [1, 1], [1, 0]]));
+    let mut value = serde_json::to_value(&trj).unwrap();
+    value["time"] = serde_json::to_value(arr1(&[0.0, 1.0])).unwrap();
+    assert!(serde_json::from_value::<Trajectory>(value).is_err());
+
+    let trj = Trajectory::new_interventional(arr1(&[0.0, 1.0]), arr2(&[[0, 1], [1, 1]]), (1, 1));
+    let mut value = serde_json::to_value(&trj).unwrap();
+    value["intervention"] = serde_json::json!([1, 0]);
+    assert!(serde_json::from_value::<Trajectory>(value).is_err());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn trajectory_serde_censored() {
+    let trj = Trajectory::new(arr1(&[0.0, 1.0]), arr2(&[[0, 1], [1, 1]]));
+    let value = serde_json::to_value(&trj).unwrap();
+    assert!(value.get("censored").is_none());
+    assert!(!serde_json::from_value::<Trajectory>(value).unwrap().is_censored());
+
+    let value = serde_json::to_value(&trj.with_censored_end()).unwrap();
+    assert!(serde_json::from_value::<Trajectory>(value).unwrap().is_censored());
+}
+
+#[test]
+fn dataset_from_csv_round_trip() {
+    let net = get_binary_chain_net();
+    let data = trajectory_generator(&net, 10, 5.0, Some(6347747169756259));
+
+    //Columns deliberately in a different order than the variables.
+    let mut csv = String::from("X2,time,trajectory,X1\n");
+    for (id, trj) in data.get_trajectories().iter().enumerate() {
+        for (t, row) in trj.get_time().iter().zip(trj.get_events().rows()) {
+            csv.push_str(&format!("{},{},{},{}\n", row[1], t, id, row[0]));
+        }
+    }
+    let restored =
+        Dataset::from_csv(csv.as_bytes(), "time", &["X1", "X2"], Some("trajectory")).unwrap();
+
+    assert_eq!(data.get_trajectories().len(), restored.get_trajectories().len());
+    for node in net.get_node_indices() {
+        let parent_set = net.get_parent_set(node);
+        assert_eq!(
+            sufficient_statistics(&net, &data, node, &parent_set),
+            sufficient_statistics(&net, &restored, node, &parent_set)
+        );
+    }
+}
+
+#[test]
+fn dataset_from_csv_single_trajectory() {
+    let csv = "time,A,B\n0.0,0,1\n0.5,1,1\n2.0,1,1\n";
+    let data = Dataset::from_csv(csv.as_bytes(), "time", &["A", "B"], None).unwrap();
+    assert_eq!(1, data.get_trajectories().len());
+    assert_eq!(&arr1(&[0.0, 0.5, 2.0]), data.get_trajectories()[0].get_time());
+    assert_eq!(
+        &arr2(&[[0, 1], [1, 1], [1, 1]]),
+        data.get_trajectories()[0].get_events()
+    );
+}
+
+#[test]
+fn dataset_from_csv_errors() {
+    let csv = "time,A,B\n0.0,0,1\n0.5,1,1\n0.5,1,0\n";
+    assert_eq!(
+        Err(DatasetError::InvalidValue(String::from(
+            "line 4, column time: time 0.5 is not greater than the previous time 0.5"
+        ))),
+        Dataset::from_csv(csv.as_bytes(), "time", &["A", "B"], None).map(|_| ())
+    );
+
+    let csv = "time,A,B\n0.0,0,1\n0.5,-1,1\n";
+    assert_eq!(
+        Err(DatasetError::InvalidValue(String::from(
+            "line 3, column A: \"-1\" is not a non-negative integer"
+        ))),
+        Dataset::from_csv(csv.as_bytes(), "time", &["A", "B"], None).map(|_| ())
+    );
+
+    let csv = "time,A,B\n0.0,0,1\n0.5,1.5,1\n";
+    assert!(matches!(
+        Dataset::from_csv(csv.as_bytes(), "time", &["A", "B"], None),
+        Err(DatasetError::InvalidValue(_))
+    ));
+
+    let csv = "time,A,B\n0.0,0,1\n";
+    assert_eq!(
+        Err(DatasetError::MissingColumn(String::from("C"))),
+        Dataset::from_csv(csv.as_bytes(), "time", &["A", "C"], None).map(|_| ())
+    );
+
+    let csv = "time,A,B\n";
+    assert!(matches!(
+        Dataset::from_csv(csv.as_bytes(), "time", &["A", "B"], None),
+        Err(DatasetError::EmptyDataset(_))
+    ));
+}
+
+#[test]
+fn dataset_to_csv_round_trip() {
+    let net = get_binary_chain_net();
+    let data = trajectory_generator(&net, 10, 5.0, Some(6347747169756259));
+
+    let mut csv = Vec::new();
+    data.to_csv(&mut csv, Some(&net)).unwrap();
+    assert!(csv.starts_with(b"trajectory_id,time,n1,n2\n"));
+    let restored =
+        Dataset::from_csv(csv.as_slice(), "time", &["n1", "n2"], Some("trajectory_id")).unwrap();
+
+    assert_eq!(data.get_trajectories().len(), restored.get_trajectories().len());
+    for (trj, restored_trj) in data
+        .get_trajectories()
+        .iter()
+        .zip(restored.get_trajectories().iter())
+    {
+        assert_eq!(trj.get_time(), restored_trj.get_time());
+        assert_eq!(trj.get_events(), restored_trj.get_events());
+    }
+}
+
+#[test]
+fn dataset_to_csv_default_labels() {
+    let data = Dataset::new(vec![
+        Trajectory::new(arr1(&[0.0, 0.1]), arr2(&[[0, 1], [1, 1]])),
+        Trajectory::new(arr1(&[0.0, 1.0 / 3.0]), arr2(&[[1, 0], [1, 0]])),
+    ]);
+    let mut csv = Vec::new();
+    data.to_csv(&mut csv, None).unwrap();
+    assert_eq!(
+        "trajectory_id,time,var_0,var_1\n0,0,0,1\n0,0.1,1,1\n1,0,1,0\n1,0.3333333333333333,1,0\n",
+        String::from_utf8(csv).unwrap()
+    );
+
+    let mut net = CtbnNetwork::new();
+    net.add_node(utils::generate_discrete_time_continous_node(String::from("n1"), 2))
+        .unwrap();
+    assert!(matches!(
+        data.to_csv(Vec::new(), Some(&net)),
+        Err(DatasetError::MismatchedNetwork(_))
+    ));
+}
+
+//Write the columns to a temporary Parquet file.
+#[cfg(feature = "parquet")]
+fn write_parquet_table(name: &str, columns: Vec<(&str, arrow_array::ArrayRef)>) -> std::path::PathBuf {
+    let batch = arrow_array::RecordBatch::try_from_iter(columns).unwrap();
+    let path = std::env::temp_dir().join(name);
+    let mut writer =
+        parquet::arrow::ArrowWriter::try_new(std::fs::File::create(&path).unwrap(), batch.schema(), None)
+            .unwrap();
+    writer.write(&batch).unwrap();
+    writer.close().unwrap();
+    path
+}
+
+#[cfg(feature = "parquet")]
+#[test]
+fn dataset_to_parquet_round_trip() {
+    let net = get_binary_chain_net();
+    let data = trajectory_generator(&net, 10, 5.0, Some(6347747169756259));
+
+    let path = std::env::temp_dir().join("reCTBN_dataset_to_parquet_round_trip.parquet");
+    data.to_parquet(std::fs::File::create(&path).unwrap(), Some(&net)).unwrap();
+    let restored = Dataset::from_parquet(
+        std::fs::File::open(&path).unwrap(),
+        "time",
+        &["n1", "n2"],
+        Some("trajectory_id"),
+    )
+    .unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(data.get_trajectories().len(), restored.get_trajectories().len());
+    for (trj, restored_trj) in data
+        .get_trajectories()
+        .iter()
+        .zip(restored.get_trajectories().iter())
+    {
+        assert_eq!(trj.get_time(), restored_trj.get_time());
+        assert_eq!(trj.get_events(), restored_trj.get_events());
+    }
+}
+
+#[cfg(feature = "parquet")]
+#[test]
+fn dataset_from_parquet_interleaved_trajectories() {
+    use arrow_array::{Float32Array, Int32Array, StringArray};
+    use std::sync::Arc;
+
+    let path = write_parquet_table(
+        "reCTBN_dataset_from_parquet_interleaved_trajectories.parquet",
+        vec![
+            ("id", Arc::new(StringArray::from(vec!["b", "a", "b", "a"])) as _),
+            ("t", Arc::new(Float32Array::from(vec![0.0, 0.0, 0.5, 1.0])) as _),
+            ("A", Arc::new(Int32Array::from(vec![0, 1, 1, 1])) as _),
+        ],
+    );
+    let data =
+        Dataset::from_parquet(std::fs::File::open(&path).unwrap(), "t", &["A"], Some("id")).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(2, data.get_trajectories().len());
+    assert_eq!(&arr1(&[0.0, 0.5]), data.get_trajectories()[0].get_time());
+    assert_eq!(&arr2(&[[0], [1]]), data.get_trajectories()[0].get_events());
+    assert_eq!(&arr1(&[0.0, 1.0]), data.get_trajectories()[1].get_time());
+    assert_eq!(&arr2(&[[1], [1]]), data.get_trajectories()[1].get_events());
+}
+
+#[cfg(feature = "parquet")]
+#[test]
+fn dataset_from_parquet_errors() {
+    use arrow_array::{Float64Array, Int64Array};
+    use std::sync::Arc;
+
+    let read = |name: &str, time: Vec<Option<f64>>, states: Vec<Option<i64>>| {
+        let path = write_parquet_table(
+            name,
+            vec![
+                ("time", Arc::new(Float64Array::from(time)) as _),
+                ("A", Arc::new(Int64Array::from(states)) as _),
+            ],
+        );
+        let result =
+            Dataset::from_parquet(std::fs::File::open(&path).unwrap(), "time", &["A"], None).map(|_| ());
+        std::fs::remove_file(&path).unwrap();
+        result
+    };
+
+    assert_eq!(
+        Err(DatasetError::InvalidValue(String::from(
+            "row 2, column time: time 0.5 is not greater than the previous time 0.5"
+        ))),
+        read(
+            "reCTBN_dataset_from_parquet_duplicated.parquet",
+            vec![Some(0.0), Some(0.5), Some(0.5)],
+            vec![Some(0), Some(1), Some(0)]
+        )
+    );
+    assert_eq!(
+        Err(DatasetError::InvalidValue(String::from(
+            "row 2, column time: time 0.2 is not greater than the previous time 0.5"
+        ))),
+        read(
+            "reCTBN_dataset_from_parquet_unsorted.parquet",
+            vec![Some(0.0), Some(0.5), Some(0.2)],
+            vec![Some(0), Some(1), Some(0)]
+        )
+    );
+    assert_eq!(
+        Err(DatasetError::InvalidValue(String::from(
+            "row 1, column A: the state is not a non-negative integer"
+        ))),
+        read(
+            "reCTBN_dataset_from_parquet_negative.parquet",
+            vec![Some(0.0), Some(0.5)],
+            vec![Some(0), Some(-1)]
+        )
+    );
+    assert_eq!(
+        Err(DatasetError::InvalidValue(String::from(
+            "row 1, column A: missing value"
+        ))),
+        read(
+            "reCTBN_dataset_from_parquet_missing.parquet",
+            vec![Some(0.0), Some(0.5)],
+            vec![Some(0), None]
+        )
+    );
+
+    let path = write_parquet_table(
+        "reCTBN_dataset_from_parquet_missing_column.parquet",
+        vec![("time", Arc::new(Float64Array::from(vec![0.0])) as _)],
+    );
+    assert_eq!(
+        Err(DatasetError::MissingColumn(String::from("A"))),
+        Dataset::from_parquet(std::fs::File::open(&path).unwrap(), "time", &["A"], None).map(|_| ())
+    );
+    //The states must be integers.
+    assert!(matches!(
+        Dataset::from_parquet(std::fs::File::open(&path).unwrap(), "time", &["time"], None),
+        Err(DatasetError::InvalidValue(_))
+    ));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn dataset_from_snapshots_collapse() {
+    let times = arr1(&[0.0, 1.0, 2.0, 3.0]);
+    let observations = vec![
+        arr2(&[[0, 1], [0, 1], [1, 1], [1, 1]]),
+        arr2(&[[1, 0], [0, 0], [0, 0], [0, 1]]),
+    ];
+
+    let data = Dataset::from_snapshots(times.clone(), observations.clone(), false);
+    assert_eq!(&times, data.get_trajectories()[0].get_time());
+    assert_eq!(&observations[0], data.get_trajectories()[0].get_events());
+
+    let collapsed = Dataset::from_snapshots(times, observations, true);
+    assert_eq!(&arr1(&[0.0, 2.0, 3.0]), collapsed.get_trajectories()[0].get_time());
+    assert_eq!(
+        &arr2(&[[0, 1], [1, 1], [1, 1]]),
+        collapsed.get_trajectories()[0].get_events()
+    );
+    assert_eq!(&arr1(&[0.0, 1.0, 3.0]), collapsed.get_trajectories()[1].get_time());
+}
+
+#[test]
+#[should_panic]
+fn dataset_from_snapshots_unsorted_times() {
+    Dataset::from_snapshots(arr1(&[0.0, 1.0, 1.0]), vec![arr2(&[[0], [1], [0]])], true);
+}
+
+#[test]
+fn dataset_from_snapshots_mle() {
+    let net = get_binary_chain_net();
+    let data = trajectory_generator(&net, 100, 20.0, Some(6347747169756259));
+
+    //Observe each trajectory on a regular grid with a step much smaller than the residence times.
+    let times = ndarray::Array1::range(0.0, 20.0, 0.01);
+    let observations = data
+        .get_trajectories()
+        .iter()
+        .map(|trj| {
+            let rows: Vec<usize> = times
+                .iter()
+                .map(|t| trj.get_time().iter().rposition(|x| x <= t).unwrap())
+                .collect();
+            trj.get_events().select(Axis(0), &rows)
+        })
+        .collect();
+    let snapshots = Dataset::from_snapshots(times, observations, true);
+
+    let mle = MLE {};
+    match mle.fit(&net, &snapshots, 0, None) {
+        params::Params::DiscreteStatesContinousTime(p) => {
+            assert!(p
+                .get_cim()
+                .as_ref()
+                .unwrap()
+                .abs_diff_eq(&arr3(&[[[-3.0, 3.0], [2.0, -2.0]]]), 0.2));
+        }
+    }
+}
+
+#[test]
+fn dataset_to_event_list() {
+    let data = Dataset::new(vec![
+        Trajectory::new(
+            arr1(&[0.0, 0.5, 1.0, 1.5, 2.0]),
+            arr2(&[[0, 0], [1, 0], [1, 2], [0, 2], [0, 2]]),
+        ),
+        Trajectory::new(arr1(&[0.0, 1.0]), arr2(&[[1, 1], [1, 1]])),
+    ]);
+    let net = get_binary_chain_net();
+    let records = data.to_event_list(Some(&net), false).unwrap();
+    assert_eq!(
+        vec![
+            EventRecord {
+                trajectory_id: 0,
+                t_start: 0.0,
+                t_end: 0.5,
+                node: 0,
+                from_state: 0,
+                to_state: 1
+            },
+            EventRecord {
+                trajectory_id: 0,
+                t_start: 0.0,
+                t_end: 1.0,
+                node: 1,
+                from_state: 0,
+                to_state: 2
+            },
+            EventRecord {
+                trajectory_id: 0,
+                t_start: 0.5,
+                t_end: 1.5,
+                node: 0,
+                from_state: 1,
+                to_state: 0
+            },
+        ],
+        records
+    );
+
+    let mut csv = Vec::new();
+    event_list_to_csv(&records, &mut csv).unwrap();
+    assert_eq!(
+        "trajectory_id,t_start,t_end,node,from_state,to_state\n0,0,0.5,0,0,1\n0,0,1,1,0,2\n0,0.5,1.5,0,1,0\n",
+        String::from_utf8(csv).unwrap()
+    );
+}
+
+#[test]
+fn dataset_to_event_list_simultaneous_changes() {
+    let data = Dataset::new(vec![Trajectory::new(
+        arr1(&[0.0, 0.5, 1.0]),
+        arr2(&[[0, 0], [1, 1], [1, 1]]),
+    )]);
+    assert!(matches!(
+        data.to_event_list::<CtbnNetwork>(None, false),
+        Err(DatasetError::InvalidValue(_))
+    ));
+    let records = data.to_event_list::<CtbnNetwork>(None, true).unwrap();
+    assert_eq!(2, records.len());
+    assert_eq!((0, 1), (records[0].node, records[1].node));
+    assert!(records.iter().all(|x| x.t_end == 0.5));
+
+    //The first node of the chain network is binary.
+    let data = Dataset::new(vec![Trajectory::new(arr1(&[0.0, 0.5]), arr2(&[[0, 0], [2, 0]]))]);
+    match data.to_event_list(Some(&get_binary_chain_net()), true) {
+        Err(DatasetError::InvalidValue(message)) => assert!(message.contains("node n1")),
+        x => panic!("unexpected result {:?}", x),
+    }
+}
+
+#[test]
+fn dataset_from_event_log_round_trip() {
+    let net = get_binary_chain_net();
+    let data = trajectory_generator(&net, 10, 5.0, Some(6347747169756259));
+
+    let trajectories: Vec<EventLogTrajectory> = data
+        .get_trajectories()
+        .iter()
+        .map(|trj| EventLogTrajectory {
+            t_start: trj.get_time()[0],
+            t_end: *trj.get_time().last().unwrap(),
+            initial_state: trj.get_events().row(0).to_vec(),
+        })
+        .collect();
+    let events: Vec<EventLogEntry> = data
+        .to_event_list(Some(&net), false)
+        .unwrap()
+        .into_iter()
+        .map(|x| EventLogEntry {
+            trajectory_id: x.trajectory_id,
+            time: x.t_end,
+            variable: net.get_node(x.node).get_label().clone(),
+            new_state: x.to_state,
+        })
+        .collect();
+    let restored = Dataset::from_event_log(&net, &trajectories, &events).unwrap();
+
+    for node in net.get_node_indices() {
+        let parent_set = net.get_parent_set(node);
+        assert_eq!(
+            sufficient_statistics(&net, &data, node, &parent_set),
+            sufficient_statistics(&net, &restored, node, &parent_set)
+        );
+    }
+}
+
+#[test]
+fn dataset_from_event_log_simultaneous_events() {
+    let net = get_binary_chain_net();
+    let trajectories = vec![EventLogTrajectory {
+        t_start: 0.0,
+        t_end: 2.0,
+        initial_state: vec![0, 0],
+    }];
+    let event = |time: f64, variable: &str, new_state: usize| EventLogEntry {
+        trajectory_id: 0,
+        time,
+        variable: String::from(variable),
+        new_state,
+    };
+    let data = Dataset::from_event_log(
+        &net,
+        &trajectories,
+        &[event(0.5, "n1", 1), event(0.5, "n2", 2), event(1.0, "n1", 0)],
+    )
+    .unwrap();
+    let trj = &data.get_trajectories()[0];
+    assert_eq!(&arr1(&[0.0, 0.5, 1.0, 2.0]), trj.get_time());
+    assert_eq!(&arr2(&[[0, 0], [1, 2], [0, 2], [0, 2]]), trj.get_events());
+}
+
+#[test]
+fn dataset_from_event_log_errors() {
+    let net = get_binary_chain_net();
+    let trajectories = vec![EventLogTrajectory {
+        t_start: 0.0,
+        t_end: 2.0,
+        initial_state: vec![0, 0],
+    }];
+    let event = |trajectory_id: usize, time: f64, variable: &str, new_state: usize| EventLogEntry {
+        trajectory_id,
+        time,
+        variable: String::from(variable),
+        new_state,
+    };
+    let check = |events: &[EventLogEntry], message: &str| match Dataset::from_event_log(
+        &net,
+        &trajectories,
+        events,
+    ) {
+        Err(DatasetError::InvalidValue(x)) => assert!(x.ends_with(message), "{}", x),
+        _ => panic!("The log must be rejected"),
+    };
+
+    check(&[event(0, 1.0, "n1", 1), event(0, 0.5, "n2", 1)], "out of order");
+    check(&[event(0, 1.0, "n3", 1)], "unknown variable");
+    check(&[event(0, 1.0, "n1", 2)], "state exceeding the cardinality of the variable");
+    check(&[event(1, 1.0, "n1", 1)], "unknown trajectory");
+    check(&[event(0, 3.0, "n1", 1)], "after the end of the trajectory");
+    match Dataset::from_event_log(&net, &trajectories, &[event(0, 1.0, "n3", 1)]) {
+        Err(DatasetError::InvalidValue(x)) => assert!(x.starts_with("event 0 ")),
+        _ => panic!("The log must be rejected"),
+    }
+}
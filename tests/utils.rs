@@ -2,14 +2,10 @@ use std::collections::BTreeSet;
 
 use reCTBN::params;
 
-#[allow(dead_code)]
-pub fn generate_discrete_time_continous_node(label: String, cardinality: usize) -> params::Params {
-    params::Params::DiscreteStatesContinousTime(generate_discrete_time_continous_params(
-        label,
-        cardinality,
-    ))
-}
+#[allow(unused_imports)]
+pub use reCTBN::params::generate_discrete_time_continous_node;
 
+#[allow(dead_code)]
 pub fn generate_discrete_time_continous_params(
     label: String,
     cardinality: usize,